@@ -0,0 +1,950 @@
+use crate::engine::{Board, Direction, bitboard::BitBoard};
+use crate::ntuple::NTupleNetwork;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Chance branches less likely than this are scored by the heuristic instead
+// of being searched further, which keeps big boards tractable.
+const MIN_PROBABILITY: f64 = 1e-4;
+pub(crate) const FOUR_PROBABILITY: f64 = 0.1;
+// Iterative deepening stops here even with time left; past this the
+// probability cutoff prunes nearly everything anyway.
+const MAX_DEPTH: u32 = 10;
+// Rough footprint of one cached chance node, table overhead included.
+const ENTRY_BYTES: usize = 32;
+const TABLE_SHARDS: usize = 16;
+
+/// Weights of the terms that score a position at the search horizon. They
+/// can be tuned from the `[heuristics]` table of the settings file.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Heuristics {
+    /// Reward per empty cell.
+    pub empty: f64,
+    /// Penalty for rows and columns that don't slope one way.
+    pub monotonicity: f64,
+    /// Penalty for rank gaps between neighbouring tiles.
+    pub smoothness: f64,
+    /// Reward for keeping the largest tile in a corner.
+    pub corner: f64,
+}
+
+impl Default for Heuristics {
+    fn default() -> Self {
+        Heuristics {
+            empty: 2.7,
+            monotonicity: 1.0,
+            smoothness: 0.1,
+            corner: 1.0,
+        }
+    }
+}
+
+fn rank(value: u32) -> f64 {
+    if value == 0 {
+        0.0
+    } else {
+        (value as f64).log2()
+    }
+}
+
+// Penalises a line that goes up and down instead of sloping one way.
+fn line_monotonicity(line: &[f64]) -> f64 {
+    let (mut inc, mut dec) = (0.0, 0.0);
+    for pair in line.windows(2) {
+        if pair[0] > pair[1] {
+            dec += pair[0] - pair[1];
+        } else {
+            inc += pair[1] - pair[0];
+        }
+    }
+    -f64::min(inc, dec)
+}
+
+// Penalises rank gaps between neighbouring tiles; empty cells are skipped.
+fn line_smoothness(line: &[f64]) -> f64 {
+    line.windows(2)
+        .filter(|pair| pair[0] != 0.0 && pair[1] != 0.0)
+        .map(|pair| -(pair[0] - pair[1]).abs())
+        .sum()
+}
+
+// Monotonicity and smoothness of every packed row, so scoring a bitboard is
+// eight lookups instead of a walk over the cells.
+fn row_terms() -> &'static [(f64, f64)] {
+    static TERMS: OnceLock<Vec<(f64, f64)>> = OnceLock::new();
+    TERMS.get_or_init(|| {
+        (0..=u16::MAX)
+            .map(|row| {
+                let line: Vec<f64> = (0..4).map(|c| ((row >> (4 * c)) & 0xf) as f64).collect();
+                (line_monotonicity(&line), line_smoothness(&line))
+            })
+            .collect()
+    })
+}
+
+impl Heuristics {
+    pub fn evaluate(&self, board: &Board) -> f64 {
+        let (rows, cols) = (board.rows(), board.cols());
+        let cells = board.cells();
+        let at = |r: usize, c: usize| rank(cells[r * cols + c]);
+
+        let empty = board.empty_cells().count() as f64;
+
+        let (mut monotonicity, mut smoothness) = (0.0, 0.0);
+        for r in 0..rows {
+            let line: Vec<f64> = (0..cols).map(|c| at(r, c)).collect();
+            monotonicity += line_monotonicity(&line);
+            smoothness += line_smoothness(&line);
+        }
+        for c in 0..cols {
+            let line: Vec<f64> = (0..rows).map(|r| at(r, c)).collect();
+            monotonicity += line_monotonicity(&line);
+            smoothness += line_smoothness(&line);
+        }
+
+        let max = rank(board.max_tile());
+        let corners = [
+            at(0, 0),
+            at(0, cols - 1),
+            at(rows - 1, 0),
+            at(rows - 1, cols - 1),
+        ];
+        let corner = if corners.contains(&max) { max } else { 0.0 };
+
+        self.weigh(empty, monotonicity, smoothness, corner)
+    }
+
+    /// Same score as [`Heuristics::evaluate`], read from the row tables.
+    pub fn evaluate_bits(&self, bits: BitBoard) -> f64 {
+        let terms = row_terms();
+        let transposed = bits.transpose();
+        let (mut monotonicity, mut smoothness) = (0.0, 0.0);
+        for r in 0..4 {
+            for row in [bits.row(r), transposed.row(r)] {
+                let (m, s) = terms[row as usize];
+                monotonicity += m;
+                smoothness += s;
+            }
+        }
+
+        let empty = bits.empty_cells().count() as f64;
+        let max = bits.max_exponent();
+        let corner = if [0, 3, 12, 15].iter().any(|&i| bits.exponent(i) == max) {
+            max as f64
+        } else {
+            0.0
+        };
+
+        self.weigh(empty, monotonicity, smoothness, corner)
+    }
+
+    fn weigh(&self, empty: f64, monotonicity: f64, smoothness: f64, corner: f64) -> f64 {
+        self.empty * empty
+            + self.monotonicity * monotonicity
+            + self.smoothness * smoothness
+            + self.corner * corner
+    }
+}
+
+/// A position the search can expand. Any board size works through [`Board`];
+/// 4x4 games are searched on the packed [`BitBoard`], which is much faster.
+trait Position: Sized {
+    fn after_move(&self, dir: Direction) -> Option<Self>;
+    fn empty_cells(&self) -> Vec<usize>;
+    fn with_tile(&self, idx: usize, value: u32) -> Self;
+    fn evaluate(&self, heuristics: &Heuristics) -> f64;
+    /// Identifies the position up to symmetry for the transposition table.
+    fn key(&self) -> Option<u64>;
+}
+
+impl Position for Board {
+    fn after_move(&self, dir: Direction) -> Option<Self> {
+        let mut next = self.clone();
+        next.slide(dir).map(|_| next)
+    }
+
+    fn empty_cells(&self) -> Vec<usize> {
+        Board::empty_cells(self).collect()
+    }
+
+    fn with_tile(&self, idx: usize, value: u32) -> Self {
+        let mut next = self.clone();
+        next.set_cell(idx, value);
+        next
+    }
+
+    fn evaluate(&self, heuristics: &Heuristics) -> f64 {
+        heuristics.evaluate(self)
+    }
+
+    fn key(&self) -> Option<u64> {
+        None
+    }
+}
+
+impl Position for BitBoard {
+    fn after_move(&self, dir: Direction) -> Option<Self> {
+        let (next, _) = self.apply_move(dir);
+        (next != *self).then_some(next)
+    }
+
+    fn empty_cells(&self) -> Vec<usize> {
+        BitBoard::empty_cells(*self).collect()
+    }
+
+    fn with_tile(&self, idx: usize, value: u32) -> Self {
+        self.with_exponent(idx, value.trailing_zeros() as u8)
+    }
+
+    fn evaluate(&self, heuristics: &Heuristics) -> f64 {
+        heuristics.evaluate_bits(*self)
+    }
+
+    fn key(&self) -> Option<u64> {
+        Some(self.canonical().0)
+    }
+}
+
+/// Depth-limited expectimax: the player picks the best direction, then the
+/// spawn is averaged over every empty cell and both tile values. Each
+/// top-level direction is searched on its own thread.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Expectimax {
+    pub depth: u32,
+    pub heuristics: Heuristics,
+    /// With a budget the search deepens one ply at a time until the time runs
+    /// out, and `depth` is ignored.
+    pub time_budget: Option<Duration>,
+    /// Memory cap for the transposition table, in megabytes; 0 disables it.
+    pub cache_mb: usize,
+}
+
+/// Expected values of chance nodes already searched, keyed by canonical
+/// board so that rotated and mirrored positions share one entry. A shard
+/// that reaches its share of the memory cap is simply emptied.
+struct TranspositionTable {
+    shards: Vec<Mutex<HashMap<u64, (u32, f64)>>>,
+    shard_capacity: usize,
+}
+
+impl TranspositionTable {
+    fn new(cache_mb: usize) -> TranspositionTable {
+        TranspositionTable {
+            shards: (0..TABLE_SHARDS).map(|_| Mutex::default()).collect(),
+            shard_capacity: cache_mb * 1024 * 1024 / ENTRY_BYTES / TABLE_SHARDS,
+        }
+    }
+
+    fn shard(&self, key: u64) -> &Mutex<HashMap<u64, (u32, f64)>> {
+        // Mix the bits first: canonical boards cluster in their low nibbles.
+        let hash = key.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 60;
+        &self.shards[hash as usize % TABLE_SHARDS]
+    }
+
+    // A value searched at least as deep as asked for.
+    fn get(&self, key: u64, depth: u32) -> Option<f64> {
+        let shard = self.shard(key).lock().unwrap();
+        shard
+            .get(&key)
+            .and_then(|&(searched, value)| (searched >= depth).then_some(value))
+    }
+
+    fn insert(&self, key: u64, depth: u32, value: f64) {
+        if self.shard_capacity == 0 {
+            return;
+        }
+        let mut shard = self.shard(key).lock().unwrap();
+        if shard.len() >= self.shard_capacity {
+            shard.clear();
+        }
+        shard.insert(key, (depth, value));
+    }
+}
+
+// Shared by the threads of one search pass; once the deadline passes every
+// branch bails out and the pass is thrown away.
+struct Pass<'a> {
+    deadline: Option<Instant>,
+    expired: AtomicBool,
+    table: &'a TranspositionTable,
+}
+
+impl Pass<'_> {
+    fn passed(&self) -> bool {
+        if self.expired.load(Ordering::Relaxed) {
+            return true;
+        }
+        let passed = self.deadline.is_some_and(|at| Instant::now() >= at);
+        if passed {
+            self.expired.store(true, Ordering::Relaxed);
+        }
+        passed
+    }
+}
+
+impl Expectimax {
+    pub fn new(depth: u32) -> Expectimax {
+        Expectimax {
+            depth: depth.max(1),
+            heuristics: Heuristics::default(),
+            time_budget: None,
+            cache_mb: 64,
+        }
+    }
+
+    pub fn with_time_budget(budget: Duration) -> Expectimax {
+        Expectimax {
+            time_budget: Some(budget),
+            ..Expectimax::new(1)
+        }
+    }
+
+    pub fn best_move(&self, board: &Board) -> Option<Direction> {
+        self.best_move_among(board, &Direction::ALL)
+    }
+
+    /// The best move when only some directions are allowed.
+    pub fn best_move_among(&self, board: &Board, allowed: &[Direction]) -> Option<Direction> {
+        self.rank_moves(board)
+            .into_iter()
+            .filter(|(dir, _)| allowed.contains(dir))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(dir, _)| dir)
+    }
+
+    /// Expected value of every direction that changes the board.
+    pub fn rank_moves(&self, board: &Board) -> Vec<(Direction, f64)> {
+        match BitBoard::from_board(board) {
+            Some(bits) => self.rank_positions(&bits),
+            None => self.rank_positions(board),
+        }
+    }
+
+    fn rank_positions<P: Position + Send + Sync>(&self, position: &P) -> Vec<(Direction, f64)> {
+        let table = TranspositionTable::new(self.cache_mb);
+        let Some(budget) = self.time_budget else {
+            let pass = Pass {
+                deadline: None,
+                expired: AtomicBool::new(false),
+                table: &table,
+            };
+            return self
+                .search_pass(position, self.depth, &pass)
+                .unwrap_or_default();
+        };
+
+        // The first ply always completes so there is a move to return.
+        let at = Instant::now() + budget;
+        let mut ranked = Vec::new();
+        for depth in 1..=MAX_DEPTH {
+            let pass = Pass {
+                deadline: (depth > 1).then_some(at),
+                expired: AtomicBool::new(false),
+                table: &table,
+            };
+            match self.search_pass(position, depth, &pass) {
+                Some(pass) => ranked = pass,
+                None => break,
+            }
+            if Instant::now() >= at {
+                break;
+            }
+        }
+        ranked
+    }
+
+    // One full search at `depth`, or `None` if the deadline cut it short.
+    fn search_pass<P: Position + Send + Sync>(
+        &self,
+        position: &P,
+        depth: u32,
+        pass: &Pass,
+    ) -> Option<Vec<(Direction, f64)>> {
+        let ranked: Vec<(Direction, f64)> = thread::scope(|scope| {
+            let branches: Vec<_> = Direction::ALL
+                .iter()
+                .filter_map(|&dir| {
+                    let next = position.after_move(dir)?;
+                    Some(scope.spawn(move || (dir, self.chance_value(&next, depth, 1.0, pass))))
+                })
+                .collect();
+            branches
+                .into_iter()
+                .map(|branch| branch.join().expect("search thread panicked"))
+                .collect()
+        });
+        (!pass.expired.load(Ordering::Relaxed)).then_some(ranked)
+    }
+
+    fn max_value<P: Position>(
+        &self,
+        position: &P,
+        depth: u32,
+        probability: f64,
+        pass: &Pass,
+    ) -> f64 {
+        if depth == 0 || probability < MIN_PROBABILITY || pass.passed() {
+            return position.evaluate(&self.heuristics);
+        }
+        Direction::ALL
+            .iter()
+            .filter_map(|&dir| position.after_move(dir))
+            .map(|next| self.chance_value(&next, depth, probability, pass))
+            .max_by(f64::total_cmp)
+            // No legal move: the game ends here, which is worse than anything.
+            .unwrap_or(position.evaluate(&self.heuristics) - 1e6)
+    }
+
+    fn chance_value<P: Position>(
+        &self,
+        position: &P,
+        depth: u32,
+        probability: f64,
+        pass: &Pass,
+    ) -> f64 {
+        let key = position.key();
+        if let Some(value) = key.and_then(|key| pass.table.get(key, depth)) {
+            return value;
+        }
+        let empty = position.empty_cells();
+        if empty.is_empty() {
+            return self.max_value(position, depth - 1, probability, pass);
+        }
+        let cell_probability = probability / empty.len() as f64;
+        let mut total = 0.0;
+        for &idx in &empty {
+            for (value, chance) in [(2, 1.0 - FOUR_PROBABILITY), (4, FOUR_PROBABILITY)] {
+                let next = position.with_tile(idx, value);
+                total += chance * self.max_value(&next, depth - 1, cell_probability * chance, pass);
+            }
+        }
+        let value = total / empty.len() as f64;
+        // Values from a pass cut short by the deadline are unreliable.
+        if let Some(key) = key.filter(|_| !pass.passed()) {
+            pass.table.insert(key, depth, value);
+        }
+        value
+    }
+}
+
+/// Which AI gives hints and plays by itself.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Strategy {
+    #[default]
+    Expectimax,
+    MonteCarlo,
+    /// Searches on an evaluation learned by self-play, once it has been
+    /// trained; expectimax plays until then.
+    Learned,
+}
+
+impl Strategy {
+    pub const ALL: [Strategy; 3] = [
+        Strategy::Expectimax,
+        Strategy::MonteCarlo,
+        Strategy::Learned,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Strategy::Expectimax => "Expectimax",
+            Strategy::MonteCarlo => "Monte Carlo",
+            Strategy::Learned => "Learned",
+        }
+    }
+}
+
+/// Pure Monte Carlo: every direction is tried and then played out with
+/// random moves and random tiles, `rollouts` times over, and the direction
+/// whose playouts last longest on average wins. It knows nothing about
+/// what makes a good board, which makes it cheap and easy to follow, if
+/// weaker than expectimax.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MonteCarlo {
+    pub rollouts: u32,
+    /// Playouts stop after this many moves, however the game is going.
+    pub max_moves: u32,
+}
+
+impl MonteCarlo {
+    pub fn new(rollouts: u32) -> MonteCarlo {
+        MonteCarlo {
+            rollouts: rollouts.max(1),
+            max_moves: 500,
+        }
+    }
+
+    /// The average length of the playouts after each direction that changes
+    /// the board.
+    pub fn rank_moves(&self, board: &Board) -> Vec<(Direction, f64)> {
+        match BitBoard::from_board(board) {
+            Some(bits) => self.rank_positions(&bits),
+            None => self.rank_positions(board),
+        }
+    }
+
+    // Each direction plays out on its own thread.
+    fn rank_positions<P: Position + Send + Sync>(&self, position: &P) -> Vec<(Direction, f64)> {
+        thread::scope(|scope| {
+            let branches: Vec<_> = Direction::ALL
+                .iter()
+                .filter_map(|&dir| {
+                    let next = position.after_move(dir)?;
+                    Some(scope.spawn(move || {
+                        let mut rng = rand::rng();
+                        let total: u64 = (0..self.rollouts)
+                            .map(|_| self.play_out(&next, &mut rng) as u64)
+                            .sum();
+                        (dir, total as f64 / self.rollouts as f64)
+                    }))
+                })
+                .collect();
+            branches
+                .into_iter()
+                .map(|branch| branch.join().expect("rollout thread panicked"))
+                .collect()
+        })
+    }
+
+    // Plays random moves from `position`, which still needs its tile, and
+    // returns how many were made before the game ended.
+    fn play_out<P: Position>(&self, position: &P, rng: &mut impl Rng) -> u32 {
+        let mut position = match random_spawn(position, rng) {
+            Some(next) => next,
+            None => return 0,
+        };
+        for moves in 0..self.max_moves {
+            let mut dirs = Direction::ALL;
+            // The first direction in a random order that moves anything.
+            for i in (1..dirs.len()).rev() {
+                dirs.swap(i, rng.random_range(0..=i));
+            }
+            let Some(next) = dirs.iter().find_map(|&dir| position.after_move(dir)) else {
+                return moves;
+            };
+            position = random_spawn(&next, rng).unwrap_or(next);
+        }
+        self.max_moves
+    }
+}
+
+fn random_spawn<P: Position>(position: &P, rng: &mut impl Rng) -> Option<P> {
+    let empty = position.empty_cells();
+    if empty.is_empty() {
+        return None;
+    }
+    let idx = empty[rng.random_range(0..empty.len())];
+    let value = match rng.random_bool(FOUR_PROBABILITY) {
+        true => 4,
+        false => 2,
+    };
+    Some(position.with_tile(idx, value))
+}
+
+/// Expectimax over a learned [`NTupleNetwork`] instead of the handcrafted
+/// heuristics. The network values the board a move leaves, so each move is
+/// worth its points plus that value, averaged over the tiles that may
+/// follow for `depth` more moves. The network only knows classic 4x4
+/// boards; anything else is left to `fallback`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Learned {
+    pub network: &'static NTupleNetwork,
+    /// Moves searched past the first; 0 plays greedily on the network.
+    pub depth: u32,
+    pub fallback: Expectimax,
+}
+
+impl Learned {
+    pub fn new(network: &'static NTupleNetwork, fallback: Expectimax) -> Learned {
+        Learned {
+            network,
+            depth: 1,
+            fallback,
+        }
+    }
+
+    /// Points plus expected value of every direction that changes the board.
+    pub fn rank_moves(&self, board: &Board) -> Vec<(Direction, f64)> {
+        let Some(bits) = BitBoard::from_board(board) else {
+            return self.fallback.rank_moves(board);
+        };
+        self.moves(bits, self.depth).collect()
+    }
+
+    fn moves(&self, bits: BitBoard, depth: u32) -> impl Iterator<Item = (Direction, f64)> {
+        Direction::ALL.into_iter().filter_map(move |dir| {
+            let (next, points) = bits.apply_move(dir);
+            (next != bits).then(|| (dir, points as f64 + self.afterstate_value(next, depth)))
+        })
+    }
+
+    // What the board a move left is worth, before its tile lands.
+    fn afterstate_value(&self, bits: BitBoard, depth: u32) -> f64 {
+        let empty: Vec<usize> = bits.empty_cells().collect();
+        if depth == 0 || empty.is_empty() {
+            return self.network.evaluate(bits) as f64;
+        }
+        let mut total = 0.0;
+        for &idx in &empty {
+            for (exponent, chance) in [(1, 1.0 - FOUR_PROBABILITY), (2, FOUR_PROBABILITY)] {
+                let next = bits.with_exponent(idx, exponent);
+                let best = self
+                    .moves(next, depth - 1)
+                    .map(|(_, value)| value)
+                    .max_by(f64::total_cmp);
+                // No legal move: nothing more is scored.
+                total += chance * best.unwrap_or(0.0);
+            }
+        }
+        total / empty.len() as f64
+    }
+}
+
+/// Whichever AI the settings pick, ready to search.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Solver {
+    Expectimax(Expectimax),
+    MonteCarlo(MonteCarlo),
+    Learned(Learned),
+}
+
+impl Solver {
+    /// How good each direction that changes the board looks, higher being
+    /// better. Each strategy scores on its own scale.
+    pub fn rank_moves(&self, board: &Board) -> Vec<(Direction, f64)> {
+        match self {
+            Solver::Expectimax(search) => search.rank_moves(board),
+            Solver::MonteCarlo(search) => search.rank_moves(board),
+            Solver::Learned(search) => search.rank_moves(board),
+        }
+    }
+
+    pub fn best_move_among(&self, board: &Board, allowed: &[Direction]) -> Option<Direction> {
+        self.rank_moves(board)
+            .into_iter()
+            .filter(|(dir, _)| allowed.contains(dir))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(dir, _)| dir)
+    }
+}
+
+/// The spawner of evil games: instead of placing tiles at random it plays
+/// minimax against the player, putting down whichever tile leaves the
+/// player's best reply worst off.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Adversary {
+    /// Spawns searched ahead, counting the one being placed.
+    pub depth: u32,
+    pub heuristics: Heuristics,
+    /// With a budget the search deepens one spawn at a time up to `depth`
+    /// and stops where the time runs out. The first spawn always completes.
+    pub time_budget: Option<Duration>,
+}
+
+impl Adversary {
+    pub fn new(depth: u32) -> Adversary {
+        Adversary {
+            depth: depth.max(1),
+            heuristics: Heuristics::default(),
+            time_budget: None,
+        }
+    }
+
+    /// The cell and value, out of `values`, that hurt the player most, or
+    /// `None` when the board is full.
+    pub fn worst_spawn(&self, board: &Board, values: &[u32]) -> Option<(usize, u32)> {
+        match BitBoard::from_board(board) {
+            Some(bits) => self.deepen(&bits, values),
+            None => self.deepen(board, values),
+        }
+    }
+
+    fn deepen<P: Position>(&self, position: &P, values: &[u32]) -> Option<(usize, u32)> {
+        let Some(budget) = self.time_budget else {
+            return self.worst_of(position, self.depth, values, None)?;
+        };
+        let at = Instant::now() + budget;
+        let mut worst = None;
+        for depth in 1..=self.depth {
+            match self.worst_of(position, depth, values, (depth > 1).then_some(at)) {
+                Some(spawn) => worst = spawn,
+                None => break,
+            }
+        }
+        worst
+    }
+
+    // The worst spawn searching `depth` spawns ahead, or `None` if the
+    // deadline cut the search short.
+    fn worst_of<P: Position>(
+        &self,
+        position: &P,
+        depth: u32,
+        values: &[u32],
+        deadline: Option<Instant>,
+    ) -> Option<Option<(usize, u32)>> {
+        let mut worst: Option<((usize, u32), f64)> = None;
+        for idx in position.empty_cells() {
+            for &value in values {
+                let next = position.with_tile(idx, value);
+                let score = self.player_value(&next, depth - 1, values, deadline)?;
+                if worst.is_none_or(|(_, least)| score < least) {
+                    worst = Some(((idx, value), score));
+                }
+            }
+        }
+        Some(worst.map(|(spawn, _)| spawn))
+    }
+
+    fn player_value<P: Position>(
+        &self,
+        position: &P,
+        depth: u32,
+        values: &[u32],
+        deadline: Option<Instant>,
+    ) -> Option<f64> {
+        if deadline.is_some_and(|at| Instant::now() >= at) {
+            return None;
+        }
+        // No legal move: the game ends here, which is worse than anything.
+        let stuck = || position.evaluate(&self.heuristics) - 1e6;
+        let replies = Direction::ALL
+            .iter()
+            .filter_map(|&dir| position.after_move(dir));
+        if depth == 0 {
+            return Some(
+                replies
+                    .map(|next| next.evaluate(&self.heuristics))
+                    .max_by(f64::total_cmp)
+                    .unwrap_or_else(stuck),
+            );
+        }
+        let mut best = None;
+        for next in replies {
+            let value = self.spawner_value(&next, depth, values, deadline)?;
+            best = Some(best.map_or(value, |best: f64| best.max(value)));
+        }
+        Some(best.unwrap_or_else(stuck))
+    }
+
+    fn spawner_value<P: Position>(
+        &self,
+        position: &P,
+        depth: u32,
+        values: &[u32],
+        deadline: Option<Instant>,
+    ) -> Option<f64> {
+        let empty = position.empty_cells();
+        if empty.is_empty() {
+            return self.player_value(position, depth - 1, values, deadline);
+        }
+        let mut worst = f64::INFINITY;
+        for idx in empty {
+            for &value in values {
+                let next = position.with_tile(idx, value);
+                worst = worst.min(self.player_value(&next, depth - 1, values, deadline)?);
+            }
+        }
+        Some(worst)
+    }
+}
+
+#[test]
+fn test_avoids_losing_move() {
+    // Only Left and Down are legal, and Down drags the 128 out of its corner.
+    let board = Board::from_cells(
+        4,
+        4,
+        vec![
+            128, 64, 32, 16, //
+            4, 8, 4, 8, //
+            8, 4, 8, 4, //
+            0, 2, 4, 2, //
+        ],
+    );
+    assert_eq!(Expectimax::new(2).best_move(&board), Some(Direction::Left));
+    assert_eq!(Expectimax::new(2).best_move(&Board::new(4, 4)), None);
+}
+
+#[test]
+fn test_bitboard_evaluation() {
+    let board = Board::from_cells(
+        4,
+        4,
+        vec![
+            2, 0, 4, 8, //
+            0, 16, 0, 2, //
+            64, 4, 4, 0, //
+            128, 0, 2, 2, //
+        ],
+    );
+    let bits = BitBoard::from_board(&board).unwrap();
+    let heuristics = Heuristics::default();
+    assert!((heuristics.evaluate(&board) - heuristics.evaluate_bits(bits)).abs() < 1e-9);
+}
+
+#[test]
+fn test_time_budget() {
+    let board = Board::from_cells(
+        4,
+        4,
+        vec![
+            2, 0, 0, 2, //
+            0, 4, 0, 0, //
+            0, 0, 0, 0, //
+            8, 0, 0, 0, //
+        ],
+    );
+    // Out of time, the search still finishes its first ply and goes no
+    // deeper.
+    let hurried = Expectimax::with_time_budget(Duration::ZERO).rank_moves(&board);
+    let one_ply = Expectimax::new(1).rank_moves(&board);
+    assert_eq!(hurried.len(), one_ply.len());
+    for ((dir, value), (expected_dir, expected)) in hurried.into_iter().zip(one_ply) {
+        assert_eq!(dir, expected_dir);
+        // Threads sharing the table can add the same terms in another order.
+        assert!((value - expected).abs() < 1e-9);
+    }
+
+    let search = Expectimax::with_time_budget(Duration::from_millis(50));
+    let started = Instant::now();
+    assert!(search.best_move(&board).is_some());
+    // The pass in flight when the budget runs out is abandoned rather than
+    // run to the end, which would take minutes. The slack is for slow,
+    // busy test machines.
+    assert!(started.elapsed() < Duration::from_secs(10));
+}
+
+#[test]
+fn test_transposition_table() {
+    let board = Board::from_cells(
+        4,
+        4,
+        vec![
+            128, 64, 32, 16, //
+            4, 8, 4, 8, //
+            8, 4, 8, 4, //
+            0, 2, 4, 2, //
+        ],
+    );
+    let cached = Expectimax::new(3);
+    let uncached = Expectimax {
+        cache_mb: 0,
+        ..cached
+    };
+    assert_eq!(cached.best_move(&board), uncached.best_move(&board));
+}
+
+#[test]
+fn test_monte_carlo() {
+    // Only sliding right moves anything.
+    let board = Board::from_cells(
+        4,
+        4,
+        vec![
+            2, 0, 0, 0, //
+            4, 0, 0, 0, //
+            8, 0, 0, 0, //
+            16, 0, 0, 0, //
+        ],
+    );
+    let search = MonteCarlo::new(20);
+    let ranked = search.rank_moves(&board);
+    assert_eq!(ranked.len(), 1);
+    assert_eq!(ranked[0].0, Direction::Right);
+    assert!(ranked[0].1 > 0.0);
+    let solver = Solver::MonteCarlo(search);
+    assert_eq!(
+        solver.best_move_among(&board, &Direction::ALL),
+        Some(Direction::Right)
+    );
+    assert_eq!(solver.best_move_among(&board, &[Direction::Up]), None);
+
+    // Playouts are cut off at the limit.
+    let short = MonteCarlo {
+        max_moves: 3,
+        ..search
+    };
+    let ranked = short.rank_moves(&Board::from_cells(3, 3, vec![2, 0, 0, 0, 0, 0, 0, 0, 0]));
+    assert!(ranked.iter().all(|&(_, moves)| moves <= 3.0));
+}
+
+#[test]
+fn test_learned() {
+    // Merging the pair of 2s scores; the untrained network values every
+    // board at 0, so the points decide.
+    let board = Board::from_cells(
+        4,
+        4,
+        vec![
+            2, 2, 0, 0, //
+            0, 0, 0, 0, //
+            0, 0, 0, 0, //
+            0, 0, 0, 0, //
+        ],
+    );
+    let network: &'static NTupleNetwork = Box::leak(Box::default());
+    let search = Learned::new(network, Expectimax::new(1));
+    let ranked = search.rank_moves(&board);
+    assert_eq!(ranked.len(), 3);
+    let best = ranked.iter().max_by(|a, b| a.1.total_cmp(&b.1));
+    assert!(matches!(
+        best,
+        Some(&(Direction::Left | Direction::Right, _))
+    ));
+    let greedy = Learned { depth: 0, ..search };
+    assert!(
+        greedy
+            .rank_moves(&board)
+            .iter()
+            .all(|&(dir, value)| value == if dir == Direction::Down { 0.0 } else { 4.0 })
+    );
+
+    // Other sizes go to the fallback.
+    let small = Board::from_cells(3, 3, vec![2, 2, 0, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(
+        search.rank_moves(&small),
+        search.fallback.rank_moves(&small)
+    );
+}
+
+#[test]
+fn test_adversary_spawn() {
+    // A 4 in the gap can merge with its neighbours; a 2 leaves no move.
+    let board = Board::from_cells(
+        4,
+        4,
+        vec![
+            2, 4, 2, 4, //
+            4, 2, 4, 2, //
+            2, 4, 2, 4, //
+            4, 2, 4, 0, //
+        ],
+    );
+    let adversary = Adversary::new(1);
+    assert_eq!(adversary.worst_spawn(&board, &[2, 4]), Some((15, 2)));
+    assert_eq!(adversary.worst_spawn(&board, &[4]), Some((15, 4)));
+
+    let mut full = board.clone();
+    full.set_cell(15, 8);
+    assert_eq!(adversary.worst_spawn(&full, &[2, 4]), None);
+    assert!(
+        Adversary::new(2)
+            .worst_spawn(&Board::new(4, 4), &[2, 4])
+            .is_some()
+    );
+
+    // Out of time, it still places the spawn it found looking one ahead.
+    let hurried = Adversary {
+        time_budget: Some(Duration::ZERO),
+        ..Adversary::new(3)
+    };
+    assert_eq!(hurried.worst_spawn(&board, &[2, 4]), Some((15, 2)));
+}
@@ -0,0 +1,206 @@
+use crate::ai::Expectimax;
+use crate::engine::{Board, Direction};
+use crate::replay::Replay;
+
+/// How far the AI looks ahead when going over a finished game. Every move
+/// of the game gets a search, so it is kept shallow.
+pub const ANALYSIS_DEPTH: u32 = 2;
+/// A move whose expected value falls this far short of the best move's, as
+/// a share of it, is a blunder.
+pub const BLUNDER_LOSS: f64 = 0.25;
+
+/// What a finished game came to.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameSummary {
+    pub board: Board,
+    pub score: u32,
+    pub moves: u32,
+    /// Time spent playing, leaving out pauses.
+    pub millis: u64,
+    /// The biggest tile a single merge made.
+    pub biggest_merge: u32,
+}
+
+impl GameSummary {
+    pub fn max_tile(&self) -> u32 {
+        self.board.max_tile()
+    }
+}
+
+/// How many of a game's moves went another way than the AI would have.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Divergence {
+    pub diverged: u32,
+    /// Moves compared; those right after a power-up are left out, as the
+    /// recorded positions don't show the board the power-up left.
+    pub checked: u32,
+}
+
+/// A move that gave away much of what the position was worth.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Blunder {
+    /// Which move of the game, from 0.
+    pub index: usize,
+    pub made: Direction,
+    pub best: Direction,
+    /// How far short of the best move's expected value it fell, as a share.
+    pub loss: f64,
+}
+
+/// Plays `replay` through, asking `search` for its move before each one
+/// that was made.
+pub fn divergence(replay: &Replay, search: &Expectimax) -> Divergence {
+    let mut result = Divergence::default();
+    for (_, made, ranked) in rankings(replay, search) {
+        let Some(&(best, _)) = ranked.iter().max_by(|a, b| a.1.total_cmp(&b.1)) else {
+            continue;
+        };
+        result.checked += 1;
+        result.diverged += (best != made) as u32;
+    }
+    result
+}
+
+/// Every move of `replay` that `search` judges a blunder, in order.
+pub fn blunders(replay: &Replay, search: &Expectimax) -> Vec<Blunder> {
+    rankings(replay, search)
+        .filter_map(|(index, made, ranked)| {
+            let (best, loss) = judge(&ranked, made)?;
+            (loss >= BLUNDER_LOSS).then_some(Blunder {
+                index,
+                made,
+                best,
+                loss,
+            })
+        })
+        .collect()
+}
+
+/// How long to draw each ranked move's bar, from 0.0 to 1.0: the best move
+/// gets a full bar and the worst a stub, with the rest spread between by
+/// value.
+pub fn bar_lengths(ranked: &[(Direction, f64)]) -> Vec<(Direction, f32)> {
+    let values = ranked.iter().map(|&(_, value)| value);
+    let best = values.clone().fold(f64::NEG_INFINITY, f64::max);
+    let worst = values.fold(f64::INFINITY, f64::min);
+    ranked
+        .iter()
+        .map(|&(dir, value)| {
+            let share = match best > worst {
+                true => (value - worst) / (best - worst),
+                false => 1.0,
+            };
+            (dir, (0.15 + 0.85 * share) as f32)
+        })
+        .collect()
+}
+
+/// The best of `ranked` moves and how far short of it `made` fell, as a
+/// share of its value. Values can be negative, so the share is taken of
+/// their size, and of at least 1.
+pub fn judge(ranked: &[(Direction, f64)], made: Direction) -> Option<(Direction, f64)> {
+    let &(best, best_value) = ranked.iter().max_by(|a, b| a.1.total_cmp(&b.1))?;
+    let &(_, value) = ranked.iter().find(|&&(dir, _)| dir == made)?;
+    Some((best, (best_value - value) / best_value.abs().max(1.0)))
+}
+
+// The AI's ranking of the moves open before each move of `replay`, with the
+// move's number and the direction made. Moves right after a power-up are
+// left out, as the recorded positions don't show the board it left.
+fn rankings<'a>(
+    replay: &'a Replay,
+    search: &'a Expectimax,
+) -> impl Iterator<Item = (usize, Direction, Vec<(Direction, f64)>)> + 'a {
+    let positions = replay.positions();
+    replay
+        .moves
+        .iter()
+        .enumerate()
+        .filter(|&(i, _)| replay.power_ups.iter().all(|&(at, _)| at != i))
+        .map(move |(i, made)| (i, made.direction, search.rank_moves(&positions[i].0)))
+}
+
+#[test]
+fn test_divergence() {
+    use crate::engine::SpawnPolicy;
+    use crate::replay::Spawn;
+
+    // Only sliding right moves anything.
+    let start = Board::from_cells(
+        4,
+        4,
+        vec![
+            2, 0, 0, 0, //
+            4, 0, 0, 0, //
+            8, 0, 0, 0, //
+            16, 0, 0, 0, //
+        ],
+    );
+    let mut replay = Replay::new(1, SpawnPolicy::CLASSIC, start);
+    let spawn = Spawn {
+        index: 0,
+        value: 2,
+        special: None,
+    };
+    replay.record(Direction::Right, vec![spawn], 100);
+    let search = Expectimax::new(1);
+    assert_eq!(
+        divergence(&replay, &search),
+        Divergence {
+            diverged: 0,
+            checked: 1,
+        }
+    );
+
+    let empty = Replay::new(1, SpawnPolicy::CLASSIC, Board::new(4, 4));
+    assert_eq!(divergence(&empty, &search), Divergence::default());
+    assert!(blunders(&replay, &search).is_empty());
+}
+
+#[test]
+fn test_bar_lengths() {
+    let ranked = [
+        (Direction::Up, 50.0),
+        (Direction::Left, 100.0),
+        (Direction::Right, 0.0),
+    ];
+    assert_eq!(
+        bar_lengths(&ranked),
+        [
+            (Direction::Up, 0.575),
+            (Direction::Left, 1.0),
+            (Direction::Right, 0.15),
+        ]
+    );
+    assert_eq!(
+        bar_lengths(&[(Direction::Down, -3.0)]),
+        [(Direction::Down, 1.0)]
+    );
+    assert!(bar_lengths(&[]).is_empty());
+}
+
+#[test]
+fn test_judge() {
+    let ranked = [
+        (Direction::Up, 80.0),
+        (Direction::Left, 100.0),
+        (Direction::Right, 60.0),
+    ];
+    assert_eq!(
+        judge(&ranked, Direction::Left),
+        Some((Direction::Left, 0.0))
+    );
+    assert_eq!(judge(&ranked, Direction::Up), Some((Direction::Left, 0.2)));
+    assert_eq!(
+        judge(&ranked, Direction::Right),
+        Some((Direction::Left, 0.4))
+    );
+    // A move that slides nothing isn't ranked.
+    assert_eq!(judge(&ranked, Direction::Down), None);
+    assert_eq!(judge(&[], Direction::Down), None);
+    let negative = [(Direction::Up, -10.0), (Direction::Down, -15.0)];
+    assert_eq!(
+        judge(&negative, Direction::Down),
+        Some((Direction::Up, 0.5))
+    );
+}
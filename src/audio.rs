@@ -0,0 +1,326 @@
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "gui")]
+use std::time::Duration;
+
+// The music that plays when the music directory has none, calmest first:
+// chords, each a little higher and fuller than the one before.
+#[cfg(feature = "gui")]
+const BUILTIN_TRACKS: [&[f32]; 3] = [
+    &[110.0, 164.8, 220.0],
+    &[130.8, 196.0, 261.6, 329.6],
+    &[146.8, 220.0, 293.7, 370.0, 440.0],
+];
+#[cfg(feature = "gui")]
+const CROSSFADE: Duration = Duration::from_secs(2);
+#[cfg(feature = "gui")]
+const FADE_STEP: Duration = Duration::from_millis(50);
+
+/// Something that happens in a game with a sound to it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sound {
+    Slide,
+    Merge,
+    Spawn,
+    Win,
+    GameOver,
+    /// A move that can't be made.
+    Blocked,
+}
+
+impl Sound {
+    pub const ALL: [Sound; 6] = [
+        Sound::Slide,
+        Sound::Merge,
+        Sound::Spawn,
+        Sound::Win,
+        Sound::GameOver,
+        Sound::Blocked,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Sound::Slide => "Slide",
+            Sound::Merge => "Merge",
+            Sound::Spawn => "Spawn",
+            Sound::Win => "Win",
+            Sound::GameOver => "Game over",
+            Sound::Blocked => "Blocked move",
+        }
+    }
+
+    /// The tones the sound is made of: (pitch in Hz, start and length in
+    /// milliseconds). A spawn waits for the slide before it to finish.
+    pub fn notes(self) -> &'static [(f32, u64, u64)] {
+        match self {
+            Sound::Slide => &[(220.0, 0, 40)],
+            Sound::Merge => &[(440.0, 0, 50), (660.0, 50, 70)],
+            Sound::Spawn => &[(880.0, 100, 30)],
+            Sound::Win => &[
+                (523.3, 0, 120),
+                (659.3, 120, 120),
+                (784.0, 240, 120),
+                (1046.5, 360, 300),
+            ],
+            Sound::GameOver => &[(392.0, 0, 180), (329.6, 180, 180), (261.6, 360, 400)],
+            Sound::Blocked => &[(82.4, 0, 70)],
+        }
+    }
+}
+
+/// How loud each sound plays, from 0.0 (silent) to 1.0.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Volumes {
+    pub slide: f32,
+    pub merge: f32,
+    pub spawn: f32,
+    pub win: f32,
+    pub game_over: f32,
+    pub blocked: f32,
+}
+
+impl Default for Volumes {
+    fn default() -> Self {
+        Volumes {
+            slide: 0.5,
+            merge: 1.0,
+            spawn: 0.5,
+            win: 1.0,
+            game_over: 1.0,
+            blocked: 0.5,
+        }
+    }
+}
+
+impl Volumes {
+    pub fn get(&self, sound: Sound) -> f32 {
+        match sound {
+            Sound::Slide => self.slide,
+            Sound::Merge => self.merge,
+            Sound::Spawn => self.spawn,
+            Sound::Win => self.win,
+            Sound::GameOver => self.game_over,
+            Sound::Blocked => self.blocked,
+        }
+        .clamp(0.0, 1.0)
+    }
+
+    pub fn set(&mut self, sound: Sound, volume: f32) {
+        *match sound {
+            Sound::Slide => &mut self.slide,
+            Sound::Merge => &mut self.merge,
+            Sound::Spawn => &mut self.spawn,
+            Sound::Win => &mut self.win,
+            Sound::GameOver => &mut self.game_over,
+            Sound::Blocked => &mut self.blocked,
+        } = volume;
+    }
+}
+
+/// Plays sounds on a thread of its own. Opening the output device can take
+/// a while, so it happens there rather than on the UI thread; sounds asked
+/// for in the meantime wait for it, and with no device at all they are
+/// dropped.
+#[cfg(feature = "gui")]
+pub struct Audio {
+    sender: std::sync::mpsc::Sender<(Sound, f32)>,
+}
+
+#[cfg(feature = "gui")]
+impl Audio {
+    // Full volume is kept well short of clipping.
+    const LEVEL: f32 = 0.2;
+
+    pub fn new() -> Audio {
+        let (sender, receiver) = std::sync::mpsc::channel::<(Sound, f32)>();
+        std::thread::spawn(move || {
+            use rodio::Source;
+            use rodio::source::SineWave;
+
+            let (_stream, handle) = match rodio::OutputStream::try_default() {
+                Ok(output) => output,
+                Err(e) => {
+                    eprintln!("Sound is unavailable: {e}");
+                    return;
+                }
+            };
+            // Ends once the `Audio` is dropped.
+            for (sound, volume) in receiver {
+                for &(pitch, start, length) in sound.notes() {
+                    let mut note =
+                        SineWave::new(pitch).take_duration(Duration::from_millis(length));
+                    // Fading each note out keeps it from clicking at the end.
+                    note.set_filter_fadeout();
+                    let note = note
+                        .amplify(volume * Audio::LEVEL)
+                        .delay(Duration::from_millis(start));
+                    handle.play_raw(note).ok();
+                }
+            }
+        });
+        Audio { sender }
+    }
+
+    pub fn play(&self, sound: Sound, volume: f32) {
+        if volume > 0.0 {
+            self.sender.send((sound, volume)).ok();
+        }
+    }
+}
+
+/// How worked up the music gets over a game whose biggest tile is `rank`
+/// steps up: from 0.0 on a fresh board to 1.0 at the winning tile's
+/// `win_rank` and beyond.
+pub fn intensity(rank: u32, win_rank: u32) -> f32 {
+    (rank as f32 / win_rank.max(1) as f32).clamp(0.0, 1.0)
+}
+
+/// Which of `count` tracks, calmest first, goes with `intensity`.
+pub fn track_for(intensity: f32, count: usize) -> usize {
+    ((intensity * count as f32) as usize).min(count.saturating_sub(1))
+}
+
+/// Loops background music on a thread of its own, crossfading whenever the
+/// track changes. As the game heats up it moves on to livelier tracks and
+/// plays a little louder.
+#[cfg(feature = "gui")]
+pub struct Music {
+    sender: std::sync::mpsc::Sender<(f32, f32)>,
+    // The volume and intensity last sent, so unchanged ones aren't.
+    last: (f32, f32),
+}
+
+#[cfg(feature = "gui")]
+impl Music {
+    pub fn new() -> Music {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        std::thread::spawn(move || Music::run(receiver));
+        Music {
+            sender,
+            last: (0.0, 0.0),
+        }
+    }
+
+    /// Plays at `volume` the track that goes with `intensity`; a volume of 0
+    /// fades the music out.
+    pub fn set(&mut self, volume: f32, intensity: f32) {
+        if (volume, intensity) != self.last {
+            self.last = (volume, intensity);
+            self.sender.send(self.last).ok();
+        }
+    }
+
+    fn run(receiver: std::sync::mpsc::Receiver<(f32, f32)>) {
+        use std::sync::mpsc::RecvTimeoutError;
+
+        let mut tracks = crate::persistence::music_tracks();
+        let (_stream, handle) = match rodio::OutputStream::try_default() {
+            Ok(output) => output,
+            Err(e) => {
+                eprintln!("Music is unavailable: {e}");
+                return;
+            }
+        };
+        let (mut volume, mut intensity) = (0.0, 0.0);
+        let mut playing: Option<(usize, rodio::Sink)> = None;
+        let mut fading: Vec<rodio::Sink> = Vec::new();
+        let step = FADE_STEP.as_secs_f32() / CROSSFADE.as_secs_f32();
+        loop {
+            match receiver.recv_timeout(FADE_STEP) {
+                Ok(set) => (volume, intensity) = set,
+                Err(RecvTimeoutError::Timeout) => {}
+                // The `Music` was dropped.
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+            let count = match tracks.is_empty() {
+                true => BUILTIN_TRACKS.len(),
+                false => tracks.len(),
+            };
+            let wanted = (volume > 0.0).then(|| track_for(intensity, count));
+            if wanted != playing.as_ref().map(|&(track, _)| track) {
+                fading.extend(playing.take().map(|(_, sink)| sink));
+                if let Some(track) = wanted {
+                    match Music::open(&handle, &tracks, track) {
+                        Ok(sink) => playing = Some((track, sink)),
+                        Err(e) if track < tracks.len() => {
+                            // Skip it from now on and try the next one.
+                            eprintln!("Couldn't play {}: {e}", tracks[track].display());
+                            tracks.remove(track);
+                        }
+                        Err(e) => {
+                            eprintln!("Music is unavailable: {e}");
+                            return;
+                        }
+                    }
+                }
+            }
+
+            fading.retain(|sink| {
+                let faded = sink.volume() - step;
+                sink.set_volume(faded.max(0.0));
+                faded > 0.0
+            });
+            if let Some((_, sink)) = &playing {
+                let target = volume * (0.6 + 0.4 * intensity);
+                let current = sink.volume();
+                sink.set_volume(match current < target {
+                    true => (current + step).min(target),
+                    false => (current - step).max(target),
+                });
+            }
+        }
+    }
+
+    // Starts `track` looping, silent until it is faded in. Only files can
+    // fail to open.
+    fn open(
+        handle: &rodio::OutputStreamHandle,
+        tracks: &[std::path::PathBuf],
+        track: usize,
+    ) -> Result<rodio::Sink, String> {
+        use rodio::Source;
+        use rodio::source::{SineWave, Zero};
+
+        let sink = rodio::Sink::try_new(handle).map_err(|e| e.to_string())?;
+        sink.set_volume(0.0);
+        match tracks.get(track) {
+            Some(path) => {
+                let file = std::fs::File::open(path).map_err(|e| e.to_string())?;
+                let decoder = rodio::Decoder::new(std::io::BufReader::new(file))
+                    .map_err(|e| e.to_string())?;
+                sink.append(decoder.repeat_infinite());
+            }
+            None => {
+                let pitches = BUILTIN_TRACKS[track];
+                let silence: Box<dyn Source<Item = f32> + Send> = Box::new(Zero::new(1, 48000));
+                let chord = pitches.iter().fold(silence, |chord, &pitch| {
+                    let voice = SineWave::new(pitch).amplify(0.3 / pitches.len() as f32);
+                    Box::new(chord.mix(voice))
+                });
+                sink.append(chord);
+            }
+        }
+        Ok(sink)
+    }
+}
+
+#[test]
+fn test_intensity() {
+    assert_eq!(intensity(0, 11), 0.0);
+    assert_eq!(intensity(14, 11), 1.0);
+    assert_eq!(track_for(0.0, 3), 0);
+    assert_eq!(track_for(0.5, 3), 1);
+    assert_eq!(track_for(1.0, 3), 2);
+    assert_eq!(track_for(0.9, 1), 0);
+}
+
+#[test]
+fn test_volumes() {
+    let mut volumes = Volumes::default();
+    volumes.set(Sound::Spawn, 0.0);
+    volumes.set(Sound::Win, 3.0);
+    assert_eq!(volumes.get(Sound::Spawn), 0.0);
+    assert_eq!(volumes.get(Sound::Win), 1.0);
+    assert_eq!(volumes.get(Sound::Merge), 1.0);
+    assert!(Sound::ALL.iter().all(|sound| !sound.notes().is_empty()));
+}
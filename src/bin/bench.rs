@@ -0,0 +1,122 @@
+// Plays the AI, or random moves, through many games with no window and
+// reports how fast and how well it went. Default settings are used rather
+// than the player's, so runs on different machines compare.
+use game_2048::ai::Strategy;
+use game_2048::engine::{Direction, GameConfig, MAX_SIZE, MIN_SIZE, parse_size, seeded_rng};
+use game_2048::selfplay::{Benchmark, play_game, random_move};
+use game_2048::settings::Settings;
+use std::env;
+use std::time::Instant;
+
+// Moves picked at random instead of by an AI, which times the engine alone.
+const RANDOM: &str = "random";
+
+struct Args {
+    games: u32,
+    config: GameConfig,
+    // `None` plays random moves.
+    strategy: Option<Strategy>,
+    depth: Option<u32>,
+}
+
+fn parse_strategy(value: &str) -> Option<Option<Strategy>> {
+    match value {
+        RANDOM => Some(None),
+        "expectimax" => Some(Some(Strategy::Expectimax)),
+        "monte-carlo" => Some(Some(Strategy::MonteCarlo)),
+        "learned" => Some(Some(Strategy::Learned)),
+        _ => None,
+    }
+}
+
+fn parse_args() -> Args {
+    let mut parsed = Args {
+        games: 100,
+        config: GameConfig::default(),
+        strategy: Some(Strategy::Expectimax),
+        depth: None,
+    };
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--games" => match args.next().and_then(|v| v.parse().ok()) {
+                Some(games) => parsed.games = games,
+                None => eprintln!("--games expects a number"),
+            },
+            "--size" => match args.next().as_deref().and_then(parse_size) {
+                Some((rows, cols)) => {
+                    parsed.config.rows = rows;
+                    parsed.config.cols = cols;
+                }
+                None => eprintln!(
+                    "--size expects N or ROWSxCOLS with each side between {MIN_SIZE} and {MAX_SIZE}"
+                ),
+            },
+            "--seed" => match args.next().and_then(|v| v.parse().ok()) {
+                Some(seed) => parsed.config.seed = Some(seed),
+                None => eprintln!("--seed expects an unsigned 64-bit number"),
+            },
+            "--strategy" => match args.next().as_deref().and_then(parse_strategy) {
+                Some(strategy) => parsed.strategy = strategy,
+                None => {
+                    eprintln!("--strategy expects random, expectimax, monte-carlo or learned")
+                }
+            },
+            "--depth" => match args.next().and_then(|v| v.parse().ok()) {
+                Some(depth) => parsed.depth = Some(depth),
+                None => eprintln!("--depth expects a number"),
+            },
+            _ => eprintln!("unknown argument: {arg}"),
+        }
+    }
+    parsed
+}
+
+fn main() {
+    let args = parse_args();
+    let mut settings = Settings::default();
+    if let Some(strategy) = args.strategy {
+        settings.ai_strategy = strategy;
+    }
+    if let Some(depth) = args.depth {
+        settings.ai_depth = depth;
+        settings.ai_time_budget_ms = 0;
+    }
+    let solver = settings.solver();
+    let seed = args.config.seed.unwrap_or_else(rand::random);
+    let mut spawns = seeded_rng(seed);
+    let mut moves = seeded_rng(seed.wrapping_add(1));
+
+    let mut benchmark = Benchmark::default();
+    for _ in 0..args.games {
+        let start = Instant::now();
+        let game = play_game(&args.config, &mut spawns, |board| match args.strategy {
+            Some(_) => solver.best_move_among(board, &Direction::ALL),
+            None => random_move(board, &mut moves),
+        });
+        benchmark.record(game, start.elapsed());
+    }
+
+    let player = args.strategy.map_or(RANDOM, Strategy::name);
+    println!(
+        "{} games of {} on {}x{}, seed {seed}, in {:.1}s",
+        benchmark.games,
+        player,
+        args.config.rows,
+        args.config.cols,
+        benchmark.elapsed.as_secs_f64()
+    );
+    println!("moves/second:  {:.0}", benchmark.moves_per_second());
+    println!(
+        "average score: {:.0} (best {})",
+        benchmark.average_score(),
+        benchmark.best_score
+    );
+    println!("max tile:");
+    for (&tile, &count) in &benchmark.max_tiles {
+        println!(
+            "  {tile:>6}  {count:>5}  reached by {:>5.1}%",
+            benchmark.reached(tile) * 100.0
+        );
+    }
+}
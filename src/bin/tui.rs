@@ -0,0 +1,216 @@
+// The game in a terminal, on the same engine, settings, best scores and
+// saved game as the window. Build it without the window's dependencies:
+// `cargo run --no-default-features --features tui --bin game_2048_tui`.
+// `--portable` keeps its data beside the executable, as for the window.
+use game_2048::engine::{Board, Cell, Direction, GameConfig, GameMode};
+use game_2048::headless::TextGame;
+use game_2048::persistence;
+use game_2048::settings;
+use game_2048::theme::Theme;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Alignment, Constraint, Layout, Rect};
+use ratatui::style::{Color, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use std::io;
+
+// Each tile's size in terminal cells, and the gaps around it. Terminal cells
+// are about twice as tall as they are wide.
+const CELL_WIDTH: u16 = 8;
+const CELL_HEIGHT: u16 = 3;
+const GAP_X: u16 = 2;
+const GAP_Y: u16 = 1;
+const HELP: &str = "arrows, wasd or hjkl move · n new game · q quit";
+
+fn color(rgb: u32) -> Color {
+    Color::Rgb((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8)
+}
+
+struct App {
+    config: GameConfig,
+    theme: Theme,
+    game: TextGame,
+    best_score: u32,
+    message: Option<String>,
+}
+
+impl App {
+    fn new() -> App {
+        let settings = settings::load();
+        // Timed, limited and daily games need the window's clocks and rules.
+        let config = GameConfig {
+            mode: GameMode::Classic,
+            ..settings.game_config()
+        };
+        let game = match persistence::load_game() {
+            Some(saved) if saved.mode == GameMode::Classic => TextGame::resume(&config, saved),
+            _ => App::fresh_game(&config),
+        };
+        App {
+            config,
+            theme: settings.theme(),
+            game,
+            best_score: persistence::load_best_score(config.spawn.difficulty()),
+            message: None,
+        }
+    }
+
+    fn fresh_game(config: &GameConfig) -> TextGame {
+        TextGame::new(config, config.seed.unwrap_or_else(rand::random))
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            let dir = match key.code {
+                KeyCode::Up | KeyCode::Char('w' | 'k') => Direction::Up,
+                KeyCode::Down | KeyCode::Char('s' | 'j') => Direction::Down,
+                KeyCode::Left | KeyCode::Char('a' | 'h') => Direction::Left,
+                KeyCode::Right | KeyCode::Char('d' | 'l') => Direction::Right,
+                KeyCode::Char('n') => {
+                    self.game = App::fresh_game(&self.config);
+                    self.message = None;
+                    persistence::clear_saved_game();
+                    continue;
+                }
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                _ => continue,
+            };
+            self.slide(dir);
+        }
+    }
+
+    fn slide(&mut self, dir: Direction) {
+        if self.game.is_over() || !self.game.slide(dir).moved {
+            return;
+        }
+        self.message = None;
+        if self.game.score() > self.best_score {
+            self.best_score = self.game.score();
+            let saved =
+                persistence::save_best_score(self.config.spawn.difficulty(), self.best_score);
+            self.check_saved("save best score", saved);
+        }
+        if self.game.has_won() {
+            self.game.keep_playing();
+            self.message = Some("You win! Keep going for a bigger tile.".to_string());
+        }
+        match self.game.is_over() {
+            true => {
+                self.message = Some("Game over. Press n for a new game.".to_string());
+                persistence::clear_saved_game();
+            }
+            false => {
+                let saved = persistence::save_game(&self.game.to_saved());
+                self.check_saved("save the game", saved);
+            }
+        }
+    }
+
+    // A failed save takes over the footer; the game goes on regardless.
+    fn check_saved(&mut self, what: &str, result: io::Result<()>) {
+        if let Err(err) = result {
+            self.message = Some(persistence::failure_message(what, &err));
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [header, body, footer] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let background = Style::new()
+            .bg(color(self.theme.background))
+            .fg(color(self.theme.text));
+        frame.render_widget(Block::new().style(background), frame.area());
+
+        let score = format!(
+            "2048   score {}   best {}",
+            self.game.score(),
+            self.best_score
+        );
+        frame.render_widget(
+            Paragraph::new(Line::from(score).bold()).alignment(Alignment::Center),
+            header,
+        );
+        self.draw_board(frame, body);
+        frame.render_widget(
+            Paragraph::new(self.message.as_deref().unwrap_or(HELP)).alignment(Alignment::Center),
+            footer,
+        );
+    }
+
+    fn draw_board(&self, frame: &mut Frame, area: Rect) {
+        let board = self.game.board();
+        let (rows, cols) = (board.rows() as u16, board.cols() as u16);
+        let width = cols * (CELL_WIDTH + GAP_X) + GAP_X;
+        let height = rows * (CELL_HEIGHT + GAP_Y) + GAP_Y;
+        let x = area.x + area.width.saturating_sub(width) / 2;
+        let y = area.y + area.height.saturating_sub(height) / 2;
+        let grid = Rect::new(x, y, width, height).intersection(area);
+        frame.render_widget(
+            Block::new().style(Style::new().bg(color(self.theme.grid))),
+            grid,
+        );
+
+        for idx in 0..board.rows() * board.cols() {
+            let (row, col) = ((idx / board.cols()) as u16, (idx % board.cols()) as u16);
+            let cell = Rect::new(
+                x + GAP_X + col * (CELL_WIDTH + GAP_X),
+                y + GAP_Y + row * (CELL_HEIGHT + GAP_Y),
+                CELL_WIDTH,
+                CELL_HEIGHT,
+            )
+            .intersection(area);
+            let label = board.cell_label(idx).unwrap_or_default();
+            frame.render_widget(
+                Paragraph::new(vec![Line::default(), Line::from(label)])
+                    .alignment(Alignment::Center)
+                    .style(self.cell_style(board, idx).bold()),
+                cell,
+            );
+        }
+    }
+
+    fn cell_style(&self, board: &Board, idx: usize) -> Style {
+        let theme = &self.theme;
+        let (bg, fg) = if board.is_hole(idx) {
+            (theme.grid, theme.grid)
+        } else if board.is_obstacle(idx) {
+            (theme.obstacle, theme.grid_text)
+        } else {
+            match board.cell(idx) {
+                Cell::Empty => (theme.empty_cell, theme.text),
+                Cell::Number(value) => {
+                    let rank = board.rule().rank(value);
+                    // Themes that work tile colors out from the rank have
+                    // none listed; the classic ones stand in for them.
+                    let tile = theme.tile(rank).or(Theme::classic().tile(rank));
+                    (tile.unwrap_or(theme.empty_cell), theme.tile_text(rank))
+                }
+                Cell::Special(_) => (theme.accent, theme.button_text),
+            }
+        };
+        Style::new().bg(color(bg)).fg(color(fg))
+    }
+}
+
+fn main() -> io::Result<()> {
+    if std::env::args().any(|arg| arg == "--portable") {
+        persistence::set_portable();
+    }
+    let mut app = App::new();
+    let mut terminal = ratatui::try_init()?;
+    let result = app.run(&mut terminal);
+    ratatui::restore();
+    result
+}
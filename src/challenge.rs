@@ -0,0 +1,318 @@
+use crate::engine::{Board, Direction, MAX_SIZE, MIN_SIZE};
+use serde::{Deserialize, Serialize};
+
+/// A set of puzzles shared as one JSON file:
+///
+/// ```json
+/// {
+///   "name": "Starter Pack",
+///   "author": "someone",
+///   "challenges": [
+///     {
+///       "name": "Left Only",
+///       "description": "Reach 64 without ever moving right.",
+///       "board": [[2, 2, 4, 8], [0, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0]],
+///       "allowed_moves": ["up", "down", "left"],
+///       "target_tile": 64,
+///       "move_limit": 40
+///     }
+///   ]
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ChallengePack {
+    pub name: String,
+    #[serde(default)]
+    pub author: String,
+    pub challenges: Vec<Challenge>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Challenge {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// The starting position, one list of tile values per row, 0 for empty.
+    pub board: Vec<Vec<u32>>,
+    #[serde(default = "all_moves")]
+    pub allowed_moves: Vec<Direction>,
+    #[serde(default)]
+    pub target_tile: Option<u32>,
+    #[serde(default)]
+    pub target_score: Option<u32>,
+    #[serde(default)]
+    pub move_limit: Option<u32>,
+    /// Fixes the spawns so every attempt plays out the same way.
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+fn all_moves() -> Vec<Direction> {
+    Direction::ALL.to_vec()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChallengeStatus {
+    InProgress,
+    Solved,
+    Failed,
+}
+
+impl ChallengePack {
+    /// Reads a pack, rejecting any challenge that can't be played. The error
+    /// says what is wrong, for whoever is writing the file.
+    pub fn parse(json: &str) -> Result<ChallengePack, String> {
+        let pack: ChallengePack = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        if pack.challenges.is_empty() {
+            return Err("the pack has no challenges".to_string());
+        }
+        for challenge in &pack.challenges {
+            challenge
+                .validate()
+                .map_err(|e| format!("challenge \"{}\": {e}", challenge.name))?;
+        }
+        Ok(pack)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+}
+
+impl Challenge {
+    fn validate(&self) -> Result<(), String> {
+        let rows = self.board.len();
+        let cols = self.board.first().map_or(0, Vec::len);
+        let sizes = MIN_SIZE..=MAX_SIZE;
+        if !sizes.contains(&rows) || !sizes.contains(&cols) {
+            return Err(format!(
+                "the board must have between {MIN_SIZE} and {MAX_SIZE} rows and columns"
+            ));
+        }
+        if self.board.iter().any(|row| row.len() != cols) {
+            return Err("every row of the board must be the same length".to_string());
+        }
+        let tiles = self.board.iter().flatten();
+        if let Some(bad) = tiles
+            .copied()
+            .find(|&v| v == 1 || !(v == 0 || v.is_power_of_two()))
+        {
+            return Err(format!("{bad} is not a tile value"));
+        }
+        if self.allowed_moves.is_empty() {
+            return Err("at least one move must be allowed".to_string());
+        }
+        if self.target_tile.is_none() && self.target_score.is_none() {
+            return Err("a target tile or target score is required".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn start_board(&self) -> Board {
+        let rows = self.board.len();
+        let cols = self.board.first().map_or(0, Vec::len);
+        Board::from_cells(rows, cols, self.board.concat())
+    }
+
+    /// A short line for lists, such as "Reach 256 in 30 moves".
+    pub fn goal(&self) -> String {
+        let target = match (self.target_tile, self.target_score) {
+            (Some(tile), Some(score)) => format!("Reach {tile} and {score} points"),
+            (Some(tile), None) => format!("Reach {tile}"),
+            (None, Some(score)) => format!("Score {score}"),
+            (None, None) => "Keep playing".to_string(),
+        };
+        match self.move_limit {
+            Some(1) => format!("{target} in 1 move"),
+            Some(limit) => format!("{target} in {limit} moves"),
+            None => target,
+        }
+    }
+
+    /// Whether the challenge is won, lost or still open after `moves` moves.
+    pub fn status(&self, board: &Board, score: u32, moves: u32) -> ChallengeStatus {
+        let tile_reached = self.target_tile.is_none_or(|t| board.max_tile() >= t);
+        let score_reached = self.target_score.is_none_or(|s| score >= s);
+        if tile_reached && score_reached {
+            return ChallengeStatus::Solved;
+        }
+        let stuck = !self.allowed_moves.iter().any(|&dir| board.can_move(dir));
+        if stuck || self.move_limit.is_some_and(|limit| moves >= limit) {
+            ChallengeStatus::Failed
+        } else {
+            ChallengeStatus::InProgress
+        }
+    }
+}
+
+/// Late-game positions that ship with the game, for practicing endgames.
+pub fn practice_pack() -> ChallengePack {
+    use Direction::{Down, Left, Right, Up};
+    let challenge = |name: &str, description: &str, board: [[u32; 4]; 4]| Challenge {
+        name: name.to_string(),
+        description: description.to_string(),
+        board: board.map(Vec::from).to_vec(),
+        allowed_moves: Direction::ALL.to_vec(),
+        target_tile: None,
+        target_score: None,
+        move_limit: None,
+        seed: None,
+    };
+    ChallengePack {
+        name: "Practice".to_string(),
+        author: String::new(),
+        challenges: vec![
+            Challenge {
+                target_tile: Some(2048),
+                move_limit: Some(1),
+                ..challenge(
+                    "One Merge from 2048",
+                    "Two 1024s side by side. Don't overthink it.",
+                    [[1024, 1024, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 2]],
+                )
+            },
+            Challenge {
+                target_tile: Some(2048),
+                move_limit: Some(8),
+                ..challenge(
+                    "Chain Reaction",
+                    "Fold the top row into itself, one merge at a time.",
+                    [
+                        [1024, 512, 256, 128],
+                        [0, 0, 0, 128],
+                        [0, 0, 0, 0],
+                        [0, 0, 0, 2],
+                    ],
+                )
+            },
+            Challenge {
+                target_score: Some(200),
+                ..challenge(
+                    "Escape the Trapped Corner",
+                    "One gap left and the corner is boxed in. Find room to breathe.",
+                    [
+                        [1024, 512, 256, 8],
+                        [2, 4, 8, 16],
+                        [4, 8, 16, 32],
+                        [2, 4, 2, 0],
+                    ],
+                )
+            },
+            Challenge {
+                allowed_moves: vec![Down, Left, Right],
+                target_tile: Some(256),
+                ..challenge(
+                    "No Going Up",
+                    "Keep the big tiles at the bottom without ever moving up.",
+                    [[0, 0, 0, 0], [2, 0, 0, 4], [8, 16, 4, 2], [128, 64, 32, 8]],
+                )
+            },
+            Challenge {
+                allowed_moves: vec![Up, Left],
+                target_tile: Some(128),
+                move_limit: Some(40),
+                ..challenge(
+                    "Two Directions",
+                    "Only up and left. Make every move count.",
+                    [[2, 16, 4, 0], [64, 0, 2, 4], [0, 8, 0, 0], [0, 0, 0, 2]],
+                )
+            },
+            Challenge {
+                target_score: Some(3000),
+                move_limit: Some(120),
+                ..challenge(
+                    "Snake Run",
+                    "A tidy snake. Keep it tidy while the points roll in.",
+                    [
+                        [512, 256, 128, 64],
+                        [4, 8, 16, 32],
+                        [2, 0, 0, 0],
+                        [0, 0, 0, 0],
+                    ],
+                )
+            },
+        ],
+    }
+}
+
+#[cfg(test)]
+const TEST_PACK: &str = r#"{
+    "name": "Test Pack",
+    "challenges": [
+        {
+            "name": "One Merge",
+            "board": [[8, 8, 0], [0, 0, 0], [0, 0, 2]],
+            "allowed_moves": ["left", "right"],
+            "target_tile": 16,
+            "move_limit": 1
+        }
+    ]
+}"#;
+
+#[test]
+fn test_parse_pack() {
+    let pack = ChallengePack::parse(TEST_PACK).unwrap();
+    let challenge = &pack.challenges[0];
+    assert_eq!(
+        challenge.allowed_moves,
+        vec![Direction::Left, Direction::Right]
+    );
+    assert_eq!(challenge.start_board().cells()[..2], [8, 8]);
+    assert_eq!(challenge.goal(), "Reach 16 in 1 move");
+    assert_eq!(ChallengePack::parse(&pack.to_json()), Ok(pack));
+
+    let ragged = TEST_PACK.replace("[0, 0, 2]", "[0, 2]");
+    assert!(
+        ChallengePack::parse(&ragged)
+            .unwrap_err()
+            .contains("same length")
+    );
+    let odd = TEST_PACK.replace("[0, 0, 2]", "[0, 0, 3]");
+    assert!(ChallengePack::parse(&odd).unwrap_err().contains("3 is not"));
+}
+
+#[test]
+fn test_challenge_status() {
+    let challenge = Challenge {
+        name: "One Merge".to_string(),
+        description: String::new(),
+        board: vec![vec![8, 8, 0], vec![0, 0, 0], vec![0, 0, 2]],
+        allowed_moves: vec![Direction::Left, Direction::Right],
+        target_tile: Some(16),
+        target_score: None,
+        move_limit: Some(1),
+        seed: None,
+    };
+    assert_eq!(challenge.validate(), Ok(()));
+    let start = challenge.start_board();
+    assert_eq!(challenge.status(&start, 0, 0), ChallengeStatus::InProgress);
+
+    let mut solved = start.clone();
+    solved.apply_move(Direction::Left);
+    assert_eq!(challenge.status(&solved, 16, 1), ChallengeStatus::Solved);
+
+    let mut wasted = start.clone();
+    wasted.set_cell(0, 4);
+    wasted.apply_move(Direction::Right);
+    assert_eq!(challenge.status(&wasted, 0, 1), ChallengeStatus::Failed);
+}
+
+#[test]
+fn test_practice_pack() {
+    let pack = practice_pack();
+    for challenge in &pack.challenges {
+        assert_eq!(challenge.validate(), Ok(()), "{}", challenge.name);
+        let start = challenge.start_board();
+        assert_eq!(
+            challenge.status(&start, 0, 0),
+            ChallengeStatus::InProgress,
+            "{}",
+            challenge.name
+        );
+    }
+
+    let one_merge = &pack.challenges[0];
+    let mut board = one_merge.start_board();
+    let score = board.apply_move(Direction::Left).score_delta;
+    assert_eq!(one_merge.status(&board, score, 1), ChallengeStatus::Solved);
+}
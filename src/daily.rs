@@ -0,0 +1,156 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+// Mixed into every daily seed so the puzzles don't line up with games
+// started with small `--seed` values.
+const DAILY_SALT: u64 = 0x2048_da11_2048_da11;
+
+/// A calendar day in UTC, identifying one daily puzzle. Serialized as
+/// `YYYY-MM-DD`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub struct Date {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl Date {
+    /// Today's date in UTC, so players in every time zone share a puzzle.
+    pub fn today() -> Date {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        Date::from_days((secs / 86_400) as i64)
+    }
+
+    /// The date `days` days after 1970-01-01.
+    pub fn from_days(days: i64) -> Date {
+        // Counts from 0000-03-01 so leap days fall at the end of the year.
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + (month <= 2) as i64;
+        Date {
+            year: year as i32,
+            month: month as u32,
+            day: day as u32,
+        }
+    }
+
+    /// Days since 1970-01-01; the inverse of [`Date::from_days`].
+    pub fn days(self) -> i64 {
+        let year = self.year as i64 - (self.month <= 2) as i64;
+        let era = year.div_euclid(400);
+        let yoe = year.rem_euclid(400);
+        let month = self.month as i64;
+        let mp = if month > 2 { month - 3 } else { month + 9 };
+        let doy = (153 * mp + 2) / 5 + self.day as i64 - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        era * 146_097 + doe - 719_468
+    }
+
+    pub fn add_days(self, days: i64) -> Date {
+        Date::from_days(self.days() + days)
+    }
+
+    /// Day of the week, from 0 for Monday to 6 for Sunday.
+    pub fn weekday(self) -> u32 {
+        // 1970-01-01 was a Thursday.
+        (self.days() + 3).rem_euclid(7) as u32
+    }
+
+    pub fn first_of_month(self) -> Date {
+        Date { day: 1, ..self }
+    }
+
+    pub fn days_in_month(self) -> u32 {
+        let next = match self.month {
+            12 => Date {
+                year: self.year + 1,
+                month: 1,
+                day: 1,
+            },
+            month => Date {
+                year: self.year,
+                month: month + 1,
+                day: 1,
+            },
+        };
+        (next.days() - self.first_of_month().days()) as u32
+    }
+
+    /// The spawn seed of this day's puzzle, the same on every machine.
+    pub fn seed(self) -> u64 {
+        (self.days() as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15) ^ DAILY_SALT
+    }
+
+    pub fn parse(s: &str) -> Option<Date> {
+        let mut parts = s.splitn(3, '-');
+        let date = Date {
+            year: parts.next()?.parse().ok()?,
+            month: parts.next()?.parse().ok()?,
+            day: parts.next()?.parse().ok()?,
+        };
+        // Rejects days past the end of the month, such as 02-30.
+        (Date::from_days(date.days()) == date).then_some(date)
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+impl From<Date> for String {
+    fn from(date: Date) -> String {
+        date.to_string()
+    }
+}
+
+impl TryFrom<String> for Date {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Date, String> {
+        Date::parse(&s).ok_or_else(|| format!("invalid date: {s}"))
+    }
+}
+
+#[test]
+fn test_civil_dates() {
+    assert_eq!(Date::from_days(0).to_string(), "1970-01-01");
+    assert_eq!(Date::from_days(-1).to_string(), "1969-12-31");
+    assert_eq!(Date::from_days(11_016).to_string(), "2000-02-29");
+    assert_eq!(Date::parse("2024-03-01").unwrap().days(), 19_783);
+    assert_eq!(Date::parse("2023-02-29"), None);
+    assert_eq!(Date::parse("2024-02-10").unwrap().days_in_month(), 29);
+    assert_eq!(
+        Date::parse("2023-12-31").unwrap().add_days(1).to_string(),
+        "2024-01-01"
+    );
+    // 2024-01-01 was a Monday.
+    assert_eq!(Date::parse("2024-01-01").unwrap().weekday(), 0);
+    for days in -800..800 {
+        assert_eq!(Date::from_days(days).days(), days);
+    }
+}
+
+#[test]
+fn test_daily_seed() {
+    let date = Date::parse("2024-06-01").unwrap();
+    assert_eq!(date.seed(), Date::from_days(date.days()).seed());
+    assert_ne!(date.seed(), date.add_days(1).seed());
+    let json = serde_json::to_string(&date).unwrap();
+    assert_eq!(json, "\"2024-06-01\"");
+    assert_eq!(serde_json::from_str::<Date>(&json).unwrap(), date);
+}
@@ -0,0 +1,2159 @@
+use bitboard::BitBoard;
+use rand::prelude::*;
+use rules::{MergeRule, Ruleset};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::Duration;
+
+pub mod bitboard;
+pub mod powerups;
+pub mod rules;
+pub mod timer;
+
+/// Random source used for tile spawns: SplitMix64, whose whole state is one
+/// `u64`, so a game in progress can be saved and resumed mid-sequence.
+/// Seeding it with the same value always reproduces the same spawns.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GameRng {
+    state: u64,
+}
+
+impl GameRng {
+    pub fn from_state(state: u64) -> GameRng {
+        GameRng { state }
+    }
+
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+}
+
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn fill_bytes(&mut self, dst: &mut [u8]) {
+        for chunk in dst.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+}
+
+pub fn seeded_rng(seed: u64) -> GameRng {
+    GameRng::from_state(seed)
+}
+
+/// Where new tiles land and what they are. Any random number generator is
+/// one; tests that need to know exactly what spawns script it with
+/// [`FixedSpawns`] instead.
+pub trait SpawnSource {
+    /// The next tile to spawn on `board` and the cell it goes on, or `None`
+    /// if nothing should spawn.
+    fn next_spawn(&mut self, board: &Board, policy: &SpawnPolicy) -> Option<(usize, Cell)>;
+}
+
+impl<R: RngCore> SpawnSource for R {
+    fn next_spawn(&mut self, board: &Board, policy: &SpawnPolicy) -> Option<(usize, Cell)> {
+        let idx = *board.empty_cells().collect::<Vec<usize>>().choose(self)?;
+        if let Some(special) = policy.special(self) {
+            return Some((idx, Cell::Special(special)));
+        }
+        let classic = policy.tile_value(self);
+        Some((idx, Cell::Number(board.rule().spawn_value(classic, self))))
+    }
+}
+
+/// Spawns given up front, handed out in order whatever the policy says.
+/// Once they run out, nothing more spawns.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FixedSpawns {
+    spawns: VecDeque<(usize, Cell)>,
+}
+
+impl FixedSpawns {
+    pub fn new(spawns: impl IntoIterator<Item = (usize, Cell)>) -> FixedSpawns {
+        FixedSpawns {
+            spawns: spawns.into_iter().collect(),
+        }
+    }
+
+    /// Number tiles only, as `(index, value)` pairs.
+    pub fn numbers(spawns: impl IntoIterator<Item = (usize, u32)>) -> FixedSpawns {
+        FixedSpawns::new(
+            spawns
+                .into_iter()
+                .map(|(idx, value)| (idx, Cell::Number(value))),
+        )
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.spawns.len()
+    }
+}
+
+impl SpawnSource for FixedSpawns {
+    fn next_spawn(&mut self, board: &Board, _policy: &SpawnPolicy) -> Option<(usize, Cell)> {
+        let (idx, cell) = self.spawns.pop_front()?;
+        // A script that drops a tile on a taken cell has lost track of the
+        // game it is scripting.
+        assert!(board.is_vacant(idx), "scripted spawn on taken cell {idx}");
+        Some((idx, cell))
+    }
+}
+
+/// Which way a move slides the tiles.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 4] = [
+        Direction::Up,
+        Direction::Down,
+        Direction::Left,
+        Direction::Right,
+    ];
+
+    /// Cell indices of every line along this direction, each ordered from the
+    /// edge tiles slide towards.
+    pub fn lines(self, rows: usize, cols: usize) -> Vec<Vec<usize>> {
+        match self {
+            Direction::Up => (0..cols)
+                .map(|c| (0..rows).map(|r| r * cols + c).collect())
+                .collect(),
+            Direction::Down => (0..cols)
+                .map(|c| (0..rows).rev().map(|r| r * cols + c).collect())
+                .collect(),
+            Direction::Left => (0..rows)
+                .map(|r| (0..cols).map(|c| r * cols + c).collect())
+                .collect(),
+            Direction::Right => (0..rows)
+                .map(|r| (0..cols).rev().map(|c| r * cols + c).collect())
+                .collect(),
+        }
+    }
+}
+
+/// Where a tile that existed before a move ended up. Tiles that merged show
+/// up twice with the same `to`; `value` is the tile's value before merging.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TileMove {
+    pub from: usize,
+    pub to: usize,
+    pub value: u32,
+    /// The tile went off one edge and came back on at the opposite one.
+    pub wrapped: bool,
+    /// Set for a special tile, whose `value` is 0.
+    pub special: Option<Special>,
+}
+
+impl TileMove {
+    /// What the tile was before it moved.
+    pub fn cell(&self) -> Cell {
+        match self.special {
+            Some(special) => Cell::Special(special),
+            None => Cell::Number(self.value),
+        }
+    }
+}
+
+/// The tiles from `sources` combined into `value` at `index`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Merge {
+    pub index: usize,
+    pub value: u32,
+    pub sources: Vec<usize>,
+}
+
+/// Everything a move did to the board, for scoring and animating it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MoveResult {
+    /// Whether any tile changed place. A move that didn't isn't a move: no
+    /// tile should spawn after it.
+    pub moved: bool,
+    pub merges: Vec<Merge>,
+    /// Points scored, the sum of the merged tiles.
+    pub score_delta: u32,
+    pub moves: Vec<TileMove>,
+    /// Bombs set off by the move.
+    pub explosions: Vec<usize>,
+}
+
+/// A tile that isn't a number. Its cell reads 0 in [`Board::cells`] but
+/// isn't empty: nothing spawns there and tiles stop against it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Special {
+    /// Never merges. A merge right next to it sets it off, clearing it and
+    /// every tile around it.
+    Bomb,
+    /// A joker: merges with any tile as if it were a copy of it.
+    Wild,
+    /// Garbage sent over by the opponent in a battle. Slides like a tile but
+    /// never merges; a merge right next to it breaks it up.
+    Junk,
+}
+
+impl Special {
+    /// The character the board's text formats, grids and replays alike,
+    /// write for it.
+    pub fn mark(self) -> char {
+        match self {
+            Special::Bomb => '*',
+            Special::Wild => '?',
+            Special::Junk => 'J',
+        }
+    }
+
+    pub fn from_mark(mark: &str) -> Option<Special> {
+        match mark {
+            "*" => Some(Special::Bomb),
+            "?" => Some(Special::Wild),
+            "J" => Some(Special::Junk),
+            _ => None,
+        }
+    }
+}
+
+/// What sits on one cell of the board.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cell {
+    Empty,
+    Number(u32),
+    Special(Special),
+}
+
+impl Cell {
+    /// The number on the cell, or 0 if there is none.
+    pub fn value(self) -> u32 {
+        match self {
+            Cell::Number(value) => value,
+            Cell::Empty | Cell::Special(_) => 0,
+        }
+    }
+
+    pub fn special(self) -> Option<Special> {
+        match self {
+            Cell::Special(special) => Some(special),
+            Cell::Empty | Cell::Number(_) => None,
+        }
+    }
+}
+
+/// What a run of neighbouring cells merges into under `rule`. Wildcards take
+/// the value of the first number in the run, so a run of only wildcards
+/// stays put, and bombs and gaps never merge.
+pub fn merge_cells(rule: &dyn MergeRule, cells: &[Cell]) -> Option<u32> {
+    let like = cells.iter().find_map(|&cell| match cell {
+        Cell::Number(value) => Some(value),
+        _ => None,
+    })?;
+    let values = cells
+        .iter()
+        .map(|&cell| match cell {
+            Cell::Number(value) => Some(value),
+            Cell::Special(Special::Wild) => Some(like),
+            Cell::Empty | Cell::Special(Special::Bomb | Special::Junk) => None,
+        })
+        .collect::<Option<Vec<u32>>>()?;
+    rule.merge(&values)
+}
+
+pub const WIN_TILE: u32 = 2048;
+/// The largest tile the sandbox editor offers.
+pub const MAX_EDIT_TILE: u32 = 1 << 17;
+pub const MIN_SIZE: usize = 3;
+pub const MAX_SIZE: usize = 8;
+
+/// Reads "5" as a square board or "4x6" as rows x columns, each side within
+/// the allowed sizes.
+pub fn parse_size(value: &str) -> Option<(usize, usize)> {
+    let (rows, cols) = match value.split_once('x') {
+        Some((rows, cols)) => (rows.parse().ok()?, cols.parse().ok()?),
+        None => {
+            let n = value.parse().ok()?;
+            (n, n)
+        }
+    };
+    let valid = MIN_SIZE..=MAX_SIZE;
+    (valid.contains(&rows) && valid.contains(&cols)).then_some((rows, cols))
+}
+
+/// The rules a game is played under.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GameMode {
+    #[default]
+    Classic,
+    TimeAttack,
+    Zen,
+    LimitedMoves,
+    /// Today's shared puzzle: a classic game everyone plays with the same
+    /// spawns.
+    Daily,
+    /// Starts from a position set up by hand.
+    Sandbox,
+    /// Every tile lands wherever hurts the player most.
+    Evil,
+    /// A few blockers sit on the board from the start, and tiles stop
+    /// against them as against the edge.
+    Obstacles,
+    /// Tiles follow the Fibonacci sequence instead of doubling.
+    Fibonacci,
+    /// Threes-style tiles: 1 and 2 make 3, then equal tiles double.
+    Threes,
+    /// Powers of three, merged three at a time.
+    Triples,
+    /// The board wraps around: tiles that slide off one edge come back on at
+    /// the opposite one.
+    Toroidal,
+    /// Big merges earn power-ups that rearrange the board.
+    Arcade,
+    /// Two players at one board: one slides the tiles, the other picks where
+    /// each new tile lands and what it is.
+    Hotseat,
+}
+
+impl GameMode {
+    pub const ALL: [GameMode; 14] = [
+        GameMode::Classic,
+        GameMode::TimeAttack,
+        GameMode::Zen,
+        GameMode::LimitedMoves,
+        GameMode::Daily,
+        GameMode::Sandbox,
+        GameMode::Evil,
+        GameMode::Obstacles,
+        GameMode::Fibonacci,
+        GameMode::Threes,
+        GameMode::Triples,
+        GameMode::Toroidal,
+        GameMode::Arcade,
+        GameMode::Hotseat,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            GameMode::Classic => "Classic",
+            GameMode::TimeAttack => "Time Attack",
+            GameMode::Zen => "Zen",
+            GameMode::LimitedMoves => "Limited Moves",
+            GameMode::Daily => "Daily Puzzle",
+            GameMode::Sandbox => "Sandbox",
+            GameMode::Evil => "Evil",
+            GameMode::Obstacles => "Obstacles",
+            GameMode::Fibonacci => "Fibonacci",
+            GameMode::Threes => "Threes",
+            GameMode::Triples => "Triples",
+            GameMode::Toroidal => "Toroidal",
+            GameMode::Arcade => "Arcade",
+            GameMode::Hotseat => "Hotseat",
+        }
+    }
+
+    /// Challenge modes keep every move final.
+    pub fn allows_undo(self) -> bool {
+        matches!(
+            self,
+            GameMode::Classic
+                | GameMode::Zen
+                | GameMode::Sandbox
+                | GameMode::Obstacles
+                | GameMode::Fibonacci
+                | GameMode::Threes
+                | GameMode::Triples
+                | GameMode::Toroidal
+        )
+    }
+
+    /// Whether reaching the winning tile stops play to celebrate.
+    pub fn stops_at_win(self) -> bool {
+        matches!(
+            self,
+            GameMode::Classic
+                | GameMode::TimeAttack
+                | GameMode::Daily
+                | GameMode::Evil
+                | GameMode::Obstacles
+                | GameMode::Fibonacci
+                | GameMode::Threes
+                | GameMode::Triples
+                | GameMode::Toroidal
+                | GameMode::Arcade
+                | GameMode::Hotseat
+        )
+    }
+
+    /// Applies the mode's rule for a board with no moves left and reports
+    /// whether the game is over. Zen games never end: the smallest tiles
+    /// are cleared away to make room instead.
+    pub fn settle(self, board: &mut Board) -> bool {
+        if !board.is_game_over() {
+            return false;
+        }
+        match self {
+            GameMode::Classic
+            | GameMode::TimeAttack
+            | GameMode::LimitedMoves
+            | GameMode::Daily
+            | GameMode::Sandbox
+            | GameMode::Evil
+            | GameMode::Obstacles
+            | GameMode::Fibonacci
+            | GameMode::Threes
+            | GameMode::Triples
+            | GameMode::Toroidal
+            | GameMode::Arcade
+            | GameMode::Hotseat => true,
+            GameMode::Zen => {
+                board.clear_smallest();
+                false
+            }
+        }
+    }
+
+    /// How long a game may last, for modes played against the clock.
+    pub fn time_limit(self) -> Option<Duration> {
+        match self {
+            GameMode::TimeAttack => Some(Duration::from_secs(180)),
+            GameMode::Classic
+            | GameMode::Zen
+            | GameMode::LimitedMoves
+            | GameMode::Daily
+            | GameMode::Sandbox
+            | GameMode::Evil
+            | GameMode::Obstacles
+            | GameMode::Fibonacci
+            | GameMode::Threes
+            | GameMode::Triples
+            | GameMode::Toroidal
+            | GameMode::Arcade
+            | GameMode::Hotseat => None,
+        }
+    }
+
+    pub fn ruleset(self) -> Ruleset {
+        match self {
+            GameMode::Fibonacci => Ruleset::Fibonacci,
+            GameMode::Threes => Ruleset::Threes,
+            GameMode::Triples => Ruleset::Triples,
+            _ => Ruleset::Classic,
+        }
+    }
+
+    /// Whether rows and columns wrap around into rings.
+    pub fn wraps(self) -> bool {
+        self == GameMode::Toroidal
+    }
+
+    /// Whether big merges earn power-ups.
+    pub fn has_power_ups(self) -> bool {
+        self == GameMode::Arcade
+    }
+
+    /// Whether new tiles are placed by a second player rather than spawned.
+    pub fn has_placer(self) -> bool {
+        self == GameMode::Hotseat
+    }
+
+    /// Blockers placed on a fresh `rows` x `cols` board.
+    pub fn obstacle_count(self, rows: usize, cols: usize) -> usize {
+        match self {
+            GameMode::Obstacles => rows * cols / 8,
+            _ => 0,
+        }
+    }
+}
+
+/// How new tiles are picked after each move.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpawnPolicy {
+    /// Chance that a spawned tile is a 4 instead of a 2.
+    pub four_probability: f64,
+    /// Chance that a spawned tile is an 8, decided before the 4.
+    pub eight_probability: f64,
+    /// Tiles that appear after every move.
+    pub tiles_per_move: u32,
+    /// Chance that a spawned tile is a bomb rather than a number.
+    pub bomb_probability: f64,
+    /// Chance that a spawned tile is a wildcard, decided after the bomb.
+    pub wild_probability: f64,
+}
+
+// Draws against `probability`, skipping the draw when it can't come up so
+// that seeded games spawn what they always have.
+fn roll(rng: &mut impl Rng, probability: f64) -> bool {
+    let probability = probability.clamp(0.0, 1.0);
+    probability > 0.0 && rng.random_bool(probability)
+}
+
+impl SpawnPolicy {
+    pub const CLASSIC: SpawnPolicy = SpawnPolicy {
+        four_probability: 0.1,
+        eight_probability: 0.0,
+        tiles_per_move: 1,
+        bomb_probability: 0.0,
+        wild_probability: 0.0,
+    };
+
+    /// Every new tile is a 4.
+    pub const HARD: SpawnPolicy = SpawnPolicy {
+        four_probability: 1.0,
+        ..SpawnPolicy::CLASSIC
+    };
+
+    pub fn tile_value(&self, rng: &mut impl Rng) -> u32 {
+        if roll(rng, self.eight_probability) {
+            return 8;
+        }
+        match rng.random_bool(1.0 - self.four_probability.clamp(0.0, 1.0)) {
+            true => 2,
+            false => 4,
+        }
+    }
+
+    /// Whether the next tile is a special one instead of a number.
+    pub fn special(&self, rng: &mut impl Rng) -> Option<Special> {
+        if roll(rng, self.bomb_probability) {
+            return Some(Special::Bomb);
+        }
+        roll(rng, self.wild_probability).then_some(Special::Wild)
+    }
+
+    /// Every value [`SpawnPolicy::tile_value`] can come up with.
+    pub fn values(&self) -> Vec<u32> {
+        let four = self.four_probability.clamp(0.0, 1.0);
+        let eight = self.eight_probability.clamp(0.0, 1.0);
+        [
+            (2, eight < 1.0 && four < 1.0),
+            (4, eight < 1.0 && four > 0.0),
+            (8, eight > 0.0),
+        ]
+        .into_iter()
+        .filter_map(|(value, possible)| possible.then_some(value))
+        .collect()
+    }
+
+    /// The difficulty a game under this policy counts towards.
+    pub fn difficulty(&self) -> Difficulty {
+        match self.tiles_per_move {
+            0 | 1 => Difficulty::Normal,
+            _ => Difficulty::Hard,
+        }
+    }
+
+    /// This policy with as many tiles per move as `difficulty` asks for.
+    pub fn with_difficulty(self, difficulty: Difficulty) -> SpawnPolicy {
+        SpawnPolicy {
+            tiles_per_move: difficulty.tiles_per_move(),
+            ..self
+        }
+    }
+}
+
+impl Default for SpawnPolicy {
+    fn default() -> Self {
+        SpawnPolicy::CLASSIC
+    }
+}
+
+/// How many tiles drop in after each move. Best scores are kept apart per
+/// difficulty, since a hard game can't be held to a normal one's.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Difficulty {
+    #[default]
+    Normal,
+    /// Two tiles after every successful move.
+    Hard,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 2] = [Difficulty::Normal, Difficulty::Hard];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Difficulty::Normal => "Normal",
+            Difficulty::Hard => "Two tiles per turn",
+        }
+    }
+
+    pub fn tiles_per_move(self) -> u32 {
+        match self {
+            Difficulty::Normal => 1,
+            Difficulty::Hard => 2,
+        }
+    }
+}
+
+/// The outline of the playfield. Cells outside it are holes: nothing spawns
+/// there and tiles stop against them as against the edge.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BoardShape {
+    #[default]
+    Square,
+    /// The corners are cut away.
+    Plus,
+    /// The middle is cut away, leaving a ring.
+    Donut,
+}
+
+impl BoardShape {
+    pub const ALL: [BoardShape; 3] = [BoardShape::Square, BoardShape::Plus, BoardShape::Donut];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            BoardShape::Square => "Square",
+            BoardShape::Plus => "Plus",
+            BoardShape::Donut => "Donut",
+        }
+    }
+
+    /// The holes of a `rows` x `cols` board of this shape, in ascending
+    /// order. The cut scales with the board: a quarter of each side, at
+    /// least one cell.
+    pub fn holes(self, rows: usize, cols: usize) -> Vec<usize> {
+        let (cut_rows, cut_cols) = ((rows / 4).max(1), (cols / 4).max(1));
+        let outer = |i: usize, cut: usize, len: usize| i < cut || i >= len - cut;
+        (0..rows * cols)
+            .filter(|&i| {
+                let (r, c) = (i / cols, i % cols);
+                match self {
+                    BoardShape::Square => false,
+                    BoardShape::Plus => outer(r, cut_rows, rows) && outer(c, cut_cols, cols),
+                    BoardShape::Donut => !outer(r, cut_rows, rows) && !outer(c, cut_cols, cols),
+                }
+            })
+            .collect()
+    }
+}
+
+/// How a game is set up: its mode, board and spawns.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GameConfig {
+    pub mode: GameMode,
+    /// Moves allowed in a limited-moves game.
+    pub move_budget: u32,
+    pub rows: usize,
+    pub cols: usize,
+    /// How many moves can be undone; 0 disables undo.
+    pub history_depth: usize,
+    /// Fixed seed for every game; a fresh random seed is drawn when `None`.
+    pub seed: Option<u64>,
+    pub spawn: SpawnPolicy,
+    pub shape: BoardShape,
+}
+
+/// Budgets offered for limited-moves games.
+pub const MOVE_BUDGETS: [u32; 4] = [50, 100, 200, 500];
+
+impl GameConfig {
+    /// The daily puzzle ignores local settings so that everyone gets the
+    /// same board size and spawn odds.
+    pub fn daily() -> GameConfig {
+        GameConfig {
+            mode: GameMode::Daily,
+            ..GameConfig::default()
+        }
+    }
+
+    /// The move limit, for modes that have one.
+    pub fn move_limit(&self) -> Option<u32> {
+        (self.mode == GameMode::LimitedMoves).then_some(self.move_budget)
+    }
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        GameConfig {
+            mode: GameMode::Classic,
+            move_budget: 100,
+            rows: 4,
+            cols: 4,
+            history_depth: 32,
+            seed: None,
+            spawn: SpawnPolicy::CLASSIC,
+            shape: BoardShape::Square,
+        }
+    }
+}
+
+/// The grid of tiles and the rules it slides by. Cells are indexed row by
+/// row from the top left; an empty cell reads 0.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "BoardData")]
+pub struct Board {
+    rows: usize,
+    cols: usize,
+    datas: Vec<u32>,
+    /// Cells taken by blockers, in ascending order. They never hold a tile.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    obstacles: Vec<usize>,
+    /// Cells cut out of the playfield, in ascending order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    holes: Vec<usize>,
+    #[serde(default)]
+    ruleset: Ruleset,
+    /// Rows and columns without walls are rings rather than lines.
+    #[serde(default)]
+    wrap: bool,
+    /// Special tiles by cell, in ascending order of cell.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    specials: Vec<(usize, Special)>,
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Board::new(4, 4)
+    }
+}
+
+// A board as read from a file or off the network, before
+// `Board::validate` has passed it.
+#[derive(Deserialize)]
+struct BoardData {
+    rows: usize,
+    cols: usize,
+    datas: Vec<u32>,
+    #[serde(default)]
+    obstacles: Vec<usize>,
+    #[serde(default)]
+    holes: Vec<usize>,
+    #[serde(default)]
+    ruleset: Ruleset,
+    #[serde(default)]
+    wrap: bool,
+    #[serde(default)]
+    specials: Vec<(usize, Special)>,
+}
+
+impl TryFrom<BoardData> for Board {
+    type Error = String;
+
+    fn try_from(data: BoardData) -> Result<Board, String> {
+        let board = Board {
+            rows: data.rows,
+            cols: data.cols,
+            datas: data.datas,
+            obstacles: data.obstacles,
+            holes: data.holes,
+            ruleset: data.ruleset,
+            wrap: data.wrap,
+            specials: data.specials,
+        };
+        board.validate()?;
+        Ok(board)
+    }
+}
+
+impl Board {
+    /// An empty board under the classic rules. Panics unless both sides are
+    /// between [`MIN_SIZE`] and [`MAX_SIZE`].
+    pub fn new(rows: usize, cols: usize) -> Board {
+        assert!(
+            (MIN_SIZE..=MAX_SIZE).contains(&rows) && (MIN_SIZE..=MAX_SIZE).contains(&cols),
+            "board dimensions must be between {MIN_SIZE} and {MAX_SIZE}"
+        );
+        Board {
+            rows,
+            cols,
+            datas: vec![0; rows * cols],
+            obstacles: Vec::new(),
+            holes: Vec::new(),
+            ruleset: Ruleset::Classic,
+            wrap: false,
+            specials: Vec::new(),
+        }
+    }
+
+    pub fn with_shape(rows: usize, cols: usize, shape: BoardShape) -> Board {
+        Board {
+            holes: shape.holes(rows, cols),
+            ..Board::new(rows, cols)
+        }
+    }
+
+    /// An empty board of the size, shape and rules `config` asks for.
+    pub fn for_config(config: &GameConfig) -> Board {
+        let mut board = Board::with_shape(config.rows, config.cols, config.shape);
+        board.set_mode(config.mode);
+        board
+    }
+
+    /// A board holding `cells`, row by row.
+    pub fn from_cells(rows: usize, cols: usize, cells: Vec<u32>) -> Board {
+        assert_eq!(cells.len(), rows * cols);
+        Board {
+            rows,
+            cols,
+            datas: cells,
+            obstacles: Vec::new(),
+            holes: Vec::new(),
+            ruleset: Ruleset::Classic,
+            wrap: false,
+            specials: Vec::new(),
+        }
+    }
+
+    /// The board as a compact text grid, one line per row: a number for
+    /// each tile, `.` for an empty cell, `#` for an obstacle, `-` for a hole
+    /// and `*`, `?` or `J` for a bomb, wildcard or junk.
+    pub fn to_grid(&self) -> String {
+        let rows = (0..self.rows).map(|row| {
+            let cells = (0..self.cols).map(|col| {
+                let idx = row * self.cols + col;
+                self.cell_label(idx)
+                    .unwrap_or_else(|| match self.is_hole(idx) {
+                        true => "-".to_string(),
+                        false => ".".to_string(),
+                    })
+            });
+            cells.collect::<Vec<_>>().join(" ")
+        });
+        rows.collect::<Vec<_>>().join("\n")
+    }
+
+    /// What cell `idx` shows in text: its number, `#` for an obstacle, or a
+    /// letter for a special tile. Empty cells and holes show nothing.
+    pub fn cell_label(&self, idx: usize) -> Option<String> {
+        if self.is_hole(idx) {
+            return None;
+        }
+        if self.is_obstacle(idx) {
+            return Some("#".to_string());
+        }
+        match self.cell(idx) {
+            Cell::Empty => None,
+            Cell::Number(value) => Some(value.to_string()),
+            Cell::Special(special) => Some(special.mark().to_string()),
+        }
+    }
+
+    /// The board drawn with box-drawing characters under a score line, for
+    /// pasting into a forum post or a terminal. Cells read as
+    /// [`Board::cell_label`] has them.
+    pub fn to_box_text(&self, score: u32) -> String {
+        let labels: Vec<String> = (0..self.rows * self.cols)
+            .map(|idx| self.cell_label(idx).unwrap_or_default())
+            .collect();
+        let width = labels.iter().map(String::len).max().unwrap_or(0).max(4);
+        let rule = |left: &str, middle: &str, right: &str| {
+            let line = vec!["─".repeat(width + 2); self.cols].join(middle);
+            format!("{left}{line}{right}\n")
+        };
+        let mut text = format!("Score: {score}\n");
+        text.push_str(&rule("┌", "┬", "┐"));
+        for (row, cells) in labels.chunks(self.cols).enumerate() {
+            if row > 0 {
+                text.push_str(&rule("├", "┼", "┤"));
+            }
+            for label in cells {
+                text.push_str(&format!("│ {label:>width$} "));
+            }
+            text.push_str("│\n");
+        }
+        text.push_str(&rule("└", "┴", "┘"));
+        text
+    }
+
+    /// Reads a grid written by [`Board::to_grid`], under the classic rules.
+    /// Rows may also be split by `/`, to fit a board on one line, and `0`
+    /// reads as an empty cell.
+    pub fn parse_grid(text: &str) -> Result<Board, String> {
+        let rows: Vec<Vec<&str>> = text
+            .split(['\n', '/'])
+            .map(|row| row.split_whitespace().collect::<Vec<_>>())
+            .filter(|row| !row.is_empty())
+            .collect();
+        let cols = rows.first().map_or(0, Vec::len);
+        if rows.iter().any(|row| row.len() != cols) {
+            return Err("rows differ in length".to_string());
+        }
+        if !(MIN_SIZE..=MAX_SIZE).contains(&rows.len()) || !(MIN_SIZE..=MAX_SIZE).contains(&cols) {
+            return Err(format!(
+                "a board is {MIN_SIZE} to {MAX_SIZE} cells on each side"
+            ));
+        }
+        let mut board = Board::new(rows.len(), cols);
+        for (idx, label) in rows.concat().into_iter().enumerate() {
+            match label {
+                "." | "0" => {}
+                "#" => board.set_obstacle(idx),
+                "-" => board.set_hole(idx),
+                _ => match (Special::from_mark(label), label.parse()) {
+                    (Some(special), _) => board.put(idx, Cell::Special(special)),
+                    (None, Ok(value)) => board.set_cell(idx, value),
+                    (None, Err(_)) => return Err(format!("bad cell {label:?}")),
+                },
+            }
+        }
+        Ok(board)
+    }
+
+    /// Checks a board from outside, such as a shared file: that its size is
+    /// in range, that it has a value for every cell, and that its obstacles,
+    /// holes and special tiles sit on cells it has, in order.
+    pub fn validate(&self) -> Result<(), String> {
+        if !(MIN_SIZE..=MAX_SIZE).contains(&self.rows)
+            || !(MIN_SIZE..=MAX_SIZE).contains(&self.cols)
+        {
+            return Err(format!(
+                "a board is {MIN_SIZE} to {MAX_SIZE} cells on each side"
+            ));
+        }
+        let len = self.rows * self.cols;
+        if self.datas.len() != len {
+            return Err(format!(
+                "a {}x{} board has {len} cells, not {}",
+                self.rows,
+                self.cols,
+                self.datas.len()
+            ));
+        }
+        let in_place = |cells: &[usize]| {
+            cells.windows(2).all(|pair| pair[0] < pair[1]) && cells.iter().all(|&idx| idx < len)
+        };
+        let specials: Vec<usize> = self.specials.iter().map(|&(idx, _)| idx).collect();
+        match in_place(&self.obstacles) && in_place(&self.holes) && in_place(&specials) {
+            true => Ok(()),
+            false => Err("obstacles, holes or special tiles off the board".to_string()),
+        }
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn cells(&self) -> &[u32] {
+        &self.datas
+    }
+
+    pub fn ruleset(&self) -> Ruleset {
+        self.ruleset
+    }
+
+    pub fn set_ruleset(&mut self, ruleset: Ruleset) {
+        self.ruleset = ruleset;
+    }
+
+    pub fn wraps(&self) -> bool {
+        self.wrap
+    }
+
+    /// Takes on the merge rule and edges `mode` plays with.
+    pub fn set_mode(&mut self, mode: GameMode) {
+        self.ruleset = mode.ruleset();
+        self.wrap = mode.wraps();
+    }
+
+    pub fn rule(&self) -> &'static dyn MergeRule {
+        self.ruleset.rule()
+    }
+
+    pub fn set_cell(&mut self, idx: usize, value: u32) {
+        self.datas[idx] = value;
+    }
+
+    /// The cell at `idx`; walls read as empty.
+    pub fn cell(&self, idx: usize) -> Cell {
+        match (self.datas[idx], self.special(idx)) {
+            (_, Some(special)) => Cell::Special(special),
+            (0, None) => Cell::Empty,
+            (value, None) => Cell::Number(value),
+        }
+    }
+
+    /// Replaces whatever is on `idx` with `cell`.
+    pub fn put(&mut self, idx: usize, cell: Cell) {
+        self.set_special(idx, cell.special());
+        self.datas[idx] = cell.value();
+    }
+
+    /// Steps the cell at `idx` to the next tile value up or down, wrapping
+    /// around between an empty cell and `MAX_EDIT_TILE`.
+    pub fn cycle_cell(&mut self, idx: usize, up: bool) {
+        if self.is_wall(idx) {
+            return;
+        }
+        // A special tile steps down to an empty cell.
+        if self.special(idx).is_some() {
+            self.set_special(idx, None);
+            return;
+        }
+        let value = self.datas[idx];
+        self.datas[idx] = match (value, up) {
+            (0, true) => 2,
+            (v, true) if v >= MAX_EDIT_TILE => 0,
+            (v, true) => v * 2,
+            (0, false) => MAX_EDIT_TILE,
+            (2, false) => 0,
+            (v, false) => v / 2,
+        };
+    }
+
+    pub fn obstacles(&self) -> &[usize] {
+        &self.obstacles
+    }
+
+    pub fn is_obstacle(&self, idx: usize) -> bool {
+        self.obstacles.binary_search(&idx).is_ok()
+    }
+
+    /// Puts a blocker on `idx`, removing any tile there.
+    pub fn set_obstacle(&mut self, idx: usize) {
+        self.datas[idx] = 0;
+        if let Err(at) = self.obstacles.binary_search(&idx) {
+            self.obstacles.insert(at, idx);
+        }
+    }
+
+    pub fn holes(&self) -> &[usize] {
+        &self.holes
+    }
+
+    pub fn is_hole(&self, idx: usize) -> bool {
+        self.holes.binary_search(&idx).is_ok()
+    }
+
+    /// Cuts `idx` out of the playfield, removing any tile there.
+    pub fn set_hole(&mut self, idx: usize) {
+        self.datas[idx] = 0;
+        if let Err(at) = self.holes.binary_search(&idx) {
+            self.holes.insert(at, idx);
+        }
+    }
+
+    /// Whether `idx` is an obstacle or a hole, which both stop tiles.
+    pub fn is_wall(&self, idx: usize) -> bool {
+        self.is_obstacle(idx) || self.is_hole(idx)
+    }
+
+    pub fn specials(&self) -> &[(usize, Special)] {
+        &self.specials
+    }
+
+    pub fn special(&self, idx: usize) -> Option<Special> {
+        let at = self.specials.binary_search_by_key(&idx, |&(i, _)| i).ok()?;
+        Some(self.specials[at].1)
+    }
+
+    /// Puts `special` on `idx` in place of any tile there, or takes the
+    /// special tile away for `None`.
+    pub fn set_special(&mut self, idx: usize, special: Option<Special>) {
+        self.datas[idx] = 0;
+        match (
+            self.specials.binary_search_by_key(&idx, |&(i, _)| i),
+            special,
+        ) {
+            (Ok(at), Some(special)) => self.specials[at].1 = special,
+            (Ok(at), None) => {
+                self.specials.remove(at);
+            }
+            (Err(at), Some(special)) => self.specials.insert(at, (idx, special)),
+            (Err(_), None) => {}
+        }
+    }
+
+    /// Puts a blocker on a random empty cell and returns its index, or `None`
+    /// when the board is full.
+    pub fn place_obstacle(&mut self, rng: &mut impl Rng) -> Option<usize> {
+        let idx = *self.empty_cells().collect::<Vec<usize>>().choose(rng)?;
+        self.set_obstacle(idx);
+        Some(idx)
+    }
+
+    pub fn empty_cells(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.datas.len()).filter(|&i| self.is_vacant(i))
+    }
+
+    /// Whether sliding towards `dir` would change anything.
+    pub fn can_move(&self, dir: Direction) -> bool {
+        match self.packed() {
+            Some(bits) => bits.apply_move(dir).0 != bits,
+            None => self.clone().apply_move(dir).moved,
+        }
+    }
+
+    /// Slides the board as [`Board::apply_move`] does, for callers that only
+    /// want the points: `None` when nothing moved.
+    pub fn slide(&mut self, dir: Direction) -> Option<u32> {
+        let Some(bits) = self.packed() else {
+            let result = self.apply_move(dir);
+            return result.moved.then_some(result.score_delta);
+        };
+        let (next, points) = bits.apply_move(dir);
+        if next == bits {
+            return None;
+        }
+        self.datas = next.to_board().datas;
+        Some(points)
+    }
+
+    // The board packed for the classic 4x4 fast path. Two 32768s merge past
+    // what a packed cell holds, so a board with one stays unpacked.
+    fn packed(&self) -> Option<BitBoard> {
+        BitBoard::from_board(self).filter(|bits| bits.max_exponent() < 15)
+    }
+
+    /// Slides every tile as far as it goes towards `dir`, merging tiles
+    /// that combine under the board's rules, each at most once per move.
+    /// New tiles are left to the caller.
+    pub fn apply_move(&mut self, dir: Direction) -> MoveResult {
+        let mut result = MoveResult::default();
+        for line in dir.lines(self.rows, self.cols) {
+            if self.is_ring(&line) {
+                let first = result.moves.len();
+                let ring = self.cut_ring(&line);
+                self.slide_line(&ring, &mut result);
+                // Tiles only ever head for the front of the line, so one that
+                // ends up further back went round the edge.
+                let position = |idx: usize| line.iter().position(|&i| i == idx);
+                for tile_move in &mut result.moves[first..] {
+                    tile_move.wrapped = position(tile_move.to) > position(tile_move.from);
+                }
+                continue;
+            }
+            // Walls cut a line into stretches that slide independently.
+            let stretches: Vec<&[usize]> = line.split(|&i| self.is_wall(i)).collect();
+            for stretch in stretches {
+                self.slide_line(stretch, &mut result);
+            }
+        }
+        result.explosions = self.detonate(&result.merges);
+        self.break_junk(&result.merges);
+        result
+    }
+
+    /// Places a 2 (90%) or a 4 on a random empty cell and returns its index,
+    /// or `None` when the board is full.
+    pub fn spawn_random_tile(&mut self, rng: &mut impl Rng) -> Option<usize> {
+        self.spawn_tile_with(rng, &SpawnPolicy::CLASSIC)
+    }
+
+    /// Places one tile picked by `policy` on an empty cell, at random
+    /// unless `source` is scripted.
+    pub fn spawn_tile_with(
+        &mut self,
+        source: &mut impl SpawnSource,
+        policy: &SpawnPolicy,
+    ) -> Option<usize> {
+        let (idx, cell) = source.next_spawn(self, policy)?;
+        self.put(idx, cell);
+        Some(idx)
+    }
+
+    /// Drops up to `count` junk tiles on random empty cells and returns
+    /// where they landed.
+    pub fn add_junk(&mut self, count: u32, rng: &mut impl Rng) -> Vec<usize> {
+        let mut cells: Vec<usize> = self.empty_cells().collect();
+        cells.shuffle(rng);
+        cells.truncate(count as usize);
+        for &idx in &cells {
+            self.set_special(idx, Some(Special::Junk));
+        }
+        cells
+    }
+
+    /// Puts a tile worth `value` on `idx` by hand, as the placer does in
+    /// hotseat games; false unless the cell is free.
+    pub fn place_tile(&mut self, idx: usize, value: u32) -> bool {
+        if idx >= self.datas.len() || !self.is_vacant(idx) || value == 0 {
+            return false;
+        }
+        self.datas[idx] = value;
+        true
+    }
+
+    /// Empties every cell holding the smallest tile on the board and returns
+    /// their indices.
+    pub fn clear_smallest(&mut self) -> Vec<usize> {
+        let Some(smallest) = self.datas.iter().copied().filter(|&v| v != 0).min() else {
+            return Vec::new();
+        };
+        let cleared: Vec<usize> = (0..self.datas.len())
+            .filter(|&i| self.datas[i] == smallest)
+            .collect();
+        for &i in &cleared {
+            self.datas[i] = 0;
+        }
+        cleared
+    }
+
+    pub fn max_tile(&self) -> u32 {
+        self.datas.iter().copied().max().unwrap_or(0)
+    }
+
+    pub fn has_won(&self) -> bool {
+        self.max_tile() >= self.rule().win_tile()
+    }
+
+    pub fn is_game_over(&self) -> bool {
+        if let Some(bits) = self.packed() {
+            return Direction::ALL
+                .into_iter()
+                .all(|dir| bits.apply_move(dir).0 == bits);
+        }
+        if self.empty_cells().next().is_some() {
+            return false;
+        }
+        // With the board full, a move only changes something if a run of
+        // neighbours in some row or column merges.
+        let mut lines = Direction::Left
+            .lines(self.rows, self.cols)
+            .into_iter()
+            .chain(Direction::Up.lines(self.rows, self.cols));
+        !lines.any(|line| {
+            if self.is_ring(&line) {
+                return (0..line.len()).any(|start| self.merges_across(&line, start));
+            }
+            let group = self.rule().group();
+            line.split(|&i| self.is_wall(i)).any(|stretch| {
+                stretch
+                    .windows(group)
+                    .any(|run| self.merges_across(run, group - 1))
+            })
+        })
+    }
+}
+
+impl Board {
+    // about core logic
+    fn is_vacant(&self, idx: usize) -> bool {
+        self.cell(idx) == Cell::Empty && !self.is_wall(idx)
+    }
+
+    // Sets off every bomb next to one of `merges`, emptying the cells around
+    // it, and returns where they were.
+    fn detonate(&mut self, merges: &[Merge]) -> Vec<usize> {
+        let (rows, cols) = (self.rows as isize, self.cols as isize);
+        let at = |idx: usize| (idx as isize / cols, idx as isize % cols);
+        let bombs = self.specials_next_to(Special::Bomb, merges);
+        for &bomb in &bombs {
+            let (r, c) = at(bomb);
+            for (dr, dc) in (-1..=1).flat_map(|dr| (-1..=1).map(move |dc| (dr, dc))) {
+                let (r, c) = (r + dr, c + dc);
+                if !(0..rows).contains(&r) || !(0..cols).contains(&c) {
+                    continue;
+                }
+                let idx = (r * cols + c) as usize;
+                if !self.is_wall(idx) {
+                    self.set_special(idx, None);
+                }
+            }
+        }
+        bombs
+    }
+
+    // Clears every junk tile next to one of `merges`.
+    fn break_junk(&mut self, merges: &[Merge]) {
+        for idx in self.specials_next_to(Special::Junk, merges) {
+            self.set_special(idx, None);
+        }
+    }
+
+    // Where the `special` tiles right beside one of `merges` are.
+    fn specials_next_to(&self, special: Special, merges: &[Merge]) -> Vec<usize> {
+        let at = |idx: usize| (idx / self.cols, idx % self.cols);
+        self.specials
+            .iter()
+            .filter(|&&(_, s)| s == special)
+            .map(|&(idx, _)| idx)
+            .filter(|&idx| {
+                let (r, c) = at(idx);
+                merges.iter().any(|merge| {
+                    let (mr, mc) = at(merge.index);
+                    r.abs_diff(mr) + c.abs_diff(mc) == 1
+                })
+            })
+            .collect()
+    }
+
+    fn is_ring(&self, line: &[usize]) -> bool {
+        self.wrap && !line.iter().any(|&i| self.is_wall(i))
+    }
+
+    // Whether some run of tiles that crosses the gap before `line[cut]`,
+    // reading `line` as a ring, would merge.
+    fn merges_across(&self, line: &[usize], cut: usize) -> bool {
+        let (n, rule) = (line.len(), self.rule());
+        let group = rule.group();
+        (1..group).any(|back| {
+            let cells: Vec<Cell> = (0..group)
+                .map(|k| self.cell(line[(cut + n - back + k) % n]))
+                .collect();
+            merge_cells(rule, &cells).is_some()
+        })
+    }
+
+    // Opens a ring into a line that slides like any other. It is cut just
+    // after its last empty cell, so tiles packed against the far edge hold
+    // still and those in front of them wrap round to join them. A full ring
+    // is cut where no tiles would merge across the cut.
+    fn cut_ring(&self, line: &[usize]) -> Vec<usize> {
+        let start = match line.iter().rposition(|&i| self.is_vacant(i)) {
+            Some(empty) => empty + 1,
+            None => (0..line.len())
+                .find(|&cut| !self.merges_across(line, cut))
+                .unwrap_or(0),
+        };
+        line[start..]
+            .iter()
+            .chain(&line[..start])
+            .copied()
+            .collect()
+    }
+
+    fn slide_line(&mut self, line: &[usize], result: &mut MoveResult) {
+        let rule = self.rule();
+        let tiles: Vec<(usize, Cell)> = line
+            .iter()
+            .filter(|&&i| !self.is_vacant(i))
+            .map(|&i| (i, self.cell(i)))
+            .collect();
+        for &i in line {
+            self.put(i, Cell::Empty);
+        }
+
+        let mut k = 0;
+        for &to in line {
+            let Some(&(from, cell)) = tiles.get(k) else {
+                break;
+            };
+            // The next `group` tiles either merge together or the first one
+            // just slides.
+            let run = tiles.get(k..k + rule.group()).and_then(|run| {
+                let cells: Vec<Cell> = run.iter().map(|&(_, cell)| cell).collect();
+                Some((run, merge_cells(rule, &cells)?))
+            });
+            match run {
+                Some((run, merged)) => {
+                    self.datas[to] = merged;
+                    result.score_delta = result.score_delta.saturating_add(merged);
+                    for &(from, cell) in run {
+                        result.moves.push(TileMove {
+                            from,
+                            to,
+                            value: cell.value(),
+                            wrapped: false,
+                            special: cell.special(),
+                        });
+                    }
+                    result.merges.push(Merge {
+                        index: to,
+                        value: merged,
+                        sources: run.iter().map(|&(i, _)| i).collect(),
+                    });
+                    result.moved = true;
+                    k += run.len();
+                }
+                None => {
+                    self.put(to, cell);
+                    result.moves.push(TileMove {
+                        from,
+                        to,
+                        value: cell.value(),
+                        wrapped: false,
+                        special: cell.special(),
+                    });
+                    result.moved |= from != to;
+                    k += 1;
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_apply_move() {
+    let mut board = Board::from_cells(
+        4,
+        4,
+        vec![
+            2, 2, 4, 0, //
+            0, 0, 0, 0, //
+            4, 0, 4, 8, //
+            0, 0, 0, 2, //
+        ],
+    );
+
+    let result = board.apply_move(Direction::Left);
+    assert!(result.moved);
+    assert_eq!(result.score_delta, 12);
+    assert_eq!(
+        board.cells(),
+        [
+            4, 4, 0, 0, //
+            0, 0, 0, 0, //
+            8, 8, 0, 0, //
+            2, 0, 0, 0, //
+        ]
+    );
+
+    let result = board.apply_move(Direction::Up);
+    assert!(result.moved);
+    assert_eq!(result.score_delta, 0);
+    assert_eq!(&board.cells()[4..8], [8, 8, 0, 0]);
+    assert!(!board.apply_move(Direction::Up).moved);
+
+    assert_eq!(board.apply_move(Direction::Right).score_delta, 24);
+}
+
+#[test]
+fn test_game_over() {
+    let mut board = Board::from_cells(
+        4,
+        4,
+        vec![
+            2, 4, 2, 4, //
+            4, 2, 4, 2, //
+            2, 4, 2, 4, //
+            4, 2, 4, 2, //
+        ],
+    );
+    assert!(board.is_game_over());
+    assert_eq!(board.spawn_random_tile(&mut rand::rng()), None);
+    assert!(!board.apply_move(Direction::Down).moved);
+}
+
+#[test]
+fn test_obstacles() {
+    let mut board = Board::from_cells(
+        4,
+        4,
+        vec![
+            2, 0, 2, 2, //
+            0, 4, 0, 4, //
+            0, 0, 0, 0, //
+            8, 0, 4, 0, //
+        ],
+    );
+    board.set_obstacle(6);
+    board.set_obstacle(1);
+    assert_eq!(board.obstacles(), [1, 6]);
+
+    let result = board.apply_move(Direction::Left);
+    assert_eq!(result.score_delta, 4);
+    assert_eq!(&board.cells()[..8], [2, 0, 4, 0, 4, 0, 0, 4]);
+    // The 4 above the obstacle in the third column can't fall past it.
+    board.apply_move(Direction::Down);
+    assert_eq!((board.cells()[2], board.cells()[6]), (4, 0));
+    assert!(board.empty_cells().all(|i| !board.is_obstacle(i)));
+
+    let mut blocked = Board::from_cells(
+        3,
+        3,
+        vec![
+            2, 0, 4, //
+            4, 8, 2, //
+            2, 4, 8, //
+        ],
+    );
+    blocked.set_obstacle(1);
+    assert!(blocked.is_game_over());
+    assert_eq!(blocked.spawn_random_tile(&mut rand::rng()), None);
+}
+
+#[test]
+fn test_board_shapes() {
+    assert_eq!(BoardShape::Plus.holes(4, 4), [0, 3, 12, 15]);
+    assert_eq!(BoardShape::Donut.holes(4, 4), [5, 6, 9, 10]);
+    assert_eq!(BoardShape::Donut.holes(3, 3), [4]);
+    assert_eq!(BoardShape::Plus.holes(8, 8).len(), 16);
+    assert!(BoardShape::Square.holes(5, 5).is_empty());
+
+    let mut board = Board::with_shape(4, 4, BoardShape::Donut);
+    assert_eq!(board.empty_cells().count(), 12);
+    board.set_cell(4, 2);
+    board.set_cell(7, 2);
+    // The hole in the middle keeps the two 2s apart.
+    assert!(!board.apply_move(Direction::Left).moved);
+    assert!(board.apply_move(Direction::Up).moved);
+    assert_eq!((board.cells()[0], board.cells()[3]), (2, 2));
+
+    let mut plus = Board::with_shape(3, 3, BoardShape::Plus);
+    for (i, value) in [(1, 2), (3, 4), (4, 8), (5, 2), (7, 4)] {
+        plus.set_cell(i, value);
+    }
+    assert!(plus.is_game_over());
+    plus.cycle_cell(0, true);
+    assert_eq!(plus.cells()[0], 0);
+}
+
+#[test]
+fn test_fibonacci_board() {
+    let config = GameConfig {
+        mode: GameMode::Fibonacci,
+        ..GameConfig::default()
+    };
+    let mut board = Board::for_config(&config);
+    for (i, value) in [(0, 1), (1, 2), (2, 3), (3, 3)] {
+        board.set_cell(i, value);
+    }
+    let result = board.apply_move(Direction::Left);
+    assert_eq!(&board.cells()[..4], [3, 3, 3, 0]);
+    assert_eq!(result.score_delta, 3);
+    assert_eq!(result.merges[0].sources, [0, 1]);
+    assert!(!board.has_won());
+
+    let mut rng = seeded_rng(5);
+    let idx = board.spawn_random_tile(&mut rng).unwrap();
+    assert!([1, 2].contains(&board.cells()[idx]));
+
+    let mut stuck = Board::from_cells(3, 3, vec![2, 2, 2, 2, 2, 2, 2, 2, 2]);
+    assert!(!stuck.is_game_over());
+    stuck.set_ruleset(Ruleset::Fibonacci);
+    assert!(stuck.is_game_over());
+}
+
+#[test]
+fn test_threes_board() {
+    let mut board = Board::from_cells(
+        3,
+        3,
+        vec![
+            1, 2, 2, //
+            3, 3, 1, //
+            1, 1, 0, //
+        ],
+    );
+    board.set_ruleset(Ruleset::Threes);
+    let result = board.apply_move(Direction::Left);
+    assert_eq!(board.cells(), [3, 2, 0, 6, 1, 0, 1, 1, 0]);
+    assert_eq!(result.score_delta, 9);
+}
+
+#[test]
+fn test_triples_board() {
+    let mut board = Board::from_cells(
+        4,
+        4,
+        vec![
+            3, 3, 3, 3, //
+            9, 9, 0, 3, //
+            3, 3, 0, 0, //
+            27, 9, 3, 27, //
+        ],
+    );
+    board.set_ruleset(Ruleset::Triples);
+    let result = board.apply_move(Direction::Left);
+    assert_eq!(&board.cells()[..12], [9, 3, 0, 0, 9, 9, 3, 0, 3, 3, 0, 0]);
+    assert_eq!(result.score_delta, 9);
+    assert_eq!(result.merges[0].sources, [0, 1, 2]);
+
+    let mut full = Board::from_cells(3, 3, vec![3, 3, 9, 9, 9, 3, 3, 3, 9]);
+    full.set_ruleset(Ruleset::Triples);
+    assert!(full.is_game_over());
+    full.set_cell(2, 3);
+    assert!(!full.is_game_over());
+}
+
+#[test]
+fn test_toroidal_board() {
+    let config = GameConfig {
+        mode: GameMode::Toroidal,
+        ..GameConfig::default()
+    };
+    let mut board = Board::for_config(&config);
+    for (idx, val) in [(0, 2), (3, 2), (5, 2), (7, 2), (8, 2), (9, 4), (11, 8)] {
+        board.set_cell(idx, val);
+    }
+    let result = board.apply_move(Direction::Left);
+    // The 2 on the left edge goes round to meet the one on the right, and
+    // tiles already packed up against the far edge from behind stay put.
+    assert_eq!(&board.cells()[..12], [0, 0, 0, 4, 0, 0, 0, 4, 2, 4, 0, 8]);
+    assert!(
+        result
+            .moves
+            .iter()
+            .any(|m| m.from == 0 && m.to == 3 && m.wrapped)
+    );
+    assert!(result.moves.iter().all(|m| m.wrapped == (m.to > m.from)));
+
+    // Only the wrap-around neighbours can merge.
+    let mut full = Board::from_cells(2, 3, vec![2, 4, 2, 4, 8, 16]);
+    assert!(full.is_game_over());
+    full.set_mode(GameMode::Toroidal);
+    assert!(!full.is_game_over());
+    let result = full.apply_move(Direction::Right);
+    assert_eq!(full.cells(), [4, 4, 0, 4, 8, 16]);
+    assert!(
+        result
+            .moves
+            .iter()
+            .any(|m| m.from == 2 && m.to == 0 && m.wrapped)
+    );
+}
+
+#[test]
+fn test_bombs() {
+    let mut board = Board::from_cells(
+        4,
+        4,
+        vec![
+            2, 2, 0, 0, //
+            0, 0, 0, 8, //
+            0, 0, 0, 0, //
+            0, 2, 0, 2, //
+        ],
+    );
+    board.set_special(4, Some(Special::Bomb));
+    board.set_special(14, Some(Special::Bomb));
+    assert_eq!(board.empty_cells().count(), 9);
+
+    // The merge in the corner sets off the bomb under it, which takes the 8
+    // that slid up beside it. The other bomb slides like a tile and keeps
+    // the 2s apart.
+    let result = board.apply_move(Direction::Left);
+    assert_eq!(result.explosions, [4]);
+    assert_eq!(result.score_delta, 4);
+    assert!(
+        result
+            .moves
+            .iter()
+            .any(|m| m.from == 14 && m.to == 13 && m.special.is_some())
+    );
+    assert_eq!(board.specials(), [(13, Special::Bomb)]);
+    assert_eq!(
+        board.cells(),
+        [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 2, 0]
+    );
+
+    // Bombs never merge, not even with each other.
+    let mut full = Board::from_cells(3, 3, vec![2, 0, 0, 4, 8, 2, 2, 4, 8]);
+    full.set_special(1, Some(Special::Bomb));
+    full.set_special(2, Some(Special::Bomb));
+    assert!(full.is_game_over());
+
+    let policy = SpawnPolicy {
+        bomb_probability: 1.0,
+        ..SpawnPolicy::CLASSIC
+    };
+    let mut board = Board::new(3, 3);
+    let idx = board.spawn_tile_with(&mut seeded_rng(1), &policy).unwrap();
+    assert_eq!(board.special(idx), Some(Special::Bomb));
+}
+
+#[test]
+fn test_wildcards() {
+    let (wild, bomb) = (Cell::Special(Special::Wild), Cell::Special(Special::Bomb));
+    let doubling = Ruleset::Classic.rule();
+    assert_eq!(merge_cells(doubling, &[wild, Cell::Number(8)]), Some(16));
+    assert_eq!(merge_cells(doubling, &[wild, wild]), None);
+    assert_eq!(merge_cells(doubling, &[wild, bomb]), None);
+    let tripling = Ruleset::Triples.rule();
+    assert_eq!(
+        merge_cells(tripling, &[wild, wild, Cell::Number(3)]),
+        Some(9)
+    );
+    assert_eq!(
+        merge_cells(tripling, &[wild, Cell::Number(3), Cell::Number(9)]),
+        None
+    );
+
+    let mut board = Board::from_cells(3, 3, vec![0, 4, 4, 2, 0, 0, 0, 0, 0]);
+    board.put(0, wild);
+    let result = board.apply_move(Direction::Left);
+    assert_eq!(board.cells(), [8, 4, 0, 2, 0, 0, 0, 0, 0]);
+    assert_eq!(result.score_delta, 8);
+    assert!(result.moves.iter().any(|m| m.cell() == wild && m.to == 0));
+    assert!(board.specials().is_empty());
+
+    let mut full = Board::from_cells(3, 3, vec![2, 4, 8, 8, 2, 4, 2, 4, 8]);
+    assert!(full.is_game_over());
+    full.put(4, wild);
+    assert_eq!(full.cell(4), wild);
+    assert!(!full.is_game_over());
+}
+
+#[test]
+fn test_zen_settle() {
+    let cells = vec![
+        2, 4, 2, 4, //
+        4, 2, 4, 2, //
+        2, 4, 2, 4, //
+        4, 2, 4, 2, //
+    ];
+    let mut board = Board::from_cells(4, 4, cells.clone());
+    assert!(GameMode::Classic.settle(&mut board));
+    assert_eq!(board.cells(), cells);
+
+    assert!(!GameMode::Zen.settle(&mut board));
+    assert_eq!(board.empty_cells().count(), 8);
+    assert!(board.cells().iter().all(|&v| v == 0 || v == 4));
+}
+
+#[test]
+fn test_cycle_cell() {
+    let mut board = Board::new(4, 4);
+    board.cycle_cell(0, true);
+    board.cycle_cell(0, true);
+    assert_eq!(board.cells()[0], 4);
+    board.cycle_cell(0, false);
+    board.cycle_cell(0, false);
+    assert_eq!(board.cells()[0], 0);
+    board.cycle_cell(0, false);
+    assert_eq!(board.cells()[0], MAX_EDIT_TILE);
+    board.cycle_cell(0, true);
+    assert_eq!(board.cells()[0], 0);
+}
+
+#[test]
+fn test_board_validate() {
+    let mut board = Board::new(4, 4);
+    board.set_obstacle(15);
+    board.put(3, Cell::Special(Special::Bomb));
+    assert_eq!(board.validate(), Ok(()));
+
+    assert!(Board::from_cells(2, 2, vec![0; 4]).validate().is_err());
+    assert!(Board::from_cells(9, 3, vec![0; 27]).validate().is_err());
+    let short = Board {
+        datas: vec![0; 15],
+        ..Board::new(4, 4)
+    };
+    assert!(short.validate().is_err());
+    let off_board = Board {
+        holes: vec![16],
+        ..Board::new(4, 4)
+    };
+    assert!(off_board.validate().is_err());
+    let unsorted = Board {
+        obstacles: vec![5, 2],
+        ..Board::new(4, 4)
+    };
+    assert!(unsorted.validate().is_err());
+}
+
+#[test]
+fn test_board_grid() {
+    let mut board = Board::from_cells(3, 4, vec![2, 0, 0, 4, 0, 0, 0, 0, 0, 0, 1024, 0]);
+    board.set_obstacle(5);
+    board.set_hole(8);
+    board.put(6, Cell::Special(Special::Wild));
+    board.put(7, Cell::Special(Special::Bomb));
+    assert_eq!(board.to_grid(), "2 . . 4\n. # ? *\n- . 1024 .");
+    assert_eq!(Board::parse_grid(&board.to_grid()), Ok(board.clone()));
+    assert_eq!(
+        Board::parse_grid(" 2 0 0 4 / 0 # ? * / - 0 1024 0 "),
+        Ok(board)
+    );
+
+    assert!(Board::parse_grid("2 2 2\n2 2").is_err());
+    assert!(Board::parse_grid("2 2\n2 2").is_err());
+    assert!(Board::parse_grid("2 x 2/2 2 2/2 2 2").is_err());
+    assert!(Board::parse_grid("").is_err());
+}
+
+#[test]
+fn test_box_text() {
+    let mut board = Board::from_cells(3, 3, vec![2, 0, 0, 0, 16384, 0, 0, 0, 4]);
+    board.set_obstacle(2);
+    assert_eq!(
+        board.to_box_text(1234),
+        "Score: 1234\n\
+         ┌───────┬───────┬───────┐\n\
+         │     2 │       │     # │\n\
+         ├───────┼───────┼───────┤\n\
+         │       │ 16384 │       │\n\
+         ├───────┼───────┼───────┤\n\
+         │       │       │     4 │\n\
+         └───────┴───────┴───────┘\n"
+    );
+}
+
+#[test]
+fn test_larger_board() {
+    let mut board = Board::from_cells(
+        5,
+        5,
+        vec![
+            2, 2, 2, 2, 2, //
+            0, 0, 0, 0, 4, //
+            0, 0, 0, 0, 0, //
+            0, 0, 0, 0, 0, //
+            8, 0, 0, 0, 0, //
+        ],
+    );
+    assert_eq!(board.apply_move(Direction::Right).score_delta, 8);
+    assert_eq!(&board.cells()[..5], [0, 0, 2, 4, 4]);
+    assert_eq!(board.apply_move(Direction::Down).score_delta, 8);
+    assert_eq!(&board.cells()[20..], [0, 0, 2, 4, 8]);
+}
+
+#[test]
+fn test_rectangular_board() {
+    let mut board = Board::from_cells(
+        3,
+        5,
+        vec![
+            2, 0, 2, 0, 4, //
+            0, 0, 0, 0, 4, //
+            2, 0, 0, 0, 0, //
+        ],
+    );
+    assert_eq!(board.apply_move(Direction::Left).score_delta, 4);
+    assert_eq!((board.rows(), board.cols()), (3, 5));
+    assert_eq!(&board.cells()[..5], [4, 4, 0, 0, 0]);
+    assert_eq!(board.apply_move(Direction::Up).score_delta, 8);
+    assert_eq!(
+        board.cells(),
+        [
+            8, 4, 0, 0, 0, //
+            2, 0, 0, 0, 0, //
+            0, 0, 0, 0, 0, //
+        ]
+    );
+}
+
+#[test]
+fn test_tile_moves() {
+    let mut board = Board::from_cells(
+        3,
+        3,
+        vec![
+            0, 2, 2, //
+            4, 0, 0, //
+            0, 0, 8, //
+        ],
+    );
+    let result = board.apply_move(Direction::Left);
+    assert_eq!(
+        result.merges,
+        [Merge {
+            index: 0,
+            value: 4,
+            sources: vec![1, 2]
+        }]
+    );
+    let mut moves = result
+        .moves
+        .iter()
+        .map(|m| (m.from, m.to, m.value))
+        .collect::<Vec<_>>();
+    moves.sort();
+    assert_eq!(moves, [(1, 0, 2), (2, 0, 2), (3, 3, 4), (8, 6, 8)]);
+}
+
+#[test]
+fn test_seeded_spawns() {
+    let play = |seed| {
+        let mut rng = seeded_rng(seed);
+        let mut board = Board::new(4, 4);
+        for dir in Direction::ALL.iter().cycle().take(40) {
+            board.apply_move(*dir);
+            board.spawn_random_tile(&mut rng);
+        }
+        board
+    };
+    assert_eq!(play(42), play(42));
+}
+
+#[test]
+fn test_spawn_policy() {
+    let mut rng = seeded_rng(3);
+    let values = |policy: SpawnPolicy, rng: &mut GameRng| {
+        (0..200).map(|_| policy.tile_value(rng)).collect::<Vec<_>>()
+    };
+    assert!(values(SpawnPolicy::HARD, &mut rng).iter().all(|&v| v == 4));
+    let eights = SpawnPolicy {
+        eight_probability: 1.0,
+        ..SpawnPolicy::CLASSIC
+    };
+    assert!(values(eights, &mut rng).iter().all(|&v| v == 8));
+    let classic = values(SpawnPolicy::CLASSIC, &mut rng);
+    let twos = classic.iter().filter(|&&v| v == 2).count();
+    assert!(twos > 150 && classic.iter().all(|&v| v == 2 || v == 4));
+
+    assert_eq!(SpawnPolicy::CLASSIC.values(), [2, 4]);
+    assert_eq!(SpawnPolicy::HARD.values(), [4]);
+    assert_eq!(eights.values(), [8]);
+
+    assert_eq!(SpawnPolicy::CLASSIC.difficulty(), Difficulty::Normal);
+    let hard = eights.with_difficulty(Difficulty::Hard);
+    assert_eq!(hard.tiles_per_move, 2);
+    assert_eq!(hard.eight_probability, 1.0);
+    assert_eq!(hard.difficulty(), Difficulty::Hard);
+}
+
+#[test]
+fn test_junk() {
+    let mut board = Board::from_cells(3, 3, vec![2, 2, 0, 0, 0, 0, 0, 0, 0]);
+    board.set_special(3, Some(Special::Junk));
+    board.set_special(8, Some(Special::Junk));
+    // The merge in the corner breaks the junk below it; the other junk
+    // slides over like a tile and stays.
+    let result = board.apply_move(Direction::Left);
+    assert_eq!(result.score_delta, 4);
+    assert_eq!(board.specials(), [(6, Special::Junk)]);
+    assert_eq!(board.cells(), [4, 0, 0, 0, 0, 0, 0, 0, 0]);
+    assert!(merge_cells(board.rule(), &[Cell::Special(Special::Junk); 2]).is_none());
+
+    let landed = board.add_junk(20, &mut seeded_rng(5));
+    assert_eq!(landed.len(), 7);
+    assert_eq!(board.empty_cells().count(), 0);
+}
+
+#[test]
+fn test_place_tile() {
+    let mut board = Board::from_cells(3, 3, vec![2, 0, 0, 0, 0, 0, 0, 0, 0]);
+    board.set_obstacle(4);
+    board.set_special(5, Some(Special::Bomb));
+    assert!(!board.place_tile(0, 4));
+    assert!(!board.place_tile(4, 4));
+    assert!(!board.place_tile(5, 4));
+    assert!(!board.place_tile(9, 4));
+    assert!(board.place_tile(1, 4));
+    assert_eq!(board.cells()[..3], [2, 4, 0]);
+    assert!(GameMode::Hotseat.has_placer() && !GameMode::Hotseat.allows_undo());
+}
+
+#[test]
+fn test_rng_resume() {
+    let mut rng = seeded_rng(7);
+    rng.next_u64();
+    let mut resumed = GameRng::from_state(rng.state());
+    assert_eq!(rng.next_u64(), resumed.next_u64());
+}
+
+#[test]
+fn test_fixed_spawns() {
+    let config = GameConfig {
+        rows: 3,
+        cols: 3,
+        ..GameConfig::default()
+    };
+    let mut board = Board::for_config(&config);
+    let mut spawns = FixedSpawns::numbers([(0, 2), (2, 2), (8, 4)]);
+    board.spawn_tile_with(&mut spawns, &config.spawn);
+    board.spawn_tile_with(&mut spawns, &config.spawn);
+    assert_eq!(board.cells(), [2, 0, 2, 0, 0, 0, 0, 0, 0]);
+    board.apply_move(Direction::Left);
+    assert_eq!(board.spawn_tile_with(&mut spawns, &config.spawn), Some(8));
+    assert_eq!(board.cells(), [4, 0, 0, 0, 0, 0, 0, 0, 4]);
+    assert_eq!(spawns.remaining(), 0);
+    assert_eq!(board.spawn_tile_with(&mut spawns, &config.spawn), None);
+
+    let mut spawns = FixedSpawns::new([(4, Cell::Special(Special::Bomb))]);
+    board.spawn_tile_with(&mut spawns, &config.spawn);
+    assert_eq!(board.cell(4), Cell::Special(Special::Bomb));
+}
+
+// One case from fixtures/moves.txt: a board slid one way, and what came of it.
+#[cfg(test)]
+struct MoveCase {
+    text: String,
+    dir: Direction,
+    before: Board,
+    after: Board,
+    score: u32,
+}
+
+#[cfg(test)]
+fn parse_move_case(block: &str) -> Result<MoveCase, String> {
+    let board = |rows: &[&str]| -> Result<Board, String> {
+        let cells: Vec<Vec<u32>> = rows
+            .iter()
+            .map(|row| row.split_whitespace().map(str::parse).collect())
+            .collect::<Result<_, _>>()
+            .map_err(|err| format!("bad cell: {err}"))?;
+        let cols = cells.first().map_or(0, Vec::len);
+        if cols == 0 || cells.iter().any(|row| row.len() != cols) {
+            return Err("rows differ in length".to_string());
+        }
+        Ok(Board::from_cells(cells.len(), cols, cells.concat()))
+    };
+
+    let lines: Vec<&str> = block
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#'))
+        .collect();
+    let [dir, rest @ ..] = lines.as_slice() else {
+        return Err("empty case".to_string());
+    };
+    let dir = match *dir {
+        "up" => Direction::Up,
+        "down" => Direction::Down,
+        "left" => Direction::Left,
+        "right" => Direction::Right,
+        other => return Err(format!("unknown direction {other:?}")),
+    };
+    let arrow = rest.iter().position(|&line| line == "=>").ok_or("no =>")?;
+    let [after @ .., score] = &rest[arrow + 1..] else {
+        return Err("no board after =>".to_string());
+    };
+    let score = score
+        .strip_prefix("score ")
+        .and_then(|score| score.parse().ok())
+        .ok_or("no score line")?;
+    Ok(MoveCase {
+        text: block.trim().to_string(),
+        dir,
+        before: board(&rest[..arrow])?,
+        after: board(after)?,
+        score,
+    })
+}
+
+#[test]
+fn test_move_fixtures() {
+    let fixtures = include_str!("engine/fixtures/moves.txt");
+    // The first block describes the file.
+    let cases: Vec<MoveCase> = fixtures
+        .split("\n\n")
+        .skip(1)
+        .map(|block| parse_move_case(block).unwrap_or_else(|err| panic!("{err} in:\n{block}")))
+        .collect();
+    assert!(cases.len() >= 10);
+    for case in cases {
+        let mut board = case.before.clone();
+        let result = board.apply_move(case.dir);
+        assert_eq!(board, case.after, "\n{}", case.text);
+        assert_eq!(result.score_delta, case.score, "\n{}", case.text);
+        assert_eq!(result.moved, case.before != case.after, "\n{}", case.text);
+
+        let mut slid = case.before.clone();
+        let points = slid.slide(case.dir);
+        assert_eq!(slid, case.after, "\n{}", case.text);
+        assert_eq!(
+            points,
+            result.moved.then_some(case.score),
+            "\n{}",
+            case.text
+        );
+        assert_eq!(
+            case.before.can_move(case.dir),
+            result.moved,
+            "\n{}",
+            case.text
+        );
+    }
+}
+
+#[test]
+fn test_slide() {
+    let mut board = Board::from_cells(4, 4, vec![2, 2, 4, 8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+    assert!(board.can_move(Direction::Down));
+    assert_eq!(board.slide(Direction::Left), Some(4));
+    assert_eq!(&board.cells()[..4], &[4, 4, 8, 0]);
+    assert_eq!(board.slide(Direction::Up), None);
+
+    // Two 32768s merge past what the bitboard holds.
+    let mut top = vec![0; 16];
+    top[..2].copy_from_slice(&[32768, 32768]);
+    let mut board = Board::from_cells(4, 4, top);
+    assert!(board.can_move(Direction::Left));
+    assert_eq!(board.slide(Direction::Left), Some(65536));
+    assert_eq!(board.max_tile(), 65536);
+}
+
+#[cfg(test)]
+mod proptests {
+    use super::*;
+
+    // These tests build boards from ranks: 0 is an empty cell and any other
+    // rank r the tile 2^r. Boards come in every size from 2x2 to 6x6.
+    fn board_from_ranks(rows: usize, cols: usize, ranks: &[u32]) -> Board {
+        let cells = ranks[..rows * cols]
+            .iter()
+            .map(|&rank| match rank {
+                0 => 0,
+                rank => 1 << rank,
+            })
+            .collect();
+        Board::from_cells(rows, cols, cells)
+    }
+
+    fn sorted_tiles(cells: impl IntoIterator<Item = u32>) -> Vec<u32> {
+        let mut tiles: Vec<u32> = cells.into_iter().filter(|&value| value > 0).collect();
+        tiles.sort_unstable();
+        tiles
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn test_move_keeps_tile_total(
+            rows in 2usize..7,
+            cols in 2usize..7,
+            ranks in proptest::collection::vec(0u32..12, 36),
+            dir in 0usize..4,
+        ) {
+            let mut board = board_from_ranks(rows, cols, &ranks);
+            let total: u32 = board.cells().iter().sum();
+            board.apply_move(Direction::ALL[dir]);
+            proptest::prop_assert_eq!(board.cells().iter().sum::<u32>(), total);
+        }
+
+        #[test]
+        fn test_score_is_merged_values(
+            rows in 2usize..7,
+            cols in 2usize..7,
+            ranks in proptest::collection::vec(0u32..12, 36),
+            dir in 0usize..4,
+        ) {
+            let mut board = board_from_ranks(rows, cols, &ranks);
+            let result = board.apply_move(Direction::ALL[dir]);
+            let merged: u32 = result.merges.iter().map(|merge| merge.value).sum();
+            proptest::prop_assert_eq!(result.score_delta, merged);
+            proptest::prop_assert!(result.moved || result.merges.is_empty());
+        }
+
+        #[test]
+        fn test_move_makes_only_merged_tiles(
+            rows in 2usize..7,
+            cols in 2usize..7,
+            ranks in proptest::collection::vec(0u32..12, 36),
+            dir in 0usize..4,
+        ) {
+            let mut board = board_from_ranks(rows, cols, &ranks);
+            let before = board.cells().to_vec();
+            let result = board.apply_move(Direction::ALL[dir]);
+            // Every tile afterwards either slid there untouched or is a merge
+            // of tiles that were on the board before.
+            let mut expected = before.clone();
+            for merge in &result.merges {
+                let sources: u32 = merge.sources.iter().map(|&idx| before[idx]).sum();
+                proptest::prop_assert_eq!(sources, merge.value);
+                for &idx in &merge.sources {
+                    expected[idx] = 0;
+                }
+                expected.push(merge.value);
+            }
+            proptest::prop_assert_eq!(sorted_tiles(board.cells().to_vec()), sorted_tiles(expected));
+        }
+
+        #[test]
+        fn test_game_over_is_no_move(
+            rows in 2usize..7,
+            cols in 2usize..7,
+            ranks in proptest::collection::vec(1u32..5, 36),
+        ) {
+            // Small tiles on a full board leave some boards stuck and some not.
+            let board = board_from_ranks(rows, cols, &ranks);
+            let stuck = Direction::ALL
+                .into_iter()
+                .all(|dir| !board.clone().apply_move(dir).moved);
+            proptest::prop_assert_eq!(board.is_game_over(), stuck);
+        }
+    }
+}
@@ -0,0 +1,249 @@
+use super::rules::Ruleset;
+use super::{Board, Direction};
+use std::sync::OnceLock;
+
+const ROW_MASK: u64 = 0xffff;
+// A nibble can hold exponents up to 15, i.e. a 32768 tile.
+const MAX_EXPONENT: u8 = 15;
+
+/// A 4x4 board packed into a `u64`: each cell is a 4-bit exponent (0 for an
+/// empty cell, `k` for a `2^k` tile), cell `i` sitting at bits `4*i..4*i+4`.
+/// Moves are row-table lookups, which is what makes deep searches affordable.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BitBoard(pub u64);
+
+struct Tables {
+    left: Vec<u16>,
+    right: Vec<u16>,
+    score: Vec<u32>,
+}
+
+fn decode_row(row: u16) -> [u8; 4] {
+    std::array::from_fn(|c| ((row >> (4 * c)) & 0xf) as u8)
+}
+
+fn encode_row(line: [u8; 4]) -> u16 {
+    line.iter()
+        .enumerate()
+        .fold(0, |row, (c, &e)| row | (e as u16) << (4 * c))
+}
+
+// Slides one row towards cell 0, the same way `Board::slide_line` does.
+fn slide_row(line: [u8; 4]) -> ([u8; 4], u32) {
+    let tiles: Vec<u8> = line.iter().copied().filter(|&e| e != 0).collect();
+    let mut out = [0u8; 4];
+    let mut score = 0;
+    let (mut k, mut slot) = (0, 0);
+    while k < tiles.len() {
+        if k + 1 < tiles.len() && tiles[k] == tiles[k + 1] && tiles[k] < MAX_EXPONENT {
+            out[slot] = tiles[k] + 1;
+            score += 1 << out[slot];
+            k += 2;
+        } else {
+            out[slot] = tiles[k];
+            k += 1;
+        }
+        slot += 1;
+    }
+    (out, score)
+}
+
+fn tables() -> &'static Tables {
+    static TABLES: OnceLock<Tables> = OnceLock::new();
+    TABLES.get_or_init(|| {
+        let mut tables = Tables {
+            left: vec![0; 1 << 16],
+            right: vec![0; 1 << 16],
+            score: vec![0; 1 << 16],
+        };
+        for row in 0..=u16::MAX {
+            let line = decode_row(row);
+            let (left, score) = slide_row(line);
+            let mut reversed = line;
+            reversed.reverse();
+            let (mut right, _) = slide_row(reversed);
+            right.reverse();
+
+            tables.left[row as usize] = encode_row(left);
+            tables.right[row as usize] = encode_row(right);
+            tables.score[row as usize] = score;
+        }
+        tables
+    })
+}
+
+impl BitBoard {
+    /// Packs a 4x4 board whose tiles all fit in a nibble.
+    pub fn from_board(board: &Board) -> Option<BitBoard> {
+        let walls = !board.obstacles().is_empty() || !board.holes().is_empty();
+        if board.rows() != 4
+            || board.cols() != 4
+            || walls
+            || board.wraps()
+            || !board.specials().is_empty()
+            || board.ruleset() != Ruleset::Classic
+        {
+            return None;
+        }
+        let mut bits = 0u64;
+        for (i, &value) in board.cells().iter().enumerate() {
+            let exponent = match value {
+                0 => 0,
+                v if v.is_power_of_two() && v.trailing_zeros() <= MAX_EXPONENT as u32 => {
+                    v.trailing_zeros() as u64
+                }
+                _ => return None,
+            };
+            bits |= exponent << (4 * i);
+        }
+        Some(BitBoard(bits))
+    }
+
+    pub fn to_board(self) -> Board {
+        let cells = (0..16)
+            .map(|i| match self.exponent(i) {
+                0 => 0,
+                e => 1 << e,
+            })
+            .collect();
+        Board::from_cells(4, 4, cells)
+    }
+
+    pub fn exponent(self, idx: usize) -> u8 {
+        ((self.0 >> (4 * idx)) & 0xf) as u8
+    }
+
+    pub fn with_exponent(self, idx: usize, exponent: u8) -> BitBoard {
+        let shift = 4 * idx;
+        BitBoard((self.0 & !(0xf << shift)) | ((exponent as u64 & 0xf) << shift))
+    }
+
+    pub fn empty_cells(self) -> impl Iterator<Item = usize> {
+        (0..16).filter(move |&i| self.exponent(i) == 0)
+    }
+
+    pub fn max_exponent(self) -> u8 {
+        (0..16).map(|i| self.exponent(i)).max().unwrap_or(0)
+    }
+
+    pub fn row(self, r: usize) -> u16 {
+        ((self.0 >> (16 * r)) & ROW_MASK) as u16
+    }
+
+    pub fn transpose(self) -> BitBoard {
+        let x = self.0;
+        let a1 = x & 0xf0f0_0f0f_f0f0_0f0f;
+        let a2 = x & 0x0000_f0f0_0000_f0f0;
+        let a3 = x & 0x0f0f_0000_0f0f_0000;
+        let a = a1 | (a2 << 12) | (a3 >> 12);
+        let b1 = a & 0xff00_ff00_00ff_00ff;
+        let b2 = a & 0x00ff_00ff_0000_0000;
+        let b3 = a & 0x0000_0000_ff00_ff00;
+        BitBoard(b1 | (b2 >> 24) | (b3 << 24))
+    }
+
+    /// The board reflected left to right.
+    pub fn mirror(self) -> BitBoard {
+        let x = self.0;
+        BitBoard(
+            ((x & 0x000f_000f_000f_000f) << 12)
+                | ((x & 0x00f0_00f0_00f0_00f0) << 4)
+                | ((x & 0x0f00_0f00_0f00_0f00) >> 4)
+                | ((x & 0xf000_f000_f000_f000) >> 12),
+        )
+    }
+
+    /// The board reflected top to bottom.
+    pub fn flip(self) -> BitBoard {
+        let x = self.0;
+        BitBoard((x << 48) | ((x & 0xffff_0000) << 16) | ((x >> 16) & 0xffff_0000) | (x >> 48))
+    }
+
+    /// The smallest encoding among the board's eight rotations and
+    /// reflections, so symmetric positions compare equal.
+    pub fn canonical(self) -> BitBoard {
+        let mut best = self;
+        for t in [self, self.transpose()] {
+            for v in [t, t.flip()] {
+                for h in [v, v.mirror()] {
+                    best = best.min(h);
+                }
+            }
+        }
+        best
+    }
+
+    /// The board after sliding in `dir`, with the points scored.
+    pub fn apply_move(self, dir: Direction) -> (BitBoard, u32) {
+        let tables = tables();
+        let (board, table) = match dir {
+            Direction::Left => (self, &tables.left),
+            Direction::Right => (self, &tables.right),
+            Direction::Up => (self.transpose(), &tables.left),
+            Direction::Down => (self.transpose(), &tables.right),
+        };
+        let mut bits = 0u64;
+        let mut score = 0;
+        for r in 0..4 {
+            let row = board.row(r) as usize;
+            bits |= (table[row] as u64) << (16 * r);
+            score += tables.score[row];
+        }
+        let moved = BitBoard(bits);
+        match dir {
+            Direction::Up | Direction::Down => (moved.transpose(), score),
+            Direction::Left | Direction::Right => (moved, score),
+        }
+    }
+}
+
+#[test]
+fn test_matches_board_moves() {
+    use rand::prelude::*;
+
+    let mut rng = super::seeded_rng(3);
+    for _ in 0..200 {
+        let cells = (0..16)
+            .map(|_| match rng.random_range(0..4) {
+                0 => 0,
+                _ => 1u32 << rng.random_range(1..5),
+            })
+            .collect();
+        let board = Board::from_cells(4, 4, cells);
+        let bits = BitBoard::from_board(&board).unwrap();
+        assert_eq!(bits.transpose().transpose(), bits);
+        assert_eq!(bits.to_board(), board);
+
+        for dir in Direction::ALL {
+            let mut expected = board.clone();
+            let result = expected.apply_move(dir);
+            let (moved, score) = bits.apply_move(dir);
+            assert_eq!(moved.to_board(), expected);
+            assert_eq!(score, result.score_delta);
+        }
+    }
+}
+
+#[test]
+fn test_canonical() {
+    let board = Board::from_cells(
+        4,
+        4,
+        vec![
+            2, 4, 0, 0, //
+            0, 8, 0, 0, //
+            0, 0, 0, 16, //
+            0, 0, 0, 2, //
+        ],
+    );
+    let bits = BitBoard::from_board(&board).unwrap();
+    assert_eq!(bits.mirror().mirror(), bits);
+    assert_eq!(bits.flip().flip(), bits);
+    assert_eq!(bits.mirror().exponent(3), 1);
+    assert_eq!(bits.flip().exponent(12), 1);
+
+    let rotated = bits.transpose().mirror();
+    assert_ne!(rotated, bits);
+    assert_eq!(rotated.canonical(), bits.canonical());
+    assert_eq!(bits.flip().mirror().canonical(), bits.canonical());
+}
@@ -0,0 +1,197 @@
+use super::Board;
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// A one-off helper earned in arcade games.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PowerUp {
+    /// Trades the places of two tiles.
+    Swap,
+    /// Takes one tile off the board.
+    Delete,
+    /// Scatters every tile across the open cells.
+    Shuffle,
+}
+
+impl PowerUp {
+    pub const ALL: [PowerUp; 3] = [PowerUp::Swap, PowerUp::Delete, PowerUp::Shuffle];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PowerUp::Swap => "Swap",
+            PowerUp::Delete => "Delete",
+            PowerUp::Shuffle => "Shuffle",
+        }
+    }
+
+    /// How many tiles have to be picked before it goes off.
+    pub fn targets(self) -> usize {
+        match self {
+            PowerUp::Swap => 2,
+            PowerUp::Delete => 1,
+            PowerUp::Shuffle => 0,
+        }
+    }
+
+    /// The power-up earned by merging into a tile of `rank`. Everything from
+    /// 128 up pays out, going round swap, delete and shuffle.
+    pub fn reward(rank: u32) -> Option<PowerUp> {
+        let step = rank.checked_sub(7)?;
+        Some(PowerUp::ALL[step as usize % PowerUp::ALL.len()])
+    }
+}
+
+/// The power-ups a player has in hand.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Inventory {
+    #[serde(default)]
+    swap: u32,
+    #[serde(default)]
+    delete: u32,
+    #[serde(default)]
+    shuffle: u32,
+}
+
+impl Inventory {
+    fn slot(&mut self, power_up: PowerUp) -> &mut u32 {
+        match power_up {
+            PowerUp::Swap => &mut self.swap,
+            PowerUp::Delete => &mut self.delete,
+            PowerUp::Shuffle => &mut self.shuffle,
+        }
+    }
+
+    pub fn count(self, power_up: PowerUp) -> u32 {
+        match power_up {
+            PowerUp::Swap => self.swap,
+            PowerUp::Delete => self.delete,
+            PowerUp::Shuffle => self.shuffle,
+        }
+    }
+
+    pub fn is_empty(self) -> bool {
+        self == Inventory::default()
+    }
+
+    pub fn add(&mut self, power_up: PowerUp) {
+        let slot = self.slot(power_up);
+        *slot = slot.saturating_add(1);
+    }
+
+    /// Uses up one `power_up`; false if there is none left.
+    pub fn take(&mut self, power_up: PowerUp) -> bool {
+        let slot = self.slot(power_up);
+        let had = *slot > 0;
+        *slot = slot.saturating_sub(1);
+        had
+    }
+}
+
+/// A power-up aimed at the board. A shuffle carries the cells it ends up
+/// with, so replays can redo it without the RNG.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PowerUpUse {
+    Swap(usize, usize),
+    Delete(usize),
+    Shuffle(Vec<u32>),
+}
+
+impl PowerUpUse {
+    pub fn power_up(&self) -> PowerUp {
+        match self {
+            PowerUpUse::Swap(..) => PowerUp::Swap,
+            PowerUpUse::Delete(_) => PowerUp::Delete,
+            PowerUpUse::Shuffle(_) => PowerUp::Shuffle,
+        }
+    }
+}
+
+impl Board {
+    fn is_tile(&self, idx: usize) -> bool {
+        self.datas.get(idx).is_some_and(|&v| v != 0)
+    }
+
+    /// Trades the tiles on `a` and `b`; false unless both hold one.
+    pub fn swap_tiles(&mut self, a: usize, b: usize) -> bool {
+        if a == b || !self.is_tile(a) || !self.is_tile(b) {
+            return false;
+        }
+        self.datas.swap(a, b);
+        true
+    }
+
+    /// Empties `idx`; false if there was no tile on it.
+    pub fn remove_tile(&mut self, idx: usize) -> bool {
+        if !self.is_tile(idx) {
+            return false;
+        }
+        self.datas[idx] = 0;
+        true
+    }
+
+    // Cells a shuffle can put numbers on.
+    fn is_open(&self, idx: usize) -> bool {
+        !self.is_wall(idx) && self.special(idx).is_none()
+    }
+
+    /// The cells after scattering every number at random over the open
+    /// cells. Special tiles stay where they are.
+    pub fn shuffled_cells(&self, rng: &mut impl Rng) -> Vec<u32> {
+        let open: Vec<usize> = (0..self.datas.len()).filter(|&i| self.is_open(i)).collect();
+        let mut values: Vec<u32> = open.iter().map(|&i| self.datas[i]).collect();
+        values.shuffle(rng);
+        let mut cells = self.datas.clone();
+        for (&i, value) in open.iter().zip(values) {
+            cells[i] = value;
+        }
+        cells
+    }
+
+    /// Carries out `power_up`; false, leaving the board alone, if it doesn't
+    /// fit this board.
+    pub fn apply_power_up(&mut self, power_up: &PowerUpUse) -> bool {
+        match power_up {
+            &PowerUpUse::Swap(a, b) => self.swap_tiles(a, b),
+            &PowerUpUse::Delete(idx) => self.remove_tile(idx),
+            PowerUpUse::Shuffle(cells) => {
+                let fits = cells.len() == self.datas.len()
+                    && (0..cells.len()).all(|i| self.is_open(i) || cells[i] == 0);
+                if fits {
+                    self.datas.clone_from(cells);
+                }
+                fits
+            }
+        }
+    }
+}
+
+#[test]
+fn test_power_ups() {
+    assert_eq!(PowerUp::reward(6), None);
+    assert_eq!(PowerUp::reward(7), Some(PowerUp::Swap));
+    assert_eq!(PowerUp::reward(9), Some(PowerUp::Shuffle));
+    assert_eq!(PowerUp::reward(10), Some(PowerUp::Swap));
+
+    let mut inventory = Inventory::default();
+    assert!(!inventory.take(PowerUp::Delete));
+    inventory.add(PowerUp::Delete);
+    assert_eq!(inventory.count(PowerUp::Delete), 1);
+    assert!(inventory.take(PowerUp::Delete));
+    assert!(inventory.is_empty());
+
+    let mut board = Board::from_cells(2, 2, vec![2, 4, 0, 8]);
+    board.set_obstacle(2);
+    assert!(board.apply_power_up(&PowerUpUse::Swap(0, 3)));
+    assert_eq!(board.cells(), [8, 4, 0, 2]);
+    assert!(!board.apply_power_up(&PowerUpUse::Swap(0, 2)));
+    assert!(board.apply_power_up(&PowerUpUse::Delete(1)));
+    assert!(!board.apply_power_up(&PowerUpUse::Delete(1)));
+    assert_eq!(board.cells(), [8, 0, 0, 2]);
+
+    let cells = board.shuffled_cells(&mut super::seeded_rng(3));
+    assert_eq!(cells[2], 0);
+    assert_eq!(cells.iter().sum::<u32>(), 10);
+    assert!(board.apply_power_up(&PowerUpUse::Shuffle(cells)));
+    assert!(!board.apply_power_up(&PowerUpUse::Shuffle(vec![0, 0, 2, 0])));
+}
@@ -0,0 +1,228 @@
+use super::WIN_TILE;
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
+
+/// How tiles combine when one slides into another, along with the values
+/// that follow from it.
+pub trait MergeRule: Send + Sync {
+    /// How many tiles combine at once.
+    fn group(&self) -> usize {
+        2
+    }
+
+    /// What a run of [`MergeRule::group`] neighbouring tiles makes, or `None`
+    /// if they don't combine. The answer must not depend on the order of
+    /// `tiles`, as they are read in the direction of the move.
+    fn merge(&self, tiles: &[u32]) -> Option<u32>;
+
+    /// This rule's counterpart of a classic spawn of 2, 4 or 8. Rules that
+    /// need more randomness than that draw it from `rng`.
+    fn spawn_value(&self, classic: u32, _rng: &mut dyn RngCore) -> u32 {
+        classic
+    }
+
+    /// The tile that wins the game.
+    fn win_tile(&self) -> u32;
+
+    /// How far up the sequence `value` is, 1 for the smallest tile. Tiles are
+    /// colored by it.
+    fn rank(&self, value: u32) -> u32;
+}
+
+/// Equal tiles double: 2 + 2 = 4, 4 + 4 = 8...
+pub struct Doubling;
+
+impl MergeRule for Doubling {
+    fn merge(&self, tiles: &[u32]) -> Option<u32> {
+        let &[a, b] = tiles else { return None };
+        (a == b).then(|| a << 1)
+    }
+
+    fn win_tile(&self) -> u32 {
+        WIN_TILE
+    }
+
+    fn rank(&self, value: u32) -> u32 {
+        value.trailing_zeros()
+    }
+}
+
+/// Neighbouring Fibonacci numbers add up: 1 + 1 = 2, 1 + 2 = 3, 2 + 3 = 5...
+pub struct Fibonacci;
+
+// Position of `value` in 1, 2, 3, 5, 8..., counting from 1.
+fn fibonacci_index(value: u32) -> Option<u32> {
+    let (mut a, mut b, mut index) = (1u32, 2u32, 1);
+    while a < value {
+        (a, b) = (b, a.checked_add(b)?);
+        index += 1;
+    }
+    (a == value).then_some(index)
+}
+
+impl MergeRule for Fibonacci {
+    fn merge(&self, tiles: &[u32]) -> Option<u32> {
+        let &[a, b] = tiles else { return None };
+        let (i, j) = (fibonacci_index(a)?, fibonacci_index(b)?);
+        (i.abs_diff(j) == 1 || a == 1 && b == 1).then(|| a + b)
+    }
+
+    fn spawn_value(&self, classic: u32, _rng: &mut dyn RngCore) -> u32 {
+        match classic {
+            2 => 1,
+            4 => 2,
+            _ => 3,
+        }
+    }
+
+    // The first Fibonacci number past 2048.
+    fn win_tile(&self) -> u32 {
+        2584
+    }
+
+    fn rank(&self, value: u32) -> u32 {
+        fibonacci_index(value).unwrap_or(0)
+    }
+}
+
+/// Like Threes: a 1 and a 2 make 3, and from there on equal tiles double.
+/// 1s and 2s never merge with their own kind.
+pub struct Threes;
+
+impl MergeRule for Threes {
+    fn merge(&self, tiles: &[u32]) -> Option<u32> {
+        match *tiles {
+            [1, 2] | [2, 1] => Some(3),
+            [a, b] if a == b && a >= 3 => Some(a * 2),
+            _ => None,
+        }
+    }
+
+    // 1s and 2s come up about equally often; the classic 4s and 8s become
+    // the rarer 3s and 6s.
+    fn spawn_value(&self, classic: u32, rng: &mut dyn RngCore) -> u32 {
+        match classic {
+            2 if rng.random_bool(0.5) => 1,
+            2 => 2,
+            4 => 3,
+            _ => 6,
+        }
+    }
+
+    // 3 x 1024, standing in for 2048.
+    fn win_tile(&self) -> u32 {
+        3072
+    }
+
+    fn rank(&self, value: u32) -> u32 {
+        match value {
+            0 => 0,
+            1 | 2 => 1,
+            v => (v / 3).trailing_zeros() + 2,
+        }
+    }
+}
+
+/// Powers of three: it takes three equal tiles in a row to make one tile
+/// worth three times as much, 3 + 3 + 3 = 9.
+pub struct Tripling;
+
+impl MergeRule for Tripling {
+    fn group(&self) -> usize {
+        3
+    }
+
+    fn merge(&self, tiles: &[u32]) -> Option<u32> {
+        let &[a, b, c] = tiles else { return None };
+        (a == b && b == c).then(|| a.saturating_mul(3))
+    }
+
+    fn spawn_value(&self, classic: u32, _rng: &mut dyn RngCore) -> u32 {
+        match classic {
+            2 => 3,
+            4 => 9,
+            _ => 27,
+        }
+    }
+
+    // 3^7, the first power of three past 2048.
+    fn win_tile(&self) -> u32 {
+        2187
+    }
+
+    fn rank(&self, value: u32) -> u32 {
+        value.checked_ilog(3).unwrap_or(0)
+    }
+}
+
+/// Which [`MergeRule`] a board plays by.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Ruleset {
+    #[default]
+    Classic,
+    Fibonacci,
+    Threes,
+    Triples,
+}
+
+impl Ruleset {
+    pub fn rule(self) -> &'static dyn MergeRule {
+        match self {
+            Ruleset::Classic => &Doubling,
+            Ruleset::Fibonacci => &Fibonacci,
+            Ruleset::Threes => &Threes,
+            Ruleset::Triples => &Tripling,
+        }
+    }
+}
+
+#[test]
+fn test_doubling() {
+    assert_eq!(Doubling.merge(&[4, 4]), Some(8));
+    assert_eq!(Doubling.merge(&[4, 8]), None);
+    assert_eq!(Doubling.rank(2048), 11);
+}
+
+#[test]
+fn test_fibonacci() {
+    assert_eq!(Fibonacci.merge(&[1, 1]), Some(2));
+    assert_eq!(Fibonacci.merge(&[2, 1]), Some(3));
+    assert_eq!(Fibonacci.merge(&[5, 8]), Some(13));
+    assert_eq!(Fibonacci.merge(&[2, 2]), None);
+    assert_eq!(Fibonacci.merge(&[3, 8]), None);
+    assert_eq!(Fibonacci.merge(&[4, 5]), None);
+    assert_eq!(
+        [1, 2, 3, 5, 8, 2584].map(|v| Fibonacci.rank(v)),
+        [1, 2, 3, 4, 5, 17]
+    );
+    assert_eq!(Fibonacci.spawn_value(2, &mut rand::rng()), 1);
+}
+
+#[test]
+fn test_threes() {
+    assert_eq!(Threes.merge(&[1, 2]), Some(3));
+    assert_eq!(Threes.merge(&[2, 1]), Some(3));
+    assert_eq!(Threes.merge(&[1, 1]), None);
+    assert_eq!(Threes.merge(&[2, 2]), None);
+    assert_eq!(Threes.merge(&[3, 3]), Some(6));
+    assert_eq!(Threes.merge(&[6, 3]), None);
+    assert_eq!(
+        [1, 2, 3, 6, 12, 3072].map(|v| Threes.rank(v)),
+        [1, 1, 2, 3, 4, 12]
+    );
+
+    let mut rng = super::seeded_rng(9);
+    let spawns: Vec<u32> = (0..100).map(|_| Threes.spawn_value(2, &mut rng)).collect();
+    assert!(spawns.contains(&1) && spawns.contains(&2));
+    assert!(spawns.iter().all(|&v| v == 1 || v == 2));
+    assert_eq!(Threes.spawn_value(4, &mut rng), 3);
+}
+
+#[test]
+fn test_tripling() {
+    assert_eq!(Tripling.merge(&[3, 3, 3]), Some(9));
+    assert_eq!(Tripling.merge(&[3, 3]), None);
+    assert_eq!(Tripling.merge(&[9, 9, 3]), None);
+    assert_eq!([3, 9, 27, 2187].map(|v| Tripling.rank(v)), [1, 2, 3, 7]);
+}
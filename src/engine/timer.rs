@@ -0,0 +1,65 @@
+use std::time::{Duration, Instant};
+
+/// A countdown that only runs while resumed, so time spent paused (with the
+/// window in the background, say) doesn't count against the player. Every
+/// method takes the current instant, which keeps it deterministic in tests.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Countdown {
+    limit: Duration,
+    elapsed: Duration,
+    resumed_at: Option<Instant>,
+}
+
+impl Countdown {
+    /// A paused countdown with the whole `limit` left.
+    pub fn new(limit: Duration) -> Countdown {
+        Countdown {
+            limit,
+            elapsed: Duration::ZERO,
+            resumed_at: None,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.resumed_at.is_some()
+    }
+
+    pub fn resume(&mut self, now: Instant) {
+        self.resumed_at.get_or_insert(now);
+    }
+
+    pub fn pause(&mut self, now: Instant) {
+        if let Some(resumed_at) = self.resumed_at.take() {
+            self.elapsed += now.saturating_duration_since(resumed_at);
+        }
+    }
+
+    pub fn remaining(&self, now: Instant) -> Duration {
+        let running = self
+            .resumed_at
+            .map_or(Duration::ZERO, |at| now.saturating_duration_since(at));
+        self.limit.saturating_sub(self.elapsed + running)
+    }
+
+    pub fn is_expired(&self, now: Instant) -> bool {
+        self.remaining(now).is_zero()
+    }
+}
+
+#[test]
+fn test_countdown_pauses() {
+    let start = Instant::now();
+    let at = |secs| start + Duration::from_secs(secs);
+    let mut countdown = Countdown::new(Duration::from_secs(60));
+    assert_eq!(countdown.remaining(at(30)), Duration::from_secs(60));
+
+    countdown.resume(at(0));
+    countdown.pause(at(20));
+    assert!(!countdown.is_running());
+    assert_eq!(countdown.remaining(at(500)), Duration::from_secs(40));
+
+    countdown.resume(at(500));
+    assert_eq!(countdown.remaining(at(530)), Duration::from_secs(10));
+    assert!(countdown.is_expired(at(540)));
+    assert_eq!(countdown.remaining(at(600)), Duration::ZERO);
+}
@@ -0,0 +1,101 @@
+use crate::engine::{Direction, Merge, MoveResult};
+
+/// Something that happened in a game. The move handlers only say what
+/// happened; sound, statistics, celebrations and the like each pick out
+/// the events they care about.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// A move slid the tiles.
+    Moved(Direction),
+    /// A move was asked for that slides nothing.
+    Blocked(Direction),
+    TilesMerged(Vec<Merge>),
+    TileSpawned {
+        index: usize,
+        value: u32,
+    },
+    /// `gained` points brought the score to `score`.
+    ScoreChanged {
+        score: u32,
+        gained: u32,
+    },
+    /// A merge made a tile bigger than any on the board before.
+    Milestone(u32),
+    Won,
+    GameOver,
+}
+
+/// What a move that slid the tiles amounts to. `record` is the biggest tile
+/// before the move, and `score` the score after it.
+pub fn move_events(dir: Direction, result: &MoveResult, record: u32, score: u32) -> Vec<Event> {
+    let mut events = vec![Event::Moved(dir)];
+    if !result.merges.is_empty() {
+        events.push(Event::TilesMerged(result.merges.clone()));
+    }
+    if result.score_delta > 0 {
+        events.push(Event::ScoreChanged {
+            score,
+            gained: result.score_delta,
+        });
+    }
+    let biggest = result.merges.iter().map(|merge| merge.value).max();
+    if let Some(tile) = biggest.filter(|&tile| tile > record) {
+        events.push(Event::Milestone(tile));
+    }
+    events
+}
+
+/// Events waiting to be handled, in the order they happened.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EventQueue {
+    pending: Vec<Event>,
+}
+
+impl EventQueue {
+    pub fn emit(&mut self, event: Event) {
+        self.pending.push(event);
+    }
+
+    pub fn extend(&mut self, events: impl IntoIterator<Item = Event>) {
+        self.pending.extend(events);
+    }
+
+    /// Everything emitted since the last drain.
+    pub fn drain(&mut self) -> Vec<Event> {
+        std::mem::take(&mut self.pending)
+    }
+}
+
+#[test]
+fn test_move_events() {
+    use crate::engine::Board;
+
+    let mut board = Board::from_cells(3, 3, vec![2, 2, 0, 8, 8, 0, 0, 4, 0]);
+    let result = board.apply_move(Direction::Left);
+    let events = move_events(Direction::Left, &result, 8, 20);
+    assert_eq!(events[0], Event::Moved(Direction::Left));
+    assert!(matches!(&events[1], Event::TilesMerged(merges) if merges.len() == 2));
+    assert_eq!(
+        events[2..],
+        [
+            Event::ScoreChanged {
+                score: 20,
+                gained: 20,
+            },
+            Event::Milestone(16),
+        ]
+    );
+
+    // Sliding without merging is only a move.
+    let result = board.apply_move(Direction::Right);
+    assert_eq!(
+        move_events(Direction::Right, &result, 16, 20),
+        [Event::Moved(Direction::Right)]
+    );
+
+    let mut queue = EventQueue::default();
+    queue.emit(Event::Won);
+    queue.extend([Event::GameOver]);
+    assert_eq!(queue.drain(), [Event::Won, Event::GameOver]);
+    assert!(queue.drain().is_empty());
+}
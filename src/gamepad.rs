@@ -0,0 +1,143 @@
+use crate::engine::Direction;
+
+// How far a stick has to be pushed to count as a flick, and how close to the
+// middle it has to come back before it can flick again.
+const FLICK: f32 = 0.6;
+const RELEASE: f32 = 0.3;
+
+/// What a controller asks for, the same as one of the keyboard's actions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PadCommand {
+    Move(Direction),
+    NewGame,
+    Undo,
+}
+
+/// Turns a stick's position into one move per flick, however long the stick
+/// is held out.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Stick {
+    x: f32,
+    y: f32,
+    // Whether the stick is still out from the last flick.
+    held: bool,
+}
+
+impl Stick {
+    /// The move a change of the horizontal axis flicks, if any.
+    pub fn tilt_x(&mut self, x: f32) -> Option<Direction> {
+        self.x = x;
+        self.flick()
+    }
+
+    /// The move a change of the vertical axis flicks, if any. Up is positive.
+    pub fn tilt_y(&mut self, y: f32) -> Option<Direction> {
+        self.y = y;
+        self.flick()
+    }
+
+    fn flick(&mut self) -> Option<Direction> {
+        let reach = self.x.abs().max(self.y.abs());
+        if self.held {
+            self.held = reach >= RELEASE;
+            return None;
+        }
+        if reach < FLICK {
+            return None;
+        }
+        self.held = true;
+        // A diagonal goes the way the stick leans more.
+        Some(match self.x.abs() > self.y.abs() {
+            true if self.x > 0.0 => Direction::Right,
+            true => Direction::Left,
+            false if self.y > 0.0 => Direction::Up,
+            false => Direction::Down,
+        })
+    }
+}
+
+/// Something that happened on a controller since the last poll.
+#[cfg(feature = "gui")]
+#[derive(Clone, Debug, PartialEq)]
+pub enum PadEvent {
+    Command(PadCommand),
+    Connected(String),
+    Disconnected(String),
+}
+
+/// Every controller plugged in, including ones plugged in later.
+#[cfg(feature = "gui")]
+pub struct Gamepads {
+    gilrs: gilrs::Gilrs,
+    sticks: std::collections::HashMap<gilrs::GamepadId, Stick>,
+}
+
+#[cfg(feature = "gui")]
+impl Gamepads {
+    pub fn new() -> Result<Gamepads, gilrs::Error> {
+        Ok(Gamepads {
+            gilrs: gilrs::Gilrs::new()?,
+            sticks: Default::default(),
+        })
+    }
+
+    /// What the controllers have done since the last call. Never blocks.
+    pub fn poll(&mut self) -> Vec<PadEvent> {
+        use gilrs::{Axis, Button, EventType};
+
+        let mut events = Vec::new();
+        while let Some(gilrs::Event { id, event, .. }) = self.gilrs.next_event() {
+            let name = || self.gilrs.gamepad(id).name().to_string();
+            let command = match event {
+                EventType::Connected => {
+                    events.push(PadEvent::Connected(name()));
+                    None
+                }
+                EventType::Disconnected => {
+                    events.push(PadEvent::Disconnected(name()));
+                    self.sticks.remove(&id);
+                    None
+                }
+                EventType::ButtonPressed(button, _) => match button {
+                    Button::DPadUp => Some(PadCommand::Move(Direction::Up)),
+                    Button::DPadDown => Some(PadCommand::Move(Direction::Down)),
+                    Button::DPadLeft => Some(PadCommand::Move(Direction::Left)),
+                    Button::DPadRight => Some(PadCommand::Move(Direction::Right)),
+                    Button::Start | Button::South => Some(PadCommand::NewGame),
+                    Button::East | Button::Select => Some(PadCommand::Undo),
+                    _ => None,
+                },
+                EventType::AxisChanged(axis, value, _) => {
+                    let stick = self.sticks.entry(id).or_default();
+                    match axis {
+                        Axis::LeftStickX => stick.tilt_x(value),
+                        Axis::LeftStickY => stick.tilt_y(value),
+                        _ => None,
+                    }
+                    .map(PadCommand::Move)
+                }
+                _ => None,
+            };
+            events.extend(command.map(PadEvent::Command));
+        }
+        events
+    }
+}
+
+#[test]
+fn test_stick() {
+    let mut stick = Stick::default();
+    assert_eq!(stick.tilt_y(0.4), None);
+    assert_eq!(stick.tilt_y(0.9), Some(Direction::Up));
+    // Holding the stick out doesn't repeat the move.
+    assert_eq!(stick.tilt_y(1.0), None);
+    assert_eq!(stick.tilt_x(-0.5), None);
+    assert_eq!(stick.tilt_y(0.0), None);
+    assert_eq!(stick.tilt_x(0.1), None);
+    // A diagonal goes the way it leans more.
+    assert_eq!(stick.tilt_y(-0.5), None);
+    assert_eq!(stick.tilt_x(0.7), Some(Direction::Right));
+    stick.tilt_x(0.0);
+    stick.tilt_y(0.0);
+    assert_eq!(stick.tilt_y(-0.8), Some(Direction::Down));
+}
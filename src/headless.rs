@@ -0,0 +1,246 @@
+use crate::engine::powerups::Inventory;
+use crate::engine::{Board, Direction, GameConfig, GameRng, MoveResult, seeded_rng};
+use crate::events::{self, Event, EventQueue};
+use crate::persistence::SavedGame;
+
+/// One thing typed at a headless game.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    Move(Direction),
+    Quit,
+}
+
+/// Reads a line typed at a headless game, one command per letter, so a
+/// script can send a whole run of moves at once ("uulr"). Spaces are
+/// skipped; any other letter comes back as the error.
+pub fn parse_commands(line: &str) -> Vec<Result<Command, char>> {
+    line.chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| match c.to_ascii_lowercase() {
+            'u' => Ok(Command::Move(Direction::Up)),
+            'd' => Ok(Command::Move(Direction::Down)),
+            'l' => Ok(Command::Move(Direction::Left)),
+            'r' => Ok(Command::Move(Direction::Right)),
+            'q' => Ok(Command::Quit),
+            _ => Err(c),
+        })
+        .collect()
+}
+
+/// The board drawn in plain ASCII, every cell as wide as the widest label.
+pub fn render(board: &Board) -> String {
+    let (rows, cols) = (board.rows(), board.cols());
+    let labels: Vec<String> = (0..rows * cols)
+        .map(|idx| board.cell_label(idx).unwrap_or_default())
+        .collect();
+    let width = labels.iter().map(String::len).max().unwrap_or(0).max(4);
+    let rule = format!("+{}\n", format!("{}+", "-".repeat(width + 2)).repeat(cols));
+    let mut text = rule.clone();
+    for row in labels.chunks(cols) {
+        text.push('|');
+        for label in row {
+            text.push_str(&format!(" {label:>width$} |"));
+        }
+        text.push('\n');
+        text.push_str(&rule);
+    }
+    text
+}
+
+/// A game played without a window, over plain text or in a terminal. It
+/// saves as the windowed game does, so either can pick up the other's game.
+pub struct TextGame {
+    config: GameConfig,
+    board: Board,
+    rng: GameRng,
+    seed: u64,
+    score: u32,
+    moves: u32,
+    merges: u32,
+    biggest_merge: u32,
+    keep_playing: bool,
+    events: EventQueue,
+}
+
+impl TextGame {
+    pub fn new(config: &GameConfig, seed: u64) -> TextGame {
+        let mut game = TextGame {
+            config: *config,
+            board: Board::for_config(config),
+            rng: seeded_rng(seed),
+            seed,
+            score: 0,
+            moves: 0,
+            merges: 0,
+            biggest_merge: 0,
+            keep_playing: false,
+            events: EventQueue::default(),
+        };
+        for _ in 0..2 {
+            game.board.spawn_tile_with(&mut game.rng, &config.spawn);
+        }
+        game
+    }
+
+    /// Picks a saved game back up, spawns and all.
+    pub fn resume(config: &GameConfig, saved: SavedGame) -> TextGame {
+        TextGame {
+            config: GameConfig {
+                mode: saved.mode,
+                ..*config
+            },
+            board: saved.board,
+            rng: GameRng::from_state(saved.rng_state),
+            seed: saved.seed,
+            score: saved.score,
+            moves: saved.moves_made,
+            merges: saved.merges_made,
+            biggest_merge: saved.biggest_merge,
+            keep_playing: saved.keep_playing,
+            events: EventQueue::default(),
+        }
+    }
+
+    pub fn to_saved(&self) -> SavedGame {
+        SavedGame {
+            board: self.board.clone(),
+            score: self.score,
+            seed: self.seed,
+            rng_state: self.rng.state(),
+            keep_playing: self.keep_playing,
+            mode: self.config.mode,
+            time_left_ms: None,
+            move_budget: None,
+            moves_made: self.moves,
+            merges_made: self.merges,
+            biggest_merge: self.biggest_merge,
+            daily: None,
+            power_ups: Inventory::default(),
+        }
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn score(&self) -> u32 {
+        self.score
+    }
+
+    pub fn moves(&self) -> u32 {
+        self.moves
+    }
+
+    /// Slides the board, spawning new tiles if anything moved. What came of
+    /// it waits in [`TextGame::drain_events`].
+    pub fn slide(&mut self, dir: Direction) -> MoveResult {
+        let _span = tracing::debug_span!("move", ?dir, moves = self.moves).entered();
+        let (record, had_won) = (self.board.max_tile(), self.has_won());
+        let result = self.board.apply_move(dir);
+        if !result.moved {
+            self.events.emit(Event::Blocked(dir));
+            return result;
+        }
+        self.score += result.score_delta;
+        self.moves += 1;
+        self.merges += result.merges.len() as u32;
+        let biggest = result.merges.iter().map(|merge| merge.value).max();
+        self.biggest_merge = self.biggest_merge.max(biggest.unwrap_or(0));
+        self.events
+            .extend(events::move_events(dir, &result, record, self.score));
+        for _ in 0..self.config.spawn.tiles_per_move {
+            let spawned = self
+                .board
+                .spawn_tile_with(&mut self.rng, &self.config.spawn);
+            if let Some(index) = spawned {
+                let value = self.board.cell(index).value();
+                self.events.emit(Event::TileSpawned { index, value });
+            }
+        }
+        if self.has_won() && !had_won {
+            self.events.emit(Event::Won);
+        }
+        if self.is_over() {
+            self.events.emit(Event::GameOver);
+        }
+        result
+    }
+
+    /// Everything that happened since the last call, in order.
+    pub fn drain_events(&mut self) -> Vec<Event> {
+        self.events.drain()
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.board.is_game_over()
+    }
+
+    /// Whether the winning tile is on the board and play hasn't yet been
+    /// carried on past it.
+    pub fn has_won(&self) -> bool {
+        !self.keep_playing && self.board.has_won()
+    }
+
+    pub fn keep_playing(&mut self) {
+        self.keep_playing = true;
+    }
+}
+
+#[test]
+fn test_headless() {
+    assert_eq!(
+        parse_commands("u D\tq"),
+        [
+            Ok(Command::Move(Direction::Up)),
+            Ok(Command::Move(Direction::Down)),
+            Ok(Command::Quit),
+        ]
+    );
+    assert_eq!(
+        parse_commands("lx"),
+        [Ok(Command::Move(Direction::Left)), Err('x')]
+    );
+    assert!(parse_commands("  ").is_empty());
+
+    let board = Board::from_cells(3, 3, vec![2, 0, 0, 0, 1024, 0, 0, 0, 16384]);
+    assert_eq!(
+        render(&board),
+        "+-------+-------+-------+\n\
+         |     2 |       |       |\n\
+         +-------+-------+-------+\n\
+         |       |  1024 |       |\n\
+         +-------+-------+-------+\n\
+         |       |       | 16384 |\n\
+         +-------+-------+-------+\n"
+    );
+
+    let config = GameConfig::default();
+    let mut game = TextGame::new(&config, 5);
+    assert_eq!(game.board().empty_cells().count(), 14);
+    let moved = Direction::ALL
+        .into_iter()
+        .find(|&dir| game.slide(dir).moved)
+        .expect("a fresh board has a move");
+    assert_eq!(game.moves(), 1);
+    let events = game.drain_events();
+    assert_eq!(events[0], Event::Moved(moved));
+    assert!(matches!(events.last(), Some(Event::TileSpawned { .. })));
+    assert!(game.drain_events().is_empty());
+    // Replaying the seed replays the game.
+    let mut again = TextGame::new(&config, game.seed());
+    again.slide(moved);
+    assert_eq!(again.board(), game.board());
+    assert!(!game.is_over());
+
+    let mut resumed = TextGame::resume(&config, game.to_saved());
+    assert_eq!(resumed.to_saved(), game.to_saved());
+    for dir in Direction::ALL {
+        assert_eq!(resumed.slide(dir), game.slide(dir));
+    }
+    assert_eq!(resumed.board(), game.board());
+    assert!(!game.has_won());
+}
@@ -0,0 +1,109 @@
+use serde::{Deserialize, Serialize};
+
+const ELEMENTS: [&str; 36] = [
+    "H", "He", "Li", "Be", "B", "C", "N", "O", "F", "Ne", "Na", "Mg", "Al", "Si", "P", "S", "Cl",
+    "Ar", "K", "Ca", "Sc", "Ti", "V", "Cr", "Mn", "Fe", "Co", "Ni", "Cu", "Zn", "Ga", "Ge", "As",
+    "Se", "Br", "Kr",
+];
+
+const EMOJI: [&str; 20] = [
+    "🌱", "🌿", "🍀", "🌷", "🌻", "🌳", "🐣", "🐥", "🐦", "🦉", "🦅", "🐉", "🌙", "⭐", "🌞", "🌍",
+    "🪐", "🌌", "💎", "👑",
+];
+
+// Shapes that tell tiles apart without color, in the order tiles climb.
+const BADGES: [&str; 8] = ["●", "▲", "■", "◆", "▼", "✚", "♥", "♣"];
+
+/// The shape badge of a tile `rank` steps up. Once the shapes run out they
+/// come round again doubled, then tripled.
+pub fn badge(rank: u32) -> Option<String> {
+    let index = (rank as usize).checked_sub(1)?;
+    Some(BADGES[index % BADGES.len()].repeat(index / BADGES.len() + 1))
+}
+
+/// What is written on a tile. Everything but numbers goes by the tile's rank
+/// under the board's rules, so a set works the same for every variant;
+/// tiles past the end of a set fall back to their number.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TileLabels {
+    #[default]
+    Numbers,
+    /// A, B, C..., then AA, AB...
+    Letters,
+    /// H, He, Li...
+    Elements,
+    Emoji,
+}
+
+impl TileLabels {
+    pub const ALL: [TileLabels; 4] = [
+        TileLabels::Numbers,
+        TileLabels::Letters,
+        TileLabels::Elements,
+        TileLabels::Emoji,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            TileLabels::Numbers => "Numbers",
+            TileLabels::Letters => "Letters",
+            TileLabels::Elements => "Elements",
+            TileLabels::Emoji => "Emoji",
+        }
+    }
+
+    /// The label of a tile worth `value`, `rank` steps up its sequence.
+    pub fn text(self, value: u32, rank: u32) -> String {
+        let from = |set: &[&str]| {
+            let index = (rank as usize).checked_sub(1)?;
+            set.get(index).map(|s| s.to_string())
+        };
+        let label = match self {
+            TileLabels::Numbers => None,
+            TileLabels::Letters => (rank > 0).then(|| letters(rank)),
+            TileLabels::Elements => from(&ELEMENTS),
+            TileLabels::Emoji => from(&EMOJI),
+        };
+        label.unwrap_or_else(|| value.to_string())
+    }
+}
+
+// Spreadsheet-style column names: 1 is A, 26 is Z, 27 is AA.
+fn letters(mut rank: u32) -> String {
+    let mut out = Vec::new();
+    while rank > 0 {
+        rank -= 1;
+        out.push(b'A' + (rank % 26) as u8);
+        rank /= 26;
+    }
+    out.reverse();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn test_tile_labels() {
+    assert_eq!(TileLabels::Numbers.text(2048, 11), "2048");
+    assert_eq!(TileLabels::Letters.text(2, 1), "A");
+    assert_eq!(TileLabels::Letters.text(2048, 11), "K");
+    assert_eq!(TileLabels::Letters.text(0, 27), "AA");
+    assert_eq!(TileLabels::Elements.text(4, 2), "He");
+    assert_eq!(TileLabels::Emoji.text(2, 1), "🌱");
+    // Past the end of the set, and tiles the rules don't rank.
+    assert_eq!(TileLabels::Emoji.text(1 << 30, 30), "1073741824");
+    assert_eq!(TileLabels::Elements.text(5, 0), "5");
+}
+
+#[test]
+fn test_badges() {
+    assert_eq!(badge(0), None);
+    assert_eq!(badge(1).unwrap(), "●");
+    assert_eq!(badge(11).unwrap(), "■■");
+    let badges: Vec<String> = (1..=24).filter_map(badge).collect();
+    assert!(
+        badges
+            .iter()
+            .enumerate()
+            .all(|(i, b)| !badges[..i].contains(b))
+    );
+}
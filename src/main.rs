@@ -1,31 +1,217 @@
-use game_2048::{Down, Enter, Game, Left, Right, Up};
-use gpui::{
-    App, AppContext, Application, Bounds, KeyBinding, WindowBounds, WindowOptions, px, size,
-};
+use game_2048::engine::{GameConfig, MAX_SIZE, MIN_SIZE, parse_size};
+use game_2048::headless::{self, Command, TextGame};
+use game_2048::ui::{Root, bind_keys, window_options};
+use game_2048::{ntuple, persistence, settings};
+use gpui::{App, AppContext, Application};
+use std::io::{self, BufRead};
+use std::path::Path;
+use std::{env, process};
 
-fn main() {
-    Application::new().run(|cx: &mut App| {
-        cx.bind_keys([
-            KeyBinding::new("up", Up, None),
-            KeyBinding::new("left", Left, None),
-            KeyBinding::new("down", Down, None),
-            KeyBinding::new("right", Right, None),
-            KeyBinding::new("w", Up, None),
-            KeyBinding::new("a", Left, None),
-            KeyBinding::new("s", Down, None),
-            KeyBinding::new("d", Right, None),
-            KeyBinding::new("enter", Enter, None),
-        ]);
-
-        let bounds = Bounds::centered(None, size(px(500.), px(600.0)), cx);
-        cx.open_window(
-            WindowOptions {
-                window_bounds: Some(WindowBounds::Windowed(bounds)),
-                ..Default::default()
+// Games between progress reports, and saves, while training.
+const TRAINING_BATCH: u32 = 1000;
+
+struct Args {
+    config: GameConfig,
+    // A replay to check instead of playing, and the score claimed for it.
+    verify: Option<String>,
+    claimed_score: Option<u32>,
+    // Play in the terminal instead of a window.
+    headless: bool,
+}
+
+fn parse_args(mut config: GameConfig) -> Args {
+    let (mut verify, mut claimed_score, mut headless) = (None, None, false);
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--size" => match args.next().as_deref().and_then(parse_size) {
+                Some((rows, cols)) => {
+                    config.rows = rows;
+                    config.cols = cols;
+                }
+                None => eprintln!(
+                    "--size expects N or ROWSxCOLS with each side between {MIN_SIZE} and {MAX_SIZE}"
+                ),
+            },
+            "--seed" => match args.next().and_then(|v| v.parse().ok()) {
+                Some(seed) => config.seed = Some(seed),
+                None => eprintln!("--seed expects an unsigned 64-bit number"),
+            },
+            "--undo-depth" => match args.next().and_then(|v| v.parse().ok()) {
+                Some(depth) => config.history_depth = depth,
+                None => eprintln!("--undo-depth expects a number"),
+            },
+            "--verify" => match args.next() {
+                Some(path) => verify = Some(path),
+                None => eprintln!("--verify expects the path of a replay"),
+            },
+            "--score" => match args.next().and_then(|v| v.parse().ok()) {
+                Some(score) => claimed_score = Some(score),
+                None => eprintln!("--score expects a number"),
             },
-            |_, cx| cx.new(Game::new),
-        )
-        .unwrap();
+            "--headless" => headless = true,
+            // Already seen to before the settings were loaded.
+            "--portable" => {}
+            _ => eprintln!("unknown argument: {arg}"),
+        }
+    }
+    Args {
+        config,
+        verify,
+        claimed_score,
+        headless,
+    }
+}
+
+// Re-plays a replay from its seed and reports whether it, and the score
+// claimed for it, hold up. Returns the exit code.
+fn verify_replay(path: &str, claimed_score: Option<u32>) -> i32 {
+    let Some(replay) = persistence::load_replay(Path::new(path)) else {
+        eprintln!("{path}: not a replay");
+        return 2;
+    };
+    match replay.verify() {
+        Ok(score) => match claimed_score {
+            Some(claimed) if claimed != score => {
+                eprintln!("{path}: claims {claimed} but scores {score}");
+                1
+            }
+            _ => {
+                println!("{path}: verified, scores {score}");
+                0
+            }
+        },
+        Err(mismatch) => {
+            eprintln!("{path}: {mismatch}");
+            1
+        }
+    }
+}
+
+// Plays in the terminal: moves come in on stdin as u, d, l and r, and the
+// board is printed after each one. Returns the exit code.
+fn play_headless(config: &GameConfig) -> i32 {
+    let mut game = TextGame::new(config, config.seed.unwrap_or_else(rand::random));
+    println!("seed {}", game.seed());
+    print!("{}", headless::render(game.board()));
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else {
+            return 1;
+        };
+        for command in headless::parse_commands(&line) {
+            match command {
+                Ok(Command::Move(dir)) => {
+                    if !game.slide(dir).moved {
+                        println!("blocked");
+                        continue;
+                    }
+                    print!("{}", headless::render(game.board()));
+                    println!("score {}", game.score());
+                }
+                Ok(Command::Quit) => return 0,
+                Err(c) => {
+                    eprintln!("unknown move {c:?}: use u, d, l or r, or q to quit");
+                    continue;
+                }
+            }
+            if game.has_won() {
+                game.keep_playing();
+                println!("won");
+            }
+            if game.is_over() {
+                println!(
+                    "game over after {} moves, score {}",
+                    game.moves(),
+                    game.score()
+                );
+                return 0;
+            }
+        }
+    }
+    0
+}
+
+// Trains the learned evaluation by self-play, carrying on from the saved
+// weights and saving after every batch. Returns the exit code.
+fn train(mut args: impl Iterator<Item = String>) -> i32 {
+    let (mut games, mut rate) = (100_000, ntuple::LEARNING_RATE);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--games" => match args.next().and_then(|v| v.parse().ok()) {
+                Some(count) => games = count,
+                None => eprintln!("--games expects a number"),
+            },
+            "--rate" => match args.next().and_then(|v| v.parse().ok()) {
+                Some(value) => rate = value,
+                None => eprintln!("--rate expects a number"),
+            },
+            _ => eprintln!("unknown argument: {arg}"),
+        }
+    }
+
+    let mut network = persistence::load_weights().unwrap_or_default();
+    let mut rng = rand::rng();
+    let mut played = 0;
+    while played < games {
+        let batch = TRAINING_BATCH.min(games - played);
+        let (mut total, mut wins) = (0u64, 0u32);
+        for _ in 0..batch {
+            let (score, end) = network.learn_game(rate, &mut rng);
+            total += score as u64;
+            wins += (end.to_board().max_tile() >= 2048) as u32;
+        }
+        played += batch;
+        println!(
+            "{played} games: average score {}, 2048 in {:.1}%",
+            total / batch as u64,
+            wins as f64 * 100.0 / batch as f64
+        );
+        if let Err(e) = persistence::save_weights(&network) {
+            eprintln!("Couldn't save the weights: {e}");
+            return 1;
+        }
+    }
+    println!("Weights saved to {}", persistence::weights_path().display());
+    0
+}
+
+fn main() {
+    // Nothing is logged unless asked for: RUST_LOG=game_2048=debug traces
+    // every move, spawn and save to stderr.
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .with_writer(std::io::stderr)
+        .init();
+    if env::args().any(|arg| arg == "--portable") {
+        persistence::set_portable();
+    }
+    if env::args().nth(1).as_deref() == Some("train") {
+        process::exit(train(env::args().skip(2)));
+    }
+    let settings = settings::load();
+    let Args {
+        config,
+        verify,
+        claimed_score,
+        headless,
+    } = parse_args(settings.game_config());
+    if let Some(path) = verify {
+        process::exit(verify_replay(&path, claimed_score));
+    }
+    if headless {
+        process::exit(play_headless(&config));
+    }
+    Application::new().run(move |cx: &mut App| {
+        bind_keys(&settings.keybindings, cx);
+
+        let opened = cx.open_window(
+            window_options(false, settings.ui_scale, cx),
+            |window, cx| cx.new(|cx| Root::new(config, settings, window, cx)),
+        );
+        if let Err(err) = opened {
+            eprintln!("Couldn't open the window: {err}");
+            cx.quit();
+        }
     });
 }
 
@@ -0,0 +1,240 @@
+use crate::engine::{Board, BoardShape, GameConfig, MAX_SIZE, MIN_SIZE, SpawnPolicy};
+use crate::race::Race;
+use serde::{Deserialize, Serialize};
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+/// The port games are hosted on, and the one an address without a port
+/// connects to.
+pub const DEFAULT_PORT: u16 = 2048;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What two players send each other, one JSON object per line.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Message {
+    /// From the host: race on these rules from `seed`. Sent again for every
+    /// rematch.
+    Start {
+        seed: u64,
+        rows: usize,
+        cols: usize,
+        shape: BoardShape,
+        spawn: SpawnPolicy,
+        #[serde(default)]
+        battle: bool,
+    },
+    /// The sender's board after each of their moves.
+    Board {
+        board: Board,
+        score: u32,
+        moves: u32,
+    },
+    /// Junk tiles for the receiver's board, sent by a big merge in a battle.
+    Attack { junk: u32 },
+}
+
+impl Message {
+    pub fn start(race: &Race) -> Message {
+        let config = race.config();
+        Message::Start {
+            seed: race.seed(),
+            rows: config.rows,
+            cols: config.cols,
+            shape: config.shape,
+            spawn: config.spawn,
+            battle: race.is_battle(),
+        }
+    }
+
+    /// The race a `Start` sets up, or `None` if its board is too small or
+    /// too big to play on.
+    pub fn race(&self) -> Option<Race> {
+        match *self {
+            Message::Start {
+                seed,
+                rows,
+                cols,
+                shape,
+                spawn,
+                battle,
+            } => {
+                if ![rows, cols]
+                    .iter()
+                    .all(|side| (MIN_SIZE..=MAX_SIZE).contains(side))
+                {
+                    return None;
+                }
+                let config = GameConfig {
+                    rows,
+                    cols,
+                    shape,
+                    spawn,
+                    ..GameConfig::default()
+                };
+                Some(match battle {
+                    true => Race::battle(config, seed),
+                    false => Race::new(config, seed),
+                })
+            }
+            Message::Board { .. } | Message::Attack { .. } => None,
+        }
+    }
+}
+
+/// A link to the other player. Receiving never blocks, so the UI can poll
+/// it between frames.
+pub struct Connection {
+    stream: TcpStream,
+    // Bytes of a line that hasn't fully arrived yet.
+    pending: Vec<u8>,
+}
+
+impl Connection {
+    fn new(stream: TcpStream) -> io::Result<Connection> {
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+        Ok(Connection {
+            stream,
+            pending: Vec::new(),
+        })
+    }
+
+    /// Connects to a host at `address`, which gets [`DEFAULT_PORT`] unless
+    /// it names a port.
+    pub fn connect(address: &str) -> io::Result<Connection> {
+        let address = address.trim();
+        let has_port = address
+            .rsplit_once(':')
+            .is_some_and(|(_, port)| port.parse::<u16>().is_ok());
+        let address = match has_port {
+            true => address.to_string(),
+            false => format!("{address}:{DEFAULT_PORT}"),
+        };
+        let mut last_error = io::Error::new(ErrorKind::NotFound, "no such host");
+        for addr in address.to_socket_addrs()? {
+            match TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT) {
+                Ok(stream) => return Connection::new(stream),
+                Err(e) => last_error = e,
+            }
+        }
+        Err(last_error)
+    }
+
+    pub fn send(&mut self, message: &Message) -> io::Result<()> {
+        let mut line = serde_json::to_string(message).map_err(io::Error::other)?;
+        line.push('\n');
+        // Messages are small, but a full send buffer mustn't cut one short.
+        self.stream.set_nonblocking(false)?;
+        let sent = self.stream.write_all(line.as_bytes());
+        self.stream.set_nonblocking(true)?;
+        sent
+    }
+
+    /// Every message that has arrived in full since the last call. Lines
+    /// that don't parse are skipped; an error means the link is gone.
+    pub fn receive(&mut self) -> io::Result<Vec<Message>> {
+        let mut buf = [0; 4096];
+        loop {
+            match self.stream.read(&mut buf) {
+                Ok(0) => return Err(ErrorKind::ConnectionAborted.into()),
+                Ok(n) => self.pending.extend_from_slice(&buf[..n]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        let Some(end) = self.pending.iter().rposition(|&b| b == b'\n') else {
+            return Ok(Vec::new());
+        };
+        let lines: Vec<u8> = self.pending.drain(..=end).collect();
+        Ok(lines
+            .split(|&b| b == b'\n')
+            .filter_map(|line| serde_json::from_slice(line).ok())
+            .collect())
+    }
+}
+
+/// Waits for someone to join a hosted game.
+pub struct Host {
+    listener: TcpListener,
+}
+
+impl Host {
+    pub fn bind(port: u16) -> io::Result<Host> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        listener.set_nonblocking(true)?;
+        Ok(Host { listener })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.listener.local_addr().map_or(0, |addr| addr.port())
+    }
+
+    /// The guest, once one has turned up.
+    pub fn accept(&self) -> io::Result<Option<Connection>> {
+        match self.listener.accept() {
+            Ok((stream, _)) => Connection::new(stream).map(Some),
+            Err(e) if e.kind() == ErrorKind::WouldBlock => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[test]
+fn test_connection() {
+    fn poll(mut ready: impl FnMut() -> bool) {
+        for _ in 0..200 {
+            if ready() {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        panic!("timed out");
+    }
+
+    let host = Host::bind(0).unwrap();
+    let mut guest = Connection::connect(&format!("127.0.0.1:{}", host.port())).unwrap();
+    let mut joined = None;
+    poll(|| {
+        joined = host.accept().unwrap();
+        joined.is_some()
+    });
+    let mut joined = joined.unwrap();
+
+    let race = Race::battle(GameConfig::default(), 99);
+    let start = Message::start(&race);
+    let started = start.race().unwrap();
+    assert!(started.is_battle());
+    assert_eq!(started.seed(), 99);
+    assert_eq!(started.config(), race.config());
+    let huge = Message::Start {
+        seed: 1,
+        rows: 100,
+        cols: 4,
+        shape: BoardShape::Square,
+        spawn: SpawnPolicy::CLASSIC,
+        battle: false,
+    };
+    assert_eq!(huge.race().map(|race| race.seed()), None);
+    let board = Message::Board {
+        board: Board::from_cells(3, 3, vec![2, 0, 0, 0, 4, 0, 0, 0, 8]),
+        score: 12,
+        moves: 3,
+    };
+    let attack = Message::Attack { junk: 2 };
+    for message in [&start, &board, &attack] {
+        joined.send(message).unwrap();
+    }
+    let mut received = Vec::new();
+    poll(|| {
+        received.extend(guest.receive().unwrap());
+        received.len() == 3
+    });
+    assert_eq!(received, [start, board, attack]);
+
+    drop(joined);
+    poll(|| guest.receive().is_err());
+}
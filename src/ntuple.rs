@@ -0,0 +1,201 @@
+use crate::ai::FOUR_PROBABILITY;
+use crate::engine::Direction;
+use crate::engine::bitboard::BitBoard;
+use rand::Rng;
+use std::fmt;
+
+// The cells each tuple reads, row by row. Every tuple is also read on the
+// board's seven other rotations and reflections, sharing one table, so the
+// two rows cover every row and column and the three squares every 2x2 block.
+const TUPLES: [[usize; 4]; 5] = [
+    [0, 1, 2, 3],
+    [4, 5, 6, 7],
+    [0, 1, 4, 5],
+    [1, 2, 5, 6],
+    [5, 6, 9, 10],
+];
+// One entry for every way of filling a tuple's four cells.
+const TABLE_SIZE: usize = 1 << 16;
+const WEIGHTS_HEADER: &[u8] = b"2048-ntuple 1\n";
+
+/// How far each training update moves a weight towards its target.
+pub const LEARNING_RATE: f32 = 0.0025;
+
+/// A learned evaluation of 4x4 boards: lookup tables over small groups of
+/// cells, whose entries add up to the score still to come after a move.
+/// The tables are learned by playing against itself with temporal-difference
+/// updates, so it knows only what it has found pays.
+#[derive(Clone, PartialEq)]
+pub struct NTupleNetwork {
+    tables: Vec<Vec<f32>>,
+}
+
+impl fmt::Debug for NTupleNetwork {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "NTupleNetwork({} tuples)", self.tables.len())
+    }
+}
+
+impl Default for NTupleNetwork {
+    fn default() -> Self {
+        NTupleNetwork::new()
+    }
+}
+
+// The board in all eight of its rotations and reflections.
+fn symmetries(bits: BitBoard) -> [BitBoard; 8] {
+    let t = bits.transpose();
+    [
+        bits,
+        bits.mirror(),
+        bits.flip(),
+        bits.mirror().flip(),
+        t,
+        t.mirror(),
+        t.flip(),
+        t.mirror().flip(),
+    ]
+}
+
+fn index(bits: BitBoard, tuple: &[usize; 4]) -> usize {
+    tuple.iter().enumerate().fold(0, |index, (i, &cell)| {
+        index | (bits.exponent(cell) as usize) << (4 * i)
+    })
+}
+
+fn spawn(bits: BitBoard, rng: &mut impl Rng) -> Option<BitBoard> {
+    let empty: Vec<usize> = bits.empty_cells().collect();
+    if empty.is_empty() {
+        return None;
+    }
+    let idx = empty[rng.random_range(0..empty.len())];
+    let exponent = match rng.random_bool(FOUR_PROBABILITY) {
+        true => 2,
+        false => 1,
+    };
+    Some(bits.with_exponent(idx, exponent))
+}
+
+impl NTupleNetwork {
+    /// A network that knows nothing yet: every board is worth 0.
+    pub fn new() -> NTupleNetwork {
+        NTupleNetwork {
+            tables: vec![vec![0.0; TABLE_SIZE]; TUPLES.len()],
+        }
+    }
+
+    /// The score the network expects still to come from `bits`, a board
+    /// just moved and not yet given its new tile.
+    pub fn evaluate(&self, bits: BitBoard) -> f32 {
+        symmetries(bits)
+            .iter()
+            .map(|&board| {
+                TUPLES
+                    .iter()
+                    .zip(&self.tables)
+                    .map(|(tuple, table)| table[index(board, tuple)])
+                    .sum::<f32>()
+            })
+            .sum()
+    }
+
+    fn adjust(&mut self, bits: BitBoard, delta: f32) {
+        for board in symmetries(bits) {
+            for (tuple, table) in TUPLES.iter().zip(&mut self.tables) {
+                table[index(board, tuple)] += delta;
+            }
+        }
+    }
+
+    /// The move whose points plus the value of the board it leaves are
+    /// highest, with that board and the points.
+    pub fn best_move(&self, bits: BitBoard) -> Option<(Direction, BitBoard, u32)> {
+        Direction::ALL
+            .iter()
+            .filter_map(|&dir| {
+                let (next, points) = bits.apply_move(dir);
+                (next != bits).then_some((dir, next, points))
+            })
+            .max_by(|a, b| {
+                let value = |&(_, next, points): &(Direction, BitBoard, u32)| {
+                    points as f32 + self.evaluate(next)
+                };
+                value(a).total_cmp(&value(b))
+            })
+    }
+
+    /// Plays one game by its own judgement, learning as it goes: after each
+    /// move the value of the board the move before left is nudged towards
+    /// the points this one scored plus the value of the board it leaves,
+    /// and the last towards nothing. Returns the score and the final board.
+    pub fn learn_game(&mut self, rate: f32, rng: &mut impl Rng) -> (u32, BitBoard) {
+        let start = spawn(BitBoard(0), rng).and_then(|bits| spawn(bits, rng));
+        let mut bits = start.expect("an empty board has room for two tiles");
+        let mut score = 0;
+        let mut previous: Option<BitBoard> = None;
+        while let Some((_, next, points)) = self.best_move(bits) {
+            if let Some(previous) = previous {
+                let error = points as f32 + self.evaluate(next) - self.evaluate(previous);
+                self.adjust(previous, rate * error);
+            }
+            score += points;
+            previous = Some(next);
+            bits = spawn(next, rng).unwrap_or(next);
+        }
+        if let Some(previous) = previous {
+            let error = -self.evaluate(previous);
+            self.adjust(previous, rate * error);
+        }
+        (score, bits)
+    }
+
+    /// The weights as a header and then every entry, little-endian.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = WEIGHTS_HEADER.to_vec();
+        for weight in self.tables.iter().flatten() {
+            bytes.extend_from_slice(&weight.to_le_bytes());
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Option<NTupleNetwork> {
+        let weights = bytes.strip_prefix(WEIGHTS_HEADER)?;
+        if weights.len() != TUPLES.len() * TABLE_SIZE * 4 {
+            return None;
+        }
+        let weights: Vec<f32> = weights
+            .chunks_exact(4)
+            .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+            .collect();
+        Some(NTupleNetwork {
+            tables: weights.chunks(TABLE_SIZE).map(<[f32]>::to_vec).collect(),
+        })
+    }
+}
+
+#[test]
+fn test_ntuple_network() {
+    use crate::engine::seeded_rng;
+
+    let mut network = NTupleNetwork::new();
+    let bits = BitBoard(0x0000_0000_0012_0321);
+    assert_eq!(network.evaluate(bits), 0.0);
+    network.adjust(bits, 1.0);
+    // Every rotation and reflection is the same board to it.
+    for board in symmetries(bits) {
+        assert_eq!(network.evaluate(board), network.evaluate(bits));
+    }
+    assert!(network.evaluate(bits) > 0.0);
+
+    let mut rng = seeded_rng(7);
+    let mut network = NTupleNetwork::new();
+    let (score, end) = network.learn_game(LEARNING_RATE, &mut rng);
+    assert!(score > 0);
+    assert!(network.best_move(end).is_none());
+    assert_ne!(network, NTupleNetwork::new());
+
+    let bytes = network.to_bytes();
+    assert_eq!(NTupleNetwork::from_bytes(&bytes), Some(network));
+    assert_eq!(NTupleNetwork::from_bytes(&bytes[1..]), None);
+    assert_eq!(NTupleNetwork::from_bytes(&bytes[..bytes.len() - 4]), None);
+}
@@ -0,0 +1,847 @@
+use crate::challenge::ChallengePack;
+use crate::daily::Date;
+use crate::digest::{hex, hmac_sha1};
+use crate::engine::powerups::Inventory;
+use crate::engine::{Board, Difficulty, GameMode};
+use crate::ntuple::NTupleNetwork;
+use crate::replay::Replay;
+use crate::splits::SplitRecords;
+use crate::stats::Stats;
+use serde::{Deserialize, Serialize};
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    sync::{
+        OnceLock,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const APP_DIR: &str = "game_2048";
+/// Names a directory to keep everything in instead of the usual one, for
+/// scripts and tests that mustn't touch real saves.
+pub const DATA_DIR_VAR: &str = "GAME_2048_DATA_DIR";
+/// A file by this name beside the executable runs the game portable: its
+/// data is kept in `PORTABLE_DIR` next to the executable, as when running
+/// from a USB stick, instead of in the platform directory.
+pub const PORTABLE_MARKER: &str = "portable";
+const PORTABLE_DIR: &str = "data";
+const SAVE_FILE: &str = "savegame.json";
+const SESSION_FILE: &str = "session.json";
+const BEST_SCORE_FILE: &str = "best_score";
+const HARD_BEST_SCORE_FILE: &str = "best_score_hard";
+const REPLAY_DIR: &str = "replays";
+const EXPORT_DIR: &str = "exports";
+const SCREENSHOT_DIR: &str = "screenshots";
+const STATS_FILE: &str = "stats.json";
+const SPLITS_DIR: &str = "splits";
+const CHALLENGE_DIR: &str = "challenges";
+const BEST_GAME_FILE: &str = "best_game.2048replay";
+const WEIGHTS_FILE: &str = "ntuple.weights";
+const PROFILE_DIR: &str = "profiles";
+const MUSIC_DIR: &str = "music";
+const MUSIC_EXTENSIONS: [&str; 4] = ["flac", "mp3", "ogg", "wav"];
+const ACTIVE_PROFILE_FILE: &str = "profile";
+const MAX_PROFILE_NAME: usize = 16;
+// Older builds wrote the best score to a file named `config` in the working
+// directory.
+const LEGACY_BEST_SCORE_FILE: &str = "config";
+
+const SAVE_HEADER: &str = "2048-save";
+// Bumped whenever `SavedGame` changes in a way older saves need upgrading
+// for; `migrate_save` knows every step up to it.
+const SAVE_VERSION: u32 = 1;
+// The same for `Session`, which sessions and game exports carry as a
+// `version` field beside the game; `migrate_session` upgrades older ones.
+const SESSION_VERSION: u32 = 1;
+const RECORD_HEADER: &str = "2048-record";
+const RECORD_VERSION: u32 = 1;
+// Baked into every build, so it only keeps hand edits from passing as
+// records; it is no secret.
+const RECORD_KEY: &[u8] = b"game_2048 records";
+// Best scores and statistics, with what each holds.
+const RECORDS: [(&str, &str); 3] = [
+    (BEST_SCORE_FILE, "Best score"),
+    (HARD_BEST_SCORE_FILE, "Hard best score"),
+    (STATS_FILE, "Statistics"),
+];
+
+/// Why a record file doesn't read back.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecordError {
+    /// Plain contents, as builds before checksums wrote them.
+    Unsealed,
+    /// Written by a build with a newer record format.
+    Version(u32),
+    /// The contents don't match their checksum.
+    Tampered,
+}
+
+/// What became of a record file that didn't read back.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Recovery {
+    /// The last good copy was put back over the damaged file.
+    Restored(RecordError),
+    /// There was no good copy, so the damaged file was set aside and the
+    /// record starts over.
+    Reset(RecordError),
+    /// Written by a newer build, so it was neither loaded nor touched.
+    Newer(u32),
+}
+
+/// Everything needed to pick a game back up exactly where it was left,
+/// including the spawn RNG so the upcoming tiles don't change.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedGame {
+    pub board: Board,
+    pub score: u32,
+    pub seed: u64,
+    pub rng_state: u64,
+    pub keep_playing: bool,
+    #[serde(default)]
+    pub mode: GameMode,
+    /// Clock left on a timed game.
+    #[serde(default)]
+    pub time_left_ms: Option<u64>,
+    #[serde(default)]
+    pub move_budget: Option<u32>,
+    #[serde(default)]
+    pub moves_made: u32,
+    #[serde(default)]
+    pub merges_made: u32,
+    #[serde(default)]
+    pub biggest_merge: u32,
+    /// The day of a daily puzzle in progress.
+    #[serde(default)]
+    pub daily: Option<Date>,
+    /// Power-ups in hand in an arcade game.
+    #[serde(default)]
+    pub power_ups: Inventory,
+}
+
+/// The live game as of its last move, kept only while the game is running
+/// so that a crash loses nothing. Beyond the saved game it carries the
+/// undo history and the replay so far.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Session {
+    pub game: SavedGame,
+    /// Positions to undo back to, oldest first.
+    pub history: Vec<SessionStep>,
+    /// The replay so far, encoded.
+    pub replay: String,
+    /// How long the game had been played, pauses left out. The replay's
+    /// move times count from its start.
+    #[serde(default)]
+    pub elapsed_ms: u64,
+}
+
+// A session as it is written out, tagged with its format version.
+#[derive(Serialize)]
+struct VersionedSession<'a> {
+    version: u32,
+    #[serde(flatten)]
+    session: &'a Session,
+}
+
+#[derive(Deserialize)]
+struct SessionVersion {
+    // Sessions from before the field are the first format.
+    #[serde(default)]
+    version: u32,
+}
+
+/// One position in a [`Session`]'s undo history.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionStep {
+    pub board: Board,
+    pub score: u32,
+    pub rng_state: u64,
+}
+
+/// The profile everyone starts on. Its data sits straight in the data
+/// directory, where it was before there were profiles.
+pub const DEFAULT_PROFILE: &str = "Default";
+
+// Set by `--portable`, which runs portable without the marker file.
+static PORTABLE: AtomicBool = AtomicBool::new(false);
+
+/// Keeps data beside the executable for the rest of the run, as if the
+/// [`PORTABLE_MARKER`] file were there. Call it before anything is loaded.
+pub fn set_portable() {
+    PORTABLE.store(true, Ordering::Relaxed);
+}
+
+// Where data goes when running portable, if the game is.
+fn portable_dir() -> Option<PathBuf> {
+    let exe = env::current_exe().ok()?;
+    let exe_dir = exe.parent()?;
+    let portable = PORTABLE.load(Ordering::Relaxed) || exe_dir.join(PORTABLE_MARKER).exists();
+    portable.then(|| exe_dir.join(PORTABLE_DIR))
+}
+
+/// The one directory every file the game keeps is found under: the one
+/// [`DATA_DIR_VAR`] names if it is set, else the portable one beside the
+/// executable when running portable, else the platform data directory
+/// (`~/.local/share/game_2048`, `%APPDATA%\game_2048`, ...), or the working
+/// directory if there is none. Replays and challenge packs live here for
+/// every profile to share.
+pub fn root_dir() -> PathBuf {
+    if let Some(dir) = env::var_os(DATA_DIR_VAR).filter(|dir| !dir.is_empty()) {
+        return PathBuf::from(dir);
+    }
+    if let Some(dir) = portable_dir() {
+        return dir;
+    }
+    dirs::data_dir()
+        .map(|dir| dir.join(APP_DIR))
+        .or_else(|| env::current_dir().ok())
+        .unwrap_or_default()
+}
+
+/// Where the active profile keeps its settings, best scores, statistics
+/// and saved game.
+pub fn data_dir() -> PathBuf {
+    profile_dir(&active_profile())
+}
+
+fn profile_dir(name: &str) -> PathBuf {
+    match name {
+        DEFAULT_PROFILE => root_dir(),
+        name => root_dir().join(PROFILE_DIR).join(name),
+    }
+}
+
+/// The profile being played, which is the default one unless another has
+/// been switched to.
+pub fn active_profile() -> String {
+    fs::read_to_string(root_dir().join(ACTIVE_PROFILE_FILE))
+        .ok()
+        .map(|name| name.trim().to_string())
+        .filter(|name| profiles().contains(name))
+        .unwrap_or_else(|| DEFAULT_PROFILE.to_string())
+}
+
+/// Every profile, the default one first and the rest by name.
+pub fn profiles() -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(root_dir().join(PROFILE_DIR))
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect();
+    names.sort();
+    names.insert(0, DEFAULT_PROFILE.to_string());
+    names
+}
+
+/// `name` tidied up for use as a profile name, or why it can't be one.
+pub fn profile_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("A profile needs a name".to_string());
+    }
+    if name.chars().count() > MAX_PROFILE_NAME {
+        return Err(format!(
+            "Profile names are at most {MAX_PROFILE_NAME} letters"
+        ));
+    }
+    let allowed = |c: char| c.is_alphanumeric() || " -_".contains(c);
+    if !name.chars().all(allowed) {
+        return Err("Profile names are letters, digits, spaces, - and _".to_string());
+    }
+    Ok(name.to_string())
+}
+
+/// Makes a new, empty profile and returns its name.
+pub fn create_profile(name: &str) -> Result<String, String> {
+    let name = profile_name(name)?;
+    if profiles().iter().any(|p| p.eq_ignore_ascii_case(&name)) {
+        return Err(format!("There's already a profile called {name}"));
+    }
+    fs::create_dir_all(profile_dir(&name)).map_err(|e| e.to_string())?;
+    Ok(name)
+}
+
+/// Makes `name` the active profile, from now on and in later sessions.
+pub fn switch_profile(name: &str) -> io::Result<()> {
+    fs::create_dir_all(root_dir())?;
+    write_atomic(&root_dir().join(ACTIVE_PROFILE_FILE), name)
+}
+
+/// A failed save put the way the player should hear it: what was being
+/// done, and in plain words why it didn't work.
+pub fn failure_message(what: &str, err: &io::Error) -> String {
+    let why = match err.kind() {
+        io::ErrorKind::PermissionDenied => "permission denied".to_string(),
+        io::ErrorKind::StorageFull => "the disk is full".to_string(),
+        io::ErrorKind::ReadOnlyFilesystem => "the disk is read-only".to_string(),
+        _ => err.to_string(),
+    };
+    format!("couldn't {what}: {why}")
+}
+
+/// What became of a record, put the way the player should hear it. `holds`
+/// is what the record holds, as [`repair_records`] gives it.
+pub fn recovery_message(holds: &str, recovery: Recovery) -> String {
+    let what = match recovery {
+        Recovery::Newer(_) => return format!("{holds} is from a newer version and was left alone"),
+        Recovery::Restored(damage) | Recovery::Reset(damage) => match damage {
+            RecordError::Tampered => "had been tampered with",
+            // A record missing its seal, or missing altogether, beside a
+            // backup was cut short or replaced.
+            RecordError::Unsealed | RecordError::Version(_) => "couldn't be read",
+        },
+    };
+    match recovery {
+        Recovery::Restored(_) => format!("{holds} {what}; restored a backup"),
+        _ => format!("{holds} {what} and was reset"),
+    }
+}
+
+/// Writes `contents` to a file beside `path` and moves it over `path`, so a
+/// crash midway leaves the old file or the new one, never half of either.
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    fs::write(&temp, contents)?;
+    fs::rename(temp, path)
+}
+
+fn data_path(name: &str) -> PathBuf {
+    data_dir().join(name)
+}
+
+fn write_data(name: &str, contents: &str) -> io::Result<()> {
+    let _span = tracing::debug_span!("write_data", name).entered();
+    fs::create_dir_all(data_dir())
+        .and_then(|()| write_atomic(&data_path(name), contents))
+        .inspect_err(|err| tracing::warn!(%err, "couldn't write"))
+}
+
+fn read_score(path: PathBuf) -> Option<u32> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+fn checksum(payload: &str) -> String {
+    hex(&hmac_sha1(RECORD_KEY, payload.as_bytes()))
+}
+
+/// `payload` behind a header line with the format version and a checksum.
+pub fn seal_record(payload: &str) -> String {
+    format!(
+        "{RECORD_HEADER} {RECORD_VERSION} {}\n{payload}",
+        checksum(payload)
+    )
+}
+
+/// The payload [`seal_record`] sealed into `text`.
+pub fn open_record(text: &str) -> Result<&str, RecordError> {
+    let (header, payload) = text.split_once('\n').unwrap_or((text, ""));
+    let mut fields = header.split(' ');
+    if fields.next() != Some(RECORD_HEADER) {
+        return Err(RecordError::Unsealed);
+    }
+    let version = fields
+        .next()
+        .and_then(|v| v.parse().ok())
+        .ok_or(RecordError::Tampered)?;
+    if version != RECORD_VERSION {
+        return Err(RecordError::Version(version));
+    }
+    match fields.next() == Some(&checksum(payload)) {
+        true => Ok(payload),
+        false => Err(RecordError::Tampered),
+    }
+}
+
+fn backup_path(name: &str) -> PathBuf {
+    data_path(&format!("{name}.bak"))
+}
+
+// Seals `payload` into the record `name`, keeping the copy it replaces as a
+// backup. The new file lands whole or not at all.
+fn write_record(name: &str, payload: &str) -> io::Result<()> {
+    let _span = tracing::debug_span!("write_record", name).entered();
+    fs::create_dir_all(data_dir())?;
+    let path = data_path(name);
+    if let Ok(text) = fs::read_to_string(&path) {
+        match open_record(&text) {
+            Ok(_) => {
+                fs::copy(&path, backup_path(name))?;
+            }
+            // Left for the build that wrote it.
+            Err(RecordError::Version(_)) => {
+                return Err(io::Error::other("it was saved by a newer version"));
+            }
+            Err(_) => {}
+        }
+    }
+    write_atomic(&path, seal_record(payload))
+}
+
+// The payload of the record `name`, and how it was recovered if the file
+// itself didn't read back. Plain files from before checksums are taken as
+// they are, but only until a sealed record has been written. One from a
+// newer build is left as it is, for that build.
+fn read_record(name: &str) -> (Option<String>, Option<Recovery>) {
+    let _span = tracing::debug_span!("read_record", name).entered();
+    let path = data_path(name);
+    let backup = fs::read_to_string(backup_path(name)).ok();
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(_) if backup.is_none() => return (None, None),
+        Err(_) => String::new(),
+    };
+    let damage = match open_record(&text) {
+        Ok(payload) => return (Some(payload.to_string()), None),
+        Err(RecordError::Unsealed) if backup.is_none() => return (Some(text), None),
+        Err(RecordError::Version(version)) => {
+            tracing::warn!(version, "from a newer version; left alone");
+            return (None, Some(Recovery::Newer(version)));
+        }
+        Err(damage) => damage,
+    };
+    if let Some(backup) = backup
+        && let Ok(payload) = open_record(&backup)
+    {
+        write_atomic(&path, &backup).ok();
+        tracing::warn!("restored from the backup");
+        return (Some(payload.to_string()), Some(Recovery::Restored(damage)));
+    }
+    tracing::warn!("unreadable, with no good backup; reset");
+    fs::rename(&path, data_path(&format!("{name}.damaged"))).ok();
+    fs::remove_file(backup_path(name)).ok();
+    (None, Some(Recovery::Reset(damage)))
+}
+
+/// Checks the best scores and statistics, restoring any file that doesn't
+/// read back from its backup. Returns what each recovered file holds and
+/// how it was recovered.
+pub fn repair_records() -> Vec<(&'static str, Recovery)> {
+    RECORDS
+        .into_iter()
+        .filter_map(|(name, holds)| Some((holds, read_record(name).1?)))
+        .collect()
+}
+
+fn best_score_file(difficulty: Difficulty) -> &'static str {
+    match difficulty {
+        Difficulty::Normal => BEST_SCORE_FILE,
+        Difficulty::Hard => HARD_BEST_SCORE_FILE,
+    }
+}
+
+/// The best score at `difficulty`; the old pre-data-dir file only ever held
+/// normal scores, from before there were profiles.
+pub fn load_best_score(difficulty: Difficulty) -> u32 {
+    let (record, recovery) = read_record(best_score_file(difficulty));
+    if let Some(score) = record.and_then(|s| s.trim().parse().ok()) {
+        return score;
+    }
+    if recovery.is_some() {
+        return 0;
+    }
+    if difficulty != Difficulty::Normal || active_profile() != DEFAULT_PROFILE {
+        return 0;
+    }
+    let legacy = env::current_dir()
+        .ok()
+        .and_then(|dir| read_score(dir.join(LEGACY_BEST_SCORE_FILE)));
+    match legacy {
+        Some(score) => {
+            save_best_score(difficulty, score).ok();
+            score
+        }
+        None => 0,
+    }
+}
+
+pub fn save_best_score(difficulty: Difficulty, score: u32) -> io::Result<()> {
+    write_record(best_score_file(difficulty), &score.to_string())
+}
+
+/// `game` as it goes in the save file: a header line with the format
+/// version, then the game as JSON.
+pub fn encode_save(game: &SavedGame) -> io::Result<String> {
+    let json = serde_json::to_string(game)?;
+    Ok(format!("{SAVE_HEADER} {SAVE_VERSION}\n{json}"))
+}
+
+/// Reads a save written by this build or any before it, upgrading older
+/// formats. A save from a newer build can't be read.
+pub fn decode_save(text: &str) -> Option<SavedGame> {
+    let (version, json) = match text.split_once('\n') {
+        Some((header, json)) if header.starts_with(SAVE_HEADER) => {
+            (header[SAVE_HEADER.len()..].trim().parse().ok()?, json)
+        }
+        // Saves from before the header are the first format.
+        _ => (0, text),
+    };
+    if version > SAVE_VERSION {
+        tracing::warn!(version, "saved game is from a newer version");
+        return None;
+    }
+    let game = serde_json::from_str(json).ok()?;
+    serde_json::from_value(migrate_save(version, game)).ok()
+}
+
+// Brings a save from `version` up to the current format a step at a time.
+// Steps work on the JSON, so each needs to know only the fields it changes.
+fn migrate_save(version: u32, game: serde_json::Value) -> serde_json::Value {
+    (version..SAVE_VERSION).fold(game, |game, from| match from {
+        // Version 1 added the header and changed nothing else.
+        0 => game,
+        _ => unreachable!("no migration from save version {from}"),
+    })
+}
+
+pub fn save_game(game: &SavedGame) -> io::Result<()> {
+    write_data(SAVE_FILE, &encode_save(game)?)
+}
+
+pub fn load_game() -> Option<SavedGame> {
+    let _span = tracing::debug_span!("load_game").entered();
+    decode_save(&fs::read_to_string(data_path(SAVE_FILE)).ok()?)
+}
+
+pub fn clear_saved_game() {
+    fs::remove_file(data_path(SAVE_FILE)).ok();
+}
+
+fn versioned(session: &Session) -> VersionedSession<'_> {
+    VersionedSession {
+        version: SESSION_VERSION,
+        session,
+    }
+}
+
+/// Reads a session or game export written by this build or any before it,
+/// upgrading older formats. One from a newer build can't be read.
+pub fn decode_session(text: &str) -> Result<Session, String> {
+    let SessionVersion { version } =
+        serde_json::from_str(text).map_err(|e| format!("not a saved game: {e}"))?;
+    if version > SESSION_VERSION {
+        return Err(format!("saved by a newer version of the game ({version})"));
+    }
+    let session = serde_json::from_str(text).map_err(|e| format!("not a saved game: {e}"))?;
+    serde_json::from_value(migrate_session(version, session))
+        .map_err(|e| format!("not a saved game: {e}"))
+}
+
+// Brings a session from `version` up to the current format, as
+// `migrate_save` does for saves.
+fn migrate_session(version: u32, session: serde_json::Value) -> serde_json::Value {
+    (version..SESSION_VERSION).fold(session, |session, from| match from {
+        // Version 1 added the version field and changed nothing else.
+        0 => session,
+        _ => unreachable!("no migration from session version {from}"),
+    })
+}
+
+pub fn save_session(session: &Session) -> io::Result<()> {
+    write_data(SESSION_FILE, &serde_json::to_string(&versioned(session))?)
+}
+
+/// The session the last run left behind. A clean exit clears it, so there
+/// only is one after a crash.
+pub fn load_session() -> Option<Session> {
+    let _span = tracing::debug_span!("load_session").entered();
+    decode_session(&fs::read_to_string(data_path(SESSION_FILE)).ok()?)
+        .inspect_err(|err| tracing::warn!(%err, "couldn't read the session"))
+        .ok()
+}
+
+pub fn clear_session() {
+    fs::remove_file(data_path(SESSION_FILE)).ok();
+}
+
+pub fn load_stats() -> Stats {
+    read_record(STATS_FILE)
+        .0
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_stats(stats: &Stats) -> io::Result<()> {
+    write_record(STATS_FILE, &serde_json::to_string(stats)?)
+}
+
+// Times on different board sizes aren't comparable, so each has its own.
+fn splits_file(rows: usize, cols: usize) -> String {
+    format!("{SPLITS_DIR}/{rows}x{cols}.json")
+}
+
+/// The speedrun records on a `rows` by `cols` board.
+pub fn load_splits(rows: usize, cols: usize) -> SplitRecords {
+    fs::read_to_string(data_path(&splits_file(rows, cols)))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_splits(rows: usize, cols: usize, records: &SplitRecords) -> io::Result<()> {
+    fs::create_dir_all(data_dir().join(SPLITS_DIR))?;
+    write_data(&splits_file(rows, cols), &serde_json::to_string(records)?)
+}
+
+/// Writes the replay to a new timestamped file under the `replays` directory
+/// and returns its path.
+pub fn export_replay(replay: &Replay) -> io::Result<PathBuf> {
+    let dir = root_dir().join(REPLAY_DIR);
+    fs::create_dir_all(&dir)?;
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let path = dir.join(format!("{stamp}-{}.2048replay", replay.seed));
+    write_atomic(&path, replay.encode())?;
+    Ok(path)
+}
+
+/// Writes the whole game, undo history and replay included, to a new
+/// timestamped JSON file under the `exports` directory and returns its path.
+pub fn export_game(session: &Session) -> io::Result<PathBuf> {
+    let dir = root_dir().join(EXPORT_DIR);
+    fs::create_dir_all(&dir)?;
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let path = dir.join(format!("{stamp}-{}.json", session.game.seed));
+    write_atomic(&path, serde_json::to_string_pretty(&versioned(session))?)?;
+    Ok(path)
+}
+
+/// Writes a PNG to a new timestamped file under the `screenshots` directory
+/// and returns its path.
+pub fn save_screenshot(png: &[u8]) -> io::Result<PathBuf> {
+    let dir = root_dir().join(SCREENSHOT_DIR);
+    fs::create_dir_all(&dir)?;
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+    let path = dir.join(format!("{stamp}.png"));
+    write_atomic(&path, png)?;
+    Ok(path)
+}
+
+/// Reads a game written by [`export_game`], here or on another machine, by
+/// this build or an older one. Files edited by hand get their boards
+/// checked as they load, so one that doesn't hold together is turned away
+/// with the reason rather than breaking the game later.
+pub fn import_game(path: &Path) -> Result<Session, String> {
+    let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    decode_session(&contents)
+}
+
+pub fn load_replay(path: &Path) -> Option<Replay> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|s| Replay::parse(&s))
+}
+
+pub fn has_best_game() -> bool {
+    data_path(BEST_GAME_FILE).exists()
+}
+
+/// The replay of the best classic game played from its seed, to race
+/// against as a ghost.
+pub fn load_best_game() -> Option<Replay> {
+    load_replay(&data_path(BEST_GAME_FILE))
+}
+
+pub fn save_best_game(replay: &Replay) -> io::Result<()> {
+    write_data(BEST_GAME_FILE, &replay.encode())
+}
+
+/// Where training keeps the learned evaluation, shared by every profile.
+pub fn weights_path() -> PathBuf {
+    root_dir().join(WEIGHTS_FILE)
+}
+
+pub fn load_weights() -> Option<NTupleNetwork> {
+    NTupleNetwork::from_bytes(&fs::read(weights_path()).ok()?)
+}
+
+pub fn save_weights(network: &NTupleNetwork) -> io::Result<()> {
+    fs::create_dir_all(root_dir())?;
+    // Stopping training midway never leaves half a file.
+    write_atomic(&weights_path(), network.to_bytes())
+}
+
+/// The learned evaluation, read the first time it is asked for and kept
+/// for the rest of the run. Training afterwards is picked up on restart.
+pub fn learned_network() -> Option<&'static NTupleNetwork> {
+    static NETWORK: OnceLock<Option<NTupleNetwork>> = OnceLock::new();
+    NETWORK.get_or_init(load_weights).as_ref()
+}
+
+pub fn music_dir() -> PathBuf {
+    root_dir().join(MUSIC_DIR)
+}
+
+/// The tracks in the music directory, sorted by file name.
+pub fn music_tracks() -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(music_dir()) else {
+        return Vec::new();
+    };
+    let mut paths = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| MUSIC_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+        })
+        .collect::<Vec<_>>();
+    paths.sort();
+    paths
+}
+
+pub fn challenge_dir() -> PathBuf {
+    root_dir().join(CHALLENGE_DIR)
+}
+
+/// Every `.json` pack in the challenges directory, sorted by file name,
+/// with the reason for each one that doesn't load.
+pub fn load_challenge_packs() -> Vec<(PathBuf, Result<ChallengePack, String>)> {
+    let Ok(entries) = fs::read_dir(challenge_dir()) else {
+        return Vec::new();
+    };
+    let mut paths = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect::<Vec<_>>();
+    paths.sort();
+    paths
+        .into_iter()
+        .map(|path| {
+            let pack = fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|s| ChallengePack::parse(&s));
+            (path, pack)
+        })
+        .collect()
+}
+
+/// Checks the pack at `path` and copies it into the challenges directory so
+/// it shows up from then on.
+pub fn import_challenge_pack(path: &Path) -> Result<ChallengePack, String> {
+    let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let pack = ChallengePack::parse(&contents)?;
+    let name = path.file_name().ok_or("not a file")?;
+    fs::create_dir_all(challenge_dir()).map_err(|e| e.to_string())?;
+    write_atomic(&challenge_dir().join(name).with_extension("json"), contents)
+        .map_err(|e| e.to_string())?;
+    Ok(pack)
+}
+
+#[test]
+fn test_records() {
+    let sealed = seal_record("1234");
+    assert!(sealed.starts_with("2048-record 1 "));
+    assert_eq!(open_record(&sealed), Ok("1234"));
+    assert_eq!(
+        open_record(&sealed.replace("1234", "9999")),
+        Err(RecordError::Tampered)
+    );
+    assert_eq!(open_record("1234"), Err(RecordError::Unsealed));
+    assert_eq!(
+        open_record(&sealed.replacen(" 1 ", " 2 ", 1)),
+        Err(RecordError::Version(2))
+    );
+}
+
+#[test]
+fn test_profile_name() {
+    assert_eq!(profile_name("  Sam "), Ok("Sam".to_string()));
+    assert_eq!(profile_name("Mia-2_b"), Ok("Mia-2_b".to_string()));
+    assert!(profile_name("   ").is_err());
+    assert!(profile_name("../settings").is_err());
+    assert!(profile_name("a much too long profile name").is_err());
+}
+
+#[test]
+fn test_failure_message() {
+    let denied = io::Error::from(io::ErrorKind::PermissionDenied);
+    assert_eq!(
+        failure_message("save best score", &denied),
+        "couldn't save best score: permission denied"
+    );
+    let other = io::Error::other("disk on fire");
+    assert_eq!(
+        failure_message("save the game", &other),
+        "couldn't save the game: disk on fire"
+    );
+}
+
+#[test]
+fn test_recovery_message() {
+    assert_eq!(
+        recovery_message("Statistics", Recovery::Restored(RecordError::Tampered)),
+        "Statistics had been tampered with; restored a backup"
+    );
+    assert_eq!(
+        recovery_message("Best score", Recovery::Reset(RecordError::Unsealed)),
+        "Best score couldn't be read and was reset"
+    );
+    assert_eq!(
+        recovery_message("Best score", Recovery::Newer(2)),
+        "Best score is from a newer version and was left alone"
+    );
+}
+
+#[test]
+fn test_write_atomic() {
+    let dir = env::temp_dir().join(format!("game_2048-atomic-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("best_score");
+    write_atomic(&path, "10").unwrap();
+    write_atomic(&path, "20").unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "20");
+    // Nothing is left beside it.
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+    fs::remove_dir_all(dir).ok();
+}
+
+#[test]
+fn test_save_versions() {
+    use crate::engine::GameConfig;
+    use crate::headless::TextGame;
+
+    let game = TextGame::new(&GameConfig::default(), 3).to_saved();
+    let text = encode_save(&game).unwrap();
+    assert!(text.starts_with("2048-save 1\n"));
+    assert_eq!(decode_save(&text), Some(game.clone()));
+    // Saves from before versions came in still load.
+    let legacy = serde_json::to_string(&game).unwrap();
+    assert_eq!(decode_save(&legacy), Some(game));
+    assert_eq!(decode_save("2048-save 99\n{}"), None);
+    assert_eq!(decode_save("2048-save x\n{}"), None);
+}
+
+#[test]
+fn test_session_versions() {
+    use crate::engine::GameConfig;
+    use crate::headless::TextGame;
+
+    let session = Session {
+        game: TextGame::new(&GameConfig::default(), 3).to_saved(),
+        history: Vec::new(),
+        replay: String::new(),
+        elapsed_ms: 1500,
+    };
+    let text = serde_json::to_string(&versioned(&session)).unwrap();
+    assert!(text.starts_with("{\"version\":1,"));
+    assert_eq!(decode_session(&text), Ok(session.clone()));
+    // Sessions from before versions came in still load.
+    let legacy = serde_json::to_string(&session).unwrap();
+    assert_eq!(decode_session(&legacy), Ok(session));
+    assert!(
+        decode_session("{\"version\":99}")
+            .unwrap_err()
+            .contains("newer")
+    );
+}
@@ -0,0 +1,310 @@
+use crate::engine::rules::MergeRule;
+use crate::engine::{Board, Direction, GameConfig, GameMode, GameRng, Merge, seeded_rng};
+use std::cmp::Ordering;
+
+// Merges below this rank send nothing in a battle; each rank above it sends
+// one more junk tile, so a 64 sends one and a 128 two.
+const GARBAGE_RANK: u32 = 5;
+
+/// The junk tiles `merges` send to the opponent in a battle.
+pub fn garbage(rule: &dyn MergeRule, merges: &[Merge]) -> u32 {
+    merges
+        .iter()
+        .map(|merge| rule.rank(merge.value).saturating_sub(GARBAGE_RANK))
+        .sum()
+}
+
+/// Which of the two boards in a race.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+impl Side {
+    pub const BOTH: [Side; 2] = [Side::Left, Side::Right];
+
+    pub fn other(self) -> Side {
+        match self {
+            Side::Left => Side::Right,
+            Side::Right => Side::Left,
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Won(Side),
+    Draw,
+}
+
+/// One player's board in a race.
+#[derive(Clone, Debug)]
+pub struct Racer {
+    board: Board,
+    rng: GameRng,
+    score: u32,
+    moves: u32,
+    junk_sent: u32,
+}
+
+impl Racer {
+    fn new(config: &GameConfig, seed: u64) -> Racer {
+        let mut racer = Racer {
+            board: Board::for_config(config),
+            rng: seeded_rng(seed),
+            score: 0,
+            moves: 0,
+            junk_sent: 0,
+        };
+        for _ in 0..2 {
+            racer.board.spawn_tile_with(&mut racer.rng, &config.spawn);
+        }
+        racer
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn score(&self) -> u32 {
+        self.score
+    }
+
+    pub fn moves(&self) -> u32 {
+        self.moves
+    }
+
+    /// Junk tiles this board's merges have sent the other way.
+    pub fn junk_sent(&self) -> u32 {
+        self.junk_sent
+    }
+}
+
+/// Two classic games played side by side from the same seed. The first to
+/// reach the winning tile takes the race; if a board locks up first, the
+/// higher score does. In a battle, big merges also drop junk tiles on the
+/// other board.
+#[derive(Clone, Debug)]
+pub struct Race {
+    config: GameConfig,
+    seed: u64,
+    battle: bool,
+    racers: [Racer; 2],
+    outcome: Option<Outcome>,
+}
+
+impl Race {
+    pub fn new(config: GameConfig, seed: u64) -> Race {
+        let config = GameConfig {
+            mode: GameMode::Classic,
+            ..config
+        };
+        let racer = Racer::new(&config, seed);
+        Race {
+            config,
+            seed,
+            battle: false,
+            racers: [racer.clone(), racer],
+            outcome: None,
+        }
+    }
+
+    pub fn battle(config: GameConfig, seed: u64) -> Race {
+        Race {
+            battle: true,
+            ..Race::new(config, seed)
+        }
+    }
+
+    /// A fresh race on the same rules.
+    pub fn rematch(&self, seed: u64) -> Race {
+        Race {
+            battle: self.battle,
+            ..Race::new(self.config, seed)
+        }
+    }
+
+    pub fn config(&self) -> &GameConfig {
+        &self.config
+    }
+
+    /// Whether `board` could be played in this race: the same size, with the
+    /// same cells cut out of it.
+    pub fn fits(&self, board: &Board) -> bool {
+        let own = self.racer(Side::Left).board();
+        (board.rows(), board.cols(), board.holes()) == (own.rows(), own.cols(), own.holes())
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn is_battle(&self) -> bool {
+        self.battle
+    }
+
+    pub fn racer(&self, side: Side) -> &Racer {
+        &self.racers[side.index()]
+    }
+
+    pub fn outcome(&self) -> Option<Outcome> {
+        self.outcome
+    }
+
+    /// Slides `side`'s board towards `dir`; false if nothing moved or the
+    /// race is already decided.
+    pub fn play(&mut self, side: Side, dir: Direction) -> bool {
+        if self.outcome.is_some() {
+            return false;
+        }
+        let spawn = self.config.spawn;
+        let racer = &mut self.racers[side.index()];
+        let result = racer.board.apply_move(dir);
+        if !result.moved {
+            return false;
+        }
+        racer.score = racer.score.saturating_add(result.score_delta);
+        racer.moves += 1;
+        for _ in 0..spawn.tiles_per_move {
+            racer.board.spawn_tile_with(&mut racer.rng, &spawn);
+        }
+        let junk = match self.battle {
+            true => garbage(racer.board.rule(), &result.merges),
+            false => 0,
+        };
+        racer.junk_sent += junk;
+        self.outcome = self.judge(side);
+        self.attack(side.other(), junk);
+        true
+    }
+
+    /// Drops `junk` tiles on `side`'s board, which may lock it up.
+    pub fn attack(&mut self, side: Side, junk: u32) {
+        if self.outcome.is_some() || junk == 0 {
+            return;
+        }
+        let racer = &mut self.racers[side.index()];
+        racer.board.add_junk(junk, &mut racer.rng);
+        self.outcome = self.judge(side);
+    }
+
+    /// Takes `side`'s board as its player reports it, in a race played over
+    /// the network.
+    pub fn mirror(&mut self, side: Side, board: Board, score: u32, moves: u32) {
+        if self.outcome.is_some() {
+            return;
+        }
+        let racer = &mut self.racers[side.index()];
+        racer.board = board;
+        racer.score = score;
+        racer.moves = moves;
+        self.outcome = self.judge(side);
+    }
+
+    // Whether `side`'s last move settled the race.
+    fn judge(&self, side: Side) -> Option<Outcome> {
+        let (mover, other) = (self.racer(side), self.racer(side.other()));
+        if mover.board.has_won() {
+            return Some(Outcome::Won(side));
+        }
+        if !mover.board.is_game_over() {
+            return None;
+        }
+        Some(match mover.score.cmp(&other.score) {
+            Ordering::Greater => Outcome::Won(side),
+            Ordering::Less => Outcome::Won(side.other()),
+            Ordering::Equal => Outcome::Draw,
+        })
+    }
+}
+
+#[test]
+fn test_race() {
+    let mut race = Race::new(GameConfig::default(), 7);
+    assert_eq!(
+        race.racer(Side::Left).board().cells(),
+        race.racer(Side::Right).board().cells()
+    );
+    let moved = Direction::ALL
+        .into_iter()
+        .find(|&dir| race.play(Side::Left, dir))
+        .unwrap();
+    assert_eq!(race.racer(Side::Left).moves(), 1);
+    assert_eq!(race.racer(Side::Right).moves(), 0);
+    assert_eq!(race.outcome(), None);
+
+    let left = race.racer(Side::Left).clone();
+    assert!(race.fits(&left.board));
+    assert!(!race.fits(&Board::new(5, 4)));
+    race.mirror(Side::Right, left.board, left.score, left.moves);
+    assert_eq!(race.racer(Side::Right).moves(), 1);
+    assert_eq!(race.outcome(), None);
+
+    race.racers[1].board = Board::from_cells(
+        4,
+        4,
+        vec![1024, 1024, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    );
+    assert!(race.play(Side::Right, Direction::Left));
+    assert_eq!(race.outcome(), Some(Outcome::Won(Side::Right)));
+    assert!(!race.play(Side::Left, moved));
+
+    // A board that locks up hands the race to the higher score.
+    let mut race = Race::new(GameConfig::default(), 7);
+    race.racers[0].board = Board::from_cells(
+        3,
+        3,
+        vec![
+            8, 16, 8, //
+            16, 8, 16, //
+            32, 64, 0, //
+        ],
+    );
+    race.racers[1].score = 100;
+    assert!(race.play(Side::Left, Direction::Right));
+    assert!(race.racer(Side::Left).board().is_game_over());
+    assert_eq!(race.outcome(), Some(Outcome::Won(Side::Right)));
+}
+
+#[test]
+fn test_battle() {
+    let mut race = Race::battle(GameConfig::default(), 3);
+    race.racers[0].board = Board::from_cells(
+        4,
+        4,
+        vec![
+            64, 64, 2, 2, //
+            0, 0, 0, 0, //
+            0, 0, 0, 0, //
+            0, 0, 0, 0, //
+        ],
+    );
+    // A 128 sends two junk tiles and the 4 nothing.
+    assert!(race.play(Side::Left, Direction::Left));
+    assert_eq!(race.racer(Side::Left).junk_sent(), 2);
+    assert_eq!(race.racer(Side::Right).board().specials().len(), 2);
+    assert_eq!(race.outcome(), None);
+
+    // Junk that fills the last gaps locks the board up.
+    race.racers[1].board = Board::from_cells(
+        3,
+        3,
+        vec![
+            8, 16, 8, //
+            16, 8, 16, //
+            32, 64, 0, //
+        ],
+    );
+    race.racers[1].score = 10;
+    race.attack(Side::Right, 3);
+    assert_eq!(race.outcome(), Some(Outcome::Won(Side::Left)));
+
+    let rematch = race.rematch(4);
+    assert!(rematch.is_battle() && rematch.outcome().is_none());
+    assert_eq!(rematch.seed(), 4);
+}
@@ -0,0 +1,611 @@
+use crate::engine::powerups::{Inventory, PowerUp, PowerUpUse};
+use crate::engine::{
+    Board, Direction, GameMode, GameRng, MAX_SIZE, MIN_SIZE, SpawnPolicy, Special, seeded_rng,
+};
+use std::fmt::{self, Write};
+
+const HEADER: &str = "2048-replay 1";
+
+/// The tile that appeared after a move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Spawn {
+    pub index: usize,
+    pub value: u32,
+    /// Set for a special tile, whose `value` is 0.
+    pub special: Option<Special>,
+}
+
+/// One move of a recorded game. `millis` counts from the start of the game.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReplayMove {
+    pub direction: Direction,
+    pub spawns: Vec<Spawn>,
+    pub millis: u64,
+}
+
+/// A game as a starting board plus every move and spawn that followed, which
+/// is enough to rebuild each position without the RNG.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Replay {
+    /// Zen games clear tiles between moves, so playback needs the rules.
+    pub mode: GameMode,
+    pub seed: u64,
+    pub spawn: SpawnPolicy,
+    pub start: Board,
+    pub moves: Vec<ReplayMove>,
+    /// Power-ups used in arcade games, each with the number of moves made
+    /// before it.
+    pub power_ups: Vec<(usize, PowerUpUse)>,
+}
+
+/// Why a replay fails [`Replay::verify`]. Moves count from 0.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mismatch {
+    /// Tiles in this mode aren't dealt from the seed alone.
+    Unseeded(GameMode),
+    /// The starting board isn't the one the seed deals.
+    Start,
+    /// The move slides nothing.
+    Stuck(usize),
+    /// The tiles after the move aren't the ones the seed deals.
+    Spawns(usize),
+    /// A power-up used before the move wasn't earned, doesn't fit the board
+    /// or, for a shuffle, isn't the one the seed deals.
+    PowerUp(usize),
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Mismatch::Unseeded(mode) => {
+                write!(f, "{} games aren't dealt from their seed", mode.label())
+            }
+            Mismatch::Start => write!(f, "the starting board doesn't match the seed"),
+            Mismatch::Stuck(i) => write!(f, "move {} doesn't slide anything", i + 1),
+            Mismatch::Spawns(i) => write!(f, "the tiles after move {} don't match the seed", i + 1),
+            Mismatch::PowerUp(i) => write!(f, "a power-up before move {} doesn't add up", i + 1),
+        }
+    }
+}
+
+fn direction_char(dir: Direction) -> char {
+    match dir {
+        Direction::Up => 'U',
+        Direction::Down => 'D',
+        Direction::Left => 'L',
+        Direction::Right => 'R',
+    }
+}
+
+fn parse_special(s: &str) -> Option<Special> {
+    match s {
+        // Replays from before junk had a mark of its own wrote its spawns
+        // as `#`; on the start board that is an obstacle.
+        "#" => Some(Special::Junk),
+        s => Special::from_mark(s),
+    }
+}
+
+fn parse_direction(s: &str) -> Option<Direction> {
+    match s {
+        "U" => Some(Direction::Up),
+        "D" => Some(Direction::Down),
+        "L" => Some(Direction::Left),
+        "R" => Some(Direction::Right),
+        _ => None,
+    }
+}
+
+// `None` if the line isn't a power-up, `Some(None)` if it is one but
+// malformed.
+fn parse_power_up(parts: &[&str], cells: usize) -> Option<Option<PowerUpUse>> {
+    let (&name, args) = parts.split_first()?;
+    let args: Option<Vec<usize>> = args.iter().map(|a| a.parse().ok()).collect();
+    let cell = |i: usize| i < cells;
+    let power_up = match (name, args.as_deref()) {
+        ("swap", Some(&[a, b])) if cell(a) && cell(b) => PowerUpUse::Swap(a, b),
+        ("delete", Some(&[idx])) if cell(idx) => PowerUpUse::Delete(idx),
+        ("shuffle", Some(values)) if values.len() == cells => {
+            PowerUpUse::Shuffle(values.iter().map(|&v| v as u32).collect())
+        }
+        ("swap" | "delete" | "shuffle", _) => return Some(None),
+        _ => return None,
+    };
+    Some(Some(power_up))
+}
+
+// Uses `power_up` again while verifying, the way the game did: a shuffle
+// draws its cells before the power-up is spent.
+fn redo_power_up(
+    board: &mut Board,
+    inventory: &mut Inventory,
+    power_up: &PowerUpUse,
+    rng: &mut GameRng,
+) -> bool {
+    if let PowerUpUse::Shuffle(cells) = power_up
+        && board.shuffled_cells(rng) != *cells
+    {
+        return false;
+    }
+    inventory.take(power_up.power_up()) && board.apply_power_up(power_up)
+}
+
+impl Replay {
+    pub fn new(seed: u64, spawn: SpawnPolicy, start: Board) -> Replay {
+        Replay {
+            mode: GameMode::Classic,
+            seed,
+            spawn,
+            start,
+            moves: Vec::new(),
+            power_ups: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, direction: Direction, spawns: Vec<Spawn>, millis: u64) {
+        self.moves.push(ReplayMove {
+            direction,
+            spawns,
+            millis,
+        });
+    }
+
+    pub fn record_power_up(&mut self, power_up: PowerUpUse) {
+        self.power_ups.push((self.moves.len(), power_up));
+    }
+
+    /// Board and score after every move; the first entry is the start.
+    pub fn positions(&self) -> Vec<(Board, u32)> {
+        let mut board = self.start.clone();
+        let mut score = 0u32;
+        let mut positions = vec![(board.clone(), score)];
+        let mut power_ups = self.power_ups.iter().peekable();
+        for (i, m) in self.moves.iter().enumerate() {
+            // A power-up shows up together with the move that follows it.
+            while let Some((_, power_up)) = power_ups.next_if(|(at, _)| *at <= i) {
+                board.apply_power_up(power_up);
+            }
+            score = score.saturating_add(board.slide(m.direction).unwrap_or(0));
+            for spawn in &m.spawns {
+                match spawn.special {
+                    Some(special) => board.set_special(spawn.index, Some(special)),
+                    None => board.set_cell(spawn.index, spawn.value),
+                }
+            }
+            self.mode.settle(&mut board);
+            positions.push((board.clone(), score));
+        }
+        // Any used after the last move show up in the final position.
+        if power_ups.peek().is_some() {
+            power_ups.for_each(|(_, power_up)| {
+                board.apply_power_up(power_up);
+            });
+            *positions.last_mut().unwrap() = (board, score);
+        }
+        positions
+    }
+
+    /// Plays the game again from its seed, dealing every tile afresh, and
+    /// returns the final score if each recorded tile is the one the seed
+    /// deals. A score claimed for the replay should match it.
+    pub fn verify(&self) -> Result<u32, Mismatch> {
+        if matches!(self.mode, GameMode::Evil | GameMode::Sandbox) || self.mode.has_placer() {
+            return Err(Mismatch::Unseeded(self.mode));
+        }
+        let mut rng = seeded_rng(self.seed);
+        let mut board = Board::new(self.start.rows(), self.start.cols());
+        for &hole in self.start.holes() {
+            board.set_hole(hole);
+        }
+        board.set_mode(self.mode);
+        for _ in 0..self.mode.obstacle_count(board.rows(), board.cols()) {
+            board.place_obstacle(&mut rng);
+        }
+        for _ in 0..2 {
+            board.spawn_tile_with(&mut rng, &self.spawn);
+        }
+        if board != self.start {
+            return Err(Mismatch::Start);
+        }
+
+        let mut score = 0u32;
+        let mut inventory = Inventory::default();
+        let mut power_ups = self.power_ups.iter().peekable();
+        for (i, m) in self.moves.iter().enumerate() {
+            while let Some((_, power_up)) = power_ups.next_if(|(at, _)| *at <= i) {
+                if !redo_power_up(&mut board, &mut inventory, power_up, &mut rng) {
+                    return Err(Mismatch::PowerUp(i));
+                }
+            }
+            let result = board.apply_move(m.direction);
+            if !result.moved {
+                return Err(Mismatch::Stuck(i));
+            }
+            score = score.saturating_add(result.score_delta);
+            if self.mode.has_power_ups() {
+                let rule = board.rule();
+                result
+                    .merges
+                    .iter()
+                    .filter_map(|merge| PowerUp::reward(rule.rank(merge.value)))
+                    .for_each(|power_up| inventory.add(power_up));
+            }
+            let dealt: Vec<Spawn> = (0..self.spawn.tiles_per_move)
+                .map_while(|_| {
+                    let index = board.spawn_tile_with(&mut rng, &self.spawn)?;
+                    Some(Spawn {
+                        index,
+                        value: board.cells()[index],
+                        special: board.special(index),
+                    })
+                })
+                .collect();
+            if dealt != m.spawns {
+                return Err(Mismatch::Spawns(i));
+            }
+            self.mode.settle(&mut board);
+        }
+        for (_, power_up) in power_ups {
+            if !redo_power_up(&mut board, &mut inventory, power_up, &mut rng) {
+                return Err(Mismatch::PowerUp(self.moves.len()));
+            }
+        }
+        Ok(score)
+    }
+
+    /// Writes the replay as plain text: a short header, the starting cells
+    /// with `#` marking obstacles, `.` holes, `*` bombs, `?` wildcards and
+    /// `J` junk, then one `<direction> <spawn index> <spawn value> <millis>`
+    /// line per move, with `- -` standing in for a missing spawn, the same
+    /// marks for special tiles and further index and value pairs for extra
+    /// spawns. Power-ups get a line of their own, named after the power-up
+    /// and followed by its target cells or, for a shuffle, the cells it left.
+    pub fn encode(&self) -> String {
+        let mut out = String::new();
+        let cells: Vec<String> = self
+            .start
+            .cells()
+            .iter()
+            .enumerate()
+            .map(|(i, value)| {
+                if self.start.is_obstacle(i) {
+                    "#".to_string()
+                } else if self.start.is_hole(i) {
+                    ".".to_string()
+                } else if let Some(special) = self.start.special(i) {
+                    special.mark().to_string()
+                } else {
+                    value.to_string()
+                }
+            })
+            .collect();
+        writeln!(out, "{HEADER}").unwrap();
+        writeln!(out, "size {}x{}", self.start.rows(), self.start.cols()).unwrap();
+        writeln!(out, "seed {}", self.seed).unwrap();
+        writeln!(out, "four {}", self.spawn.four_probability).unwrap();
+        if self.spawn.eight_probability > 0.0 {
+            writeln!(out, "eight {}", self.spawn.eight_probability).unwrap();
+        }
+        if self.spawn.tiles_per_move != 1 {
+            writeln!(out, "spawns {}", self.spawn.tiles_per_move).unwrap();
+        }
+        if self.spawn.bomb_probability > 0.0 {
+            writeln!(out, "bombs {}", self.spawn.bomb_probability).unwrap();
+        }
+        if self.spawn.wild_probability > 0.0 {
+            writeln!(out, "wilds {}", self.spawn.wild_probability).unwrap();
+        }
+        writeln!(out, "mode {}", self.mode.label()).unwrap();
+        writeln!(out, "start {}", cells.join(" ")).unwrap();
+        let mut power_ups = self.power_ups.iter().peekable();
+        let mut write_power_ups = |out: &mut String, before: usize| {
+            while let Some((_, power_up)) = power_ups.next_if(|(at, _)| *at <= before) {
+                let args: Vec<String> = match power_up {
+                    PowerUpUse::Swap(a, b) => vec![a.to_string(), b.to_string()],
+                    PowerUpUse::Delete(idx) => vec![idx.to_string()],
+                    PowerUpUse::Shuffle(cells) => cells.iter().map(u32::to_string).collect(),
+                };
+                let name = power_up.power_up().label().to_lowercase();
+                writeln!(out, "{name} {}", args.join(" ")).unwrap();
+            }
+        };
+        for (i, m) in self.moves.iter().enumerate() {
+            write_power_ups(&mut out, i);
+            write!(out, "{}", direction_char(m.direction)).unwrap();
+            if m.spawns.is_empty() {
+                write!(out, " - -").unwrap();
+            }
+            for spawn in &m.spawns {
+                match spawn.special {
+                    Some(special) => write!(out, " {} {}", spawn.index, special.mark()),
+                    None => write!(out, " {} {}", spawn.index, spawn.value),
+                }
+                .unwrap();
+            }
+            writeln!(out, " {}", m.millis).unwrap();
+        }
+        write_power_ups(&mut out, usize::MAX);
+        out
+    }
+
+    /// Reads what [`Replay::encode`] wrote; `None` if anything is malformed.
+    pub fn parse(text: &str) -> Option<Replay> {
+        let mut lines = text
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .peekable();
+        if lines.next()? != HEADER {
+            return None;
+        }
+        let mut field = |name: &str| {
+            let line = lines.next_if(|l| l.split(' ').next() == Some(name))?;
+            line.strip_prefix(name)?
+                .strip_prefix(' ')
+                .map(str::to_string)
+        };
+        let (rows, cols) = field("size")?
+            .split_once('x')
+            .and_then(|(r, c)| Some((r.parse::<usize>().ok()?, c.parse::<usize>().ok()?)))?;
+        let seed = field("seed")?.parse().ok()?;
+        let mut spawn = SpawnPolicy {
+            four_probability: field("four")?.parse().ok()?,
+            ..SpawnPolicy::CLASSIC
+        };
+        if let Some(eight) = field("eight") {
+            spawn.eight_probability = eight.parse().ok()?;
+        }
+        if let Some(count) = field("spawns") {
+            spawn.tiles_per_move = count.parse().ok()?;
+        }
+        if let Some(bombs) = field("bombs") {
+            spawn.bomb_probability = bombs.parse().ok()?;
+        }
+        if let Some(wilds) = field("wilds") {
+            spawn.wild_probability = wilds.parse().ok()?;
+        }
+        // Replays from before modes existed are classic games.
+        let mode = match field("mode") {
+            Some(label) => GameMode::ALL.into_iter().find(|m| m.label() == label)?,
+            None => GameMode::Classic,
+        };
+        let start = field("start")?;
+        let cells = start
+            .split_whitespace()
+            .map(|c| match c {
+                "#" | "." => Some(0),
+                c if parse_special(c).is_some() => Some(0),
+                c => c.parse().ok(),
+            })
+            .collect::<Option<Vec<u32>>>()?;
+        let valid = MIN_SIZE..=MAX_SIZE;
+        if !valid.contains(&rows) || !valid.contains(&cols) || cells.len() != rows * cols {
+            return None;
+        }
+
+        let mut board = Board::from_cells(rows, cols, cells);
+        board.set_mode(mode);
+        for (i, c) in start.split_whitespace().enumerate() {
+            match c {
+                "#" => board.set_obstacle(i),
+                "." => board.set_hole(i),
+                c => {
+                    if let Some(special) = parse_special(c) {
+                        board.set_special(i, Some(special));
+                    }
+                }
+            }
+        }
+        let mut replay = Replay::new(seed, spawn, board);
+        replay.mode = mode;
+        for line in lines {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if let Some(power_up) = parse_power_up(&parts, rows * cols) {
+                replay.record_power_up(power_up?);
+                continue;
+            }
+            let [dir, spawns @ .., millis] = &parts[..] else {
+                return None;
+            };
+            if spawns.is_empty() || spawns.len() % 2 != 0 {
+                return None;
+            }
+            let spawns = match spawns {
+                ["-", "-"] => Vec::new(),
+                spawns => spawns
+                    .chunks(2)
+                    .map(|pair| {
+                        let index = pair[0].parse().ok().filter(|&i| i < rows * cols)?;
+                        Some(match parse_special(pair[1]) {
+                            Some(special) => Spawn {
+                                index,
+                                value: 0,
+                                special: Some(special),
+                            },
+                            None => Spawn {
+                                index,
+                                value: pair[1].parse().ok()?,
+                                special: None,
+                            },
+                        })
+                    })
+                    .collect::<Option<Vec<_>>>()?,
+            };
+            replay.record(parse_direction(dir)?, spawns, millis.parse().ok()?);
+        }
+        Some(replay)
+    }
+}
+
+#[test]
+fn test_replay_round_trip() {
+    let mut start = Board::new(4, 4);
+    start.set_cell(3, 2);
+    start.set_cell(9, 4);
+    start.set_obstacle(6);
+    start.set_hole(12);
+    let mut replay = Replay::new(42, SpawnPolicy::CLASSIC, start);
+    replay.record(
+        Direction::Left,
+        vec![Spawn {
+            index: 15,
+            value: 2,
+            special: None,
+        }],
+        350,
+    );
+    replay.record(Direction::Up, Vec::new(), 1200);
+    replay.record(
+        Direction::Right,
+        vec![
+            Spawn {
+                index: 0,
+                value: 4,
+                special: None,
+            },
+            Spawn {
+                index: 4,
+                value: 2,
+                special: None,
+            },
+        ],
+        1500,
+    );
+
+    let text = replay.encode();
+    assert!(text.contains("start 0 0 0 2 0 0 # 0 0 4 0 0 . 0"));
+    assert!(text.ends_with("L 15 2 350\nU - - 1200\nR 0 4 4 2 1500\n"));
+    assert_eq!(Replay::parse(&text), Some(replay.clone()));
+    assert_eq!(Replay::parse("2048-replay 1\nsize 4x4\n"), None);
+
+    replay.spawn = SpawnPolicy::HARD;
+    assert_eq!(Replay::parse(&replay.encode()), Some(replay.clone()));
+
+    replay.spawn.bomb_probability = 0.05;
+    let bomb = Spawn {
+        index: 5,
+        value: 0,
+        special: Some(Special::Bomb),
+    };
+    let wild = Spawn {
+        index: 6,
+        value: 0,
+        special: Some(Special::Wild),
+    };
+    replay.record(Direction::Down, vec![bomb, wild], 1800);
+    let text = replay.encode();
+    assert!(text.contains("bombs 0.05\n") && text.ends_with("D 5 * 6 ? 1800\n"));
+    assert_eq!(Replay::parse(&text), Some(replay));
+
+    // Junk and obstacles come back as what they were.
+    let mut start = Board::new(3, 3);
+    start.set_obstacle(0);
+    start.set_special(4, Some(Special::Junk));
+    let replay = Replay::new(7, SpawnPolicy::CLASSIC, start);
+    let text = replay.encode();
+    assert!(text.contains("start # 0 0 0 J 0 0 0 0\n"));
+    assert_eq!(Replay::parse(&text), Some(replay));
+}
+
+#[test]
+fn test_replay_power_ups() {
+    let start = Board::from_cells(3, 3, vec![2, 4, 0, 0, 0, 0, 0, 0, 0]);
+    let mut replay = Replay::new(1, SpawnPolicy::CLASSIC, start);
+    replay.mode = GameMode::Arcade;
+    replay.record_power_up(PowerUpUse::Swap(0, 1));
+    replay.record(
+        Direction::Down,
+        vec![Spawn {
+            index: 0,
+            value: 2,
+            special: None,
+        }],
+        100,
+    );
+    replay.record_power_up(PowerUpUse::Delete(6));
+    replay.record_power_up(PowerUpUse::Shuffle(vec![0, 2, 0, 0, 0, 0, 0, 0, 2]));
+
+    let text = replay.encode();
+    assert!(text.ends_with("swap 0 1\nD 0 2 100\ndelete 6\nshuffle 0 2 0 0 0 0 0 0 2\n"));
+    assert_eq!(Replay::parse(&text), Some(replay.clone()));
+    assert_eq!(Replay::parse(&text.replace("delete 6", "delete 9")), None);
+
+    let positions = replay.positions();
+    assert_eq!(positions.len(), 2);
+    assert_eq!(positions[1].0.cells(), [0, 2, 0, 0, 0, 0, 0, 0, 2]);
+}
+
+#[test]
+fn test_replay_positions() {
+    let start = Board::from_cells(
+        3,
+        3,
+        vec![
+            2, 2, 0, //
+            0, 0, 0, //
+            0, 0, 4, //
+        ],
+    );
+    let mut replay = Replay::new(1, SpawnPolicy::CLASSIC, start.clone());
+    replay.record(
+        Direction::Left,
+        vec![Spawn {
+            index: 8,
+            value: 4,
+            special: None,
+        }],
+        100,
+    );
+    replay.record(Direction::Up, Vec::new(), 200);
+
+    let positions = replay.positions();
+    assert_eq!(positions.len(), 3);
+    assert_eq!(positions[0], (start, 0));
+    assert_eq!(positions[1].0.cells(), [4, 0, 0, 0, 0, 0, 4, 0, 4]);
+    assert_eq!(positions[2].0.cells(), [8, 0, 4, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(positions[2].1, 12);
+}
+
+#[test]
+fn test_replay_verify() {
+    let mut rng = seeded_rng(5);
+    let mut board = Board::new(4, 4);
+    for _ in 0..2 {
+        board.spawn_tile_with(&mut rng, &SpawnPolicy::CLASSIC);
+    }
+    let mut replay = Replay::new(5, SpawnPolicy::CLASSIC, board.clone());
+    let mut score = 0;
+    for (i, dir) in Direction::ALL.into_iter().cycle().take(40).enumerate() {
+        let result = board.apply_move(dir);
+        if !result.moved {
+            continue;
+        }
+        score += result.score_delta;
+        let index = board
+            .spawn_tile_with(&mut rng, &SpawnPolicy::CLASSIC)
+            .unwrap();
+        let spawn = Spawn {
+            index,
+            value: board.cells()[index],
+            special: None,
+        };
+        replay.record(dir, vec![spawn], i as u64 * 100);
+    }
+    assert!(replay.moves.len() > 10);
+    assert_eq!(replay.verify(), Ok(score));
+
+    let mut tampered = replay.clone();
+    tampered.moves[3].spawns[0].value *= 2;
+    assert_eq!(tampered.verify(), Err(Mismatch::Spawns(3)));
+    tampered.seed = 6;
+    assert_eq!(tampered.verify(), Err(Mismatch::Start));
+    tampered.mode = GameMode::Evil;
+    assert_eq!(tampered.verify(), Err(Mismatch::Unseeded(GameMode::Evil)));
+
+    // Power-ups have to be earned before they're used.
+    let mut tampered = replay.clone();
+    tampered.mode = GameMode::Arcade;
+    tampered.record_power_up(PowerUpUse::Delete(replay.moves[0].spawns[0].index));
+    let last = replay.moves.len();
+    assert_eq!(tampered.verify(), Err(Mismatch::PowerUp(last)));
+}
@@ -0,0 +1,275 @@
+// Draws the board into an image in memory and writes it out as a PNG, with
+// no window or GPU involved. The lettering is a blocky 3x5 pixel font, and
+// the PNG goes uncompressed; both keep this free of dependencies.
+use crate::engine::{Board, Cell};
+use crate::theme::Theme;
+
+const TILE: usize = 100;
+const GAP: usize = 12;
+const HEADER: usize = 64;
+
+/// An RGB picture, row by row from the top left, one 0xRRGGBB per pixel.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u32>,
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize, color: u32) -> Image {
+        Image {
+            width,
+            height,
+            pixels: vec![color; width * height],
+        }
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> u32 {
+        self.pixels[y * self.width + x]
+    }
+
+    /// Fills a rectangle, clipped to the image.
+    pub fn fill(&mut self, x: usize, y: usize, width: usize, height: usize, color: u32) {
+        self.blend(x, y, width, height, (color << 8) | 0xff);
+    }
+
+    /// Lays `rgba` (0xRRGGBBAA) over a rectangle, clipped to the image.
+    pub fn blend(&mut self, x: usize, y: usize, width: usize, height: usize, rgba: u32) {
+        let alpha = rgba & 0xff;
+        let mix = |under: u32, shift: u32| {
+            let (over, under) = ((rgba >> (shift + 8)) & 0xff, (under >> shift) & 0xff);
+            ((over * alpha + under * (255 - alpha)) / 255) << shift
+        };
+        for row in y..(y + height).min(self.height) {
+            for col in x..(x + width).min(self.width) {
+                let under = self.pixels[row * self.width + col];
+                self.pixels[row * self.width + col] =
+                    mix(under, 16) | mix(under, 8) | mix(under, 0);
+            }
+        }
+    }
+
+    /// Writes `text` centered on (`x`, `y`), each font pixel `size` pixels
+    /// square. Letters are drawn in capitals.
+    pub fn text(&mut self, text: &str, x: usize, y: usize, size: usize, color: u32) {
+        let count = text.chars().count();
+        let width = (count * 4).saturating_sub(1) * size;
+        let left = x.saturating_sub(width / 2);
+        let top = y.saturating_sub(5 * size / 2);
+        for (i, c) in text.chars().enumerate() {
+            for (row, bits) in glyph(c).into_iter().enumerate() {
+                for col in 0..3 {
+                    if bits & (0b100 >> col) != 0 {
+                        let (px, py) = (left + (i * 4 + col) * size, top + row * size);
+                        self.fill(px, py, size, size, color);
+                    }
+                }
+            }
+        }
+    }
+
+    /// The image as a PNG file.
+    pub fn to_png(&self) -> Vec<u8> {
+        let mut header = Vec::new();
+        header.extend_from_slice(&(self.width as u32).to_be_bytes());
+        header.extend_from_slice(&(self.height as u32).to_be_bytes());
+        // 8 bits a channel, RGB, and the standard compression, filtering
+        // and (no) interlacing.
+        header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+        // Each row starts with the filter it uses, here none.
+        let mut raw = Vec::with_capacity(self.height * (self.width * 3 + 1));
+        for row in self.pixels.chunks(self.width) {
+            raw.push(0);
+            for &pixel in row {
+                raw.extend_from_slice(&pixel.to_be_bytes()[1..]);
+            }
+        }
+
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        chunk(&mut png, b"IHDR", &header);
+        chunk(&mut png, b"IDAT", &zlib_stored(&raw));
+        chunk(&mut png, b"IEND", &[]);
+        png
+    }
+}
+
+/// The board under its score, as the window shows it. `banner` is laid over
+/// the board the way the window's game-over or win overlay is.
+pub fn render(board: &Board, score: u32, theme: &Theme, banner: Option<&str>) -> Image {
+    let (rows, cols) = (board.rows(), board.cols());
+    let width = cols * (TILE + GAP) + GAP;
+    let height = HEADER + rows * (TILE + GAP) + GAP;
+    let mut image = Image::new(width, height, theme.background);
+    image.text(
+        &format!("Score {score}"),
+        width / 2,
+        HEADER / 2,
+        5,
+        theme.text,
+    );
+
+    image.fill(0, HEADER, width, height - HEADER, theme.grid);
+    for idx in 0..rows * cols {
+        if board.is_hole(idx) {
+            continue;
+        }
+        let x = GAP + (idx % cols) * (TILE + GAP);
+        let y = HEADER + GAP + (idx / cols) * (TILE + GAP);
+        let (color, label, label_color) = match board.cell(idx) {
+            _ if board.is_obstacle(idx) => (theme.obstacle, String::new(), theme.grid_text),
+            Cell::Empty => (theme.empty_cell, String::new(), theme.text),
+            Cell::Number(value) => {
+                let rank = board.rule().rank(value);
+                // As in the terminal, themes that work tile colors out from
+                // the rank borrow the classic ones.
+                let tile = theme.tile(rank).or(Theme::classic().tile(rank));
+                let color = tile.unwrap_or(theme.empty_cell);
+                (color, value.to_string(), theme.tile_text(rank))
+            }
+            Cell::Special(_) => (
+                theme.accent,
+                board.cell_label(idx).unwrap_or_default(),
+                theme.button_text,
+            ),
+        };
+        image.fill(x, y, TILE, TILE, color);
+        let chars = label.chars().count().max(1);
+        let size = (TILE * 7 / 10 / (chars * 4 - 1)).clamp(1, TILE / 10);
+        image.text(&label, x + TILE / 2, y + TILE / 2, size, label_color);
+    }
+
+    if let Some(banner) = banner {
+        image.blend(0, HEADER, width, height - HEADER, theme.overlay);
+        let size = (width * 8 / 10 / (banner.chars().count() * 4)).clamp(1, 8);
+        let middle = HEADER + (height - HEADER) / 2;
+        image.text(banner, width / 2, middle, size, theme.text);
+    }
+    image
+}
+
+fn chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+// A zlib stream of uncompressed deflate blocks, which hold 65535 bytes at
+// most.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01];
+    let mut blocks = data.chunks(0xffff).peekable();
+    if blocks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        out.push(blocks.peek().is_none() as u8);
+        let len = block.len() as u16;
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    out.extend_from_slice(&((b << 16) | a).to_be_bytes());
+    out
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = match crc & 1 {
+                1 => (crc >> 1) ^ 0xedb88320,
+                _ => crc >> 1,
+            };
+        }
+    }
+    !crc
+}
+
+// Five rows of three pixels, the high bit leftmost. Anything missing is left
+// blank.
+fn glyph(c: char) -> [u8; 5] {
+    match c.to_ascii_uppercase() {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        '*' => [0b000, 0b101, 0b010, 0b101, 0b000],
+        '!' => [0b010, 0b010, 0b010, 0b000, 0b010],
+        '?' => [0b111, 0b001, 0b010, 0b000, 0b010],
+        '\'' => [0b010, 0b010, 0b000, 0b000, 0b000],
+        _ => [0; 5],
+    }
+}
+
+#[test]
+fn test_screenshot() {
+    let board = Board::from_cells(3, 3, vec![2, 0, 0, 0, 2048, 0, 0, 0, 0]);
+    let theme = Theme::classic();
+    let image = render(&board, 20, &theme, None);
+    assert_eq!((image.width, image.height), (348, HEADER + 348));
+    assert_eq!(image.pixel(0, 0), theme.background);
+    assert_eq!(image.pixel(GAP / 2, HEADER + GAP / 2), theme.grid);
+    // The corner of a tile is clear of its number.
+    assert_eq!(image.pixel(GAP, HEADER + GAP), theme.tile(1).unwrap());
+    assert_eq!(
+        image.pixel(GAP + TILE + GAP, HEADER + GAP),
+        theme.empty_cell
+    );
+
+    let over = render(&board, 20, &theme, Some("Game Over!"));
+    assert_ne!(
+        over.pixel(GAP, HEADER + GAP),
+        image.pixel(GAP, HEADER + GAP)
+    );
+    assert_eq!(over.pixel(0, 0), theme.background);
+
+    let png = image.to_png();
+    assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+    assert_eq!(&png[12..16], b"IHDR");
+    assert_eq!(png[16..24], [0, 0, 1, 92, 0, 0, 1, 156]);
+    assert!(png.ends_with(&[0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82]));
+    assert_eq!(crc32(b"123456789"), 0xcbf43926);
+}
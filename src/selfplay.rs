@@ -0,0 +1,128 @@
+use crate::engine::{Board, Direction, GameConfig, SpawnSource};
+use rand::Rng;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// How one game played with nobody watching went.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GameResult {
+    pub score: u32,
+    pub moves: u32,
+    pub max_tile: u32,
+}
+
+/// Plays a game of `config` to the end, moving wherever `choose` says until
+/// it has no move to give. Tiles spawn from `spawns`.
+pub fn play_game(
+    config: &GameConfig,
+    spawns: &mut impl SpawnSource,
+    mut choose: impl FnMut(&Board) -> Option<Direction>,
+) -> GameResult {
+    let mut board = Board::for_config(config);
+    for _ in 0..2 {
+        board.spawn_tile_with(spawns, &config.spawn);
+    }
+    let (mut score, mut moves) = (0, 0);
+    while let Some(dir) = choose(&board) {
+        // A move that slides nothing would be asked for again and again.
+        let Some(points) = board.slide(dir) else {
+            break;
+        };
+        score += points;
+        moves += 1;
+        for _ in 0..config.spawn.tiles_per_move {
+            board.spawn_tile_with(spawns, &config.spawn);
+        }
+    }
+    GameResult {
+        score,
+        moves,
+        max_tile: board.max_tile(),
+    }
+}
+
+/// Any direction that changes the board, picked at random. Playing these
+/// exercises the engine with next to no thinking in the way.
+pub fn random_move(board: &Board, rng: &mut impl Rng) -> Option<Direction> {
+    let open: Vec<Direction> = Direction::ALL
+        .into_iter()
+        .filter(|&dir| board.can_move(dir))
+        .collect();
+    (!open.is_empty()).then(|| open[rng.random_range(0..open.len())])
+}
+
+/// What a run of headless games added up to.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Benchmark {
+    pub games: u32,
+    pub moves: u64,
+    pub total_score: u64,
+    pub best_score: u32,
+    /// How many games ended with each biggest tile.
+    pub max_tiles: BTreeMap<u32, u32>,
+    /// Time spent playing, spawns and thinking included.
+    pub elapsed: Duration,
+}
+
+impl Benchmark {
+    pub fn record(&mut self, game: GameResult, elapsed: Duration) {
+        self.games += 1;
+        self.moves += game.moves as u64;
+        self.total_score += game.score as u64;
+        self.best_score = self.best_score.max(game.score);
+        *self.max_tiles.entry(game.max_tile).or_default() += 1;
+        self.elapsed += elapsed;
+    }
+
+    pub fn average_score(&self) -> f64 {
+        self.total_score as f64 / self.games.max(1) as f64
+    }
+
+    pub fn moves_per_second(&self) -> f64 {
+        match self.elapsed.is_zero() {
+            true => 0.0,
+            false => self.moves as f64 / self.elapsed.as_secs_f64(),
+        }
+    }
+
+    /// The share of games, from 0.0 to 1.0, whose biggest tile reached
+    /// `tile`.
+    pub fn reached(&self, tile: u32) -> f64 {
+        let reached: u32 = self.max_tiles.range(tile..).map(|(_, &count)| count).sum();
+        reached as f64 / self.games.max(1) as f64
+    }
+}
+
+#[test]
+fn test_benchmark() {
+    use crate::engine::seeded_rng;
+
+    let config = GameConfig::default();
+    let mut rng = seeded_rng(3);
+    let mut moves = seeded_rng(4);
+    let game = play_game(&config, &mut rng, |board| random_move(board, &mut moves));
+    assert!(game.moves > 0);
+    assert!(game.max_tile >= 4);
+    // Nobody moving ends the game at once.
+    let idle = play_game(&config, &mut rng, |_| None);
+    assert_eq!((idle.score, idle.moves), (0, 0));
+
+    let mut benchmark = Benchmark::default();
+    assert_eq!(benchmark.average_score(), 0.0);
+    assert_eq!(benchmark.moves_per_second(), 0.0);
+    let result = |score, max_tile| GameResult {
+        score,
+        moves: 100,
+        max_tile,
+    };
+    benchmark.record(result(1000, 128), Duration::from_millis(500));
+    benchmark.record(result(3000, 256), Duration::from_millis(500));
+    benchmark.record(result(2000, 128), Duration::from_millis(1000));
+    assert_eq!(benchmark.average_score(), 2000.0);
+    assert_eq!(benchmark.best_score, 3000);
+    assert_eq!(benchmark.moves_per_second(), 150.0);
+    assert_eq!(benchmark.max_tiles[&128], 2);
+    assert_eq!(benchmark.reached(256), 1.0 / 3.0);
+    assert_eq!(benchmark.reached(128), 1.0);
+    assert_eq!(benchmark.reached(2048), 0.0);
+}
@@ -0,0 +1,517 @@
+use crate::ai::{Adversary, Expectimax, Heuristics, Learned, MonteCarlo, Solver, Strategy};
+use crate::audio::Volumes;
+use crate::engine::{BoardShape, GameConfig, MAX_SIZE, MIN_SIZE, SpawnPolicy};
+use crate::labels::TileLabels;
+use crate::persistence;
+use crate::theme::{Theme, TilePalette};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs, io,
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+
+const SETTINGS_FILE: &str = "settings.toml";
+
+/// Keys for each action, written the way gpui parses keystrokes
+/// (`"ctrl-z"`, `"shift-up"`, ...).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Keybindings {
+    pub up: Vec<String>,
+    pub down: Vec<String>,
+    pub left: Vec<String>,
+    pub right: Vec<String>,
+    pub new_game: Vec<String>,
+    pub undo: Vec<String>,
+    pub redo: Vec<String>,
+    pub keep_playing: Vec<String>,
+    pub hint: Vec<String>,
+    pub autoplay: Vec<String>,
+    pub export_replay: Vec<String>,
+    pub open_replay: Vec<String>,
+    pub pause: Vec<String>,
+    pub mini_mode: Vec<String>,
+    pub debug_overlay: Vec<String>,
+    pub copy_board: Vec<String>,
+    pub paste_board: Vec<String>,
+    pub export_text: Vec<String>,
+    pub screenshot: Vec<String>,
+}
+
+/// Something keys can be bound to, one per list in [`Keybindings`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyAction {
+    Up,
+    Down,
+    Left,
+    Right,
+    NewGame,
+    Undo,
+    Redo,
+    KeepPlaying,
+    Hint,
+    Autoplay,
+    ExportReplay,
+    OpenReplay,
+    Pause,
+    MiniMode,
+    DebugOverlay,
+    CopyBoard,
+    PasteBoard,
+    ExportText,
+    Screenshot,
+}
+
+impl KeyAction {
+    pub const ALL: [KeyAction; 19] = [
+        KeyAction::Up,
+        KeyAction::Down,
+        KeyAction::Left,
+        KeyAction::Right,
+        KeyAction::NewGame,
+        KeyAction::Undo,
+        KeyAction::Redo,
+        KeyAction::KeepPlaying,
+        KeyAction::Hint,
+        KeyAction::Autoplay,
+        KeyAction::ExportReplay,
+        KeyAction::OpenReplay,
+        KeyAction::Pause,
+        KeyAction::MiniMode,
+        KeyAction::DebugOverlay,
+        KeyAction::CopyBoard,
+        KeyAction::PasteBoard,
+        KeyAction::ExportText,
+        KeyAction::Screenshot,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            KeyAction::Up => "Up",
+            KeyAction::Down => "Down",
+            KeyAction::Left => "Left",
+            KeyAction::Right => "Right",
+            KeyAction::NewGame => "New game",
+            KeyAction::Undo => "Undo",
+            KeyAction::Redo => "Redo",
+            KeyAction::KeepPlaying => "Keep playing",
+            KeyAction::Hint => "Hint",
+            KeyAction::Autoplay => "Autoplay",
+            KeyAction::ExportReplay => "Export replay",
+            KeyAction::OpenReplay => "Open replay",
+            KeyAction::Pause => "Pause",
+            KeyAction::MiniMode => "Mini mode",
+            KeyAction::DebugOverlay => "Debug overlay",
+            KeyAction::CopyBoard => "Copy board",
+            KeyAction::PasteBoard => "Paste board",
+            KeyAction::ExportText => "Export as text",
+            KeyAction::Screenshot => "Save screenshot",
+        }
+    }
+}
+
+/// Ready-made sets of keys for moving.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyLayout {
+    /// The arrow keys and WASD.
+    Arrows,
+    /// hjkl, as in vi. Hint moves to shift-h.
+    Vim,
+    /// 8, 4, 2 and 6 on the number pad.
+    Numpad,
+}
+
+impl KeyLayout {
+    pub const ALL: [KeyLayout; 3] = [KeyLayout::Arrows, KeyLayout::Vim, KeyLayout::Numpad];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            KeyLayout::Arrows => "Arrows + WASD",
+            KeyLayout::Vim => "Vim (hjkl)",
+            KeyLayout::Numpad => "Numpad",
+        }
+    }
+}
+
+fn keys(keys: &[&str]) -> Vec<String> {
+    keys.iter().map(|k| k.to_string()).collect()
+}
+
+impl Keybindings {
+    pub fn with_layout(layout: KeyLayout) -> Keybindings {
+        let defaults = Keybindings::default();
+        match layout {
+            KeyLayout::Arrows => defaults,
+            KeyLayout::Vim => Keybindings {
+                up: keys(&["up", "k"]),
+                down: keys(&["down", "j"]),
+                left: keys(&["left", "h"]),
+                right: keys(&["right", "l"]),
+                hint: keys(&["shift-h"]),
+                ..defaults
+            },
+            KeyLayout::Numpad => Keybindings {
+                up: keys(&["up", "8"]),
+                down: keys(&["down", "2"]),
+                left: keys(&["left", "4"]),
+                right: keys(&["right", "6"]),
+                ..defaults
+            },
+        }
+    }
+
+    /// The layout these bindings match, if they haven't been customised.
+    pub fn layout(&self) -> Option<KeyLayout> {
+        KeyLayout::ALL
+            .into_iter()
+            .find(|&layout| *self == Keybindings::with_layout(layout))
+    }
+
+    pub fn keys(&self, action: KeyAction) -> &[String] {
+        match action {
+            KeyAction::Up => &self.up,
+            KeyAction::Down => &self.down,
+            KeyAction::Left => &self.left,
+            KeyAction::Right => &self.right,
+            KeyAction::NewGame => &self.new_game,
+            KeyAction::Undo => &self.undo,
+            KeyAction::Redo => &self.redo,
+            KeyAction::KeepPlaying => &self.keep_playing,
+            KeyAction::Hint => &self.hint,
+            KeyAction::Autoplay => &self.autoplay,
+            KeyAction::ExportReplay => &self.export_replay,
+            KeyAction::OpenReplay => &self.open_replay,
+            KeyAction::Pause => &self.pause,
+            KeyAction::MiniMode => &self.mini_mode,
+            KeyAction::DebugOverlay => &self.debug_overlay,
+            KeyAction::CopyBoard => &self.copy_board,
+            KeyAction::PasteBoard => &self.paste_board,
+            KeyAction::ExportText => &self.export_text,
+            KeyAction::Screenshot => &self.screenshot,
+        }
+    }
+
+    fn keys_mut(&mut self, action: KeyAction) -> &mut Vec<String> {
+        match action {
+            KeyAction::Up => &mut self.up,
+            KeyAction::Down => &mut self.down,
+            KeyAction::Left => &mut self.left,
+            KeyAction::Right => &mut self.right,
+            KeyAction::NewGame => &mut self.new_game,
+            KeyAction::Undo => &mut self.undo,
+            KeyAction::Redo => &mut self.redo,
+            KeyAction::KeepPlaying => &mut self.keep_playing,
+            KeyAction::Hint => &mut self.hint,
+            KeyAction::Autoplay => &mut self.autoplay,
+            KeyAction::ExportReplay => &mut self.export_replay,
+            KeyAction::OpenReplay => &mut self.open_replay,
+            KeyAction::Pause => &mut self.pause,
+            KeyAction::MiniMode => &mut self.mini_mode,
+            KeyAction::DebugOverlay => &mut self.debug_overlay,
+            KeyAction::CopyBoard => &mut self.copy_board,
+            KeyAction::PasteBoard => &mut self.paste_board,
+            KeyAction::ExportText => &mut self.export_text,
+            KeyAction::Screenshot => &mut self.screenshot,
+        }
+    }
+
+    /// Makes `key` the only key for `action`, taking it away from whatever
+    /// it did before.
+    pub fn rebind(&mut self, action: KeyAction, key: &str) {
+        for other in KeyAction::ALL {
+            self.keys_mut(other).retain(|k| k != key);
+        }
+        *self.keys_mut(action) = vec![key.to_string()];
+    }
+}
+
+impl Default for Keybindings {
+    fn default() -> Self {
+        Keybindings {
+            up: keys(&["up", "w"]),
+            down: keys(&["down", "s"]),
+            left: keys(&["left", "a"]),
+            right: keys(&["right", "d"]),
+            new_game: keys(&["enter"]),
+            undo: keys(&["z", "ctrl-z"]),
+            redo: keys(&["y", "ctrl-y", "ctrl-shift-z"]),
+            keep_playing: keys(&["c"]),
+            hint: keys(&["h"]),
+            autoplay: keys(&["shift-a"]),
+            export_replay: keys(&["ctrl-s"]),
+            open_replay: keys(&["ctrl-o"]),
+            pause: keys(&["escape", "p"]),
+            mini_mode: keys(&["m"]),
+            debug_overlay: keys(&["f3"]),
+            copy_board: keys(&["ctrl-c"]),
+            paste_board: keys(&["ctrl-v"]),
+            export_text: keys(&["ctrl-shift-c"]),
+            screenshot: keys(&["f12"]),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub rows: usize,
+    pub cols: usize,
+    pub shape: BoardShape,
+    /// Chance that a spawned tile is a 4 instead of a 2.
+    pub four_probability: f64,
+    /// Chance that a spawned tile is an 8.
+    pub eight_probability: f64,
+    /// Tiles that appear after every move.
+    pub spawn_count: u32,
+    /// Chance that a spawned tile is a bomb.
+    pub bomb_probability: f64,
+    /// Chance that a spawned tile is a wildcard.
+    pub wild_probability: f64,
+    pub undo_depth: usize,
+    /// The name of the theme to draw in, built in or from `custom_themes`.
+    pub theme: String,
+    /// Tile colors to use instead of the theme's.
+    pub tile_palette: TilePalette,
+    pub tile_labels: TileLabels,
+    /// Whether tiles carry a shape badge, so they can be told apart without
+    /// relying on color.
+    pub tile_badges: bool,
+    /// How much bigger than usual to draw tiles, text and spacing alike;
+    /// 2.0 is twice the size.
+    pub ui_scale: f32,
+    pub animations: bool,
+    /// Multiplier on animation playback speed; 2.0 plays twice as fast.
+    pub animation_speed: f32,
+    /// Tiles appear where they land and fade in rather than sliding, popping
+    /// or shaking, and there is no confetti.
+    pub reduced_motion: bool,
+    /// Off mutes every sound, whatever its volume.
+    pub sound: bool,
+    pub volumes: Volumes,
+    /// Background music: the tracks in the `music` folder of the data
+    /// directory, calmest first by file name, or built-in ones if it has none.
+    pub music: bool,
+    pub music_volume: f32,
+    /// Which AI gives hints, plays autoplay and races against you.
+    pub ai_strategy: Strategy,
+    /// How many playouts the Monte Carlo AI runs for each direction.
+    pub ai_rollouts: u32,
+    /// How many moves ahead the hint search looks when it has no time budget.
+    pub ai_depth: u32,
+    /// Milliseconds the AI may think per move; 0 searches to `ai_depth`.
+    pub ai_time_budget_ms: u64,
+    /// Memory the AI may spend caching searched positions, in megabytes.
+    pub ai_cache_mb: usize,
+    /// Evaluation weights the AI plays by.
+    pub heuristics: Heuristics,
+    /// Moves per second while the AI plays by itself.
+    pub autoplay_speed: f32,
+    /// Whether to stream the game to overlays over a local WebSocket on
+    /// [`SPECTATE_PORT`](crate::spectate::SPECTATE_PORT).
+    pub spectate: bool,
+    /// Whether to show arrow buttons under the board, for playing with the
+    /// mouse alone.
+    pub dpad: bool,
+    /// Whether bars along the board's edges show how the AI rates each
+    /// direction from the current position.
+    pub coach: bool,
+    /// Whether classic games show a speedrun timer, split at 256, 512, 1024
+    /// and 2048 against the best run on the same board size.
+    pub speedrun: bool,
+    /// Whether holding a move key repeats the move on the game's own timer
+    /// instead of the system's key repeat.
+    pub key_repeat: bool,
+    /// How long a move key is held before it starts repeating.
+    pub repeat_delay_ms: u64,
+    /// Time between repeated moves.
+    pub repeat_interval_ms: u64,
+    pub keybindings: Keybindings,
+    /// Themes of your own, written as `[[custom_themes]]` tables.
+    pub custom_themes: Vec<Theme>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        let config = GameConfig::default();
+        Settings {
+            rows: config.rows,
+            cols: config.cols,
+            shape: config.shape,
+            four_probability: config.spawn.four_probability,
+            eight_probability: config.spawn.eight_probability,
+            spawn_count: config.spawn.tiles_per_move,
+            bomb_probability: config.spawn.bomb_probability,
+            wild_probability: config.spawn.wild_probability,
+            undo_depth: config.history_depth,
+            theme: Theme::default().name,
+            tile_palette: TilePalette::default(),
+            tile_labels: TileLabels::default(),
+            tile_badges: false,
+            ui_scale: 1.0,
+            animations: true,
+            animation_speed: 1.0,
+            reduced_motion: false,
+            sound: true,
+            volumes: Volumes::default(),
+            music: false,
+            music_volume: 0.5,
+            ai_strategy: Strategy::default(),
+            ai_rollouts: 100,
+            ai_depth: 3,
+            ai_time_budget_ms: 100,
+            ai_cache_mb: 64,
+            heuristics: Heuristics::default(),
+            autoplay_speed: 4.0,
+            spectate: false,
+            dpad: false,
+            coach: false,
+            speedrun: false,
+            key_repeat: false,
+            repeat_delay_ms: 300,
+            repeat_interval_ms: 150,
+            keybindings: Keybindings::default(),
+            custom_themes: Vec::new(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn game_config(&self) -> GameConfig {
+        let valid = MIN_SIZE..=MAX_SIZE;
+        let defaults = GameConfig::default();
+        GameConfig {
+            rows: if valid.contains(&self.rows) {
+                self.rows
+            } else {
+                defaults.rows
+            },
+            cols: if valid.contains(&self.cols) {
+                self.cols
+            } else {
+                defaults.cols
+            },
+            shape: self.shape,
+            spawn: self.spawn_policy(),
+            history_depth: self.undo_depth,
+            ..defaults
+        }
+    }
+
+    pub fn theme(&self) -> Theme {
+        Theme::named(&self.theme, &self.custom_themes).with_palette(self.tile_palette)
+    }
+
+    /// Every theme there is to pick from: the built-in ones, then any from
+    /// the settings file that don't replace one of them.
+    pub fn themes(&self) -> Vec<String> {
+        let mut names: Vec<String> = Theme::builtin().map(|theme| theme.name).into();
+        for theme in &self.custom_themes {
+            if !names
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case(&theme.name))
+            {
+                names.push(theme.name.clone());
+            }
+        }
+        names
+    }
+
+    pub fn spawn_policy(&self) -> SpawnPolicy {
+        SpawnPolicy {
+            four_probability: self.four_probability.clamp(0.0, 1.0),
+            eight_probability: self.eight_probability.clamp(0.0, 1.0),
+            tiles_per_move: self.spawn_count.clamp(1, 4),
+            bomb_probability: self.bomb_probability.clamp(0.0, 1.0),
+            wild_probability: self.wild_probability.clamp(0.0, 1.0),
+        }
+    }
+
+    pub fn set_spawn_policy(&mut self, policy: SpawnPolicy) {
+        self.four_probability = policy.four_probability;
+        self.eight_probability = policy.eight_probability;
+        self.spawn_count = policy.tiles_per_move;
+        self.bomb_probability = policy.bomb_probability;
+        self.wild_probability = policy.wild_probability;
+    }
+
+    /// The spawner for evil games. It looks two spawns ahead, which is
+    /// already merciless, but on big boards gives up the second after 30ms
+    /// so the move it answers doesn't stall.
+    pub fn adversary(&self) -> Adversary {
+        Adversary {
+            heuristics: self.heuristics,
+            time_budget: Some(Duration::from_millis(30)),
+            ..Adversary::new(2)
+        }
+    }
+
+    pub fn solver(&self) -> Solver {
+        match self.ai_strategy {
+            Strategy::Expectimax => Solver::Expectimax(self.expectimax()),
+            Strategy::MonteCarlo => Solver::MonteCarlo(MonteCarlo::new(self.ai_rollouts)),
+            Strategy::Learned => match persistence::learned_network() {
+                Some(network) => Solver::Learned(Learned::new(network, self.expectimax())),
+                None => Solver::Expectimax(self.expectimax()),
+            },
+        }
+    }
+
+    pub fn expectimax(&self) -> Expectimax {
+        let search = match self.ai_time_budget_ms {
+            0 => Expectimax::new(self.ai_depth),
+            ms => Expectimax::with_time_budget(Duration::from_millis(ms)),
+        };
+        Expectimax {
+            cache_mb: self.ai_cache_mb,
+            heuristics: self.heuristics,
+            ..search
+        }
+    }
+}
+
+pub fn settings_path() -> PathBuf {
+    persistence::data_dir().join(SETTINGS_FILE)
+}
+
+/// Last modification time of the settings file, used to notice edits.
+pub fn modified_time() -> Option<SystemTime> {
+    fs::metadata(settings_path())
+        .and_then(|m| m.modified())
+        .ok()
+}
+
+/// Reads the settings file, writing the defaults on first run. A file that
+/// fails to parse is left untouched and the defaults are used instead.
+pub fn load() -> Settings {
+    match fs::read_to_string(settings_path()) {
+        Ok(contents) => toml::from_str(&contents).unwrap_or_default(),
+        Err(_) => {
+            let settings = Settings::default();
+            save(&settings).ok();
+            settings
+        }
+    }
+}
+
+pub fn save(settings: &Settings) -> io::Result<()> {
+    let contents = toml::to_string_pretty(settings).map_err(io::Error::other)?;
+    fs::create_dir_all(persistence::data_dir())?;
+    persistence::write_atomic(&settings_path(), contents)
+}
+
+#[test]
+fn test_rebind() {
+    let mut keys = Keybindings::default();
+    assert_eq!(keys.layout(), Some(KeyLayout::Arrows));
+    keys.rebind(KeyAction::Hint, "w");
+    assert_eq!(keys.keys(KeyAction::Hint), ["w"]);
+    assert_eq!(keys.keys(KeyAction::Up), ["up"]);
+    assert_eq!(keys.layout(), None);
+
+    let vim = Keybindings::with_layout(KeyLayout::Vim);
+    assert_eq!(vim.layout(), Some(KeyLayout::Vim));
+    // No key is left doing two things.
+    let all: Vec<&String> = KeyAction::ALL.iter().flat_map(|&a| vim.keys(a)).collect();
+    assert!(all.iter().enumerate().all(|(i, k)| !all[..i].contains(k)));
+}
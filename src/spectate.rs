@@ -0,0 +1,257 @@
+use crate::digest::sha1;
+use crate::engine::{Board, GameMode};
+use serde::Serialize;
+use std::io::{self, BufRead, BufReader, ErrorKind, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::Duration;
+
+/// The port the spectator feed listens on, next to the one online races use.
+pub const SPECTATE_PORT: u16 = 2049;
+
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(100);
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+// A spectator that can't keep up is dropped rather than left to stall moves.
+const WRITE_TIMEOUT: Duration = Duration::from_millis(200);
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// The state of the game, sent to spectators as JSON after every change.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Update {
+    pub board: Board,
+    pub score: u32,
+    pub best_score: u32,
+    pub moves: u32,
+    pub mode: GameMode,
+    pub won: bool,
+    pub game_over: bool,
+}
+
+#[derive(Default)]
+struct Audience {
+    clients: Vec<TcpStream>,
+    // The last update sent, framed, for spectators who tune in between moves.
+    last: Option<Vec<u8>>,
+}
+
+/// A local WebSocket server that streams [`Update`]s to overlays such as OBS
+/// browser sources. It only ever sends; whatever spectators send is ignored.
+pub struct Spectators {
+    port: u16,
+    audience: Arc<Mutex<Audience>>,
+}
+
+impl Spectators {
+    /// Starts listening on `port` of this machine. The listener shuts down
+    /// shortly after the `Spectators` is dropped.
+    pub fn bind(port: u16) -> io::Result<Spectators> {
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+        listener.set_nonblocking(true)?;
+        let port = listener.local_addr()?.port();
+        let audience = Arc::new(Mutex::new(Audience::default()));
+        let watching = Arc::downgrade(&audience);
+        thread::spawn(move || listen(listener, watching));
+        Ok(Spectators { port, audience })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Sends `update` to everyone watching, unless it is the one they saw
+    /// last. Spectators who have gone away are dropped.
+    pub fn broadcast(&self, update: &Update) {
+        let Ok(json) = serde_json::to_string(update) else {
+            return;
+        };
+        let frame = text_frame(&json);
+        let mut audience = self.audience.lock().unwrap();
+        if audience.last.as_ref() == Some(&frame) {
+            return;
+        }
+        audience
+            .clients
+            .retain_mut(|client| client.write_all(&frame).is_ok());
+        audience.last = Some(frame);
+    }
+}
+
+// Lets spectators in until the server is dropped.
+fn listen(listener: TcpListener, audience: Weak<Mutex<Audience>>) {
+    loop {
+        let stream = match listener.accept() {
+            Ok((stream, _)) => stream,
+            Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                if audience.strong_count() == 0 {
+                    return;
+                }
+                thread::sleep(ACCEPT_INTERVAL);
+                continue;
+            }
+            Err(_) => return,
+        };
+        let Ok(mut client) = handshake(stream) else {
+            continue;
+        };
+        let Some(audience) = audience.upgrade() else {
+            return;
+        };
+        let mut audience = audience.lock().unwrap();
+        if let Some(last) = &audience.last
+            && client.write_all(last).is_err()
+        {
+            continue;
+        }
+        audience.clients.push(client);
+    }
+}
+
+// Answers a WebSocket opening handshake, leaving `stream` ready for frames.
+fn handshake(stream: TcpStream) -> io::Result<TcpStream> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    let mut key = None;
+    let mut reader = BufReader::new(&stream);
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(ErrorKind::UnexpectedEof.into());
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':')
+            && name.trim().eq_ignore_ascii_case("sec-websocket-key")
+        {
+            key = Some(value.trim().to_string());
+        }
+    }
+    let Some(key) = key else {
+        (&stream).write_all(b"HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\n\r\n")?;
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            "not a WebSocket handshake",
+        ));
+    };
+    write!(
+        &stream,
+        "HTTP/1.1 101 Switching Protocols\r\n\
+         Upgrade: websocket\r\n\
+         Connection: Upgrade\r\n\
+         Sec-WebSocket-Accept: {}\r\n\r\n",
+        accept_key(&key)
+    )?;
+    Ok(stream)
+}
+
+fn accept_key(key: &str) -> String {
+    base64(&sha1(format!("{key}{WEBSOCKET_GUID}").as_bytes()))
+}
+
+// An unmasked text frame, the only kind a server sends here.
+fn text_frame(text: &str) -> Vec<u8> {
+    let len = text.len();
+    let mut frame = vec![0x81];
+    match len {
+        0..126 => frame.push(len as u8),
+        126..=0xffff => {
+            frame.push(126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        _ => {
+            frame.push(127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(text.as_bytes());
+    frame
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            match i <= chunk.len() {
+                true => out.push(ALPHABET[(n >> (18 - 6 * i)) as usize & 63] as char),
+                false => out.push('='),
+            }
+        }
+    }
+    out
+}
+
+#[test]
+fn test_accept_key() {
+    // The example from RFC 6455.
+    assert_eq!(
+        accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+        "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+    );
+    assert_eq!(base64(b"ab"), "YWI=");
+    assert_eq!(text_frame("hi"), b"\x81\x02hi");
+    assert_eq!(&text_frame(&"x".repeat(300))[..4], [0x81, 126, 1, 44]);
+}
+
+#[test]
+fn test_spectators() {
+    use std::io::Read;
+
+    let update = |score| Update {
+        board: Board::from_cells(3, 3, vec![2, 0, 0, 0, 4, 0, 0, 0, 8]),
+        score,
+        best_score: 40,
+        moves: 3,
+        mode: GameMode::Classic,
+        won: false,
+        game_over: false,
+    };
+    let spectators = Spectators::bind(0).unwrap();
+    spectators.broadcast(&update(12));
+
+    let mut stream = TcpStream::connect(("127.0.0.1", spectators.port())).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    stream
+        .write_all(
+            b"GET / HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\n\
+              Connection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+              Sec-WebSocket-Version: 13\r\n\r\n",
+        )
+        .unwrap();
+    let mut reader = BufReader::new(stream);
+    let mut response = String::new();
+    while !response.ends_with("\r\n\r\n") {
+        reader.read_line(&mut response).unwrap();
+    }
+    assert!(response.starts_with("HTTP/1.1 101"));
+    assert!(response.contains("s3pPLMBiTxaQ9kYGzzhZRbK+xOo="));
+
+    let mut next_frame = || {
+        let mut header = [0; 2];
+        reader.read_exact(&mut header).unwrap();
+        assert_eq!(header[0], 0x81);
+        let mut len = header[1] as usize;
+        if len == 126 {
+            let mut extended = [0; 2];
+            reader.read_exact(&mut extended).unwrap();
+            len = u16::from_be_bytes(extended) as usize;
+        }
+        let mut payload = vec![0; len];
+        reader.read_exact(&mut payload).unwrap();
+        String::from_utf8(payload).unwrap()
+    };
+    // Tuning in mid-game shows the latest state straight away.
+    assert_eq!(next_frame(), serde_json::to_string(&update(12)).unwrap());
+    spectators.broadcast(&update(12));
+    spectators.broadcast(&update(16));
+    assert_eq!(next_frame(), serde_json::to_string(&update(16)).unwrap());
+}
@@ -0,0 +1,202 @@
+use serde::{Deserialize, Serialize};
+
+/// The tiles a speedrun is split at, in the order they are reached.
+pub const SPLIT_TILES: [u32; 4] = [256, 512, 1024, 2048];
+
+/// How a split compares with the records.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pace {
+    /// The fastest this segment has ever been played.
+    Gold,
+    /// Ahead of the personal best at this split.
+    Ahead,
+    Behind,
+}
+
+/// One split reached in a run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Split {
+    pub tile: u32,
+    /// Since the run's first move.
+    pub millis: u64,
+    /// Against the personal best at the same split; negative is ahead.
+    /// Missing where the best run never got this far.
+    pub delta: Option<i64>,
+    pub pace: Pace,
+}
+
+/// The best runs on one board size.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SplitRecords {
+    /// The split times of the best run, since its first move.
+    pub personal_best: Vec<u64>,
+    /// The fastest each segment has ever been played, from the split before
+    /// it (or the first move).
+    pub best_segments: Vec<u64>,
+}
+
+impl SplitRecords {
+    /// Compares the split at `index`, reached `millis` into the run, with the
+    /// records. `previous` is when the split before it was reached.
+    pub fn judge(&self, index: usize, millis: u64, previous: u64) -> Split {
+        let delta = self
+            .personal_best
+            .get(index)
+            .map(|&best| millis as i64 - best as i64);
+        let segment = millis.saturating_sub(previous);
+        let gold = self
+            .best_segments
+            .get(index)
+            .is_none_or(|&best| segment < best);
+        Split {
+            tile: SPLIT_TILES[index],
+            millis,
+            delta,
+            pace: match (gold, delta) {
+                (true, _) => Pace::Gold,
+                (false, Some(delta)) if delta < 0 => Pace::Ahead,
+                (false, _) => Pace::Behind,
+            },
+        }
+    }
+
+    /// Takes in a finished run's split times. It becomes the personal best if
+    /// it got further than the best run, or as far but sooner, and any segment
+    /// played faster than ever replaces the old best. Returns whether it was
+    /// a personal best.
+    pub fn record(&mut self, times: &[u64]) -> bool {
+        let mut previous = 0;
+        for (index, &millis) in times.iter().enumerate() {
+            let segment = millis.saturating_sub(previous);
+            match self.best_segments.get_mut(index) {
+                Some(best) => *best = (*best).min(segment),
+                None => self.best_segments.push(segment),
+            }
+            previous = millis;
+        }
+        let best = &self.personal_best;
+        let faster = !times.is_empty()
+            && (times.len() > best.len()
+                || (times.len() == best.len() && times.last() < best.last()));
+        if faster {
+            self.personal_best = times.to_vec();
+        }
+        faster
+    }
+}
+
+/// A speedrun in progress, timed in the game's own milliseconds.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Run {
+    /// When the first move was made.
+    pub start: u64,
+    pub splits: Vec<Split>,
+    /// When the run stopped, once it has.
+    pub end: Option<u64>,
+}
+
+impl Run {
+    pub fn new(start: u64) -> Run {
+        Run {
+            start,
+            splits: Vec::new(),
+            end: None,
+        }
+    }
+
+    /// How long the run has gone on, with the game's clock at `now`.
+    pub fn elapsed(&self, now: u64) -> u64 {
+        self.end.unwrap_or(now).saturating_sub(self.start)
+    }
+
+    /// Splits at every split tile up to `max_tile` not yet reached. Returns
+    /// whether there were any.
+    pub fn reach(&mut self, max_tile: u32, now: u64, records: &SplitRecords) -> bool {
+        let before = self.splits.len();
+        let millis = self.elapsed(now);
+        while let Some(&tile) = SPLIT_TILES.get(self.splits.len())
+            && tile <= max_tile
+            && self.end.is_none()
+        {
+            let previous = self.splits.last().map_or(0, |split| split.millis);
+            self.splits
+                .push(records.judge(self.splits.len(), millis, previous));
+        }
+        self.splits.len() > before
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.splits.len() == SPLIT_TILES.len()
+    }
+
+    pub fn times(&self) -> Vec<u64> {
+        self.splits.iter().map(|split| split.millis).collect()
+    }
+}
+
+/// A run's time as m:ss.t, or h:mm:ss.t past the hour.
+pub fn format_time(millis: u64) -> String {
+    let tenths = millis / 100 % 10;
+    let secs = millis / 1000;
+    match secs / 3600 {
+        0 => format!("{}:{:02}.{tenths}", secs / 60, secs % 60),
+        hours => format!("{hours}:{:02}:{:02}.{tenths}", secs / 60 % 60, secs % 60),
+    }
+}
+
+/// A delta as +s.t or -s.t, with minutes once it reaches one.
+pub fn format_delta(delta: i64) -> String {
+    let sign = match delta < 0 {
+        true => '-',
+        false => '+',
+    };
+    let millis = delta.unsigned_abs();
+    match millis < 60_000 {
+        true => format!("{sign}{}.{}", millis / 1000, millis / 100 % 10),
+        false => format!("{sign}{}", format_time(millis)),
+    }
+}
+
+#[test]
+fn test_splits() {
+    let mut records = SplitRecords::default();
+    let mut run = Run::new(1_000);
+    assert!(!run.reach(128, 5_000, &records));
+    // Jumping two split tiles in one move splits at both.
+    assert!(run.reach(512, 61_000, &records));
+    assert_eq!(run.times(), [60_000, 60_000]);
+    assert!(run.splits.iter().all(|split| split.pace == Pace::Gold));
+    assert_eq!(run.splits[0].delta, None);
+    run.end = Some(100_000);
+    assert!(!run.reach(2048, 200_000, &records));
+    assert_eq!(run.elapsed(500_000), 99_000);
+    assert!(records.record(&run.times()));
+    assert_eq!(records.best_segments, [60_000, 0]);
+
+    let mut run = Run::new(0);
+    run.reach(256, 50_000, &records);
+    run.reach(512, 120_000, &records);
+    run.reach(1024, 200_000, &records);
+    assert_eq!(run.splits[0].pace, Pace::Gold);
+    assert_eq!(run.splits[0].delta, Some(-10_000));
+    assert_eq!(run.splits[1].pace, Pace::Behind);
+    assert_eq!(run.splits[1].delta, Some(60_000));
+    assert_eq!(run.splits[2].delta, None);
+    assert!(!run.is_complete());
+    // Getting further beats getting there sooner.
+    assert!(records.record(&run.times()));
+    assert_eq!(records.personal_best, [50_000, 120_000, 200_000]);
+    assert_eq!(records.best_segments, [50_000, 0, 80_000]);
+    assert!(!records.record(&[40_000]));
+    assert_eq!(records.best_segments[0], 40_000);
+
+    let mut run = Run::new(0);
+    run.reach(256, 45_000, &records);
+    assert_eq!(run.splits[0].pace, Pace::Ahead);
+
+    assert_eq!(format_time(61_250), "1:01.2");
+    assert_eq!(format_time(3_723_000), "1:02:03.0");
+    assert_eq!(format_delta(-1_500), "-1.5");
+    assert_eq!(format_delta(75_000), "+1:15.0");
+}
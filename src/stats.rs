@@ -0,0 +1,236 @@
+use crate::daily::Date;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// How many final scores the history keeps.
+pub const SCORE_HISTORY: usize = 50;
+/// Games shorter than this don't count towards the efficiency records, or a
+/// lucky handful of moves would beat every real game.
+pub const EFFICIENCY_MIN_MOVES: u32 = 100;
+
+/// Lifetime totals across every finished game.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Stats {
+    pub games_played: u32,
+    pub games_won: u32,
+    pub total_score: u64,
+    pub total_moves: u64,
+    pub highest_tile: u32,
+    /// How many games ended with each tile as the largest on the board.
+    pub highest_tiles: BTreeMap<u32, u32>,
+    /// Final scores of the most recent games, oldest first.
+    pub recent_scores: Vec<u32>,
+    /// Time attack results are kept apart, as their scores aren't comparable.
+    pub time_attack_games: u32,
+    pub time_attack_best: u32,
+    /// Recent limited-moves scores for each move budget, oldest first.
+    pub move_challenges: BTreeMap<u32, Vec<u32>>,
+    /// Daily puzzle results, kept apart from the lifetime totals.
+    pub dailies: BTreeMap<Date, DailyResult>,
+    /// Solved challenges, as `pack/challenge` names.
+    pub solved_challenges: BTreeSet<String>,
+    /// The games that made the most of their moves.
+    pub best_score_per_move: Option<Efficiency>,
+    pub best_merges_per_move: Option<Efficiency>,
+}
+
+/// How much a game made of its moves.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Efficiency {
+    pub score: u32,
+    pub merges: u32,
+    pub moves: u32,
+}
+
+impl Efficiency {
+    pub fn score_per_move(&self) -> f64 {
+        match self.moves {
+            0 => 0.0,
+            n => self.score as f64 / n as f64,
+        }
+    }
+
+    pub fn merges_per_move(&self) -> f64 {
+        match self.moves {
+            0 => 0.0,
+            n => self.merges as f64 / n as f64,
+        }
+    }
+}
+
+/// The best of every attempt at one day's puzzle.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DailyResult {
+    pub attempts: u32,
+    pub best_score: u32,
+    pub highest_tile: u32,
+    pub won: bool,
+}
+
+impl Stats {
+    pub fn record_game(&mut self, score: u32, max_tile: u32, moves: u32, won: bool) {
+        self.games_played += 1;
+        self.games_won += won as u32;
+        self.total_score += score as u64;
+        self.total_moves += moves as u64;
+        self.highest_tile = self.highest_tile.max(max_tile);
+        *self.highest_tiles.entry(max_tile).or_default() += 1;
+        self.recent_scores.push(score);
+        if self.recent_scores.len() > SCORE_HISTORY {
+            self.recent_scores.remove(0);
+        }
+    }
+
+    /// Keeps `game` as the record for each rate it beats. Returns whether it
+    /// set the score per move and the merges per move records.
+    pub fn record_efficiency(&mut self, game: Efficiency) -> (bool, bool) {
+        if game.moves < EFFICIENCY_MIN_MOVES {
+            return (false, false);
+        }
+        // Compared across, so the rates never go through floating point.
+        let beats = |best: Option<Efficiency>, rate: fn(&Efficiency) -> u32| {
+            best.is_none_or(|best| {
+                rate(&game) as u64 * best.moves as u64 > rate(&best) as u64 * game.moves as u64
+            })
+        };
+        let score = beats(self.best_score_per_move, |e| e.score);
+        let merges = beats(self.best_merges_per_move, |e| e.merges);
+        if score {
+            self.best_score_per_move = Some(game);
+        }
+        if merges {
+            self.best_merges_per_move = Some(game);
+        }
+        (score, merges)
+    }
+
+    pub fn record_time_attack(&mut self, score: u32) {
+        self.time_attack_games += 1;
+        self.time_attack_best = self.time_attack_best.max(score);
+    }
+
+    pub fn record_move_challenge(&mut self, budget: u32, score: u32) {
+        let scores = self.move_challenges.entry(budget).or_default();
+        scores.push(score);
+        if scores.len() > SCORE_HISTORY {
+            scores.remove(0);
+        }
+    }
+
+    pub fn record_daily(&mut self, date: Date, score: u32, max_tile: u32, won: bool) {
+        let result = self.dailies.entry(date).or_default();
+        result.attempts += 1;
+        result.best_score = result.best_score.max(score);
+        result.highest_tile = result.highest_tile.max(max_tile);
+        result.won |= won;
+    }
+
+    /// Consecutive days with a finished daily, counting back from `today`,
+    /// or from yesterday while today's is still unplayed.
+    pub fn daily_streak(&self, today: Date) -> u32 {
+        let start = if self.dailies.contains_key(&today) {
+            today
+        } else {
+            today.add_days(-1)
+        };
+        (0..)
+            .take_while(|&i| self.dailies.contains_key(&start.add_days(-i)))
+            .count() as u32
+    }
+
+    /// Share of games won, from 0.0 to 1.0.
+    pub fn win_rate(&self) -> f64 {
+        match self.games_played {
+            0 => 0.0,
+            n => self.games_won as f64 / n as f64,
+        }
+    }
+
+    pub fn average_score(&self) -> f64 {
+        match self.games_played {
+            0 => 0.0,
+            n => self.total_score as f64 / n as f64,
+        }
+    }
+}
+
+#[test]
+fn test_record_game() {
+    let mut stats = Stats::default();
+    assert_eq!(stats.win_rate(), 0.0);
+    stats.record_game(1000, 128, 120, false);
+    stats.record_game(3000, 256, 200, false);
+    stats.record_game(22000, 2048, 950, true);
+    stats.record_game(900, 128, 100, false);
+
+    assert_eq!(stats.games_played, 4);
+    assert_eq!(stats.win_rate(), 0.25);
+    assert_eq!(stats.average_score(), 6725.0);
+    assert_eq!(stats.total_moves, 1370);
+    assert_eq!(stats.highest_tile, 2048);
+    assert_eq!(stats.highest_tiles[&128], 2);
+}
+
+#[test]
+fn test_efficiency() {
+    let mut stats = Stats::default();
+    let game = |score, merges, moves| Efficiency {
+        score,
+        merges,
+        moves,
+    };
+    assert_eq!(stats.record_efficiency(game(5000, 80, 99)), (false, false));
+    assert_eq!(stats.record_efficiency(game(4000, 90, 200)), (true, true));
+    assert_eq!(stats.record_efficiency(game(3000, 100, 100)), (true, true));
+    assert_eq!(stats.record_efficiency(game(6000, 90, 200)), (false, false));
+    assert_eq!(stats.record_efficiency(game(3100, 99, 100)), (true, false));
+    assert_eq!(stats.best_score_per_move.unwrap().score_per_move(), 31.0);
+    assert_eq!(stats.best_merges_per_move.unwrap().merges_per_move(), 1.0);
+    assert_eq!(Efficiency::default().score_per_move(), 0.0);
+}
+
+#[test]
+fn test_score_history() {
+    let mut stats = Stats::default();
+    for score in 0..SCORE_HISTORY as u32 + 5 {
+        stats.record_game(score, 64, 10, false);
+    }
+    assert_eq!(stats.recent_scores.len(), SCORE_HISTORY);
+    assert_eq!(stats.recent_scores[0], 5);
+    assert_eq!(
+        stats.recent_scores.last(),
+        Some(&(SCORE_HISTORY as u32 + 4))
+    );
+}
+
+#[test]
+fn test_daily_results() {
+    let today = Date::parse("2024-03-01").unwrap();
+    let mut stats = Stats::default();
+    assert_eq!(stats.daily_streak(today), 0);
+    for days in 1..4 {
+        stats.record_daily(today.add_days(-days), 1000, 128, false);
+    }
+    stats.record_daily(today.add_days(-6), 1000, 128, false);
+    assert_eq!(stats.daily_streak(today), 3);
+
+    stats.record_daily(today, 2400, 256, false);
+    stats.record_daily(today, 1800, 512, true);
+    assert_eq!(stats.daily_streak(today), 4);
+    assert_eq!(
+        stats.dailies[&today],
+        DailyResult {
+            attempts: 2,
+            best_score: 2400,
+            highest_tile: 512,
+            won: true,
+        }
+    );
+    assert_eq!(stats.games_played, 0);
+
+    let json = serde_json::to_string(&stats).unwrap();
+    assert_eq!(serde_json::from_str::<Stats>(&json).unwrap(), stats);
+}
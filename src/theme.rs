@@ -0,0 +1,253 @@
+use serde::{Deserialize, Serialize};
+
+/// The original game's tiles, 2 to 2048, then the dark tile it uses for
+/// everything bigger.
+const CLASSIC_TILES: [u32; 12] = [
+    0xeee4da, 0xede0c8, 0xf2b179, 0xf59563, 0xf67c5f, 0xf65e3b, 0xedcf72, 0xedcc61, 0xedc850,
+    0xedc53f, 0xedc22e, 0x3c3a32,
+];
+
+/// A ramp from yellow to deep blue along the cividis map. Each step changes
+/// lightness as well as hue, so tiles stay apart with deuteranopia or
+/// protanopia.
+const COLOR_BLIND_TILES: [u32; 12] = [
+    0xfee838, 0xeed15d, 0xd3bc67, 0xbaa971, 0xa09677, 0x878479, 0x727374, 0x5e626e, 0x48526b,
+    0x2a406c, 0x00306f, 0x00204c,
+];
+
+// Light enough for black writing on every one of them.
+const HIGH_CONTRAST_TILES: [u32; 12] = [
+    0xffffff, 0xffff00, 0x00ffff, 0x00ff00, 0xff99ff, 0xffb000, 0x66ccff, 0xff8080, 0xccff66,
+    0xc0c0ff, 0xffd0a0, 0xa0ffd0,
+];
+
+/// Where tile colors come from, whichever theme is on.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TilePalette {
+    /// The theme's own.
+    #[default]
+    Theme,
+    /// Worked out from the rank, a different hue for every tile.
+    Procedural,
+    /// Exactly the original game's.
+    Classic,
+}
+
+impl TilePalette {
+    pub const ALL: [TilePalette; 3] = [
+        TilePalette::Theme,
+        TilePalette::Procedural,
+        TilePalette::Classic,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            TilePalette::Theme => "Theme's",
+            TilePalette::Procedural => "Procedural",
+            TilePalette::Classic => "Classic",
+        }
+    }
+}
+
+/// The colors the game is drawn in, as 0xRRGGBB (0xRRGGBBAA for
+/// `overlay`). Themes of your own go in the settings file under
+/// `[[custom_themes]]`; anything a theme leaves out comes from the light
+/// one.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    pub name: String,
+    /// Behind everything.
+    pub background: u32,
+    pub text: u32,
+    /// The board's frame, and the score boxes.
+    pub grid: u32,
+    pub grid_text: u32,
+    pub empty_cell: u32,
+    pub obstacle: u32,
+    /// Tile colors by rank, the lowest tile first. Tiles past the end take
+    /// the last color; with none, colors are worked out from the rank.
+    pub tiles: Vec<u32>,
+    /// On the tiles up to `dark_text_ranks`.
+    pub tile_text_dark: u32,
+    /// On every other tile.
+    pub tile_text_light: u32,
+    pub dark_text_ranks: u32,
+    pub button: u32,
+    pub button_text: u32,
+    /// Whatever is picked or armed, and the hint arrow.
+    pub accent: u32,
+    /// Laid over the board when a game ends.
+    pub overlay: u32,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::light()
+    }
+}
+
+impl Theme {
+    pub fn light() -> Theme {
+        Theme {
+            name: "Light".to_string(),
+            background: 0xfaf8ef,
+            text: 0x776e65,
+            grid: 0xbbada0,
+            grid_text: 0xeee4da,
+            empty_cell: 0xcdc1b4,
+            obstacle: 0x5c534a,
+            tiles: Vec::new(),
+            tile_text_dark: 0x776e65,
+            tile_text_light: 0xe7e7e7,
+            dark_text_ranks: 2,
+            button: 0x8f7a66,
+            button_text: 0xf9f6f2,
+            accent: 0xf67c5f,
+            overlay: 0xfaf8efcc,
+        }
+    }
+
+    pub fn dark() -> Theme {
+        Theme {
+            name: "Dark".to_string(),
+            background: 0x1f1d1b,
+            text: 0xeee4da,
+            grid: 0x4a433c,
+            empty_cell: 0x5f564d,
+            obstacle: 0x2b2724,
+            overlay: 0x1f1d1bcc,
+            ..Theme::light()
+        }
+    }
+
+    /// The original game's colors, tiles and all.
+    pub fn classic() -> Theme {
+        Theme {
+            name: "Classic".to_string(),
+            ..Theme::light().with_palette(TilePalette::Classic)
+        }
+    }
+
+    /// Safe for the common kinds of color blindness, red-green above all.
+    pub fn color_blind() -> Theme {
+        Theme {
+            name: "Color-blind".to_string(),
+            tiles: COLOR_BLIND_TILES.to_vec(),
+            tile_text_dark: 0x1f1d1b,
+            tile_text_light: 0xf9f6f2,
+            dark_text_ranks: 6,
+            accent: 0x0072b2,
+            ..Theme::light()
+        }
+    }
+
+    /// White on black, with the brightest tiles and black writing on all of
+    /// them, for low vision.
+    pub fn high_contrast() -> Theme {
+        Theme {
+            name: "High contrast".to_string(),
+            background: 0x000000,
+            text: 0xffffff,
+            grid: 0xffffff,
+            grid_text: 0x000000,
+            empty_cell: 0x000000,
+            obstacle: 0x808080,
+            tiles: HIGH_CONTRAST_TILES.to_vec(),
+            tile_text_dark: 0x000000,
+            tile_text_light: 0xffffff,
+            dark_text_ranks: u32::MAX,
+            button: 0xffffff,
+            button_text: 0x000000,
+            accent: 0xffd400,
+            overlay: 0x000000e6,
+        }
+    }
+
+    /// This theme with its tiles taken from `palette`.
+    pub fn with_palette(self, palette: TilePalette) -> Theme {
+        match palette {
+            TilePalette::Theme => self,
+            TilePalette::Procedural => Theme {
+                tiles: Vec::new(),
+                tile_text_dark: 0x776e65,
+                tile_text_light: 0xe7e7e7,
+                dark_text_ranks: 2,
+                ..self
+            },
+            TilePalette::Classic => Theme {
+                tiles: CLASSIC_TILES.to_vec(),
+                tile_text_dark: 0x776e65,
+                tile_text_light: 0xf9f6f2,
+                dark_text_ranks: 2,
+                ..self
+            },
+        }
+    }
+
+    pub fn builtin() -> [Theme; 5] {
+        [
+            Theme::light(),
+            Theme::dark(),
+            Theme::classic(),
+            Theme::color_blind(),
+            Theme::high_contrast(),
+        ]
+    }
+
+    /// The theme called `name`, looking through `custom` before the built-in
+    /// ones. An unknown name gets the light theme.
+    pub fn named(name: &str, custom: &[Theme]) -> Theme {
+        custom
+            .iter()
+            .cloned()
+            .chain(Theme::builtin())
+            .find(|theme| theme.name.eq_ignore_ascii_case(name))
+            .unwrap_or_default()
+    }
+
+    /// The color of a tile `rank` steps up, if this theme lists one.
+    pub fn tile(&self, rank: u32) -> Option<u32> {
+        let index = (rank as usize).checked_sub(1)?;
+        self.tiles.get(index).or(self.tiles.last()).copied()
+    }
+
+    pub fn tile_text(&self, rank: u32) -> u32 {
+        match rank <= self.dark_text_ranks {
+            true => self.tile_text_dark,
+            false => self.tile_text_light,
+        }
+    }
+}
+
+#[test]
+fn test_theme() {
+    let classic = Theme::named("classic", &[]);
+    assert_eq!(classic.tile(1), Some(0xeee4da));
+    assert_eq!(classic.tile(11), Some(0xedc22e));
+    assert_eq!(classic.tile(15), Some(0x3c3a32));
+    assert_eq!(classic.tile(0), None);
+    assert_eq!(Theme::light().tile(3), None);
+    assert_eq!(Theme::named("nope", &[]), Theme::light());
+    let dark = Theme::dark().with_palette(TilePalette::Classic);
+    assert_eq!(dark.tile(2), Some(0xede0c8));
+    assert_eq!(dark.background, Theme::dark().background);
+    assert_eq!(classic.with_palette(TilePalette::Procedural).tile(2), None);
+    let color_blind = Theme::named("Color-blind", &[]);
+    assert_eq!(color_blind.tile_text(6), color_blind.tile_text_dark);
+    assert_eq!(color_blind.tile_text(7), color_blind.tile_text_light);
+    assert_eq!(
+        color_blind.with_palette(TilePalette::Classic).tile_text(3),
+        0xf9f6f2
+    );
+    assert_eq!(Theme::named("high contrast", &[]).tile_text(20), 0x000000);
+
+    // A theme of your own can stand in for a built-in one.
+    let custom = Theme {
+        name: "Dark".to_string(),
+        background: 0,
+        ..Theme::dark()
+    };
+    assert_eq!(Theme::named("Dark", &[custom]).background, 0);
+}
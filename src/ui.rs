@@ -0,0 +1,23 @@
+//! The desktop window, drawn with gpui: the game itself and the screens
+//! and panels around it.
+
+mod challenge_panel;
+mod confetti;
+mod dialog;
+mod game;
+mod lobby_panel;
+mod menu;
+mod profile_panel;
+mod race_panel;
+mod screen;
+mod settings_panel;
+mod stats_panel;
+mod summary_panel;
+mod toast;
+
+pub use game::{
+    Autoplay, CopyBoard, DebugOverlay, Down, Enter, ExportReplay, ExportText, Game, GameEvent,
+    Hint, KeepPlaying, Left, MiniMode, OpenReplay, PasteBoard, Pause, Redo, Right, SaveScreenshot,
+    Undo, Up, bind_keys,
+};
+pub use screen::{Root, window_options};
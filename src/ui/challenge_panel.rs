@@ -0,0 +1,259 @@
+use crate::challenge::{self, Challenge, ChallengePack};
+use crate::persistence;
+use gpui::*;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+/// Where the panel's challenges come from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChallengeSource {
+    /// The endgames built into the game.
+    Practice,
+    /// Packs in the challenges directory.
+    Packs,
+}
+
+pub enum ChallengeEvent {
+    /// Play `challenge`; `id` names it in the statistics' solved set.
+    Start {
+        id: String,
+        challenge: Challenge,
+    },
+    Import,
+    Closed,
+}
+
+fn challenge_id(pack: &ChallengePack, challenge: &Challenge) -> String {
+    format!("{}/{}", pack.name, challenge.name)
+}
+
+/// Lists the built-in practice endgames, or the packs in the challenges
+/// directory along with the reason any file failed to load, so that pack
+/// authors can see what went wrong.
+pub struct ChallengePanel {
+    source: ChallengeSource,
+    packs: Vec<(PathBuf, Result<ChallengePack, String>)>,
+    solved: BTreeSet<String>,
+}
+
+fn load_packs(source: ChallengeSource) -> Vec<(PathBuf, Result<ChallengePack, String>)> {
+    match source {
+        ChallengeSource::Practice => vec![(PathBuf::new(), Ok(challenge::practice_pack()))],
+        ChallengeSource::Packs => persistence::load_challenge_packs(),
+    }
+}
+
+impl EventEmitter<ChallengeEvent> for ChallengePanel {}
+
+impl ChallengePanel {
+    pub fn new(source: ChallengeSource, solved: BTreeSet<String>) -> ChallengePanel {
+        ChallengePanel {
+            source,
+            packs: load_packs(source),
+            solved,
+        }
+    }
+
+    /// Picks up packs imported since the panel opened.
+    pub fn reload(&mut self, cx: &mut Context<Self>) {
+        self.packs = load_packs(self.source);
+        cx.notify();
+    }
+
+    fn close(&mut self, _: &MouseDownEvent, _window: &mut Window, cx: &mut Context<Self>) {
+        cx.emit(ChallengeEvent::Closed);
+    }
+
+    fn import(&mut self, _: &MouseDownEvent, _window: &mut Window, cx: &mut Context<Self>) {
+        cx.emit(ChallengeEvent::Import);
+    }
+
+    fn render_button(&self, id: &'static str, label: &'static str) -> Stateful<Div> {
+        div()
+            .id(id)
+            .px_4()
+            .py_2()
+            .bg(rgb(0x8f7a66))
+            .text_color(rgb(0xf9f6f2))
+            .rounded_md()
+            .font_weight(FontWeight::BOLD)
+            .child(label)
+    }
+
+    fn render_challenge(
+        &self,
+        element_id: ElementId,
+        id: String,
+        challenge: &Challenge,
+        cx: &mut Context<Self>,
+    ) -> impl IntoElement {
+        let solved = self.solved.contains(&id);
+        let name = challenge.name.clone();
+        let goal = challenge.goal();
+        let description = challenge.description.clone();
+        let challenge = challenge.clone();
+        div()
+            .id(element_id)
+            .px_3()
+            .py_1()
+            .rounded_md()
+            .bg(rgb(0xeee4da))
+            .hover(|style| style.bg(rgb(0xede0c8)))
+            .flex()
+            .justify_between()
+            .items_center()
+            .text_color(rgb(0x776e65))
+            .child(
+                div()
+                    .flex()
+                    .flex_col()
+                    .child(div().font_weight(FontWeight::BOLD).child(name))
+                    .child(div().text_xs().child(goal))
+                    .children(
+                        (!description.is_empty())
+                            .then(|| div().text_xs().italic().child(description)),
+                    ),
+            )
+            .children(solved.then(|| div().text_color(rgb(0xedc22e)).child("✓")))
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(move |_, _: &MouseDownEvent, _window, cx| {
+                    cx.emit(ChallengeEvent::Start {
+                        id: id.clone(),
+                        challenge: challenge.clone(),
+                    })
+                }),
+            )
+    }
+
+    fn render_pack(
+        &self,
+        index: usize,
+        path: &Path,
+        pack: &Result<ChallengePack, String>,
+        cx: &mut Context<Self>,
+    ) -> impl IntoElement {
+        let file = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let header = div()
+            .mt_2()
+            .text_sm()
+            .font_weight(FontWeight::BOLD)
+            .text_color(rgb(0x776e65));
+        match pack {
+            Ok(pack) => {
+                let solved = pack
+                    .challenges
+                    .iter()
+                    .filter(|c| self.solved.contains(&challenge_id(pack, c)))
+                    .count();
+                let title = match pack.author.as_str() {
+                    "" => pack.name.to_uppercase(),
+                    author => format!("{} · {author}", pack.name.to_uppercase()),
+                };
+                let rows = pack
+                    .challenges
+                    .iter()
+                    .enumerate()
+                    .map(|(i, challenge)| {
+                        self.render_challenge(
+                            SharedString::from(format!("challenge-{index}-{i}")).into(),
+                            challenge_id(pack, challenge),
+                            challenge,
+                            cx,
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                div()
+                    .flex()
+                    .flex_col()
+                    .gap_1()
+                    .child(
+                        header
+                            .flex()
+                            .justify_between()
+                            .child(title)
+                            .child(format!("{solved}/{}", pack.challenges.len())),
+                    )
+                    .children(rows)
+            }
+            Err(error) => div().flex().flex_col().child(header.child(file)).child(
+                div()
+                    .text_xs()
+                    .text_color(rgb(0xf65e3b))
+                    .child(error.clone()),
+            ),
+        }
+    }
+}
+
+impl Render for ChallengePanel {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let packs = self
+            .packs
+            .iter()
+            .enumerate()
+            .map(|(index, (path, pack))| self.render_pack(index, path, pack, cx).into_any_element())
+            .collect::<Vec<_>>();
+        let empty = packs.is_empty().then(|| {
+            div().text_sm().text_color(rgb(0x776e65)).child(format!(
+                "No packs yet. Import one, or put .json packs in {}",
+                persistence::challenge_dir().display()
+            ))
+        });
+
+        div()
+            .size_full()
+            .flex()
+            .justify_center()
+            .items_center()
+            .child(
+                div()
+                    .w(rems(23.75))
+                    .max_h(rems(35.0))
+                    .p_4()
+                    .rounded_lg()
+                    .bg(rgb(0xfaf8ef))
+                    .flex()
+                    .flex_col()
+                    .gap_2()
+                    .child(
+                        div()
+                            .text_2xl()
+                            .font_weight(FontWeight::BOLD)
+                            .text_color(rgb(0x776e65))
+                            .child(match self.source {
+                                ChallengeSource::Practice => "Practice",
+                                ChallengeSource::Packs => "Challenges",
+                            }),
+                    )
+                    .child(
+                        div()
+                            .id("challenge-packs")
+                            .flex_1()
+                            .overflow_y_scroll()
+                            .flex()
+                            .flex_col()
+                            .gap_1()
+                            .children(empty)
+                            .children(packs),
+                    )
+                    .child(
+                        div()
+                            .flex()
+                            .justify_end()
+                            .gap_2()
+                            .children((self.source == ChallengeSource::Packs).then(|| {
+                                self.render_button("import-pack", "Import…")
+                                    .on_mouse_down(MouseButton::Left, cx.listener(Self::import))
+                            }))
+                            .child(
+                                self.render_button("close-challenges", "Done")
+                                    .on_mouse_down(MouseButton::Left, cx.listener(Self::close)),
+                            ),
+                    ),
+            )
+    }
+}
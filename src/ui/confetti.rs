@@ -0,0 +1,104 @@
+use gpui::*;
+use rand::Rng;
+use std::time::Duration;
+
+const FRAME: Duration = Duration::from_millis(16);
+const LIFETIME: f32 = 1.6;
+// In pixels per second, and per second squared.
+const GRAVITY: f32 = 900.0;
+const MAX_SPEED: f32 = 520.0;
+const COLORS: [u32; 6] = [0xf67c5f, 0xedc22e, 0x66bbee, 0x8fd16a, 0xf26682, 0xb07fe0];
+
+struct Piece {
+    x: f32,
+    y: f32,
+    vx: f32,
+    vy: f32,
+    size: f32,
+    color: u32,
+    // Seconds since it was thrown.
+    age: f32,
+}
+
+/// Bursts of confetti over the board. Each piece flies out, falls and fades
+/// on its own; the layer only animates while there are pieces left.
+pub struct ConfettiLayer {
+    pieces: Vec<Piece>,
+    frames: Option<Task<()>>,
+}
+
+impl ConfettiLayer {
+    pub fn new() -> ConfettiLayer {
+        ConfettiLayer {
+            pieces: Vec::new(),
+            frames: None,
+        }
+    }
+
+    /// Throws `count` pieces out from `(x, y)`, in pixels from the layer's
+    /// top left corner.
+    pub fn burst(&mut self, x: f32, y: f32, count: usize, cx: &mut Context<Self>) {
+        let mut rng = rand::rng();
+        // With none left the last frame loop has ended, or is about to.
+        let idle = self.pieces.is_empty();
+        self.pieces.extend((0..count).map(|_| {
+            let angle = rng.random_range(0.0..std::f32::consts::TAU);
+            let speed = rng.random_range(0.3..1.0) * MAX_SPEED;
+            Piece {
+                x,
+                y,
+                vx: angle.cos() * speed,
+                // Thrown upwards more than down.
+                vy: angle.sin() * speed - MAX_SPEED / 2.0,
+                size: rng.random_range(5.0..10.0),
+                color: COLORS[rng.random_range(0..COLORS.len())],
+                age: 0.0,
+            }
+        }));
+        if idle {
+            self.frames = Some(cx.spawn(async move |this, cx| {
+                loop {
+                    cx.background_executor().timer(FRAME).await;
+                    let more = this.update(cx, |this, cx| {
+                        this.step(FRAME.as_secs_f32());
+                        cx.notify();
+                        !this.pieces.is_empty()
+                    });
+                    if !more.unwrap_or(false) {
+                        break;
+                    }
+                }
+            }));
+        }
+        cx.notify();
+    }
+
+    fn step(&mut self, seconds: f32) {
+        for piece in &mut self.pieces {
+            piece.vy += GRAVITY * seconds;
+            piece.x += piece.vx * seconds;
+            piece.y += piece.vy * seconds;
+            piece.age += seconds;
+        }
+        self.pieces.retain(|piece| piece.age < LIFETIME);
+    }
+}
+
+impl Render for ConfettiLayer {
+    fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
+        div()
+            .absolute()
+            .inset_0()
+            .children(self.pieces.iter().map(|piece| {
+                div()
+                    .absolute()
+                    .left(px(piece.x))
+                    .top(px(piece.y))
+                    .w(px(piece.size))
+                    .h(px(piece.size * 0.6))
+                    .rounded_sm()
+                    .bg(rgb(piece.color))
+                    .opacity(1.0 - piece.age / LIFETIME)
+            }))
+    }
+}
@@ -0,0 +1,146 @@
+use crate::ui::game::{Down, Enter, Left, Pause, Right, Up};
+use gpui::*;
+
+pub enum DialogEvent {
+    /// The button at this index was picked.
+    Chose(usize),
+    /// Closed without picking anything.
+    Dismissed,
+}
+
+/// A modal question shown over whatever is on screen. It holds the focus
+/// until it is answered: arrow keys or Tab move between the buttons, Enter
+/// picks one, and Escape (or the pause key) closes it.
+pub struct Dialog {
+    focus_handle: FocusHandle,
+    title: SharedString,
+    message: SharedString,
+    buttons: Vec<SharedString>,
+    selected: usize,
+}
+
+impl EventEmitter<DialogEvent> for Dialog {}
+
+impl Dialog {
+    /// A dialog offering `buttons` in order, the last one selected.
+    pub fn new(
+        title: impl Into<SharedString>,
+        message: impl Into<SharedString>,
+        buttons: Vec<SharedString>,
+        cx: &mut Context<Self>,
+    ) -> Dialog {
+        Dialog {
+            focus_handle: cx.focus_handle(),
+            title: title.into(),
+            message: message.into(),
+            selected: buttons.len().saturating_sub(1),
+            buttons,
+        }
+    }
+
+    /// Asks whether to go ahead: Cancel is button 0, `confirm_label` 1.
+    pub fn confirm(
+        title: impl Into<SharedString>,
+        message: impl Into<SharedString>,
+        confirm_label: impl Into<SharedString>,
+        cx: &mut Context<Self>,
+    ) -> Dialog {
+        Dialog::new(
+            title,
+            message,
+            vec!["Cancel".into(), confirm_label.into()],
+            cx,
+        )
+    }
+
+    fn select(&mut self, step: isize, cx: &mut Context<Self>) {
+        let len = self.buttons.len() as isize;
+        if len > 0 {
+            self.selected = (self.selected as isize + step).rem_euclid(len) as usize;
+            cx.notify();
+        }
+    }
+
+    fn key_down(&mut self, event: &KeyDownEvent, _window: &mut Window, cx: &mut Context<Self>) {
+        match event.keystroke.key.as_str() {
+            "escape" => cx.emit(DialogEvent::Dismissed),
+            "tab" if event.keystroke.modifiers.shift => self.select(-1, cx),
+            "tab" => self.select(1, cx),
+            _ => {}
+        }
+    }
+
+    fn render_button(&self, i: usize, cx: &mut Context<Self>) -> impl IntoElement {
+        div()
+            .id(("dialog-button", i))
+            .px_4()
+            .py_2()
+            .bg(if i == self.selected {
+                rgb(0xf67c5f)
+            } else {
+                rgb(0x8f7a66)
+            })
+            .text_color(rgb(0xf9f6f2))
+            .rounded_md()
+            .font_weight(FontWeight::BOLD)
+            .child(self.buttons[i].clone())
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(move |_, _: &MouseDownEvent, _window, cx| {
+                    cx.emit(DialogEvent::Chose(i))
+                }),
+            )
+    }
+}
+
+impl Focusable for Dialog {
+    fn focus_handle(&self, _: &App) -> FocusHandle {
+        self.focus_handle.clone()
+    }
+}
+
+impl Render for Dialog {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let buttons = (0..self.buttons.len())
+            .map(|i| self.render_button(i, cx))
+            .collect::<Vec<_>>();
+
+        div()
+            .size_full()
+            .track_focus(&self.focus_handle)
+            .on_key_down(cx.listener(Self::key_down))
+            .on_action(cx.listener(|this, _: &Left, _, cx| this.select(-1, cx)))
+            .on_action(cx.listener(|this, _: &Up, _, cx| this.select(-1, cx)))
+            .on_action(cx.listener(|this, _: &Right, _, cx| this.select(1, cx)))
+            .on_action(cx.listener(|this, _: &Down, _, cx| this.select(1, cx)))
+            .on_action(
+                cx.listener(|this, _: &Enter, _, cx| cx.emit(DialogEvent::Chose(this.selected))),
+            )
+            .on_action(cx.listener(|_, _: &Pause, _, cx| cx.emit(DialogEvent::Dismissed)))
+            // Clicks outside the dialog don't reach the screen behind it.
+            .occlude()
+            .bg(rgba(0x00000066))
+            .flex()
+            .justify_center()
+            .items_center()
+            .child(
+                div()
+                    .w(rems(21.25))
+                    .p_4()
+                    .rounded_lg()
+                    .bg(rgb(0xfaf8ef))
+                    .flex()
+                    .flex_col()
+                    .gap_3()
+                    .child(
+                        div()
+                            .text_xl()
+                            .font_weight(FontWeight::BOLD)
+                            .text_color(rgb(0x776e65))
+                            .child(self.title.clone()),
+                    )
+                    .child(div().text_color(rgb(0x776e65)).child(self.message.clone()))
+                    .child(div().flex().justify_end().gap_2().children(buttons)),
+            )
+    }
+}
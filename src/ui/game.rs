@@ -0,0 +1,3991 @@
+use crate::ai::Expectimax;
+use crate::analysis::{self, Blunder, GameSummary};
+use crate::audio::{self, Audio, Sound};
+use crate::challenge::{Challenge, ChallengeStatus};
+use crate::daily::Date;
+use crate::engine::powerups::{Inventory, PowerUp, PowerUpUse};
+use crate::engine::rules::Ruleset;
+use crate::engine::timer::Countdown;
+use crate::engine::{
+    Board, BoardShape, Cell, Difficulty, Direction, GameConfig, GameMode, GameRng, Merge,
+    MoveResult, Special, TileMove, seeded_rng,
+};
+use crate::events::{self, Event, EventQueue};
+use crate::headless;
+use crate::labels;
+use crate::persistence::{self, SavedGame, Session, SessionStep};
+use crate::replay::{Replay, ReplayMove, Spawn};
+use crate::screenshot;
+use crate::settings::{self, KeyAction, Keybindings, Settings};
+use crate::spectate::{SPECTATE_PORT, Spectators, Update};
+use crate::splits::{self, Pace, Run, SPLIT_TILES, SplitRecords};
+use crate::stats::{Efficiency, Stats};
+use crate::theme::Theme;
+use crate::ui::confetti::ConfettiLayer;
+use crate::ui::toast::ToastLayer;
+use gpui::*;
+use std::{
+    cmp::Ordering,
+    collections::VecDeque,
+    io,
+    time::{Duration, Instant, SystemTime},
+};
+
+gpui::actions!(
+    game,
+    [
+        Up,
+        Down,
+        Left,
+        Right,
+        Enter,
+        Undo,
+        Redo,
+        KeepPlaying,
+        Hint,
+        Autoplay,
+        ExportReplay,
+        OpenReplay,
+        Pause,
+        MiniMode,
+        DebugOverlay,
+        CopyBoard,
+        PasteBoard,
+        ExportText,
+        SaveScreenshot
+    ]
+);
+
+/// Replaces every key binding with the ones from the settings, skipping
+/// entries gpui can't parse.
+pub fn bind_keys(keys: &Keybindings, cx: &mut App) {
+    fn bindings<A: Action + Clone>(keys: &[String], action: A) -> impl Iterator<Item = KeyBinding> {
+        keys.iter()
+            .filter(|k| k.split_whitespace().all(|k| Keystroke::parse(k).is_ok()))
+            .map(move |k| KeyBinding::new(k, action.clone(), None))
+    }
+
+    cx.clear_key_bindings();
+    cx.bind_keys(
+        bindings(&keys.up, Up)
+            .chain(bindings(&keys.down, Down))
+            .chain(bindings(&keys.left, Left))
+            .chain(bindings(&keys.right, Right))
+            .chain(bindings(&keys.new_game, Enter))
+            .chain(bindings(&keys.undo, Undo))
+            .chain(bindings(&keys.redo, Redo))
+            .chain(bindings(&keys.keep_playing, KeepPlaying))
+            .chain(bindings(&keys.hint, Hint))
+            .chain(bindings(&keys.autoplay, Autoplay))
+            .chain(bindings(&keys.export_replay, ExportReplay))
+            .chain(bindings(&keys.open_replay, OpenReplay))
+            .chain(bindings(&keys.pause, Pause))
+            .chain(bindings(&keys.mini_mode, MiniMode))
+            .chain(bindings(&keys.debug_overlay, DebugOverlay))
+            .chain(bindings(&keys.copy_board, CopyBoard))
+            .chain(bindings(&keys.paste_board, PasteBoard))
+            .chain(bindings(&keys.export_text, ExportText))
+            .chain(bindings(&keys.screenshot, SaveScreenshot)),
+    );
+}
+
+pub(crate) fn get_font_color(rank: u32) -> Rgba {
+    if rank <= 2 {
+        rgb(0x776e65)
+    } else {
+        rgb(0xe7e7e7)
+    }
+}
+
+pub(crate) fn get_font_size(label: &str, tile_size: f32) -> Pixels {
+    let length = label.chars().count() as f32;
+    if length == 0.0 {
+        return px(0.0);
+    }
+
+    let size = (60.0 / (length * 0.7)).min(36.0);
+
+    px(size * tile_size / 90.0)
+}
+
+// The longer side of the grid keeps the same footprint for every size, so
+// tiles and gaps shrink as the board grows: (tile size, gap). Both grow with
+// the UI scale.
+pub(crate) fn tile_metrics(rows: usize, cols: usize, scale: f32) -> (f32, f32) {
+    let size = rows.max(cols);
+    let gap = 48.0 / size as f32;
+    let tile = (396.0 - gap * (size - 1) as f32) / size as f32;
+    (tile * scale, gap * scale)
+}
+
+const SLIDE_MS: u64 = 100;
+const POP_MS: u64 = 160;
+// A move that goes nowhere nudges the board this far its way and back.
+const BUMP_MS: u64 = 180;
+const BUMP_DISTANCE: f32 = 8.0;
+// A move's "+N" floats this far up out of the score box, starting this far
+// down it.
+const SCORE_GAIN_MS: u64 = 600;
+const SCORE_GAIN_RISE: f32 = 48.0;
+const SCORE_GAIN_TOP: f32 = 20.0;
+// How often the speedrun timer redraws while it runs.
+const RUN_REFRESH: Duration = Duration::from_millis(100);
+
+// An animation of `millis` at normal speed, sped up or slowed down by the
+// animation speed setting.
+fn timed_animation(millis: u64, speed: f32) -> Animation {
+    let millis = millis as f32 / speed.max(0.1);
+    Animation::new(Duration::from_millis(millis as u64))
+}
+
+// Every tile animation of a move runs on one shared clock: tiles slide during
+// the first SLIDE_MS, then merged and spawned tiles pop in.
+fn move_animation(speed: f32) -> Animation {
+    timed_animation(SLIDE_MS + POP_MS, speed)
+}
+
+fn slide_phase(progress: f32) -> f32 {
+    let total = (SLIDE_MS + POP_MS) as f32;
+    (progress * total / SLIDE_MS as f32).min(1.0)
+}
+
+fn pop_phase(progress: f32) -> f32 {
+    let total = (SLIDE_MS + POP_MS) as f32;
+    ((progress * total - SLIDE_MS as f32) / POP_MS as f32).clamp(0.0, 1.0)
+}
+
+// Scale of a freshly merged tile: grows past its cell and settles back.
+fn pulse_scale(pop: f32) -> f32 {
+    1.0 + 0.2 * (pop * std::f32::consts::PI).sin()
+}
+
+pub(crate) fn special_color(special: Special) -> Rgba {
+    match special {
+        Special::Bomb => rgb(0x3c3a32),
+        Special::Wild => rgb(0xb784d8),
+        Special::Junk => rgb(0x8a8178),
+    }
+}
+
+pub(crate) fn special_label(special: Special) -> &'static str {
+    match special {
+        Special::Bomb => "💣",
+        Special::Wild => "★",
+        Special::Junk => "▦",
+    }
+}
+
+fn arrow(dir: Direction) -> &'static str {
+    match dir {
+        Direction::Up => "↑",
+        Direction::Down => "↓",
+        Direction::Left => "←",
+        Direction::Right => "→",
+    }
+}
+
+// Colors go by a tile's rank under the board's rules, so that every variant
+// climbs the same palette.
+pub(crate) fn get_color(rank: u32) -> Hsla {
+    if rank == 0 {
+        return rgb(0xcdc1b4).into();
+    }
+
+    let power = rank as f32;
+
+    let hue = (30.0 + power * 20.0) % 360.0 / 360.0;
+    let saturation = (0.5 + (power * 0.04)).min(0.9);
+    let lightness = 0.45 + (0.35 * f32::powf(0.8, power - 1.0));
+
+    hsla(hue, saturation, lightness, 1.0)
+}
+
+/// Something that follows the game. It hears each batch of events the game
+/// dispatches, in the order they happened, and can act on the game.
+pub(crate) type Subscriber = Box<dyn FnMut(&mut Game, &[Event], &mut Context<Game>)>;
+
+#[derive(Clone)]
+struct Snapshot {
+    board: Board,
+    score: u32,
+    // Undoing a move rewinds the spawns too, so it can't be replayed for a
+    // luckier tile and the replay still matches its seed.
+    rng: GameRng,
+}
+
+// Recorded pauses longer than this are cut short during playback.
+const MAX_PLAYBACK_PAUSE_MS: u64 = 1500;
+const MIN_PLAYBACK_PAUSE_MS: u64 = 60;
+const PLAYBACK_SPEEDS: [(f32, &str); 4] = [(0.5, "0.5x"), (1.0, "1x"), (2.0, "2x"), (4.0, "4x")];
+const SCRUB_SEGMENTS: usize = 24;
+const BLUNDER_COLOR: u32 = 0xe0403a;
+// How thick the coach's bars are, inside the grid's padding.
+const COACH_BAR: f32 = 6.0;
+const GHOST_TICK: Duration = Duration::from_millis(100);
+const GHOST_TILE: f32 = 18.0;
+const DPAD_BUTTON: f32 = 56.0;
+const MIN_REPEAT_MS: u64 = 30;
+// Below this window height the arrow buttons fit in a single row.
+const DPAD_CROSS_MIN_HEIGHT: f32 = 860.0;
+// How big the board is drawn in mini mode.
+const MINI_SCALE: f32 = 0.5;
+
+/// A recorded game being watched on the board instead of played.
+struct Playback {
+    replay: Replay,
+    positions: Vec<(Board, u32)>,
+    index: usize,
+    speed: f32,
+    // Set while playing; dropping it pauses.
+    task: Option<Task<()>>,
+    // The moves the AI calls blunders, once it has gone over the game.
+    blunders: Option<Vec<Blunder>>,
+    _analysis: Task<()>,
+}
+
+impl Playback {
+    fn last_index(&self) -> usize {
+        self.positions.len() - 1
+    }
+
+    // Time until the next move at the pace it was originally played.
+    fn next_delay(&self) -> Option<Duration> {
+        let next = self.replay.moves.get(self.index)?;
+        let previous = match self.index {
+            0 => 0,
+            i => self.replay.moves[i - 1].millis,
+        };
+        let millis = next
+            .millis
+            .saturating_sub(previous)
+            .clamp(MIN_PLAYBACK_PAUSE_MS, MAX_PLAYBACK_PAUSE_MS);
+        Some(Duration::from_millis(millis).div_f32(self.speed))
+    }
+}
+
+/// A past game raced alongside this one, its moves played back on the
+/// timeline they were first made on.
+struct Ghost {
+    positions: Vec<(Board, u32)>,
+    // When each move was made, from the start of the game.
+    millis: Vec<u64>,
+    // How many of its moves have been played back.
+    shown: usize,
+}
+
+/// How a limited-moves game compares with earlier attempts at the same
+/// budget.
+struct ChallengeResult {
+    budget: u32,
+    score: u32,
+    previous: Vec<u32>,
+}
+
+/// A puzzle from a challenge pack, played from its own starting board.
+struct ActiveChallenge {
+    id: String,
+    challenge: Challenge,
+    status: ChallengeStatus,
+}
+
+/// Asks the window to switch to another screen.
+pub enum GameEvent {
+    OpenMenu,
+    OpenChallenges,
+    OpenSettings,
+    OpenReplay,
+    CloseReplay,
+    /// The winning tile was reached; the game waits on
+    /// [`Game::continue_playing`] or a new game.
+    Won,
+    /// New Game was asked for with a game at stake; see
+    /// [`Game::has_run_at_stake`].
+    ConfirmNewGame,
+    /// Mini mode was switched on or off; see [`Game::is_mini`].
+    ToggleMiniMode,
+    /// The game has ended; see [`Game::summary`].
+    GameOver,
+}
+
+pub struct Game {
+    config: GameConfig,
+    settings: Settings,
+    // The settings' theme, looked up once rather than on every render.
+    theme: Theme,
+    _settings_watcher: Task<()>,
+    // The overlay feed, while the settings have it on.
+    spectators: Option<Spectators>,
+    stats: Stats,
+    // Whether the current game has already been counted in `stats`.
+    stats_recorded: bool,
+    toasts: Entity<ToastLayer>,
+    // The last save that failed, and what the banner says about it.
+    storage_error: Option<(&'static str, String)>,
+    confetti: Entity<ConfettiLayer>,
+    // Replay viewers stay silent and go without.
+    audio: Option<Audio>,
+    // What moves did, waiting for `dispatch_events`.
+    events: EventQueue,
+    subscribers: Vec<Subscriber>,
+    seed: u64,
+    rng: GameRng,
+    score: u32,
+    best_score: u32,
+    // Whether this game has already shown the new-best toast.
+    best_announced: bool,
+    // The points the last scoring move made, numbered so each one floats up
+    // afresh.
+    score_gain: Option<(usize, u32)>,
+    // The last move that went nowhere, numbered so each one bumps afresh.
+    bump: Option<(usize, Direction)>,
+    board: Board,
+    is_started: bool,
+    is_game_over: bool,
+    is_won: bool,
+    keep_playing: bool,
+    focus_handle: FocusHandle,
+    spawn_count: u32,
+    new_tiles: Vec<usize>,
+    moves: Vec<TileMove>,
+    merged_tiles: Vec<usize>,
+    // Bombs that went off in the last move.
+    explosions: Vec<usize>,
+    move_count: usize,
+    undo_stack: VecDeque<Snapshot>,
+    redo_stack: Vec<Snapshot>,
+    saved_game: Option<SavedGame>,
+    hint: Option<Direction>,
+    hint_task: Option<Task<()>>,
+    autoplay_task: Option<Task<()>>,
+    replay: Replay,
+    started_at: Instant,
+    // Moves taken back by undo, restored to the replay by redo.
+    undone_moves: Vec<ReplayMove>,
+    playback: Option<Playback>,
+    // The clock of a timed game, and the task that watches it run out.
+    countdown: Option<Countdown>,
+    countdown_task: Option<Task<()>>,
+    // Moves made this game, counted against a limited-moves budget.
+    moves_made: u32,
+    merges_made: u32,
+    // The biggest tile a single merge made this game.
+    biggest_merge: u32,
+    challenge_result: Option<ChallengeResult>,
+    // How the game that just ended made use of its moves, and which records
+    // it set: (score per move, merges per move).
+    efficiency: Option<(Efficiency, (bool, bool))>,
+    // The day whose puzzle is on the board, in daily mode.
+    daily: Option<Date>,
+    challenge: Option<ActiveChallenge>,
+    // Whether a sandbox board is being set up rather than played.
+    editing: bool,
+    power_ups: Inventory,
+    // The power-up waiting to be aimed, and the tiles picked for it so far.
+    aiming: Option<(PowerUp, Vec<usize>)>,
+    // In hotseat games, the move still waiting for the placer's tile and
+    // when it was made, and the value the placer has picked.
+    placing: Option<(Direction, u64)>,
+    place_value: u32,
+    // Whether the game is on screen in the focused window; the clock only
+    // runs while it is.
+    clock_running: bool,
+    ghost: Option<Ghost>,
+    ghost_task: Option<Task<()>>,
+    // The move repeating while its key is held down.
+    repeat: Option<(Direction, Task<()>)>,
+    // When the game was paused, while it is.
+    paused: Option<Instant>,
+    mini: bool,
+    // The speedrun being timed, the records it's up against, and the task
+    // that keeps its timer moving.
+    run: Option<Run>,
+    split_records: SplitRecords,
+    run_task: Option<Task<()>>,
+    // The AI's rating of each direction, with the board it rated, and the
+    // board it is rating now.
+    coach: Option<(Board, Vec<(Direction, f64)>)>,
+    coach_task: Option<(Board, Task<()>)>,
+    // The overlay for chasing desyncs: whether it's up, when each frame of
+    // the last second was drawn, and what the last move asked for did.
+    debug_overlay: bool,
+    frame_times: VecDeque<Instant>,
+    last_move: Option<(Direction, MoveResult)>,
+}
+
+impl Game {
+    pub fn new(config: GameConfig, settings: Settings, cx: &mut Context<Self>) -> Game {
+        let mut game = Game {
+            _settings_watcher: Self::watch_settings(cx),
+            stats: persistence::load_stats(),
+            audio: Some(Audio::new()),
+            best_score: persistence::load_best_score(config.spawn.difficulty()),
+            saved_game: persistence::load_game(),
+            ..Game::blank(config, settings, cx)
+        };
+        // What follows every game from the start: sound, merge counts and
+        // power-ups, the score and its record, celebrations, and the
+        // statistics kept when a game ends.
+        game.subscribe(Game::play_sounds);
+        game.subscribe(Game::tally_merges);
+        game.subscribe(Game::track_score);
+        game.subscribe(Game::celebrate_milestones);
+        game.subscribe(Game::record_game_over);
+        game
+    }
+
+    /// A viewer playing back `replay`: just the board, the replay and what
+    /// draws them, with none of the saves, sound, settings watcher or
+    /// spectator feed of a game being played.
+    pub(crate) fn for_playback(
+        replay: Replay,
+        config: GameConfig,
+        settings: Settings,
+        cx: &mut Context<Self>,
+    ) -> Game {
+        let mut viewer = Game::blank(config, settings, cx);
+        viewer.best_score = persistence::load_best_score(config.spawn.difficulty());
+        viewer.start_playback(replay, cx);
+        viewer
+    }
+
+    // A game with nothing loaded and nothing running, which `new` and
+    // `for_playback` build on.
+    fn blank(config: GameConfig, settings: Settings, cx: &mut Context<Self>) -> Game {
+        let seed = config.seed.unwrap_or_else(rand::random);
+        let board = Board::for_config(&config);
+        Game {
+            config,
+            spectators: None,
+            theme: settings.theme(),
+            settings,
+            _settings_watcher: Task::ready(()),
+            stats: Stats::default(),
+            stats_recorded: false,
+            toasts: cx.new(|_| ToastLayer::new()),
+            storage_error: None,
+            confetti: cx.new(|_| ConfettiLayer::new()),
+            audio: None,
+            events: EventQueue::default(),
+            subscribers: Vec::new(),
+            seed,
+            rng: seeded_rng(seed),
+            score: 0,
+            best_score: 0,
+            best_announced: false,
+            score_gain: None,
+            bump: None,
+            is_started: false,
+            is_game_over: false,
+            is_won: false,
+            keep_playing: false,
+            replay: Replay::new(seed, config.spawn, board.clone()),
+            board,
+            focus_handle: cx.focus_handle(),
+            spawn_count: 0,
+            new_tiles: Vec::new(),
+            moves: Vec::new(),
+            merged_tiles: Vec::new(),
+            explosions: Vec::new(),
+            move_count: 0,
+            undo_stack: VecDeque::new(),
+            redo_stack: Vec::new(),
+            saved_game: None,
+            hint: None,
+            hint_task: None,
+            autoplay_task: None,
+            started_at: Instant::now(),
+            undone_moves: Vec::new(),
+            playback: None,
+            countdown: None,
+            countdown_task: None,
+            moves_made: 0,
+            merges_made: 0,
+            biggest_merge: 0,
+            challenge_result: None,
+            efficiency: None,
+            daily: None,
+            challenge: None,
+            editing: false,
+            power_ups: Inventory::default(),
+            aiming: None,
+            placing: None,
+            place_value: 2,
+            clock_running: true,
+            ghost: None,
+            ghost_task: None,
+            repeat: None,
+            paused: None,
+            mini: false,
+            run: None,
+            split_records: SplitRecords::default(),
+            run_task: None,
+            coach: None,
+            coach_task: None,
+            debug_overlay: false,
+            frame_times: VecDeque::new(),
+            last_move: None,
+        }
+    }
+
+    pub(crate) fn new_game(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
+        // A replay viewer never starts games of its own.
+        if self.playback.is_some() {
+            return;
+        }
+        // Walking away from a game in progress still counts it.
+        if self.is_started {
+            self.record_stats();
+            self.finish_run();
+        }
+        self.run = None;
+        self.run_task = None;
+        self.stats_recorded = false;
+        self.best_announced = false;
+        self.moves_made = 0;
+        self.merges_made = 0;
+        self.biggest_merge = 0;
+        self.challenge_result = None;
+        self.efficiency = None;
+        self.score = 0;
+        self.best_score = persistence::load_best_score(self.config.spawn.difficulty());
+        self.daily = (self.config.mode == GameMode::Daily).then(Date::today);
+        self.seed = match self.daily {
+            Some(date) => date.seed(),
+            None => self.config.seed.unwrap_or_else(rand::random),
+        };
+        self.rng = seeded_rng(self.seed);
+        self.is_started = true;
+        self.new_tiles.clear();
+        self.clear_hint();
+        self.moves.clear();
+        self.merged_tiles.clear();
+        self.explosions.clear();
+        self.board = Board::for_config(&self.config);
+        self.is_game_over = false;
+        self.is_won = false;
+        self.keep_playing = false;
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.saved_game = None;
+        self.power_ups = Inventory::default();
+        self.aiming = None;
+        self.placing = None;
+        self.place_value = self.config.spawn.values()[0];
+        self.editing = self.config.mode == GameMode::Sandbox;
+        match &mut self.challenge {
+            Some(active) => {
+                active.status = ChallengeStatus::InProgress;
+                self.board = active.challenge.start_board();
+            }
+            // A sandbox starts out empty, to be filled in by hand.
+            None if self.editing => {}
+            None => {
+                let (rows, cols) = (self.config.rows, self.config.cols);
+                for _ in 0..self.config.mode.obstacle_count(rows, cols) {
+                    self.board.place_obstacle(&mut self.rng);
+                }
+                self.spawn_tile(cx);
+                self.spawn_tile(cx);
+            }
+        }
+        tracing::info!(seed = self.seed, mode = ?self.config.mode, "new game");
+        self.start_replay();
+        self.start_clock(self.config.mode.time_limit(), cx);
+        if self.ghost.is_some() {
+            self.start_ghost(cx);
+        }
+        self.autosave();
+        cx.notify();
+    }
+
+    // Starts recording from the current board.
+    fn start_replay(&mut self) {
+        self.replay = Replay::new(self.seed, self.config.spawn, self.board.clone());
+        self.replay.mode = self.config.mode;
+        self.started_at = Instant::now();
+        self.paused = None;
+        self.undone_moves.clear();
+    }
+
+    fn export_replay(&mut self, _: &ExportReplay, _window: &mut Window, cx: &mut Context<Self>) {
+        if self.replay.moves.is_empty() {
+            return;
+        }
+        match persistence::export_replay(&self.replay) {
+            Ok(_) => self.toast("Replay saved", cx),
+            Err(_) => self.toast("Couldn't save the replay", cx),
+        }
+    }
+
+    pub(crate) fn toast(&self, message: impl Into<SharedString>, cx: &mut Context<Self>) {
+        self.toasts
+            .update(cx, |toasts, cx| toasts.show(message, cx));
+    }
+
+    // Polls the settings file and applies edits while the game is running.
+    fn watch_settings(cx: &mut Context<Self>) -> Task<()> {
+        cx.spawn(async move |this, cx| {
+            let mut last_modified: Option<SystemTime> = settings::modified_time();
+            loop {
+                cx.background_executor().timer(Duration::from_secs(1)).await;
+                let modified = settings::modified_time();
+                if modified == last_modified {
+                    continue;
+                }
+                last_modified = modified;
+                let settings = settings::load();
+                if this
+                    .update(cx, |this, cx| this.apply_settings(settings, cx))
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        })
+    }
+
+    // Board size and spawn odds take effect from the next new game.
+    pub(crate) fn apply_settings(&mut self, settings: Settings, cx: &mut Context<Self>) {
+        bind_keys(&settings.keybindings, cx);
+        // The daily puzzle's rules don't depend on the settings.
+        if self.config.mode != GameMode::Daily {
+            self.config = GameConfig {
+                mode: self.config.mode,
+                move_budget: self.config.move_budget,
+                seed: self.config.seed,
+                ..settings.game_config()
+            };
+        }
+        // Replay viewers have no feed of their own.
+        if self.playback.is_none() && settings.spectate != self.spectators.is_some() {
+            self.spectators = None;
+            if settings.spectate {
+                self.bind_spectators(cx);
+            }
+            self.broadcast();
+        }
+        self.theme = settings.theme();
+        self.settings = settings;
+        cx.notify();
+    }
+
+    fn bind_spectators(&mut self, cx: &mut Context<Self>) {
+        match Spectators::bind(SPECTATE_PORT) {
+            Ok(spectators) => self.spectators = Some(spectators),
+            Err(e) => self.toast(format!("Couldn't start the spectator feed: {e}"), cx),
+        }
+    }
+
+    /// Serves `spectators`, the feed of the game this one takes over from,
+    /// or binds a feed of its own if the settings want one and there is
+    /// none. The port only fits one feed at a time, so a game replacing
+    /// another must be handed the old one's.
+    pub(crate) fn serve_spectators(
+        &mut self,
+        spectators: Option<Spectators>,
+        cx: &mut Context<Self>,
+    ) {
+        self.spectators = spectators.filter(|_| self.settings.spectate);
+        if self.settings.spectate && self.spectators.is_none() {
+            self.bind_spectators(cx);
+        }
+        self.broadcast();
+    }
+
+    pub(crate) fn take_spectators(&mut self) -> Option<Spectators> {
+        self.spectators.take()
+    }
+
+    fn open_settings(&mut self, _: &MouseDownEvent, _window: &mut Window, cx: &mut Context<Self>) {
+        cx.emit(GameEvent::OpenSettings);
+    }
+
+    fn open_menu(&mut self, _: &MouseDownEvent, _window: &mut Window, cx: &mut Context<Self>) {
+        self.autoplay_task = None;
+        cx.emit(GameEvent::OpenMenu);
+    }
+
+    pub(crate) fn config(&self) -> GameConfig {
+        self.config
+    }
+
+    pub(crate) fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    // How much bigger than usual the board is drawn: the UI scale, shrunk
+    // in mini mode.
+    fn scale(&self) -> f32 {
+        match self.mini {
+            true => self.settings.ui_scale * MINI_SCALE,
+            false => self.settings.ui_scale,
+        }
+    }
+
+    // A size in pixels at the usual scale, grown to the one set.
+    fn scaled(&self, size: f32) -> Pixels {
+        px(size * self.scale())
+    }
+
+    /// Whether only the board and score are shown, in a small window kept
+    /// above the others.
+    pub(crate) fn is_mini(&self) -> bool {
+        self.mini
+    }
+
+    pub(crate) fn set_mini(&mut self, mini: bool, cx: &mut Context<Self>) {
+        self.mini = mini;
+        cx.notify();
+    }
+
+    fn toggle_mini_mode(&mut self, _: &MiniMode, _window: &mut Window, cx: &mut Context<Self>) {
+        cx.emit(GameEvent::ToggleMiniMode);
+    }
+
+    fn toggle_debug_overlay(
+        &mut self,
+        _: &DebugOverlay,
+        _window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.debug_overlay = !self.debug_overlay;
+        self.frame_times.clear();
+        cx.notify();
+    }
+
+    fn toggle_mini_mode_mouse(
+        &mut self,
+        _: &MouseDownEvent,
+        _window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        cx.emit(GameEvent::ToggleMiniMode);
+    }
+
+    // Mini mode's whole header: the score, which the borderless window is
+    // dragged by, and a way back to the full window.
+    fn render_mini_bar(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        div()
+            .flex()
+            .w(self.scaled(420.0))
+            .justify_between()
+            .items_center()
+            .mb_1()
+            .child(
+                div()
+                    .flex_1()
+                    .text_sm()
+                    .font_weight(FontWeight::BOLD)
+                    .text_color(self.text_color())
+                    .child(format!("{} · best {}", self.score, self.best_score))
+                    .on_mouse_down(MouseButton::Left, |_, window, _| window.start_window_move()),
+            )
+            .child(
+                self.render_button("full-window", "⤢")
+                    .px_2()
+                    .py_0()
+                    .on_mouse_down(MouseButton::Left, cx.listener(Self::toggle_mini_mode_mouse)),
+            )
+    }
+
+    pub(crate) fn is_started(&self) -> bool {
+        self.is_started
+    }
+
+    pub(crate) fn has_saved_game(&self) -> bool {
+        self.saved_game.is_some()
+    }
+
+    pub(crate) fn resume_game(&mut self, cx: &mut Context<Self>) {
+        let Some(saved) = self.saved_game.take() else {
+            return;
+        };
+        self.seed = saved.seed;
+        self.stats_recorded = false;
+        self.best_announced = false;
+        self.best_score = persistence::load_best_score(self.config.spawn.difficulty());
+        self.keep_playing = saved.keep_playing;
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.config.mode = saved.mode;
+        if let Some(budget) = saved.move_budget {
+            self.config.move_budget = budget;
+        }
+        self.moves_made = saved.moves_made;
+        self.merges_made = saved.merges_made;
+        self.biggest_merge = saved.biggest_merge;
+        // Challenges are never saved, so whatever was being played is over.
+        self.challenge = None;
+        self.challenge_result = None;
+        self.efficiency = None;
+        self.daily = saved.daily;
+        self.power_ups = saved.power_ups;
+        self.placing = None;
+        self.aiming = None;
+        self.restore(Snapshot {
+            board: saved.board,
+            score: saved.score,
+            rng: GameRng::from_state(saved.rng_state),
+        });
+        self.start_replay();
+        self.start_clock(saved.time_left_ms.map(Duration::from_millis), cx);
+        cx.notify();
+    }
+
+    fn autosave(&mut self) {
+        // Spectators follow every change that would be saved.
+        self.broadcast();
+        // Challenges are short and never replace the saved game.
+        if self.challenge.is_some() || self.editing {
+            return;
+        }
+        if self.is_game_over {
+            persistence::clear_saved_game();
+            persistence::clear_session();
+            return;
+        }
+        let saved = persistence::save_game(&self.to_saved());
+        self.check_saved("save the game", saved);
+        let kept = persistence::save_session(&self.session());
+        self.check_saved("keep the session", kept);
+    }
+
+    fn to_saved(&self) -> SavedGame {
+        SavedGame {
+            board: self.board.clone(),
+            score: self.score,
+            seed: self.seed,
+            rng_state: self.rng.state(),
+            keep_playing: self.keep_playing,
+            mode: self.config.mode,
+            time_left_ms: self
+                .countdown
+                .as_ref()
+                .map(|c| c.remaining(Instant::now()).as_millis() as u64),
+            move_budget: self.config.move_limit(),
+            moves_made: self.moves_made,
+            merges_made: self.merges_made,
+            biggest_merge: self.biggest_merge,
+            daily: self.daily,
+            power_ups: self.power_ups,
+        }
+    }
+
+    // The game with its undo history and replay, for picking up after a
+    // crash.
+    fn session(&self) -> Session {
+        let history = self.undo_stack.iter().map(|snapshot| SessionStep {
+            board: snapshot.board.clone(),
+            score: snapshot.score,
+            rng_state: snapshot.rng.state(),
+        });
+        Session {
+            game: self.to_saved(),
+            history: history.collect(),
+            replay: self.replay.encode(),
+            elapsed_ms: self.game_millis(),
+        }
+    }
+
+    /// The game as it stands, for exporting: the one being played, or else
+    /// the saved one waiting to be continued.
+    pub(crate) fn current_session(&self) -> Option<Session> {
+        match (self.is_started, &self.saved_game) {
+            (true, _) => Some(self.session()),
+            (false, Some(saved)) => Some(Session {
+                game: saved.clone(),
+                history: Vec::new(),
+                replay: String::new(),
+                elapsed_ms: 0,
+            }),
+            (false, None) => None,
+        }
+    }
+
+    /// Picks the game up from what a crashed session left, the same as
+    /// resuming its save but with the undo history, replay and clock too.
+    pub(crate) fn restore_session(&mut self, session: Session, cx: &mut Context<Self>) {
+        self.saved_game = Some(session.game);
+        self.resume_game(cx);
+        // Back-dated, so the time played and the replay's move times carry
+        // on from where they were.
+        let elapsed = Duration::from_millis(session.elapsed_ms);
+        self.started_at = Instant::now()
+            .checked_sub(elapsed)
+            .unwrap_or(self.started_at);
+        self.undo_stack = session
+            .history
+            .into_iter()
+            .map(|step| Snapshot {
+                board: step.board,
+                score: step.score,
+                rng: GameRng::from_state(step.rng_state),
+            })
+            .collect();
+        if let Some(replay) = Replay::parse(&session.replay) {
+            self.replay = replay;
+        }
+        cx.notify();
+    }
+
+    // Puts a failed save up in the banner, where it stays until dismissed
+    // or until the same save goes through. Play carries on either way.
+    fn check_saved(&mut self, what: &'static str, result: io::Result<()>) {
+        match result {
+            Ok(()) => {
+                if self
+                    .storage_error
+                    .as_ref()
+                    .is_some_and(|&(failed, _)| failed == what)
+                {
+                    self.storage_error = None;
+                }
+            }
+            Err(err) => {
+                let message = persistence::failure_message(what, &err);
+                self.storage_error = Some((what, message));
+            }
+        }
+    }
+
+    /// Shows a failure from elsewhere, such as the settings panel, in the
+    /// game's banner.
+    pub(crate) fn report_storage_error(
+        &mut self,
+        what: &'static str,
+        message: String,
+        cx: &mut Context<Self>,
+    ) {
+        self.storage_error = Some((what, message));
+        cx.notify();
+    }
+
+    fn render_storage_error(&self, cx: &mut Context<Self>) -> Option<impl IntoElement> {
+        let (_, message) = self.storage_error.as_ref()?;
+        Some(
+            div()
+                .absolute()
+                .top_0()
+                .left_0()
+                .right_0()
+                .flex()
+                .justify_center()
+                .items_center()
+                .gap_3()
+                .py_1()
+                .bg(rgb(0xf0624d))
+                .text_color(rgb(0xffffff))
+                .text_sm()
+                .child(message.clone())
+                .child(div().px_2().cursor_pointer().child("×").on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(|this, _, _, cx| {
+                        this.storage_error = None;
+                        cx.notify();
+                    }),
+                )),
+        )
+    }
+
+    fn broadcast(&self) {
+        let Some(spectators) = &self.spectators else {
+            return;
+        };
+        spectators.broadcast(&Update {
+            board: self.board.clone(),
+            score: self.score,
+            best_score: self.best_score,
+            moves: self.moves_made,
+            mode: self.config.mode,
+            won: self.is_won,
+            game_over: self.is_game_over,
+        });
+    }
+
+    fn save_best_score(&mut self) {
+        let saved = persistence::save_best_score(self.config.spawn.difficulty(), self.best_score);
+        self.check_saved("save best score", saved);
+    }
+
+    /// Plays `challenge` from its starting board; starting a new game from
+    /// here on retries it.
+    pub(crate) fn start_challenge(
+        &mut self,
+        id: String,
+        challenge: Challenge,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.challenge = Some(ActiveChallenge {
+            id,
+            challenge,
+            status: ChallengeStatus::InProgress,
+        });
+        self.new_game(window, cx);
+    }
+
+    fn allows_undo(&self) -> bool {
+        self.challenge.is_none() && !self.editing && self.config.mode.allows_undo()
+    }
+
+    fn allowed_moves(&self) -> Vec<Direction> {
+        match &self.challenge {
+            Some(active) => active.challenge.allowed_moves.clone(),
+            None => Direction::ALL.to_vec(),
+        }
+    }
+
+    fn moves_left(&self) -> Option<u32> {
+        let limit = match &self.challenge {
+            Some(active) => active.challenge.move_limit,
+            None => self.config.move_limit(),
+        };
+        limit.map(|limit| limit.saturating_sub(self.moves_made))
+    }
+
+    // Ends the challenge once it is solved or can no longer be.
+    fn update_challenge(&mut self, cx: &mut Context<Self>) {
+        let Some(active) = &mut self.challenge else {
+            return;
+        };
+        let status = active
+            .challenge
+            .status(&self.board, self.score, self.moves_made);
+        active.status = status;
+        match status {
+            ChallengeStatus::InProgress => return,
+            ChallengeStatus::Solved => self.toast("Challenge solved!", cx),
+            ChallengeStatus::Failed => {}
+        }
+        self.is_started = false;
+        self.record_stats();
+    }
+
+    fn is_out_of_moves(&self) -> bool {
+        self.config
+            .move_limit()
+            .is_some_and(|limit| self.moves_made >= limit)
+    }
+
+    pub(crate) fn record_stats(&mut self) {
+        if self.stats_recorded || self.replay.moves.is_empty() {
+            return;
+        }
+        self.stats_recorded = true;
+        // Challenges start from made-up positions, so only whether they
+        // were solved is kept.
+        if let Some(active) = &self.challenge {
+            if active.status == ChallengeStatus::Solved {
+                self.stats.solved_challenges.insert(active.id.clone());
+                let saved = persistence::save_stats(&self.stats);
+                self.check_saved("save statistics", saved);
+            }
+            return;
+        }
+        match self.config.mode {
+            GameMode::TimeAttack => self.stats.record_time_attack(self.score),
+            // Hand-made positions say nothing about how well anyone plays.
+            GameMode::Sandbox => return,
+            // Only finished challenges are comparable.
+            GameMode::LimitedMoves if !self.is_game_over => {}
+            GameMode::LimitedMoves => {
+                let budget = self.config.move_budget;
+                self.challenge_result = Some(ChallengeResult {
+                    budget,
+                    score: self.score,
+                    previous: self
+                        .stats
+                        .move_challenges
+                        .get(&budget)
+                        .cloned()
+                        .unwrap_or_default(),
+                });
+                self.stats.record_move_challenge(budget, self.score);
+            }
+            GameMode::Daily => self.stats.record_daily(
+                self.daily.unwrap_or_else(Date::today),
+                self.score,
+                self.board.max_tile(),
+                self.board.has_won(),
+            ),
+            _ => {
+                self.stats.record_game(
+                    self.score,
+                    self.board.max_tile(),
+                    self.replay.moves.len() as u32,
+                    self.board.has_won(),
+                );
+                let efficiency = Efficiency {
+                    score: self.score,
+                    merges: self.merges_made,
+                    moves: self.moves_made,
+                };
+                let records = self.stats.record_efficiency(efficiency);
+                self.efficiency = Some((efficiency, records));
+            }
+        }
+        let saved = persistence::save_stats(&self.stats);
+        self.check_saved("save statistics", saved);
+        if self.config.mode == GameMode::Classic {
+            self.keep_best_game();
+        }
+    }
+
+    // Keeps this game to race as a ghost if it beats the one kept so far.
+    // Only a game played from its seed can be raced again.
+    fn keep_best_game(&mut self) {
+        let Ok(score) = self.replay.verify() else {
+            return;
+        };
+        let best = persistence::load_best_game().and_then(|best| best.verify().ok());
+        if best.is_none_or(|best| score > best) {
+            let saved = persistence::save_best_game(&self.replay);
+            self.check_saved("keep the best game", saved);
+        }
+    }
+}
+
+impl Game {
+    // about render
+    pub(crate) fn background(&self) -> Rgba {
+        rgb(self.theme.background)
+    }
+
+    fn game_over_title(&self) -> &'static str {
+        if self.is_time_up() {
+            "Time's up!"
+        } else if self.config.mode.has_placer() {
+            "The placer wins!"
+        } else if self.is_out_of_moves() {
+            "Out of moves!"
+        } else {
+            "Game Over!"
+        }
+    }
+
+    fn text_color(&self) -> Rgba {
+        rgb(self.theme.text)
+    }
+
+    fn tile_color(&self, rank: u32) -> Hsla {
+        match self.theme.tile(rank) {
+            Some(color) => rgb(color).into(),
+            None => get_color(rank),
+        }
+    }
+
+    fn render_box(&self, label: &'static str, value: impl ToString) -> impl IntoElement {
+        div()
+            .bg(rgb(self.theme.grid))
+            .px_4()
+            .py_1()
+            .rounded_md()
+            .flex()
+            .flex_col()
+            .items_center()
+            .min_w(self.scaled(80.0))
+            .child(
+                div()
+                    .text_xs()
+                    .text_color(rgb(self.theme.grid_text))
+                    .child(label),
+            )
+            .child(
+                div()
+                    .text_lg()
+                    .text_color(rgb(self.theme.button_text))
+                    .font_weight(FontWeight::BOLD)
+                    .child(value.to_string()),
+            )
+    }
+
+    // The last move's "+N" floating up from the score and fading out, as in
+    // the original game.
+    fn render_score_gain(&self) -> Option<impl IntoElement> {
+        let (n, gain) = self.score_gain.filter(|_| self.settings.animations)?;
+        // With reduced motion it only fades, up where it would have risen to.
+        let rise = match self.settings.reduced_motion {
+            true => 0.0,
+            false => SCORE_GAIN_RISE,
+        };
+        let scale = self.scale();
+        Some(
+            div()
+                .absolute()
+                .left_0()
+                .right_0()
+                .flex()
+                .justify_center()
+                .text_lg()
+                .font_weight(FontWeight::BOLD)
+                .text_color(self.text_color())
+                .child(format!("+{gain}"))
+                .with_animation(
+                    ("score-gain", n),
+                    timed_animation(SCORE_GAIN_MS, self.settings.animation_speed)
+                        .with_easing(ease_out_quint()),
+                    move |this, progress| {
+                        let risen = SCORE_GAIN_RISE - rise * (1.0 - progress);
+                        this.top(px((SCORE_GAIN_TOP - risen) * scale))
+                            .opacity(1.0 - progress)
+                    },
+                ),
+        )
+    }
+
+    // The board with whatever is laid over it.
+    fn render_board(&self, cx: &mut Context<Self>) -> AnyElement {
+        let board = div()
+            .relative()
+            .overflow_hidden()
+            .child(self.render_grid())
+            .children(self.render_tiles())
+            .children(self.editing.then(|| self.render_editor(cx)))
+            .children(self.aiming.is_some().then(|| self.render_targets(cx)))
+            .children(self.placing.is_some().then(|| self.render_placements(cx)))
+            .children(self.render_coach())
+            .children(self.hint.map(|dir| self.render_hint(dir)))
+            .child(self.confetti.clone())
+            .children(self.is_game_over.then(|| {
+                div()
+                    .absolute()
+                    .inset_0()
+                    .bg(rgba(self.theme.overlay))
+                    .rounded_lg()
+                    .flex()
+                    .flex_col()
+                    .justify_center()
+                    .items_center()
+                    .child(
+                        div()
+                            .text_3xl()
+                            .font_weight(FontWeight::BOLD)
+                            .text_color(self.text_color())
+                            .child(self.game_over_title()),
+                    )
+                    .children(
+                        self.challenge_result
+                            .as_ref()
+                            .map(|result| self.render_challenge_result(result)),
+                    )
+                    .children(
+                        self.efficiency
+                            .map(|(game, records)| self.render_efficiency(game, records)),
+                    )
+                    .child(
+                        div()
+                            .mt_4()
+                            .text_lg()
+                            .text_color(self.text_color())
+                            .child("Press Enter to Try Again"),
+                    )
+            }))
+            .children(self.paused.is_some().then(|| self.render_pause_menu(cx)))
+            .children(
+                self.challenge
+                    .as_ref()
+                    .filter(|active| active.status != ChallengeStatus::InProgress)
+                    .map(|active| self.render_challenge_overlay(active.status, cx)),
+            )
+            .children(
+                (self.saved_game.is_some()
+                    && !self.is_started
+                    && !self.is_game_over
+                    && self.playback.is_none())
+                .then(|| self.render_resume_overlay(cx)),
+            );
+        let Some((n, dir)) = self.bump.filter(|_| self.settings.animations) else {
+            return board.into_any_element();
+        };
+        let (dx, dy) = match dir {
+            Direction::Up => (0.0, -1.0),
+            Direction::Down => (0.0, 1.0),
+            Direction::Left => (-1.0, 0.0),
+            Direction::Right => (1.0, 0.0),
+        };
+        let distance = BUMP_DISTANCE * self.scale();
+        let reduced_motion = self.settings.reduced_motion;
+        board
+            .with_animation(
+                ("bump", n),
+                timed_animation(BUMP_MS, self.settings.animation_speed),
+                move |this, progress| {
+                    let bump = (progress * std::f32::consts::PI).sin();
+                    // With reduced motion the board flashes instead.
+                    match reduced_motion {
+                        true => this.opacity(1.0 - 0.4 * bump),
+                        false => this
+                            .left(px(dx * distance * bump))
+                            .top(px(dy * distance * bump)),
+                    }
+                },
+            )
+            .into_any_element()
+    }
+
+    fn render_grid(&self) -> impl IntoElement {
+        let (rows, cols) = (self.board.rows(), self.board.cols());
+        let (tile, gap) = tile_metrics(rows, cols, self.scale());
+        div()
+            .relative()
+            .bg(rgb(self.theme.grid))
+            .p(self.scaled(12.0))
+            .rounded_lg()
+            .flex()
+            .flex_col()
+            .gap_3()
+            .child(
+                div()
+                    .flex()
+                    .flex_col()
+                    .p(self.scaled(6.0))
+                    .gap(px(gap))
+                    .children((0..rows).map(|row| {
+                        div()
+                            .flex()
+                            .flex_row()
+                            .gap(px(gap))
+                            .children((0..cols).map(|col| {
+                                let idx = row * cols + col;
+                                let cell = div().size(px(tile)).rounded_md();
+                                if self.board.is_hole(idx) {
+                                    // Cut out of the grid, showing the page behind.
+                                    cell.bg(self.background())
+                                } else if self.board.is_obstacle(idx) {
+                                    cell.bg(rgb(self.theme.obstacle))
+                                        .border_4()
+                                        .border_color(rgb(self.theme.tile_text_dark))
+                                } else {
+                                    cell.bg(rgb(self.theme.empty_cell))
+                                }
+                            }))
+                    })),
+            )
+    }
+
+    fn tile_origin(&self, idx: usize) -> (f32, f32) {
+        let cols = self.board.cols();
+        let (tile, gap) = tile_metrics(self.board.rows(), cols, self.scale());
+        // The grid's padding, 12px and then 6px.
+        let offset = 18.0 * self.scale();
+        let step = tile + gap;
+        (
+            offset + (idx / cols) as f32 * step,
+            offset + (idx % cols) as f32 * step,
+        )
+    }
+
+    fn tile_div(&self, cell: Cell) -> Div {
+        let (bg, fg) = match (self.board.ruleset(), cell) {
+            (_, Cell::Special(special)) => (
+                special_color(special).into(),
+                rgb(self.theme.tile_text_light),
+            ),
+            // Threes' 1s and 2s keep their blue and red.
+            (Ruleset::Threes, Cell::Number(1)) => (rgb(0x66bbee).into(), rgb(0xf9f6f2)),
+            (Ruleset::Threes, Cell::Number(2)) => (rgb(0xf26682).into(), rgb(0xf9f6f2)),
+            (ruleset, cell) => {
+                let val = cell.value();
+                let rank = ruleset.rule().rank(val);
+                (self.tile_color(rank), rgb(self.theme.tile_text(rank)))
+            }
+        };
+        let badge = match cell {
+            Cell::Number(val) if self.settings.tile_badges => {
+                labels::badge(self.board.rule().rank(val))
+            }
+            _ => None,
+        };
+        div()
+            .absolute()
+            .bg(bg)
+            .text_color(fg)
+            .font_weight(FontWeight::BOLD)
+            .rounded_md()
+            .flex()
+            .justify_center()
+            .items_center()
+            .child(self.tile_label(cell))
+            .children(badge.map(|badge| {
+                div()
+                    .absolute()
+                    .top(self.scaled(2.0))
+                    .left(self.scaled(5.0))
+                    .text_xs()
+                    .child(badge)
+            }))
+    }
+
+    fn tile_label(&self, cell: Cell) -> String {
+        match cell {
+            Cell::Empty => String::new(),
+            Cell::Special(special) => special_label(special).to_string(),
+            Cell::Number(val) => {
+                let rank = self.board.rule().rank(val);
+                self.settings.tile_labels.text(val, rank)
+            }
+        }
+    }
+
+    fn tile_font_size(&self, cell: Cell, tile: f32) -> Pixels {
+        get_font_size(&self.tile_label(cell), tile)
+    }
+
+    fn render_single_tile(&self, idx: usize, cell: Cell) -> AnyElement {
+        let (tile, _) = tile_metrics(self.board.rows(), self.board.cols(), self.scale());
+        let (base_top, base_left) = self.tile_origin(idx);
+        let tile_div = self.tile_div(cell);
+        let font_size = self.tile_font_size(cell, tile);
+
+        let fades_in = self.new_tiles.contains(&idx) || self.merged_tiles.contains(&idx);
+        if self.settings.animations && self.settings.reduced_motion && fades_in {
+            // Tiles appear where they land, fading in instead of popping.
+            tile_div
+                .w(px(tile))
+                .h(px(tile))
+                .top(px(base_top))
+                .left(px(base_left))
+                .text_size(font_size)
+                .with_animation(
+                    ("fade", self.move_count * 64 + idx),
+                    timed_animation(POP_MS, self.settings.animation_speed),
+                    |this, progress| this.opacity(progress),
+                )
+                .into_any_element()
+        } else if self.settings.animations && self.new_tiles.contains(&idx) {
+            tile_div
+                .with_animation(
+                    ("spawn", self.spawn_count),
+                    move_animation(self.settings.animation_speed),
+                    move |this, progress| {
+                        let current_size = tile * pop_phase(progress);
+                        let compensation = (tile - current_size) / 2.0;
+
+                        this.w(px(current_size))
+                            .h(px(current_size))
+                            .top(px(base_top + compensation))
+                            .left(px(base_left + compensation))
+                            .text_size(font_size * pop_phase(progress))
+                    },
+                )
+                .into_any_element()
+        } else {
+            tile_div
+                .w(px(tile))
+                .h(px(tile))
+                .top(px(base_top))
+                .left(px(base_left))
+                .text_size(font_size)
+                .into_any_element()
+        }
+    }
+
+    fn render_sliding_tile(&self, tile_move: &TileMove, merged: bool) -> Vec<AnyElement> {
+        let (rows, cols) = (self.board.rows(), self.board.cols());
+        let (tile, gap) = tile_metrics(rows, cols, self.scale());
+        let from = self.tile_origin(tile_move.from);
+        let to = self.tile_origin(tile_move.to);
+        if !tile_move.wrapped {
+            return vec![self.render_slide(tile_move, ("slide", tile_move.from), from, to, merged)];
+        }
+
+        // A tile going round the edge is drawn twice, once sliding off its
+        // own edge and once coming in from the opposite one; the board clips
+        // whatever hangs over.
+        let sign = if tile_move.to > tile_move.from {
+            -1.0
+        } else {
+            1.0
+        };
+        let span = |count: usize| sign * count as f32 * (tile + gap);
+        let shift = if tile_move.from / cols == tile_move.to / cols {
+            (0.0, span(cols))
+        } else {
+            (span(rows), 0.0)
+        };
+        let out = (to.0 + shift.0, to.1 + shift.1);
+        let back = (from.0 - shift.0, from.1 - shift.1);
+        vec![
+            self.render_slide(tile_move, ("wrap-out", tile_move.from), from, out, false),
+            self.render_slide(tile_move, ("wrap-in", tile_move.from), back, to, merged),
+        ]
+    }
+
+    fn render_slide(
+        &self,
+        tile_move: &TileMove,
+        (name, idx): (&'static str, usize),
+        (from_top, from_left): (f32, f32),
+        (to_top, to_left): (f32, f32),
+        merged: bool,
+    ) -> AnyElement {
+        let (tile, _) = tile_metrics(self.board.rows(), self.board.cols(), self.scale());
+
+        self.tile_div(tile_move.cell())
+            .w(px(tile))
+            .h(px(tile))
+            .text_size(self.tile_font_size(tile_move.cell(), tile))
+            .with_animation(
+                (name, self.move_count * 64 + idx),
+                move_animation(self.settings.animation_speed),
+                move |this, progress| {
+                    let t = slide_phase(progress);
+                    let this = this
+                        .top(px(from_top + (to_top - from_top) * t))
+                        .left(px(from_left + (to_left - from_left) * t));
+                    // Merging tiles give way to the combined tile once they arrive.
+                    if merged && t >= 1.0 {
+                        this.opacity(0.0)
+                    } else {
+                        this
+                    }
+                },
+            )
+            .into_any_element()
+    }
+
+    fn render_merged_tile(&self, idx: usize, val: u32) -> AnyElement {
+        let (tile, _) = tile_metrics(self.board.rows(), self.board.cols(), self.scale());
+        let (top, left) = self.tile_origin(idx);
+        let font_size = self.tile_font_size(Cell::Number(val), tile);
+
+        self.tile_div(Cell::Number(val))
+            .with_animation(
+                ("merge", self.move_count * 64 + idx),
+                move_animation(self.settings.animation_speed),
+                move |this, progress| {
+                    let scale = pulse_scale(pop_phase(progress));
+                    let current_size = tile * scale;
+                    let compensation = (tile - current_size) / 2.0;
+                    let this = this
+                        .w(px(current_size))
+                        .h(px(current_size))
+                        .top(px(top + compensation))
+                        .left(px(left + compensation))
+                        .text_size(font_size * scale);
+                    if slide_phase(progress) < 1.0 {
+                        this.opacity(0.0)
+                    } else {
+                        this
+                    }
+                },
+            )
+            .into_any_element()
+    }
+
+    fn render_button(
+        &self,
+        id: impl Into<ElementId>,
+        label: impl Into<SharedString>,
+    ) -> Stateful<Div> {
+        div()
+            .id(id)
+            .px_4()
+            .py_2()
+            .bg(rgb(self.theme.button))
+            .text_color(rgb(self.theme.button_text))
+            .rounded_md()
+            .font_weight(FontWeight::BOLD)
+            .child(label.into())
+    }
+
+    fn render_challenge_result(&self, result: &ChallengeResult) -> impl IntoElement {
+        let best = result.previous.iter().copied().max();
+        let rank = 1 + result
+            .previous
+            .iter()
+            .filter(|&&s| s > result.score)
+            .count();
+        div()
+            .mt_4()
+            .flex()
+            .gap_2()
+            .child(self.render_box("BUDGET", result.budget))
+            .child(self.render_box("SCORE", result.score))
+            .child(self.render_box(
+                "PREV BEST",
+                best.map_or("-".to_string(), |best| best.to_string()),
+            ))
+            .child(self.render_box("RANK", format!("{rank}/{}", result.previous.len() + 1)))
+    }
+
+    // Each rate with the record next to it, or a star where this game set it.
+    fn render_efficiency(&self, game: Efficiency, records: (bool, bool)) -> impl IntoElement {
+        let rate = |label: &'static str, value: f64, record: bool, best: Option<f64>| {
+            let best = match (record, best) {
+                (true, _) => "★ best".to_string(),
+                (false, Some(best)) => format!("best {best:.2}"),
+                (false, None) => String::new(),
+            };
+            div()
+                .flex()
+                .gap_2()
+                .child(format!("{value:.2} {label}"))
+                .child(div().text_color(rgb(self.theme.accent)).child(best))
+        };
+        div()
+            .mt_4()
+            .flex()
+            .flex_col()
+            .items_center()
+            .text_sm()
+            .text_color(self.text_color())
+            .child(format!("{} moves", game.moves))
+            .child(rate(
+                "points per move",
+                game.score_per_move(),
+                records.0,
+                self.stats.best_score_per_move.map(|e| e.score_per_move()),
+            ))
+            .child(rate(
+                "merges per move",
+                game.merges_per_move(),
+                records.1,
+                self.stats.best_merges_per_move.map(|e| e.merges_per_move()),
+            ))
+    }
+
+    fn render_challenge_overlay(
+        &self,
+        status: ChallengeStatus,
+        cx: &mut Context<Self>,
+    ) -> impl IntoElement {
+        let solved = status == ChallengeStatus::Solved;
+        div()
+            .absolute()
+            .inset_0()
+            .bg(if solved {
+                rgba(0xedc22e80)
+            } else {
+                rgba(self.theme.overlay)
+            })
+            .rounded_lg()
+            .flex()
+            .flex_col()
+            .justify_center()
+            .items_center()
+            .child(
+                div()
+                    .text_3xl()
+                    .font_weight(FontWeight::BOLD)
+                    .text_color(if solved {
+                        rgb(0xf9f6f2)
+                    } else {
+                        self.text_color()
+                    })
+                    .child(if solved {
+                        "Solved!"
+                    } else {
+                        "Challenge Failed"
+                    }),
+            )
+            .child(
+                div()
+                    .mt_4()
+                    .flex()
+                    .gap_2()
+                    .child(
+                        self.render_button("retry-challenge", "Retry")
+                            .on_mouse_down(MouseButton::Left, cx.listener(Self::new_game_mouse)),
+                    )
+                    .child(
+                        self.render_button("more-challenges", "Challenges")
+                            .on_mouse_down(
+                                MouseButton::Left,
+                                cx.listener(|_, _: &MouseDownEvent, _window, cx| {
+                                    cx.emit(GameEvent::OpenChallenges)
+                                }),
+                            ),
+                    ),
+            )
+    }
+
+    fn render_resume_overlay(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        div()
+            .absolute()
+            .inset_0()
+            .bg(rgba(self.theme.overlay))
+            .rounded_lg()
+            .flex()
+            .flex_col()
+            .justify_center()
+            .items_center()
+            .child(
+                div()
+                    .text_3xl()
+                    .font_weight(FontWeight::BOLD)
+                    .text_color(self.text_color())
+                    .child("Welcome Back!"),
+            )
+            .child(
+                div()
+                    .mt_4()
+                    .flex()
+                    .gap_2()
+                    .child(
+                        self.render_button("continue", "Continue")
+                            .on_mouse_down(MouseButton::Left, cx.listener(Self::resume_game_mouse)),
+                    )
+                    .child(
+                        self.render_button("resume-new-game", "New Game")
+                            .on_mouse_down(MouseButton::Left, cx.listener(Self::new_game_mouse)),
+                    ),
+            )
+    }
+
+    fn render_hint(&self, dir: Direction) -> impl IntoElement {
+        div()
+            .absolute()
+            .inset_0()
+            .flex()
+            .justify_center()
+            .items_center()
+            .text_size(self.scaled(160.0))
+            .font_weight(FontWeight::BOLD)
+            .text_color(rgba(self.theme.accent << 8 | 0xb0))
+            .child(arrow(dir))
+    }
+
+    // Arrow buttons for playing with the mouse: an inverted T where the
+    // window is tall enough, otherwise a single row that takes less height.
+    fn render_dpad(&self, window: &Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let mut button = |dir: Direction| {
+            self.render_button(("dpad", dir as usize), arrow(dir))
+                .w(self.scaled(DPAD_BUTTON))
+                .flex()
+                .justify_center()
+                .text_xl()
+                .on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(move |this, _, _, cx| this.handle_move(dir, cx)),
+                )
+        };
+        let pad = div().flex().flex_col().items_center().gap_1().mt_4();
+        match window.viewport_size().height < self.scaled(DPAD_CROSS_MIN_HEIGHT) {
+            true => pad.child(
+                div().flex().gap_1().children(
+                    [
+                        Direction::Left,
+                        Direction::Up,
+                        Direction::Down,
+                        Direction::Right,
+                    ]
+                    .map(button),
+                ),
+            ),
+            false => pad.child(button(Direction::Up)).child(
+                div()
+                    .flex()
+                    .gap_1()
+                    .children([Direction::Left, Direction::Down, Direction::Right].map(button)),
+            ),
+        }
+    }
+
+    fn render_tiles(&self) -> Vec<AnyElement> {
+        let cells = (0..self.board.cells().len())
+            .map(|idx| (idx, self.board.cell(idx)))
+            .filter(|&(_, cell)| cell != Cell::Empty);
+        if !self.settings.animations || self.settings.reduced_motion {
+            return cells
+                .map(|(idx, cell)| self.render_single_tile(idx, cell))
+                .collect();
+        }
+        let mut tiles = Vec::new();
+        for (idx, cell) in cells {
+            let incoming: Vec<&TileMove> = self.moves.iter().filter(|m| m.to == idx).collect();
+            let merged = self.merged_tiles.contains(&idx);
+            let slid = incoming.iter().any(|m| m.from != m.to);
+            if merged || slid {
+                for tile_move in incoming {
+                    tiles.extend(self.render_sliding_tile(tile_move, merged));
+                }
+            }
+            if merged {
+                tiles.push(self.render_merged_tile(idx, cell.value()));
+            } else if !slid {
+                tiles.push(self.render_single_tile(idx, cell));
+            }
+        }
+        tiles.extend(
+            self.explosions
+                .iter()
+                .map(|&idx| self.render_explosion(idx)),
+        );
+        tiles
+    }
+
+    // A flash that swells over the bomb's blast area and fades away.
+    fn render_explosion(&self, idx: usize) -> AnyElement {
+        let (tile, gap) = tile_metrics(self.board.rows(), self.board.cols(), self.scale());
+        let (top, left) = self.tile_origin(idx);
+        let (center_top, center_left) = (top + tile / 2.0, left + tile / 2.0);
+        let blast = 3.0 * tile + 2.0 * gap;
+
+        div()
+            .absolute()
+            .rounded_full()
+            .bg(rgb(self.theme.accent))
+            .with_animation(
+                ("explode", self.move_count * 64 + idx),
+                move_animation(self.settings.animation_speed),
+                move |this, progress| {
+                    let pop = pop_phase(progress);
+                    let size = tile / 2.0 + (blast - tile / 2.0) * pop;
+                    this.w(px(size))
+                        .h(px(size))
+                        .top(px(center_top - size / 2.0))
+                        .left(px(center_left - size / 2.0))
+                        .opacity(if pop > 0.0 { 0.8 * (1.0 - pop) } else { 0.0 })
+                },
+            )
+            .into_any_element()
+    }
+}
+impl Game {
+    // about core logic
+    fn spawn_tile(&mut self, cx: &mut Context<Self>) -> Option<Spawn> {
+        let idx = match self.config.mode {
+            GameMode::Evil => {
+                let values = self.config.spawn.values();
+                let (idx, value) = self
+                    .settings
+                    .adversary()
+                    .worst_spawn(&self.board, &values)?;
+                self.board.set_cell(idx, value);
+                idx
+            }
+            _ => self
+                .board
+                .spawn_tile_with(&mut self.rng, &self.config.spawn)?,
+        };
+        self.spawn_count += 1;
+        self.new_tiles.push(idx);
+        cx.notify();
+        let spawn = Spawn {
+            index: idx,
+            value: self.board.cells()[idx],
+            special: self.board.special(idx),
+        };
+        tracing::debug!(
+            index = idx,
+            value = spawn.value,
+            special = ?spawn.special,
+            rng = self.rng.state(),
+            "spawned"
+        );
+        Some(spawn)
+    }
+
+    pub(crate) fn handle_move(&mut self, dir: Direction, cx: &mut Context<Self>) {
+        let _span = tracing::debug_span!("move", ?dir, moves = self.moves_made).entered();
+        if !self.is_started
+            || self.paused.is_some()
+            || self.is_won
+            || self.editing
+            || self.placing.is_some()
+        {
+            return;
+        }
+        if !self.allowed_moves().contains(&dir) {
+            self.refuse_move(dir, cx);
+            return;
+        }
+        self.new_tiles.clear();
+        self.clear_hint();
+        self.aiming = None;
+        let before = self.snapshot();
+        let record = self.board.max_tile();
+        let result = self.board.apply_move(dir);
+        tracing::debug!(
+            moved = result.moved,
+            gained = result.score_delta,
+            merges = result.merges.len(),
+            "slid"
+        );
+        self.last_move = Some((dir, result.clone()));
+        if !result.moved {
+            self.refuse_move(dir, cx);
+            self.finish_turn(cx);
+            self.dispatch_events(cx);
+            cx.notify();
+            return;
+        }
+        self.push_history(before);
+        self.score = self.score.saturating_add(result.score_delta);
+        self.events
+            .extend(events::move_events(dir, &result, record, self.score));
+        self.moves = result.moves;
+        self.merged_tiles = result.merges.iter().map(|m| m.index).collect();
+        self.explosions = result.explosions;
+        self.move_count += 1;
+        let millis = self.started_at.elapsed().as_millis() as u64;
+        if self.config.mode.has_placer() {
+            // The turn ends once the placer has put a tile down.
+            self.placing = Some((dir, millis));
+            self.dispatch_events(cx);
+            cx.notify();
+            return;
+        }
+        let spawns: Vec<Spawn> = (0..self.config.spawn.tiles_per_move)
+            .map_while(|_| self.spawn_tile(cx))
+            .collect();
+        self.complete_turn(dir, spawns, millis, cx);
+    }
+
+    // Everything that follows a move once its new tiles are down, whether
+    // the game spawned them or the placer picked the spot. Anything that
+    // should happen once per move belongs here.
+    fn complete_turn(
+        &mut self,
+        dir: Direction,
+        spawns: Vec<Spawn>,
+        millis: u64,
+        cx: &mut Context<Self>,
+    ) {
+        self.events
+            .extend(spawns.iter().map(|spawn| Event::TileSpawned {
+                index: spawn.index,
+                value: spawn.value,
+            }));
+        self.replay.record(dir, spawns, millis);
+        self.moves_made += 1;
+        self.track_run(millis, cx);
+        self.finish_turn(cx);
+        self.dispatch_events(cx);
+        self.autosave();
+        cx.notify();
+    }
+
+    // Ends the game if the last move finished it.
+    fn finish_turn(&mut self, cx: &mut Context<Self>) {
+        if self.challenge.is_some() {
+            self.update_challenge(cx);
+        } else if self.is_out_of_moves() {
+            self.end_game(cx);
+        } else if self.config.mode.stops_at_win() && !self.keep_playing && self.board.has_won() {
+            self.is_won = true;
+            self.events.emit(Event::Won);
+            cx.emit(GameEvent::Won);
+        } else if self.config.mode.settle(&mut self.board) && self.power_ups.is_empty() {
+            // Power-ups in hand can still clear a stuck board.
+            self.end_game(cx);
+        };
+    }
+
+    // Throws confetti over the first 512, 1024 and 2048 on the board, or
+    // whatever the last three tiles up to the winning one are under its
+    // rules. `value` has just been made for the first time.
+    fn celebrate(&mut self, value: u32, cx: &mut Context<Self>) {
+        if !self.settings.animations || self.settings.reduced_motion {
+            return;
+        }
+        let rule = self.board.rule();
+        let win_rank = rule.rank(rule.win_tile());
+        let pieces = match win_rank.checked_sub(rule.rank(value)) {
+            Some(0) => 120,
+            Some(1) => 70,
+            Some(2) => 40,
+            _ => return,
+        };
+        let Some(idx) = self.board.cells().iter().position(|&cell| cell == value) else {
+            return;
+        };
+        let (top, left) = self.tile_origin(idx);
+        let (tile, _) = tile_metrics(self.board.rows(), self.board.cols(), self.scale());
+        self.confetti.update(cx, |confetti, cx| {
+            confetti.burst(left + tile / 2.0, top + tile / 2.0, pieces, cx)
+        });
+    }
+
+    // Lets the player know a move was heard but can't be made.
+    fn refuse_move(&mut self, dir: Direction, cx: &mut Context<Self>) {
+        let n = self.bump.map_or(0, |(n, _)| n + 1);
+        self.bump = Some((n, dir));
+        self.last_move = Some((dir, MoveResult::default()));
+        self.events.emit(Event::Blocked(dir));
+        self.dispatch_events(cx);
+        cx.notify();
+    }
+
+    fn end_game(&mut self, cx: &mut Context<Self>) {
+        self.is_started = false;
+        self.is_game_over = true;
+        self.events.emit(Event::GameOver);
+        self.dispatch_events(cx);
+        cx.emit(GameEvent::GameOver);
+    }
+
+    /// Has `subscriber` hear every batch of events from here on, after
+    /// those already following the game.
+    pub(crate) fn subscribe(
+        &mut self,
+        subscriber: impl FnMut(&mut Game, &[Event], &mut Context<Game>) + 'static,
+    ) {
+        self.subscribers.push(Box::new(subscriber));
+    }
+
+    // Hands what the last move did to each subscriber in turn.
+    fn dispatch_events(&mut self, cx: &mut Context<Self>) {
+        let events = self.events.drain();
+        if events.is_empty() {
+            return;
+        }
+        let mut subscribers = std::mem::take(&mut self.subscribers);
+        for subscriber in &mut subscribers {
+            subscriber(self, &events, cx);
+        }
+        // Any that subscribed while the events went round come last.
+        subscribers.append(&mut self.subscribers);
+        self.subscribers = subscribers;
+    }
+
+    // Each sound plays once however many times it came up.
+    fn play_sounds(&mut self, events: &[Event], _cx: &mut Context<Self>) {
+        let mut sounds: Vec<Sound> = events
+            .iter()
+            .filter_map(|event| match event {
+                Event::Moved(_) => Some(Sound::Slide),
+                Event::Blocked(_) => Some(Sound::Blocked),
+                Event::TilesMerged(_) => Some(Sound::Merge),
+                Event::TileSpawned { .. } => Some(Sound::Spawn),
+                Event::Won => Some(Sound::Win),
+                Event::GameOver => Some(Sound::GameOver),
+                Event::ScoreChanged { .. } | Event::Milestone(_) => None,
+            })
+            .collect();
+        // A merge is heard instead of the slide it came with.
+        if sounds.contains(&Sound::Merge) {
+            sounds.retain(|&sound| sound != Sound::Slide);
+        }
+        for sound in Sound::ALL {
+            if sounds.contains(&sound) {
+                self.play(sound);
+            }
+        }
+    }
+
+    fn tally_merges(&mut self, events: &[Event], cx: &mut Context<Self>) {
+        for event in events {
+            let Event::TilesMerged(merges) = event else {
+                continue;
+            };
+            self.merges_made += merges.len() as u32;
+            let biggest = merges.iter().map(|merge| merge.value).max();
+            self.biggest_merge = self.biggest_merge.max(biggest.unwrap_or(0));
+            if self.config.mode.has_power_ups() {
+                self.earn_power_ups(merges, cx);
+            }
+        }
+    }
+
+    fn celebrate_milestones(&mut self, events: &[Event], cx: &mut Context<Self>) {
+        for event in events {
+            if let Event::Milestone(tile) = *event {
+                self.celebrate(tile, cx);
+            }
+        }
+    }
+
+    fn record_game_over(&mut self, events: &[Event], _cx: &mut Context<Self>) {
+        if events.contains(&Event::GameOver) {
+            self.record_stats();
+            self.finish_run();
+        }
+    }
+
+    fn track_score(&mut self, events: &[Event], cx: &mut Context<Self>) {
+        for event in events {
+            let Event::ScoreChanged { score, gained } = *event else {
+                continue;
+            };
+            let n = self.score_gain.map_or(0, |(n, _)| n + 1);
+            self.score_gain = Some((n, gained));
+            if self.best_score < score {
+                // Only beating a record is news, and only once per game.
+                if self.best_score > 0 && !self.best_announced {
+                    self.best_announced = true;
+                    self.toast("New best score!", cx);
+                }
+                self.best_score = score;
+                self.save_best_score();
+            }
+        }
+    }
+
+    /// What the game came to, and its replay for the AI to go over.
+    pub(crate) fn summary(&self) -> (GameSummary, Replay) {
+        let summary = GameSummary {
+            board: self.board.clone(),
+            score: self.score,
+            moves: self.moves_made,
+            millis: self.game_millis(),
+            biggest_merge: self.biggest_merge,
+        };
+        (summary, self.replay.clone())
+    }
+}
+
+impl Game {
+    // about the sandbox editor
+    fn edit_cell(&mut self, idx: usize, up: bool, cx: &mut Context<Self>) {
+        if !self.editing {
+            return;
+        }
+        self.board.cycle_cell(idx, up);
+        self.board_edited(cx);
+    }
+
+    fn clear_board(&mut self, _: &MouseDownEvent, _window: &mut Window, cx: &mut Context<Self>) {
+        if !self.editing {
+            return;
+        }
+        self.board = Board::for_config(&self.config);
+        self.board_edited(cx);
+    }
+
+    fn board_edited(&mut self, cx: &mut Context<Self>) {
+        // Counts as a new position, so a hint still being searched for the
+        // old one is dropped.
+        self.move_count += 1;
+        self.new_tiles.clear();
+        self.moves.clear();
+        self.merged_tiles.clear();
+        self.explosions.clear();
+        self.clear_hint();
+        cx.notify();
+    }
+
+    // Switches between setting the board up and playing it from there.
+    fn toggle_editing(&mut self, _: &MouseDownEvent, _window: &mut Window, cx: &mut Context<Self>) {
+        if self.config.mode != GameMode::Sandbox || self.playback.is_some() {
+            return;
+        }
+        self.autoplay_task = None;
+        self.editing = !self.editing;
+        self.is_started = true;
+        self.is_game_over = false;
+        self.is_won = false;
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        if !self.editing {
+            if self.board.empty_cells().count() == self.board.cells().len() {
+                self.spawn_tile(cx);
+            }
+            self.stats_recorded = false;
+            self.start_replay();
+            if self.config.mode.settle(&mut self.board) {
+                self.is_started = false;
+                self.is_game_over = true;
+            }
+            self.autosave();
+        }
+        self.board_edited(cx);
+    }
+
+    // Puts the board on the clipboard as a text grid, for pasting into chat
+    // or into someone else's sandbox.
+    fn copy_board(&mut self, _: &CopyBoard, _window: &mut Window, cx: &mut Context<Self>) {
+        cx.write_to_clipboard(ClipboardItem::new_string(self.board.to_grid()));
+        self.toast("Board copied", cx);
+    }
+
+    // Copies the board and score drawn in box-drawing characters, to paste
+    // where a plain grid would look bare.
+    fn export_text(&mut self, _: &ExportText, _window: &mut Window, cx: &mut Context<Self>) {
+        let text = self.board.to_box_text(self.score);
+        cx.write_to_clipboard(ClipboardItem::new_string(text));
+        self.toast("Board copied as text", cx);
+    }
+
+    // Draws the board offscreen, with the game-over or win overlay if one is
+    // up, and saves it as a PNG.
+    fn save_screenshot(
+        &mut self,
+        _: &SaveScreenshot,
+        _window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let banner = match (self.is_game_over, self.is_won && !self.keep_playing) {
+            (true, _) => Some(self.game_over_title()),
+            (false, true) => Some("You win!"),
+            (false, false) => None,
+        };
+        let image = screenshot::render(&self.board, self.score, &self.theme, banner);
+        match persistence::save_screenshot(&image.to_png()) {
+            Ok(_) => self.toast("Screenshot saved", cx),
+            Err(_) => self.toast("Couldn't save the screenshot", cx),
+        }
+    }
+
+    // Sets the sandbox up with a grid from the clipboard, at whatever size
+    // the grid is.
+    fn paste_board(&mut self, _: &PasteBoard, _window: &mut Window, cx: &mut Context<Self>) {
+        if !self.editing {
+            if self.config.mode == GameMode::Sandbox {
+                self.toast("Switch to Edit to paste a board", cx);
+            }
+            return;
+        }
+        let text = cx
+            .read_from_clipboard()
+            .and_then(|item| item.text())
+            .unwrap_or_default();
+        match Board::parse_grid(&text) {
+            Ok(mut board) => {
+                board.set_mode(self.config.mode);
+                self.config.rows = board.rows();
+                self.config.cols = board.cols();
+                self.board = board;
+                self.board_edited(cx);
+            }
+            Err(err) => self.toast(format!("Couldn't paste the board: {err}"), cx),
+        }
+    }
+
+    fn render_editor(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let (tile, _) = tile_metrics(self.board.rows(), self.board.cols(), self.scale());
+        div().absolute().inset_0().children(
+            (0..self.board.cells().len())
+                .filter(|&idx| !self.board.is_wall(idx))
+                .map(|idx| {
+                    let (top, left) = self.tile_origin(idx);
+                    div()
+                        .id(("edit", idx))
+                        .absolute()
+                        .top(px(top))
+                        .left(px(left))
+                        .size(px(tile))
+                        .rounded_md()
+                        .cursor_pointer()
+                        .hover(|style| style.bg(rgba(0xffffff40)))
+                        .on_mouse_down(
+                            MouseButton::Left,
+                            cx.listener(move |this, _: &MouseDownEvent, _window, cx| {
+                                this.edit_cell(idx, true, cx)
+                            }),
+                        )
+                        .on_mouse_down(
+                            MouseButton::Right,
+                            cx.listener(move |this, _: &MouseDownEvent, _window, cx| {
+                                this.edit_cell(idx, false, cx)
+                            }),
+                        )
+                }),
+        )
+    }
+}
+
+impl Game {
+    // about arcade power-ups
+    fn earn_power_ups(&mut self, merges: &[Merge], cx: &mut Context<Self>) {
+        let rule = self.board.rule();
+        for merge in merges {
+            if let Some(power_up) = PowerUp::reward(rule.rank(merge.value)) {
+                self.power_ups.add(power_up);
+                self.toast(format!("{} power-up earned!", power_up.label()), cx);
+            }
+        }
+    }
+
+    // Arms `power_up`, or puts it away again if it already was. A shuffle
+    // needs no aiming and goes off at once.
+    fn pick_power_up(&mut self, power_up: PowerUp, cx: &mut Context<Self>) {
+        if !self.is_started || self.is_won || self.power_ups.count(power_up) == 0 {
+            return;
+        }
+        if self
+            .aiming
+            .as_ref()
+            .is_some_and(|(armed, _)| *armed == power_up)
+        {
+            self.aiming = None;
+        } else {
+            self.aiming = Some((power_up, Vec::new()));
+            if power_up.targets() == 0 {
+                self.use_power_up(cx);
+            }
+        }
+        cx.notify();
+    }
+
+    // Picks or unpicks the tile on `idx` as a target of the armed power-up.
+    fn pick_target(&mut self, idx: usize, cx: &mut Context<Self>) {
+        let Some((power_up, targets)) = &mut self.aiming else {
+            return;
+        };
+        match targets.iter().position(|&t| t == idx) {
+            Some(at) => {
+                targets.remove(at);
+            }
+            None => targets.push(idx),
+        }
+        if targets.len() == power_up.targets() {
+            self.use_power_up(cx);
+        }
+        cx.notify();
+    }
+
+    fn use_power_up(&mut self, cx: &mut Context<Self>) {
+        let Some((power_up, targets)) = self.aiming.take() else {
+            return;
+        };
+        let power_up_use = match (power_up, &targets[..]) {
+            (PowerUp::Swap, &[a, b]) => PowerUpUse::Swap(a, b),
+            (PowerUp::Delete, &[idx]) => PowerUpUse::Delete(idx),
+            (PowerUp::Shuffle, []) => PowerUpUse::Shuffle(self.board.shuffled_cells(&mut self.rng)),
+            _ => return,
+        };
+        if !self.power_ups.take(power_up) {
+            return;
+        }
+        self.board.apply_power_up(&power_up_use);
+        self.replay.record_power_up(power_up_use);
+        self.board_edited(cx);
+        if self.power_ups.is_empty() && self.config.mode.settle(&mut self.board) {
+            self.end_game(cx);
+        }
+        self.autosave();
+    }
+
+    fn render_power_ups(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let armed = self.aiming.as_ref().map(|(power_up, _)| *power_up);
+        let buttons = PowerUp::ALL.into_iter().map(|power_up| {
+            let count = self.power_ups.count(power_up);
+            let button = self
+                .render_button(power_up.label(), format!("{} ×{count}", power_up.label()))
+                .on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(move |this, _, _, cx| this.pick_power_up(power_up, cx)),
+                );
+            if armed == Some(power_up) {
+                button.bg(rgb(self.theme.accent))
+            } else if count == 0 {
+                button.opacity(0.5)
+            } else {
+                button
+            }
+        });
+
+        div()
+            .flex()
+            .w(self.scaled(420.0))
+            .mt_4()
+            .justify_between()
+            .items_center()
+            .child(
+                div()
+                    .text_sm()
+                    .text_color(self.text_color())
+                    .child(match armed {
+                        Some(PowerUp::Swap) => "Pick two tiles to swap",
+                        Some(PowerUp::Delete) => "Pick a tile to remove",
+                        _ => "Merge into 128 or more to earn power-ups",
+                    }),
+            )
+            .child(div().flex().gap_1().children(buttons))
+    }
+
+    // Makes the tiles clickable while a power-up is being aimed.
+    fn render_targets(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let (tile, _) = tile_metrics(self.board.rows(), self.board.cols(), self.scale());
+        let picked = self
+            .aiming
+            .as_ref()
+            .map(|(_, targets)| targets.clone())
+            .unwrap_or_default();
+        div().absolute().inset_0().children(
+            (0..self.board.cells().len())
+                .filter(|&idx| self.board.cells()[idx] != 0)
+                .map(|idx| {
+                    let (top, left) = self.tile_origin(idx);
+                    let target = div()
+                        .id(("target", idx))
+                        .absolute()
+                        .top(px(top))
+                        .left(px(left))
+                        .size(px(tile))
+                        .rounded_md()
+                        .cursor_pointer()
+                        .hover(|style| style.bg(rgba(0xffffff40)));
+                    let target = if picked.contains(&idx) {
+                        target.border_4().border_color(rgb(self.theme.accent))
+                    } else {
+                        target
+                    };
+                    target.on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(move |this, _: &MouseDownEvent, _window, cx| {
+                            this.pick_target(idx, cx)
+                        }),
+                    )
+                }),
+        )
+    }
+}
+
+impl Game {
+    // about hotseat games
+    fn pick_place_value(&mut self, value: u32, cx: &mut Context<Self>) {
+        self.place_value = value;
+        cx.notify();
+    }
+
+    // Puts the placer's tile on `idx`, which ends the turn.
+    fn place_tile(&mut self, idx: usize, cx: &mut Context<Self>) {
+        let Some((dir, millis)) = self.placing else {
+            return;
+        };
+        if !self.board.place_tile(idx, self.place_value) {
+            return;
+        }
+        self.placing = None;
+        self.spawn_count += 1;
+        self.new_tiles.push(idx);
+        let spawn = Spawn {
+            index: idx,
+            value: self.place_value,
+            special: None,
+        };
+        self.complete_turn(dir, vec![spawn], millis, cx);
+    }
+
+    // Says whose turn it is and lets the placer pick the next tile's value.
+    fn render_turn(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let placing = self.placing.is_some();
+        let values = self.config.spawn.values().into_iter().map(|value| {
+            let button = self
+                .render_button(("place-value", value as usize), value.to_string())
+                .on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(move |this, _, _, cx| this.pick_place_value(value, cx)),
+                );
+            if value == self.place_value {
+                button.bg(rgb(self.theme.accent))
+            } else {
+                button
+            }
+        });
+
+        div()
+            .flex()
+            .w(self.scaled(420.0))
+            .mt_4()
+            .justify_between()
+            .items_center()
+            .child(
+                div()
+                    .px_3()
+                    .py_1()
+                    .rounded_md()
+                    .bg(if placing {
+                        rgb(0xb784d8)
+                    } else {
+                        rgb(self.theme.button)
+                    })
+                    .text_color(rgb(self.theme.button_text))
+                    .font_weight(FontWeight::BOLD)
+                    .child(if placing {
+                        "Placer: put a tile on an empty cell"
+                    } else {
+                        "Mover: slide the tiles"
+                    }),
+            )
+            .child(div().flex().gap_1().children(values))
+    }
+
+    // Makes the empty cells clickable while the placer is choosing one.
+    fn render_placements(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let (tile, _) = tile_metrics(self.board.rows(), self.board.cols(), self.scale());
+        div()
+            .absolute()
+            .inset_0()
+            .children(self.board.empty_cells().map(|idx| {
+                let (top, left) = self.tile_origin(idx);
+                div()
+                    .id(("place", idx))
+                    .absolute()
+                    .top(px(top))
+                    .left(px(left))
+                    .size(px(tile))
+                    .rounded_md()
+                    .cursor_pointer()
+                    .border_2()
+                    .border_color(rgba(0xb784d880))
+                    .hover(|style| style.bg(rgba(0xb784d860)))
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(move |this, _: &MouseDownEvent, _window, cx| {
+                            this.place_tile(idx, cx)
+                        }),
+                    )
+            }))
+    }
+}
+
+impl Game {
+    // about the game clock
+    fn start_clock(&mut self, limit: Option<Duration>, cx: &mut Context<Self>) {
+        self.countdown = limit.map(Countdown::new);
+        self.countdown_task = None;
+        if !self.tick_clock(cx) {
+            return;
+        }
+        self.countdown_task = Some(cx.spawn(async move |this, cx| {
+            loop {
+                cx.background_executor()
+                    .timer(Duration::from_millis(250))
+                    .await;
+                let ticking = this
+                    .update(cx, |this, cx| this.tick_clock(cx))
+                    .unwrap_or(false);
+                if !ticking {
+                    break;
+                }
+            }
+        }));
+    }
+
+    // Keeps the clock in step with the game, which only spends time while
+    // it is being played. Returns false once the time is up.
+    fn tick_clock(&mut self, cx: &mut Context<Self>) -> bool {
+        let now = Instant::now();
+        let running = self.clock_running && self.is_started && self.paused.is_none();
+        let Some(countdown) = &mut self.countdown else {
+            return false;
+        };
+        match running {
+            true => countdown.resume(now),
+            false => countdown.pause(now),
+        }
+        let expired = countdown.is_expired(now);
+        if expired && self.is_started {
+            self.time_up(cx);
+        }
+        cx.notify();
+        !expired
+    }
+
+    fn time_up(&mut self, cx: &mut Context<Self>) {
+        self.autoplay_task = None;
+        self.end_game(cx);
+        self.autosave();
+    }
+
+    fn is_time_up(&self) -> bool {
+        self.countdown
+            .as_ref()
+            .is_some_and(|c| c.is_expired(Instant::now()))
+    }
+
+    /// Pauses the clock while the window is in the background or another
+    /// screen covers the game.
+    pub(crate) fn set_clock_running(&mut self, running: bool, cx: &mut Context<Self>) {
+        self.clock_running = running;
+        // A key let go while the game was out of focus never comes up here.
+        if !running {
+            self.repeat = None;
+        }
+        if self.countdown.is_some() {
+            self.tick_clock(cx);
+        }
+    }
+
+    fn toggle_pause(&mut self, _: &Pause, _window: &mut Window, cx: &mut Context<Self>) {
+        match self.paused {
+            Some(_) => self.resume(cx),
+            None => self.pause(cx),
+        }
+    }
+
+    fn pause(&mut self, cx: &mut Context<Self>) {
+        if !self.is_started || self.playback.is_some() {
+            return;
+        }
+        self.paused = Some(Instant::now());
+        self.autoplay_task = None;
+        self.repeat = None;
+        self.clear_hint();
+        self.tick_clock(cx);
+        cx.notify();
+    }
+
+    // Picks up where the game was paused. The pause is cut out of the
+    // game's time, so the ghost and the replay's timeline wait too.
+    fn resume(&mut self, cx: &mut Context<Self>) {
+        let Some(paused_at) = self.paused.take() else {
+            return;
+        };
+        self.started_at += paused_at.elapsed();
+        self.tick_clock(cx);
+        cx.notify();
+    }
+
+    // Covers the board completely, so a pause can't be spent planning.
+    fn render_pause_menu(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let button = |id: &'static str, label: &'static str| {
+            self.render_button(id, label)
+                .w(self.scaled(200.0))
+                .flex()
+                .justify_center()
+        };
+        div()
+            .absolute()
+            .inset_0()
+            .bg(self.background())
+            .rounded_lg()
+            .flex()
+            .flex_col()
+            .justify_center()
+            .items_center()
+            .gap_2()
+            .child(
+                div()
+                    .text_3xl()
+                    .font_weight(FontWeight::BOLD)
+                    .text_color(self.text_color())
+                    .mb_2()
+                    .child("Paused"),
+            )
+            .child(button("pause-resume", "Resume").on_mouse_down(
+                MouseButton::Left,
+                cx.listener(|this, _, _, cx| this.resume(cx)),
+            ))
+            .child(button("pause-restart", "Restart").on_mouse_down(
+                MouseButton::Left,
+                cx.listener(|this, _, window, cx| this.request_new_game(window, cx)),
+            ))
+            .child(
+                button("pause-settings", "Settings")
+                    .on_mouse_down(MouseButton::Left, cx.listener(Self::open_settings)),
+            )
+            .child(
+                button("pause-quit", "Quit to Menu")
+                    .on_mouse_down(MouseButton::Left, cx.listener(Self::open_menu)),
+            )
+    }
+
+    fn render_clock(&self) -> Option<impl IntoElement> {
+        let remaining = self.countdown.as_ref()?.remaining(Instant::now());
+        let secs = remaining.as_secs_f32().ceil() as u64;
+        Some(self.render_box("TIME", format!("{}:{:02}", secs / 60, secs % 60)))
+    }
+
+    // How long the game has been played, leaving out pauses.
+    fn game_millis(&self) -> u64 {
+        let now = self.paused.unwrap_or_else(Instant::now);
+        now.saturating_duration_since(self.started_at).as_millis() as u64
+    }
+}
+
+impl Game {
+    // about the speedrun timer
+    // Starts the run on a game's first move and splits whenever the board's
+    // biggest tile passes a split tile. Only plain classic games are timed,
+    // so runs stay comparable.
+    fn track_run(&mut self, now: u64, cx: &mut Context<Self>) {
+        if !self.settings.speedrun
+            || self.config.mode != GameMode::Classic
+            || self.board.ruleset() != Ruleset::Classic
+            || self.challenge.is_some()
+            || self.playback.is_some()
+        {
+            return;
+        }
+        if self.moves_made == 1 {
+            self.split_records = persistence::load_splits(self.board.rows(), self.board.cols());
+            self.run = Some(Run::new(now));
+            self.run_task = Some(cx.spawn(async move |this, cx| {
+                loop {
+                    cx.background_executor().timer(RUN_REFRESH).await;
+                    let running = this.update(cx, |this, cx| {
+                        cx.notify();
+                        this.run.as_ref().is_some_and(|run| run.end.is_none())
+                    });
+                    if !running.unwrap_or(false) {
+                        break;
+                    }
+                }
+            }));
+        }
+        let Some(run) = &mut self.run else {
+            return;
+        };
+        if run.reach(self.board.max_tile(), now, &self.split_records)
+            && run.is_complete()
+            && self.finish_run()
+        {
+            self.toast("New personal best!", cx);
+        }
+    }
+
+    // Stops the run's clock and keeps its splits towards the records.
+    // Returns whether it was a personal best.
+    fn finish_run(&mut self) -> bool {
+        let now = self.game_millis();
+        let Some(run) = self.run.as_mut().filter(|run| run.end.is_none()) else {
+            return false;
+        };
+        // A finished run stops on its last split, not whenever this is.
+        run.end = Some(match run.is_complete() {
+            true => run.start + run.times()[SPLIT_TILES.len() - 1],
+            false => now,
+        });
+        self.run_task = None;
+        if run.splits.is_empty() {
+            return false;
+        }
+        let best = self.split_records.record(&run.times());
+        let (rows, cols) = (self.board.rows(), self.board.cols());
+        let saved = persistence::save_splits(rows, cols, &self.split_records);
+        self.check_saved("save split times", saved);
+        best
+    }
+
+    // The board as text, with the seed, frame rate and what the last move
+    // did, for checking against a report of a game gone wrong.
+    fn render_debug_overlay(&self) -> Option<impl IntoElement> {
+        if !self.debug_overlay {
+            return None;
+        }
+        let last_move = match &self.last_move {
+            None => "none".to_string(),
+            Some((dir, result)) if !result.moved => format!("{dir:?}, blocked"),
+            Some((dir, result)) => format!(
+                "{dir:?}, +{} from {} merges",
+                result.score_delta,
+                result.merges.len()
+            ),
+        };
+        let board = headless::render(&self.board);
+        Some(
+            div()
+                .absolute()
+                .top_2()
+                .right_2()
+                .p_2()
+                .rounded_md()
+                .bg(rgb(self.theme.grid))
+                .text_color(rgb(self.theme.button_text))
+                .text_xs()
+                .font_family("monospace")
+                .flex()
+                .flex_col()
+                .children(board.lines().map(|line| div().child(line.to_string())))
+                .child(format!("seed {}", self.seed))
+                .child(format!("rng state {:016x}", self.rng.state()))
+                .child(format!("{} fps", self.frame_times.len()))
+                .child(format!("move {}: {last_move}", self.moves_made)),
+        )
+    }
+
+    // The timer, then each split: how far ahead of or behind the personal
+    // best it was, in gold where the segment was the fastest yet. Splits
+    // still to come show the personal best's times.
+    fn render_run(&self) -> Option<impl IntoElement> {
+        let run = self.run.as_ref()?;
+        let rows = SPLIT_TILES.iter().enumerate().map(|(i, tile)| {
+            let (time, delta) = match run.splits.get(i) {
+                Some(split) => {
+                    let color = match split.pace {
+                        Pace::Gold => 0xffc83d,
+                        Pace::Ahead => 0x5fc46a,
+                        Pace::Behind => 0xf0624d,
+                    };
+                    let delta = split.delta.map_or("★".to_string(), splits::format_delta);
+                    (
+                        splits::format_time(split.millis),
+                        Some(div().text_color(rgb(color)).child(delta)),
+                    )
+                }
+                None => (
+                    self.split_records
+                        .personal_best
+                        .get(i)
+                        .map_or("–".to_string(), |&millis| splits::format_time(millis)),
+                    None,
+                ),
+            };
+            div()
+                .flex()
+                .gap_3()
+                .child(div().flex_1().child(tile.to_string()))
+                .children(delta)
+                .child(
+                    div()
+                        .min_w(self.scaled(56.0))
+                        .flex()
+                        .justify_end()
+                        .child(time),
+                )
+        });
+        Some(
+            div()
+                .absolute()
+                .top_2()
+                .left_2()
+                .p_2()
+                .min_w(self.scaled(180.0))
+                .rounded_md()
+                .bg(rgb(self.theme.grid))
+                .text_color(rgb(self.theme.button_text))
+                .text_sm()
+                .flex()
+                .flex_col()
+                .gap_1()
+                .child(
+                    div()
+                        .text_xl()
+                        .font_weight(FontWeight::BOLD)
+                        .child(splits::format_time(run.elapsed(self.game_millis()))),
+                )
+                .children(rows),
+        )
+    }
+}
+
+impl Game {
+    // about the coach
+    // Rates the directions from the board on screen, unless that's been
+    // done or is being done already. It runs from render so that every
+    // way the board can change is caught.
+    fn update_coach(&mut self, cx: &mut Context<Self>) {
+        if !self.settings.coach || !self.is_started || self.editing || self.playback.is_some() {
+            self.coach = None;
+            self.coach_task = None;
+            return;
+        }
+        let board = &self.board;
+        if self.coach.as_ref().is_some_and(|(rated, _)| rated == board)
+            || self
+                .coach_task
+                .as_ref()
+                .is_some_and(|(rating, _)| rating == board)
+        {
+            return;
+        }
+        let board = self.board.clone();
+        let search = self.settings.solver();
+        let rated = board.clone();
+        let ranked = cx.background_spawn(async move { search.rank_moves(&rated) });
+        let task = cx.spawn(async move |this, cx| {
+            let ranked = ranked.await;
+            this.update(cx, |this, cx| {
+                this.coach = Some((board, ranked));
+                cx.notify();
+            })
+            .ok();
+        });
+        self.coach_task = Some((self.board.clone(), task));
+    }
+
+    // A bar along each edge for the direction that slides towards it, longer
+    // the better the AI rates it. The best is picked out in the accent.
+    fn render_coach(&self) -> Option<impl IntoElement> {
+        let (_, ranked) = self
+            .coach
+            .as_ref()
+            .filter(|(rated, _)| *rated == self.board)?;
+        let best = ranked
+            .iter()
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|&(dir, _)| dir);
+        let (rows, cols) = (self.board.rows(), self.board.cols());
+        let (tile, gap) = tile_metrics(rows, cols, self.scale());
+        let scale = self.scale();
+        let thickness = self.scaled(COACH_BAR);
+        let bars = analysis::bar_lengths(ranked)
+            .into_iter()
+            .map(|(dir, length)| {
+                let color = match Some(dir) == best {
+                    true => rgb(self.theme.accent),
+                    false => rgb(self.theme.text),
+                };
+                let bar = div().absolute().rounded_sm().bg(color).opacity(0.8);
+                // In the grid's padding, centred along the tiles' edge: (length,
+                // offset from the grid's corner).
+                let place = |cells: usize| {
+                    let span = (tile + gap) * cells as f32 - gap;
+                    (
+                        px(span * length),
+                        px(18.0 * scale + span * (1.0 - length) / 2.0),
+                    )
+                };
+                let edge = self.scaled(4.0);
+                match dir {
+                    Direction::Up | Direction::Down => {
+                        let (width, left) = place(cols);
+                        let bar = bar.w(width).h(thickness).left(left);
+                        match dir {
+                            Direction::Up => bar.top(edge),
+                            _ => bar.bottom(edge),
+                        }
+                    }
+                    Direction::Left | Direction::Right => {
+                        let (height, top) = place(rows);
+                        let bar = bar.h(height).w(thickness).top(top);
+                        match dir {
+                            Direction::Left => bar.left(edge),
+                            _ => bar.right(edge),
+                        }
+                    }
+                }
+            });
+        Some(div().absolute().inset_0().children(bars))
+    }
+}
+
+impl Game {
+    // about the AI hint
+    fn clear_hint(&mut self) {
+        self.hint = None;
+        self.hint_task = None;
+    }
+
+    // Searches on the background executor; the result is dropped if the
+    // board changed in the meantime.
+    fn request_hint(&mut self, cx: &mut Context<Self>) {
+        if !self.is_started || self.is_won || self.hint_task.is_some() {
+            return;
+        }
+        let board = self.board.clone();
+        let search = self.settings.solver();
+        let allowed = self.allowed_moves();
+        let search = cx.background_spawn(async move { search.best_move_among(&board, &allowed) });
+        let move_count = self.move_count;
+        self.hint_task = Some(cx.spawn(async move |this, cx| {
+            let hint = search.await;
+            this.update(cx, |this, cx| {
+                if this.move_count == move_count {
+                    this.hint = hint;
+                    cx.notify();
+                }
+            })
+            .ok();
+        }));
+    }
+
+    fn toggle_autoplay(&mut self, cx: &mut Context<Self>) {
+        if self.autoplay_task.take().is_some() || !self.is_started || self.editing {
+            cx.notify();
+            return;
+        }
+        self.autoplay_task = Some(cx.spawn(async move |this, cx| {
+            loop {
+                let Ok((board, allowed, search, delay)) = this.update(cx, |this, _| {
+                    let rate = this.settings.autoplay_speed.max(0.1);
+                    (
+                        this.board.clone(),
+                        this.allowed_moves(),
+                        this.settings.solver(),
+                        Duration::from_secs_f32(1.0 / rate),
+                    )
+                }) else {
+                    return;
+                };
+                cx.background_executor().timer(delay).await;
+                let dir = cx
+                    .background_executor()
+                    .spawn(async move { search.best_move_among(&board, &allowed) })
+                    .await;
+                let playing = this
+                    .update(cx, |this, cx| {
+                        if let Some(dir) = dir {
+                            this.handle_move(dir, cx);
+                        }
+                        dir.is_some() && this.is_started && !this.is_won
+                    })
+                    .unwrap_or(false);
+                if !playing {
+                    break;
+                }
+            }
+            this.update(cx, |this, cx| {
+                this.autoplay_task = None;
+                cx.notify();
+            })
+            .ok();
+        }));
+        cx.notify();
+    }
+
+    fn autoplay_keyboard(&mut self, _: &Autoplay, _window: &mut Window, cx: &mut Context<Self>) {
+        self.toggle_autoplay(cx);
+    }
+
+    fn autoplay_mouse(&mut self, _: &MouseDownEvent, _window: &mut Window, cx: &mut Context<Self>) {
+        self.toggle_autoplay(cx);
+    }
+
+    fn hint_keyboard(&mut self, _: &Hint, _window: &mut Window, cx: &mut Context<Self>) {
+        self.request_hint(cx);
+    }
+
+    fn hint_mouse(&mut self, _: &MouseDownEvent, _window: &mut Window, cx: &mut Context<Self>) {
+        self.request_hint(cx);
+    }
+}
+
+impl Game {
+    // about replay playback
+    fn open_replay(&mut self, _: &OpenReplay, _window: &mut Window, cx: &mut Context<Self>) {
+        cx.emit(GameEvent::OpenReplay);
+    }
+
+    pub(crate) fn start_playback(&mut self, replay: Replay, cx: &mut Context<Self>) {
+        self.autoplay_task = None;
+        match replay.verify() {
+            Ok(score) => self.toast(format!("Verified: scores {score}"), cx),
+            Err(mismatch) => self.toast(format!("Not verified: {mismatch}"), cx),
+        }
+        self.playback = Some(Playback {
+            positions: replay.positions(),
+            _analysis: self.find_blunders(replay.clone(), cx),
+            replay,
+            index: 0,
+            speed: 1.0,
+            task: None,
+            blunders: None,
+        });
+        self.show_position(0, false);
+        cx.notify();
+    }
+
+    // Has the AI go over the replay in the background, marking its blunders
+    // on the scrubber once it is done.
+    fn find_blunders(&self, replay: Replay, cx: &mut Context<Self>) -> Task<()> {
+        let search = Expectimax {
+            depth: analysis::ANALYSIS_DEPTH,
+            time_budget: None,
+            ..self.settings.expectimax()
+        };
+        let blunders = cx.background_spawn(async move { analysis::blunders(&replay, &search) });
+        cx.spawn(async move |this, cx| {
+            let blunders = blunders.await;
+            this.update(cx, |this, cx| {
+                if let Some(playback) = &mut this.playback {
+                    playback.blunders = Some(blunders);
+                    cx.notify();
+                }
+            })
+            .ok();
+        })
+    }
+
+    fn seek_blunder(&mut self, cx: &mut Context<Self>) {
+        let Some(playback) = &self.playback else {
+            return;
+        };
+        // Past the last one it wraps round to the first.
+        let blunders = playback.blunders.as_deref().unwrap_or_default();
+        let next = blunders
+            .iter()
+            .find(|blunder| blunder.index > playback.index)
+            .or(blunders.first());
+        if let Some(blunder) = next {
+            self.seek_playback(blunder.index, cx);
+        }
+    }
+
+    // Puts a recorded position on the board, animating it when it is the
+    // move right after the one shown.
+    fn show_position(&mut self, index: usize, animate: bool) {
+        let Some(playback) = &mut self.playback else {
+            return;
+        };
+        let index = index.min(playback.last_index());
+        let step = (animate && index == playback.index + 1).then(|| {
+            (
+                playback.positions[playback.index].0.clone(),
+                playback.replay.moves[playback.index].clone(),
+            )
+        });
+        playback.index = index;
+        let (board, score) = playback.positions[index].clone();
+
+        self.board = board;
+        self.score = score;
+        self.is_started = false;
+        self.is_won = false;
+        self.is_game_over = false;
+        self.clear_hint();
+        self.new_tiles.clear();
+        self.moves.clear();
+        self.merged_tiles.clear();
+        self.explosions.clear();
+        if let Some((mut before, step)) = step {
+            let result = before.apply_move(step.direction);
+            self.moves = result.moves;
+            self.merged_tiles = result.merges.iter().map(|m| m.index).collect();
+            self.explosions = result.explosions;
+            self.move_count += 1;
+            for spawn in step.spawns {
+                self.spawn_count += 1;
+                self.new_tiles.push(spawn.index);
+            }
+        }
+    }
+
+    fn seek_playback(&mut self, index: usize, cx: &mut Context<Self>) {
+        let Some(playback) = &mut self.playback else {
+            return;
+        };
+        playback.task = None;
+        let animate = index == playback.index + 1;
+        self.show_position(index, animate);
+        cx.notify();
+    }
+
+    fn step_playback(&mut self, forward: bool, cx: &mut Context<Self>) {
+        let Some(playback) = &self.playback else {
+            return;
+        };
+        let index = match forward {
+            true => playback.index + 1,
+            false => playback.index.saturating_sub(1),
+        };
+        self.seek_playback(index, cx);
+    }
+
+    fn toggle_playback(&mut self, cx: &mut Context<Self>) {
+        let Some(playback) = &mut self.playback else {
+            return;
+        };
+        if playback.task.take().is_some() {
+            cx.notify();
+            return;
+        }
+        if playback.index == playback.last_index() {
+            self.show_position(0, false);
+        }
+        let task = cx.spawn(async move |this, cx| {
+            loop {
+                let Ok(Some(delay)) = this.update(cx, |this, _| {
+                    this.playback.as_ref().and_then(Playback::next_delay)
+                }) else {
+                    break;
+                };
+                cx.background_executor().timer(delay).await;
+                let playing = this
+                    .update(cx, |this, cx| {
+                        let next = this.playback.as_ref().map_or(0, |p| p.index + 1);
+                        this.show_position(next, true);
+                        cx.notify();
+                        this.playback
+                            .as_ref()
+                            .is_some_and(|p| p.index < p.last_index())
+                    })
+                    .unwrap_or(false);
+                if !playing {
+                    break;
+                }
+            }
+            this.update(cx, |this, cx| {
+                if let Some(playback) = &mut this.playback {
+                    playback.task = None;
+                }
+                cx.notify();
+            })
+            .ok();
+        });
+        if let Some(playback) = &mut self.playback {
+            playback.task = Some(task);
+        }
+        cx.notify();
+    }
+
+    fn set_playback_speed(&mut self, speed: f32, cx: &mut Context<Self>) {
+        if let Some(playback) = &mut self.playback {
+            playback.speed = speed;
+            cx.notify();
+        }
+    }
+
+    fn exit_playback(&mut self, cx: &mut Context<Self>) {
+        if let Some(playback) = &mut self.playback {
+            playback.task = None;
+        }
+        cx.emit(GameEvent::CloseReplay);
+    }
+
+    fn render_playback_controls(
+        &self,
+        playback: &Playback,
+        cx: &mut Context<Self>,
+    ) -> impl IntoElement {
+        let last = playback.last_index();
+        let scrubber = (0..SCRUB_SEGMENTS).map(|i| {
+            let target = i * last / (SCRUB_SEGMENTS - 1);
+            div()
+                .id(("scrub", i))
+                .flex_1()
+                .h_full()
+                .bg(if target <= playback.index {
+                    rgb(self.theme.accent)
+                } else {
+                    rgb(self.theme.empty_cell)
+                })
+                .on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(move |this, _, _, cx| this.seek_playback(target, cx)),
+                )
+        });
+        let speeds = PLAYBACK_SPEEDS.iter().map(|&(speed, label)| {
+            let chip = self.render_button(label, label).on_mouse_down(
+                MouseButton::Left,
+                cx.listener(move |this, _, _, cx| this.set_playback_speed(speed, cx)),
+            );
+            if playback.speed == speed {
+                chip.bg(rgb(self.theme.accent))
+            } else {
+                chip
+            }
+        });
+
+        // A tick over the scrubber for every blunder, at the position
+        // before it was made.
+        let blunders = playback.blunders.as_deref().unwrap_or_default();
+        let markers = blunders.iter().map(|blunder| {
+            let index = blunder.index;
+            div()
+                .id(("blunder", index))
+                .absolute()
+                .top_0()
+                .left(relative(index as f32 / last.max(1) as f32))
+                .w(self.scaled(3.0))
+                .h_full()
+                .bg(rgb(BLUNDER_COLOR))
+                .on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(move |this, _, _, cx| this.seek_playback(index, cx)),
+                )
+        });
+        let note = match &playback.blunders {
+            None => "The AI is going over the game...".to_string(),
+            Some(blunders) => match blunders.iter().find(|b| b.index == playback.index) {
+                Some(blunder) => format!(
+                    "Blunder: {} gave up {:.0}% of the position; {} was best",
+                    arrow(blunder.made),
+                    blunder.loss * 100.0,
+                    arrow(blunder.best)
+                ),
+                None if blunders.len() == 1 => "1 blunder".to_string(),
+                None => format!("{} blunders", blunders.len()),
+            },
+        };
+
+        div()
+            .flex()
+            .flex_col()
+            .w(self.scaled(420.0))
+            .mt_4()
+            .gap_2()
+            .child(
+                div()
+                    .relative()
+                    .child(
+                        div()
+                            .flex()
+                            .h(self.scaled(10.0))
+                            .gap(px(1.0))
+                            .rounded_md()
+                            .overflow_hidden()
+                            .children(scrubber),
+                    )
+                    .children(markers),
+            )
+            .child(
+                div()
+                    .flex()
+                    .justify_between()
+                    .items_center()
+                    .text_sm()
+                    .text_color(self.text_color())
+                    .child(note)
+                    .children((!blunders.is_empty()).then(|| {
+                        self.render_button("next-blunder", "Next blunder")
+                            .on_mouse_down(
+                                MouseButton::Left,
+                                cx.listener(|this, _, _, cx| this.seek_blunder(cx)),
+                            )
+                    })),
+            )
+            .child(
+                div()
+                    .flex()
+                    .justify_between()
+                    .items_center()
+                    .child(
+                        div()
+                            .text_sm()
+                            .text_color(self.text_color())
+                            .child(format!("Move {} / {}", playback.index, last)),
+                    )
+                    .child(div().flex().gap_1().children(speeds)),
+            )
+            .child(
+                div()
+                    .flex()
+                    .gap_2()
+                    .justify_center()
+                    .child(self.render_button("playback-start", "⏮").on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|this, _, _, cx| this.seek_playback(0, cx)),
+                    ))
+                    .child(self.render_button("playback-back", "◀").on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|this, _, _, cx| this.step_playback(false, cx)),
+                    ))
+                    .child(
+                        self.render_button(
+                            "playback-toggle",
+                            if playback.task.is_some() {
+                                "Pause"
+                            } else {
+                                "Play"
+                            },
+                        )
+                        .on_mouse_down(
+                            MouseButton::Left,
+                            cx.listener(|this, _, _, cx| this.toggle_playback(cx)),
+                        ),
+                    )
+                    .child(self.render_button("playback-forward", "▶").on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|this, _, _, cx| this.step_playback(true, cx)),
+                    ))
+                    .child(self.render_button("playback-exit", "Exit").on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|this, _, _, cx| this.exit_playback(cx)),
+                    )),
+            )
+    }
+}
+
+impl Game {
+    // about undo/redo history
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            board: self.board.clone(),
+            score: self.score,
+            rng: self.rng.clone(),
+        }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.board = snapshot.board;
+        self.score = snapshot.score;
+        self.rng = snapshot.rng;
+        self.new_tiles.clear();
+        self.clear_hint();
+        self.moves.clear();
+        self.merged_tiles.clear();
+        self.explosions.clear();
+        self.is_started = true;
+        self.is_won = !self.keep_playing && self.board.has_won();
+        self.is_game_over = !self.is_won && self.board.is_game_over();
+    }
+
+    fn push_history(&mut self, snapshot: Snapshot) {
+        if self.config.history_depth == 0 {
+            return;
+        }
+        if self.undo_stack.len() == self.config.history_depth {
+            self.undo_stack.pop_front();
+        }
+        self.undo_stack.push_back(snapshot);
+        self.redo_stack.clear();
+        self.undone_moves.clear();
+    }
+
+    fn undo(&mut self, _: &Undo, _window: &mut Window, cx: &mut Context<Self>) {
+        if self.playback.is_some() || self.paused.is_some() || !self.allows_undo() {
+            return;
+        }
+        if let Some(snapshot) = self.undo_stack.pop_back() {
+            self.redo_stack.push(self.snapshot());
+            self.undone_moves.extend(self.replay.moves.pop());
+            self.restore(snapshot);
+            self.autosave();
+            cx.notify();
+        }
+    }
+
+    fn redo(&mut self, _: &Redo, _window: &mut Window, cx: &mut Context<Self>) {
+        if self.playback.is_some() || self.paused.is_some() || !self.allows_undo() {
+            return;
+        }
+        if let Some(snapshot) = self.redo_stack.pop() {
+            self.undo_stack.push_back(self.snapshot());
+            self.replay.moves.extend(self.undone_moves.pop());
+            self.restore(snapshot);
+            self.autosave();
+            cx.notify();
+        }
+    }
+}
+
+impl Game {
+    // about actions for keyboard and mouse
+    // The handler for one direction's move action. All four come down to
+    // the same press, so only the action type differs between them.
+    fn move_action<A: Action>(
+        dir: Direction,
+    ) -> impl Fn(&mut Self, &A, &mut Window, &mut Context<Self>) + 'static {
+        move |this, _, _, cx| this.press_move(dir, cx)
+    }
+
+    // A move key going down. With hold-to-repeat on, the game repeats the
+    // move on its own timer until the key comes up, and the system's key
+    // repeat is ignored.
+    fn press_move(&mut self, dir: Direction, cx: &mut Context<Self>) {
+        if self.repeat.as_ref().is_some_and(|(held, _)| *held == dir) {
+            return;
+        }
+        self.repeat = None;
+        self.handle_move(dir, cx);
+        if !self.settings.key_repeat {
+            return;
+        }
+        let delay = Duration::from_millis(self.settings.repeat_delay_ms);
+        let interval = Duration::from_millis(self.settings.repeat_interval_ms.max(MIN_REPEAT_MS));
+        let task = cx.spawn(async move |this, cx| {
+            cx.background_executor().timer(delay).await;
+            loop {
+                if this
+                    .update(cx, |this, cx| this.handle_move(dir, cx))
+                    .is_err()
+                {
+                    break;
+                }
+                cx.background_executor().timer(interval).await;
+            }
+        });
+        self.repeat = Some((dir, task));
+    }
+
+    fn key_up(&mut self, event: &KeyUpEvent, _window: &mut Window, _cx: &mut Context<Self>) {
+        let Some(&(dir, _)) = self.repeat.as_ref() else {
+            return;
+        };
+        let action = match dir {
+            Direction::Up => KeyAction::Up,
+            Direction::Down => KeyAction::Down,
+            Direction::Left => KeyAction::Left,
+            Direction::Right => KeyAction::Right,
+        };
+        // Modifiers may be let go first, so only the key itself counts.
+        let released = self
+            .settings
+            .keybindings
+            .keys(action)
+            .iter()
+            .any(|k| k.rsplit('-').next() == Some(event.keystroke.key.as_str()));
+        if released {
+            self.repeat = None;
+        }
+    }
+
+    fn new_game_mouse(
+        &mut self,
+        _: &MouseDownEvent,
+        _window: &mut Window,
+        _cx: &mut Context<Self>,
+    ) {
+        self.request_new_game(_window, _cx);
+    }
+
+    fn new_game_keyboard(&mut self, _: &Enter, _window: &mut Window, _cx: &mut Context<Self>) {
+        self.request_new_game(_window, _cx);
+    }
+
+    // Starts over, once the player agrees if there is a game to lose.
+    fn request_new_game(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        match self.has_run_at_stake() {
+            true => cx.emit(GameEvent::ConfirmNewGame),
+            false => self.new_game(window, cx),
+        }
+    }
+
+    /// Whether starting over would throw away a game worth keeping: one in
+    /// progress, or a saved one, with points on the board.
+    pub(crate) fn has_run_at_stake(&self) -> bool {
+        match self.is_started {
+            true => self.score > 0 && !self.is_won,
+            false => self
+                .saved_game
+                .as_ref()
+                .is_some_and(|saved| saved.score > 0),
+        }
+    }
+
+    pub(crate) fn continue_playing(&mut self, cx: &mut Context<Self>) {
+        if !self.is_won {
+            return;
+        }
+        self.is_won = false;
+        self.keep_playing = true;
+        if self.config.mode.settle(&mut self.board) {
+            self.end_game(cx);
+        }
+        self.autosave();
+        cx.notify();
+    }
+
+    fn resume_game_mouse(
+        &mut self,
+        _: &MouseDownEvent,
+        _window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.resume_game(cx);
+    }
+
+    fn keep_playing_keyboard(
+        &mut self,
+        _: &KeepPlaying,
+        _window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.continue_playing(cx);
+    }
+}
+
+impl EventEmitter<GameEvent> for Game {}
+
+impl Game {
+    // about ghost races
+    pub(crate) fn race_ghost(
+        &mut self,
+        replay: Replay,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let (rows, cols) = (replay.start.rows(), replay.start.cols());
+        self.config = GameConfig {
+            mode: GameMode::Classic,
+            rows,
+            cols,
+            shape: BoardShape::ALL
+                .into_iter()
+                .find(|shape| shape.holes(rows, cols) == replay.start.holes())
+                .unwrap_or_default(),
+            spawn: replay.spawn,
+            seed: Some(replay.seed),
+            ..self.config
+        };
+        self.ghost = Some(Ghost {
+            positions: replay.positions(),
+            millis: replay.moves.iter().map(|m| m.millis).collect(),
+            shown: 0,
+        });
+        self.new_game(window, cx);
+    }
+
+    fn start_ghost(&mut self, cx: &mut Context<Self>) {
+        self.ghost_task = None;
+        if !self.tick_ghost(cx) {
+            return;
+        }
+        self.ghost_task = Some(cx.spawn(async move |this, cx| {
+            loop {
+                cx.background_executor().timer(GHOST_TICK).await;
+                let racing = this
+                    .update(cx, |this, cx| this.tick_ghost(cx))
+                    .unwrap_or(false);
+                if !racing {
+                    break;
+                }
+            }
+        }));
+    }
+
+    // Plays back the ghost's moves made by now. Returns false once it has
+    // made them all.
+    fn tick_ghost(&mut self, cx: &mut Context<Self>) -> bool {
+        let elapsed = self.started_at.elapsed().as_millis() as u64;
+        let Some(ghost) = &mut self.ghost else {
+            return false;
+        };
+        let shown = ghost.millis.partition_point(|&ms| ms <= elapsed);
+        if shown != ghost.shown {
+            ghost.shown = shown;
+            cx.notify();
+        }
+        shown < ghost.millis.len()
+    }
+
+    fn render_ghost(&self, ghost: &Ghost) -> impl IntoElement {
+        let (board, score) = &ghost.positions[ghost.shown];
+        let (rows, cols) = (board.rows(), board.cols());
+        let lead = match self.score.cmp(score) {
+            Ordering::Greater => format!("You lead by {}", self.score - score),
+            Ordering::Less => format!("The ghost leads by {}", score - self.score),
+            Ordering::Equal => "Neck and neck".to_string(),
+        };
+        div()
+            .flex()
+            .items_center()
+            .gap_4()
+            .mt_4()
+            .child(
+                div()
+                    .bg(rgb(self.theme.grid))
+                    .p(self.scaled(2.0))
+                    .rounded_md()
+                    .flex()
+                    .flex_col()
+                    .gap(self.scaled(2.0))
+                    .children((0..rows).map(|row| {
+                        div().flex().gap(self.scaled(2.0)).children(
+                            (0..cols).map(|col| self.render_ghost_cell(board, row * cols + col)),
+                        )
+                    })),
+            )
+            .child(
+                div()
+                    .flex()
+                    .flex_col()
+                    .text_sm()
+                    .text_color(self.text_color())
+                    .child(div().font_weight(FontWeight::BOLD).child("GHOST"))
+                    .child(format!("Score {score} · best tile {}", board.max_tile()))
+                    .child(format!("Move {} of {}", ghost.shown, ghost.millis.len()))
+                    .child(lead),
+            )
+    }
+
+    fn render_ghost_cell(&self, board: &Board, idx: usize) -> Div {
+        let cell = div().size(self.scaled(GHOST_TILE)).rounded_sm();
+        if board.is_hole(idx) {
+            return cell;
+        }
+        if board.is_obstacle(idx) {
+            return cell.bg(rgb(self.theme.obstacle));
+        }
+        match board.cell(idx) {
+            Cell::Number(value) => cell.bg(self.tile_color(board.rule().rank(value))),
+            Cell::Special(special) => cell.bg(special_color(special)),
+            Cell::Empty => cell.bg(rgb(self.theme.empty_cell)),
+        }
+    }
+}
+
+impl Focusable for Game {
+    fn focus_handle(&self, _: &App) -> FocusHandle {
+        self.focus_handle.clone()
+    }
+}
+
+impl Game {
+    // about sound
+    fn play(&self, sound: Sound) {
+        if self.settings.sound
+            && let Some(audio) = &self.audio
+        {
+            audio.play(sound, self.settings.volumes.get(sound));
+        }
+    }
+
+    /// How far the game has got towards its winning tile, which the music
+    /// follows.
+    pub(crate) fn intensity(&self) -> f32 {
+        let rule = self.board.rule();
+        audio::intensity(rule.rank(self.board.max_tile()), rule.rank(rule.win_tile()))
+    }
+
+    fn toggle_mute(&mut self, _: &MouseDownEvent, _window: &mut Window, cx: &mut Context<Self>) {
+        self.settings.sound = !self.settings.sound;
+        let saved = settings::save(&self.settings);
+        self.check_saved("save settings", saved);
+        cx.notify();
+    }
+}
+
+impl Render for Game {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        self.update_coach(cx);
+        if self.debug_overlay {
+            let now = Instant::now();
+            self.frame_times.push_back(now);
+            while self
+                .frame_times
+                .front()
+                .is_some_and(|&drawn| now - drawn > Duration::from_secs(1))
+            {
+                self.frame_times.pop_front();
+            }
+            // Drawing without pause, so the count is what the window can
+            // manage rather than how often something changed.
+            window.request_animation_frame();
+        }
+        let best_label = match self.config.spawn.difficulty() {
+            Difficulty::Normal => "BEST",
+            Difficulty::Hard => "HARD BEST",
+        };
+        let root = div()
+            .flex()
+            .flex_col()
+            .size_full()
+            .justify_center()
+            .items_center()
+            .bg(self.background())
+            .track_focus(&self.focus_handle)
+            .on_action(cx.listener(Self::move_action::<Up>(Direction::Up)))
+            .on_action(cx.listener(Self::move_action::<Down>(Direction::Down)))
+            .on_action(cx.listener(Self::move_action::<Left>(Direction::Left)))
+            .on_action(cx.listener(Self::move_action::<Right>(Direction::Right)))
+            .on_action(cx.listener(Self::new_game_keyboard))
+            .on_action(cx.listener(Self::undo))
+            .on_action(cx.listener(Self::redo))
+            .on_action(cx.listener(Self::keep_playing_keyboard))
+            .on_action(cx.listener(Self::hint_keyboard))
+            .on_action(cx.listener(Self::autoplay_keyboard))
+            .on_action(cx.listener(Self::export_replay))
+            .on_action(cx.listener(Self::open_replay))
+            .on_action(cx.listener(Self::toggle_pause))
+            .on_action(cx.listener(Self::toggle_mini_mode))
+            .on_action(cx.listener(Self::toggle_debug_overlay))
+            .on_action(cx.listener(Self::copy_board))
+            .on_action(cx.listener(Self::paste_board))
+            .on_action(cx.listener(Self::export_text))
+            .on_action(cx.listener(Self::save_screenshot))
+            .on_key_up(cx.listener(Self::key_up));
+        if self.mini {
+            return root
+                .child(self.render_mini_bar(cx))
+                .child(self.render_board(cx))
+                .child(self.toasts.clone())
+                .into_any_element();
+        }
+        root.child(
+            div()
+                .flex()
+                .w(self.scaled(420.0))
+                .justify_between()
+                .items_end()
+                .mb_4()
+                .child(
+                    div()
+                        .flex()
+                        .flex_col()
+                        .text_color(self.text_color())
+                        .child(div().text_3xl().font_weight(FontWeight::BOLD).child("2048"))
+                        .children(
+                            self.daily
+                                .map(|date| div().text_sm().child(format!("Daily {date}"))),
+                        )
+                        .children(self.challenge.as_ref().map(|active| {
+                            div().text_sm().child(format!(
+                                "{} · {}",
+                                active.challenge.name,
+                                active.challenge.goal()
+                            ))
+                        })),
+                )
+                .child(
+                    div()
+                        .flex()
+                        .gap_2()
+                        .children(self.render_clock())
+                        .child(match self.moves_left() {
+                            Some(left) => self.render_box("MOVES LEFT", left),
+                            None => self.render_box("MOVES", self.moves_made),
+                        })
+                        .child(self.render_box("UNDO", self.undo_stack.len()))
+                        .child(
+                            div()
+                                .relative()
+                                .child(self.render_box("SCORE", self.score))
+                                .children(self.render_score_gain()),
+                        )
+                        .child(self.render_box(best_label, self.best_score)),
+                ),
+        )
+        .child(
+            div()
+                .flex()
+                .w(self.scaled(420.0))
+                .justify_between()
+                .items_center()
+                .mb_4()
+                .child(
+                    div()
+                        .text_sm()
+                        .text_color(self.text_color())
+                        .children(if self.editing {
+                            Some("Click to raise a tile, right-click to lower it".to_string())
+                        } else {
+                            (self.is_started || self.is_game_over)
+                                .then(|| format!("Seed: {}", self.seed))
+                        }),
+                )
+                .child(
+                    div()
+                        .flex()
+                        .gap_2()
+                        .child(
+                            self.render_button("open-settings", "⚙")
+                                .on_mouse_down(MouseButton::Left, cx.listener(Self::open_settings)),
+                        )
+                        .child(
+                            self.render_button(
+                                "mute",
+                                match self.settings.sound {
+                                    true => "🔊",
+                                    false => "🔇",
+                                },
+                            )
+                            .on_mouse_down(MouseButton::Left, cx.listener(Self::toggle_mute)),
+                        )
+                        .child(self.render_button("mini-mode", "Mini").on_mouse_down(
+                            MouseButton::Left,
+                            cx.listener(Self::toggle_mini_mode_mouse),
+                        ))
+                        .child(
+                            self.render_button("open-menu", "☰")
+                                .on_mouse_down(MouseButton::Left, cx.listener(Self::open_menu)),
+                        )
+                        .child(
+                            self.render_button(
+                                "autoplay",
+                                if self.autoplay_task.is_some() {
+                                    "Stop"
+                                } else {
+                                    "Auto"
+                                },
+                            )
+                            .on_mouse_down(MouseButton::Left, cx.listener(Self::autoplay_mouse)),
+                        )
+                        .child(
+                            self.render_button("hint", "Hint")
+                                .on_mouse_down(MouseButton::Left, cx.listener(Self::hint_mouse)),
+                        )
+                        .children(self.editing.then(|| {
+                            self.render_button("clear-board", "Clear")
+                                .on_mouse_down(MouseButton::Left, cx.listener(Self::clear_board))
+                        }))
+                        .children((self.config.mode == GameMode::Sandbox).then(|| {
+                            self.render_button(
+                                "toggle-editing",
+                                if self.editing { "Play" } else { "Edit" },
+                            )
+                            .on_mouse_down(MouseButton::Left, cx.listener(Self::toggle_editing))
+                        }))
+                        .child(
+                            self.render_button("new-game", "New Game").on_mouse_down(
+                                MouseButton::Left,
+                                cx.listener(Self::new_game_mouse),
+                            ),
+                        ),
+                ),
+        )
+        .child(self.render_board(cx))
+        .children(
+            (self.settings.dpad && self.playback.is_none()).then(|| self.render_dpad(window, cx)),
+        )
+        .children(
+            (self.config.mode.has_power_ups() && self.playback.is_none())
+                .then(|| self.render_power_ups(cx)),
+        )
+        .children(
+            (self.config.mode.has_placer() && self.playback.is_none())
+                .then(|| self.render_turn(cx)),
+        )
+        .children(
+            self.ghost
+                .as_ref()
+                .filter(|_| self.playback.is_none())
+                .map(|ghost| self.render_ghost(ghost)),
+        )
+        .children(
+            self.playback
+                .as_ref()
+                .map(|playback| self.render_playback_controls(playback, cx)),
+        )
+        .children(self.render_run())
+        .children(self.render_debug_overlay())
+        .children(self.render_storage_error(cx))
+        .child(self.toasts.clone())
+        .into_any_element()
+    }
+}
+
+// Opens a seeded game in a test window with the default keys bound and the
+// game focused, as the screen leaves it. Anything it saves goes to a scratch
+// data directory instead of the player's.
+#[cfg(test)]
+fn open_test_game(cx: &mut TestAppContext) -> (Entity<Game>, &mut VisualTestContext) {
+    static DATA_DIR: std::sync::Once = std::sync::Once::new();
+    DATA_DIR.call_once(|| {
+        let dir = std::env::temp_dir().join(format!("game_2048-test-{}", std::process::id()));
+        // SAFETY: nothing else in the tests writes the environment, and this
+        // runs before the first test game reads it.
+        unsafe { std::env::set_var(persistence::DATA_DIR_VAR, dir) };
+    });
+    cx.update(|cx| bind_keys(&Keybindings::default(), cx));
+    let config = GameConfig {
+        seed: Some(7),
+        ..GameConfig::default()
+    };
+    cx.add_window_view(|window, cx| {
+        let game = Game::new(config, Settings::default(), cx);
+        window.focus(&game.focus_handle);
+        game
+    })
+}
+
+#[gpui::test]
+fn test_window_actions(cx: &mut TestAppContext) {
+    let (game, cx) = open_test_game(cx);
+    cx.dispatch_action(Enter);
+    let mut board = game.read_with(cx, |game, _| {
+        assert!(game.is_started());
+        game.board.clone()
+    });
+    assert_eq!(board.empty_cells().count(), 14);
+
+    // Each direction's action makes the move the engine would, then spawns.
+    for dir in Direction::ALL {
+        let mut slid = board.clone();
+        let moved = slid.apply_move(dir).moved;
+        let moves_made = game.read_with(cx, |game, _| game.moves_made);
+        match dir {
+            Direction::Up => cx.dispatch_action(Up),
+            Direction::Down => cx.dispatch_action(Down),
+            Direction::Left => cx.dispatch_action(Left),
+            Direction::Right => cx.dispatch_action(Right),
+        }
+        let (after, made) = game.read_with(cx, |game, _| (game.board.clone(), game.moves_made));
+        assert_eq!(made, moves_made + moved as u32);
+        if moved {
+            let spawned: Vec<usize> = (0..16)
+                .filter(|&idx| slid.cells()[idx] != after.cells()[idx])
+                .collect();
+            assert_eq!(spawned.len(), 1);
+            assert_eq!(slid.cells()[spawned[0]], 0);
+        } else {
+            assert_eq!(after, board);
+        }
+        board = after;
+    }
+}
+
+#[gpui::test]
+fn test_window_keys(cx: &mut TestAppContext) {
+    let (game, cx) = open_test_game(cx);
+    cx.simulate_keystrokes("enter");
+    game.update(cx, |game, _| {
+        game.board = Board::from_cells(4, 4, [vec![2, 2, 0, 0], vec![0; 12]].concat());
+    });
+    cx.simulate_keystrokes("a");
+    assert_eq!(game.read_with(cx, |game, _| game.score), 4);
+
+    // With focus elsewhere the keys don't reach the game.
+    cx.update(|window, _| window.blur());
+    cx.simulate_keystrokes("d right");
+    assert_eq!(game.read_with(cx, |game, _| game.moves_made), 1);
+    game.update_in(cx, |game, window, _| window.focus(&game.focus_handle));
+    cx.simulate_keystrokes("right");
+    assert_eq!(game.read_with(cx, |game, _| game.moves_made), 2);
+}
@@ -0,0 +1,294 @@
+use crate::engine::GameConfig;
+use crate::net::{Connection, DEFAULT_PORT, Host, Message};
+use crate::race::Race;
+use gpui::*;
+use std::time::Duration;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+pub enum LobbyEvent {
+    /// Both players are in; [`LobbyPanel::take_race`] hands over the race.
+    Joined,
+    Closed,
+}
+
+/// An online race both players have agreed on.
+pub struct OnlineRace {
+    pub connection: Connection,
+    pub race: Race,
+    /// The host picks the rules and calls rematches.
+    pub hosting: bool,
+}
+
+/// Sets up an online race: host one, or a battle, and wait for someone to
+/// join, or type in the host's address and join theirs.
+pub struct LobbyPanel {
+    focus_handle: FocusHandle,
+    config: GameConfig,
+    address: String,
+    status: SharedString,
+    race: Option<OnlineRace>,
+    task: Option<Task<()>>,
+}
+
+impl EventEmitter<LobbyEvent> for LobbyPanel {}
+
+impl LobbyPanel {
+    pub fn new(config: GameConfig, cx: &mut Context<Self>) -> LobbyPanel {
+        LobbyPanel {
+            focus_handle: cx.focus_handle(),
+            config,
+            address: String::new(),
+            status: "Host a race, or type the host's address and join it.".into(),
+            race: None,
+            task: None,
+        }
+    }
+
+    pub fn take_race(&mut self) -> Option<OnlineRace> {
+        self.race.take()
+    }
+
+    fn set_status(&mut self, status: impl Into<SharedString>, cx: &mut Context<Self>) {
+        self.status = status.into();
+        cx.notify();
+    }
+
+    fn host(&mut self, battle: bool, cx: &mut Context<Self>) {
+        let host = match Host::bind(DEFAULT_PORT) {
+            Ok(host) => host,
+            Err(e) => {
+                self.set_status(format!("Couldn't host: {e}"), cx);
+                return;
+            }
+        };
+        self.set_status(
+            format!("Waiting for an opponent on port {}...", host.port()),
+            cx,
+        );
+        self.task = Some(cx.spawn(async move |this, cx| {
+            loop {
+                cx.background_executor().timer(POLL_INTERVAL).await;
+                let accepted = host.accept();
+                let waiting = this
+                    .update(cx, |this, cx| this.accepted(accepted, battle, cx))
+                    .unwrap_or(false);
+                if !waiting {
+                    break;
+                }
+            }
+        }));
+    }
+
+    // Starts the race with a guest that has just turned up. Returns whether
+    // to keep waiting.
+    fn accepted(
+        &mut self,
+        accepted: std::io::Result<Option<Connection>>,
+        battle: bool,
+        cx: &mut Context<Self>,
+    ) -> bool {
+        let mut connection = match accepted {
+            Ok(Some(connection)) => connection,
+            Ok(None) => return true,
+            Err(e) => {
+                self.set_status(format!("Stopped hosting: {e}"), cx);
+                return false;
+            }
+        };
+        let race = match battle {
+            true => Race::battle(self.config, rand::random()),
+            false => Race::new(self.config, rand::random()),
+        };
+        if let Err(e) = connection.send(&Message::start(&race)) {
+            self.set_status(format!("Couldn't reach the guest: {e}"), cx);
+            return true;
+        }
+        self.race = Some(OnlineRace {
+            connection,
+            race,
+            hosting: true,
+        });
+        cx.emit(LobbyEvent::Joined);
+        false
+    }
+
+    fn join(&mut self, cx: &mut Context<Self>) {
+        let address = self.address.trim().to_string();
+        if address.is_empty() {
+            return;
+        }
+        self.set_status(format!("Connecting to {address}..."), cx);
+        self.task = Some(cx.spawn(async move |this, cx| {
+            let connected = cx
+                .background_executor()
+                .spawn(async move { Connection::connect(&address) })
+                .await;
+            let mut connection = match connected {
+                Ok(connection) => connection,
+                Err(e) => {
+                    this.update(cx, |this, cx| {
+                        this.set_status(format!("Couldn't connect: {e}"), cx)
+                    })
+                    .ok();
+                    return;
+                }
+            };
+            // The host sends the rules as soon as we're in.
+            loop {
+                let start = match connection.receive() {
+                    Ok(messages) => messages.iter().find_map(Message::race),
+                    Err(e) => {
+                        this.update(cx, |this, cx| {
+                            this.set_status(format!("The host went away: {e}"), cx)
+                        })
+                        .ok();
+                        return;
+                    }
+                };
+                if let Some(race) = start {
+                    this.update(cx, |this, cx| {
+                        this.race = Some(OnlineRace {
+                            connection,
+                            race,
+                            hosting: false,
+                        });
+                        cx.emit(LobbyEvent::Joined);
+                    })
+                    .ok();
+                    return;
+                }
+                cx.background_executor().timer(POLL_INTERVAL).await;
+            }
+        }));
+    }
+
+    fn key_down(&mut self, event: &KeyDownEvent, _window: &mut Window, cx: &mut Context<Self>) {
+        match event.keystroke.key.as_str() {
+            "backspace" => {
+                self.address.pop();
+            }
+            "enter" => self.join(cx),
+            "escape" => cx.emit(LobbyEvent::Closed),
+            _ => {
+                let typed = event.keystroke.key_char.as_deref().unwrap_or_default();
+                let allowed = |c: char| c.is_ascii_alphanumeric() || ".:-[]".contains(c);
+                if typed.is_empty() || !typed.chars().all(allowed) {
+                    return;
+                }
+                self.address.push_str(typed);
+            }
+        }
+        cx.notify();
+    }
+
+    fn render_button(
+        &self,
+        id: &'static str,
+        label: &'static str,
+        cx: &mut Context<Self>,
+        on_click: impl Fn(&mut Self, &mut Context<Self>) + 'static,
+    ) -> impl IntoElement {
+        div()
+            .id(id)
+            .px_4()
+            .py_2()
+            .bg(rgb(0x8f7a66))
+            .text_color(rgb(0xf9f6f2))
+            .rounded_md()
+            .font_weight(FontWeight::BOLD)
+            .child(label)
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(move |this, _: &MouseDownEvent, _window, cx| on_click(this, cx)),
+            )
+    }
+}
+
+impl Focusable for LobbyPanel {
+    fn focus_handle(&self, _: &App) -> FocusHandle {
+        self.focus_handle.clone()
+    }
+}
+
+impl Render for LobbyPanel {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let address = match self.address.is_empty() {
+            true => div().text_color(rgb(0xbbada0)).child("host:port"),
+            false => div()
+                .text_color(rgb(0x776e65))
+                .child(format!("{}|", self.address)),
+        };
+
+        div()
+            .size_full()
+            .track_focus(&self.focus_handle)
+            .on_key_down(cx.listener(Self::key_down))
+            .flex()
+            .justify_center()
+            .items_center()
+            .child(
+                div()
+                    .w(rems(23.75))
+                    .p_4()
+                    .rounded_lg()
+                    .bg(rgb(0xfaf8ef))
+                    .flex()
+                    .flex_col()
+                    .gap_3()
+                    .child(
+                        div()
+                            .text_2xl()
+                            .font_weight(FontWeight::BOLD)
+                            .text_color(rgb(0x776e65))
+                            .child("Play Online"),
+                    )
+                    .child(
+                        div()
+                            .flex()
+                            .gap_2()
+                            .child(self.render_button(
+                                "host-race",
+                                "Host a Race",
+                                cx,
+                                |this, cx| this.host(false, cx),
+                            ))
+                            .child(self.render_button(
+                                "host-battle",
+                                "Host a Battle",
+                                cx,
+                                |this, cx| this.host(true, cx),
+                            )),
+                    )
+                    .child(
+                        div()
+                            .flex()
+                            .gap_2()
+                            .items_center()
+                            .child(
+                                div()
+                                    .flex_1()
+                                    .px_3()
+                                    .py_2()
+                                    .rounded_md()
+                                    .bg(rgb(0xeee4da))
+                                    .font_weight(FontWeight::BOLD)
+                                    .child(address),
+                            )
+                            .child(self.render_button("join-race", "Join", cx, Self::join)),
+                    )
+                    .child(
+                        div()
+                            .text_sm()
+                            .text_color(rgb(0x776e65))
+                            .child(self.status.clone()),
+                    )
+                    .child(div().flex().justify_end().child(self.render_button(
+                        "close-lobby",
+                        "Back",
+                        cx,
+                        |_, cx| cx.emit(LobbyEvent::Closed),
+                    ))),
+            )
+    }
+}
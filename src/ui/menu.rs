@@ -0,0 +1,288 @@
+use crate::engine::{GameMode, MAX_SIZE, MIN_SIZE, MOVE_BUDGETS};
+use crate::persistence;
+use crate::ui::challenge_panel::ChallengeSource;
+use crate::ui::game::{Down, Enter, Left, Right, Up};
+use crate::ui::race_panel::Opponent;
+use gpui::*;
+
+pub enum MenuEvent {
+    Continue,
+    /// Start a fresh game; `size` overrides the board size from the settings.
+    Start {
+        mode: GameMode,
+        size: Option<usize>,
+        move_budget: u32,
+    },
+    OpenChallenges(ChallengeSource),
+    OpenRace(Opponent),
+    OpenLobby,
+    OpenReplay,
+    ExportGame,
+    ImportGame,
+    OpenStats,
+    OpenSettings,
+    OpenProfiles,
+    /// Race a ghost of the best game kept so far.
+    RaceGhost,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Entry {
+    Continue,
+    Mode(GameMode),
+    Custom,
+    Race(Opponent),
+    Ghost,
+    Practice,
+    Challenges,
+    Replay,
+    Export,
+    Import,
+    Stats,
+    Settings,
+    Profile,
+}
+
+/// The screen the window opens on: pick a mode, or head elsewhere. Arrow
+/// keys move the selection and change the custom board size or the move
+/// budget, Enter picks.
+pub struct MainMenu {
+    focus_handle: FocusHandle,
+    selected: usize,
+    custom_size: usize,
+    // Index into `MOVE_BUDGETS`.
+    budget: usize,
+    can_continue: bool,
+    has_ghost: bool,
+    profile: String,
+}
+
+impl EventEmitter<MenuEvent> for MainMenu {}
+
+impl MainMenu {
+    pub fn new(cx: &mut Context<Self>) -> MainMenu {
+        MainMenu {
+            focus_handle: cx.focus_handle(),
+            selected: 0,
+            custom_size: 5,
+            budget: 1,
+            can_continue: false,
+            has_ghost: persistence::has_best_game(),
+            profile: persistence::active_profile(),
+        }
+    }
+
+    pub fn set_profile(&mut self, profile: String, cx: &mut Context<Self>) {
+        self.profile = profile;
+        cx.notify();
+    }
+
+    /// Offers "Continue" while there is a game to go back to.
+    pub fn set_can_continue(&mut self, can_continue: bool, cx: &mut Context<Self>) {
+        if self.can_continue != can_continue {
+            self.can_continue = can_continue;
+            self.selected = 0;
+            cx.notify();
+        }
+    }
+
+    /// Offers "Race Your Best" once a game has been kept to race.
+    pub fn set_has_ghost(&mut self, has_ghost: bool, cx: &mut Context<Self>) {
+        if self.has_ghost != has_ghost {
+            self.has_ghost = has_ghost;
+            self.selected = 0;
+            cx.notify();
+        }
+    }
+
+    fn entries(&self) -> Vec<Entry> {
+        let mut entries = Vec::new();
+        if self.can_continue {
+            entries.push(Entry::Continue);
+        }
+        entries.extend(GameMode::ALL.map(Entry::Mode));
+        entries.extend([
+            Entry::Custom,
+            Entry::Race(Opponent::Human),
+            Entry::Race(Opponent::Ai),
+            Entry::Race(Opponent::Remote),
+        ]);
+        if self.has_ghost {
+            entries.push(Entry::Ghost);
+        }
+        entries.extend([Entry::Practice, Entry::Challenges, Entry::Replay]);
+        if self.can_continue {
+            entries.push(Entry::Export);
+        }
+        entries.extend([Entry::Import, Entry::Stats, Entry::Settings, Entry::Profile]);
+        entries
+    }
+
+    fn label(&self, entry: Entry) -> SharedString {
+        match entry {
+            Entry::Continue => "Continue".into(),
+            Entry::Mode(GameMode::LimitedMoves) => {
+                format!("◀  {} Moves  ▶", MOVE_BUDGETS[self.budget]).into()
+            }
+            Entry::Mode(mode) => mode.label().into(),
+            Entry::Custom => format!("◀  Custom {0}x{0}  ▶", self.custom_size).into(),
+            Entry::Race(Opponent::Human) => "Race a Friend".into(),
+            Entry::Race(Opponent::Ai) => "Race the AI".into(),
+            Entry::Race(Opponent::Remote) => "Play Online".into(),
+            Entry::Ghost => "Race Your Best".into(),
+            Entry::Practice => "Practice".into(),
+            Entry::Challenges => "Challenges".into(),
+            Entry::Replay => "Watch Replay".into(),
+            Entry::Export => "Export Game".into(),
+            Entry::Import => "Import Game".into(),
+            Entry::Stats => "Statistics".into(),
+            Entry::Settings => "Settings".into(),
+            Entry::Profile => format!("Profile: {}", self.profile).into(),
+        }
+    }
+
+    fn activate(&mut self, entry: Entry, cx: &mut Context<Self>) {
+        let move_budget = MOVE_BUDGETS[self.budget];
+        cx.emit(match entry {
+            Entry::Continue => MenuEvent::Continue,
+            Entry::Mode(mode) => MenuEvent::Start {
+                mode,
+                size: None,
+                move_budget,
+            },
+            Entry::Custom => MenuEvent::Start {
+                mode: GameMode::Classic,
+                size: Some(self.custom_size),
+                move_budget,
+            },
+            // Online races start from the lobby.
+            Entry::Race(Opponent::Remote) => MenuEvent::OpenLobby,
+            Entry::Race(opponent) => MenuEvent::OpenRace(opponent),
+            Entry::Ghost => MenuEvent::RaceGhost,
+            Entry::Practice => MenuEvent::OpenChallenges(ChallengeSource::Practice),
+            Entry::Challenges => MenuEvent::OpenChallenges(ChallengeSource::Packs),
+            Entry::Replay => MenuEvent::OpenReplay,
+            Entry::Export => MenuEvent::ExportGame,
+            Entry::Import => MenuEvent::ImportGame,
+            Entry::Stats => MenuEvent::OpenStats,
+            Entry::Settings => MenuEvent::OpenSettings,
+            Entry::Profile => MenuEvent::OpenProfiles,
+        });
+    }
+
+    fn select_next(&mut self, _: &Down, _window: &mut Window, cx: &mut Context<Self>) {
+        self.selected = (self.selected + 1) % self.entries().len();
+        cx.notify();
+    }
+
+    fn select_previous(&mut self, _: &Up, _window: &mut Window, cx: &mut Context<Self>) {
+        let count = self.entries().len();
+        self.selected = (self.selected + count - 1) % count;
+        cx.notify();
+    }
+
+    // Steps the value shown between arrows on the selected entry, if any.
+    fn resize(&mut self, delta: isize, cx: &mut Context<Self>) {
+        match self.entries()[self.selected] {
+            Entry::Custom => {
+                self.custom_size = self
+                    .custom_size
+                    .saturating_add_signed(delta)
+                    .clamp(MIN_SIZE, MAX_SIZE);
+            }
+            Entry::Mode(GameMode::LimitedMoves) => {
+                self.budget = self
+                    .budget
+                    .saturating_add_signed(delta)
+                    .min(MOVE_BUDGETS.len() - 1);
+            }
+            _ => return,
+        }
+        cx.notify();
+    }
+
+    fn shrink(&mut self, _: &Left, _window: &mut Window, cx: &mut Context<Self>) {
+        self.resize(-1, cx);
+    }
+
+    fn grow(&mut self, _: &Right, _window: &mut Window, cx: &mut Context<Self>) {
+        self.resize(1, cx);
+    }
+
+    fn confirm(&mut self, _: &Enter, _window: &mut Window, cx: &mut Context<Self>) {
+        let entry = self.entries()[self.selected];
+        self.activate(entry, cx);
+    }
+
+    fn render_entry(&self, index: usize, entry: Entry, cx: &mut Context<Self>) -> impl IntoElement {
+        let selected = index == self.selected;
+        div()
+            .id(("menu", index))
+            .w(rems(15.0))
+            .py_2()
+            .flex()
+            .justify_center()
+            .rounded_md()
+            .font_weight(FontWeight::BOLD)
+            .bg(if selected {
+                rgb(0xf67c5f)
+            } else {
+                rgb(0x8f7a66)
+            })
+            .text_color(rgb(0xf9f6f2))
+            .child(self.label(entry))
+            .on_hover(cx.listener(move |this, hovered: &bool, _window, cx| {
+                if *hovered {
+                    this.selected = index;
+                    cx.notify();
+                }
+            }))
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(move |this, _: &MouseDownEvent, _window, cx| {
+                    this.selected = index;
+                    this.activate(entry, cx)
+                }),
+            )
+    }
+}
+
+impl Focusable for MainMenu {
+    fn focus_handle(&self, _: &App) -> FocusHandle {
+        self.focus_handle.clone()
+    }
+}
+
+impl Render for MainMenu {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let entries = self
+            .entries()
+            .into_iter()
+            .enumerate()
+            .map(|(index, entry)| self.render_entry(index, entry, cx))
+            .collect::<Vec<_>>();
+
+        div()
+            .size_full()
+            .track_focus(&self.focus_handle)
+            .on_action(cx.listener(Self::select_next))
+            .on_action(cx.listener(Self::select_previous))
+            .on_action(cx.listener(Self::shrink))
+            .on_action(cx.listener(Self::grow))
+            .on_action(cx.listener(Self::confirm))
+            .flex()
+            .flex_col()
+            .justify_center()
+            .items_center()
+            .gap_3()
+            .child(
+                div()
+                    .mb_4()
+                    .text_size(rems(4.0))
+                    .font_weight(FontWeight::BOLD)
+                    .text_color(rgb(0x776e65))
+                    .child("2048"),
+            )
+            .children(entries)
+    }
+}
@@ -0,0 +1,185 @@
+use crate::persistence;
+use gpui::*;
+
+pub enum ProfileEvent {
+    /// Play as this profile from now on.
+    Switch(String),
+    Closed,
+}
+
+/// Lists the profiles to switch between, and makes new ones from a typed
+/// name.
+pub struct ProfilePanel {
+    focus_handle: FocusHandle,
+    profiles: Vec<String>,
+    active: String,
+    name: String,
+    status: SharedString,
+}
+
+impl EventEmitter<ProfileEvent> for ProfilePanel {}
+
+impl ProfilePanel {
+    pub fn new(cx: &mut Context<Self>) -> ProfilePanel {
+        ProfilePanel {
+            focus_handle: cx.focus_handle(),
+            profiles: persistence::profiles(),
+            active: persistence::active_profile(),
+            name: String::new(),
+            status: "Each profile keeps its own scores, statistics and settings.".into(),
+        }
+    }
+
+    fn create(&mut self, cx: &mut Context<Self>) {
+        match persistence::create_profile(&self.name) {
+            Ok(name) => {
+                self.name.clear();
+                self.profiles = persistence::profiles();
+                cx.emit(ProfileEvent::Switch(name));
+            }
+            Err(e) => self.status = e.into(),
+        }
+        cx.notify();
+    }
+
+    fn key_down(&mut self, event: &KeyDownEvent, _window: &mut Window, cx: &mut Context<Self>) {
+        match event.keystroke.key.as_str() {
+            "backspace" => {
+                self.name.pop();
+            }
+            "enter" => self.create(cx),
+            "escape" => cx.emit(ProfileEvent::Closed),
+            _ => {
+                let typed = event.keystroke.key_char.as_deref().unwrap_or_default();
+                let allowed = |c: char| c.is_alphanumeric() || " -_".contains(c);
+                if typed.is_empty() || !typed.chars().all(allowed) {
+                    return;
+                }
+                self.name.push_str(typed);
+            }
+        }
+        cx.notify();
+    }
+
+    fn render_button(
+        &self,
+        id: impl Into<ElementId>,
+        label: impl Into<SharedString>,
+        selected: bool,
+        cx: &mut Context<Self>,
+        on_click: impl Fn(&mut Self, &mut Context<Self>) + 'static,
+    ) -> impl IntoElement {
+        div()
+            .id(id)
+            .px_4()
+            .py_2()
+            .bg(if selected {
+                rgb(0xf67c5f)
+            } else {
+                rgb(0x8f7a66)
+            })
+            .text_color(rgb(0xf9f6f2))
+            .rounded_md()
+            .font_weight(FontWeight::BOLD)
+            .child(label.into())
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(move |this, _: &MouseDownEvent, _window, cx| on_click(this, cx)),
+            )
+    }
+}
+
+impl Focusable for ProfilePanel {
+    fn focus_handle(&self, _: &App) -> FocusHandle {
+        self.focus_handle.clone()
+    }
+}
+
+impl Render for ProfilePanel {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let profiles = self
+            .profiles
+            .clone()
+            .into_iter()
+            .enumerate()
+            .map(|(i, profile)| {
+                let selected = profile == self.active;
+                self.render_button(
+                    ("profile", i),
+                    profile.clone(),
+                    selected,
+                    cx,
+                    move |_, cx| cx.emit(ProfileEvent::Switch(profile.clone())),
+                )
+            })
+            .collect::<Vec<_>>();
+        let name = match self.name.is_empty() {
+            true => div().text_color(rgb(0xbbada0)).child("New profile name"),
+            false => div()
+                .text_color(rgb(0x776e65))
+                .child(format!("{}|", self.name)),
+        };
+
+        div()
+            .size_full()
+            .track_focus(&self.focus_handle)
+            .on_key_down(cx.listener(Self::key_down))
+            .flex()
+            .justify_center()
+            .items_center()
+            .child(
+                div()
+                    .w(rems(23.75))
+                    .p_4()
+                    .rounded_lg()
+                    .bg(rgb(0xfaf8ef))
+                    .flex()
+                    .flex_col()
+                    .gap_3()
+                    .child(
+                        div()
+                            .text_2xl()
+                            .font_weight(FontWeight::BOLD)
+                            .text_color(rgb(0x776e65))
+                            .child("Profiles"),
+                    )
+                    .child(div().flex().flex_wrap().gap_2().children(profiles))
+                    .child(
+                        div()
+                            .flex()
+                            .gap_2()
+                            .items_center()
+                            .child(
+                                div()
+                                    .flex_1()
+                                    .px_3()
+                                    .py_2()
+                                    .rounded_md()
+                                    .bg(rgb(0xeee4da))
+                                    .font_weight(FontWeight::BOLD)
+                                    .child(name),
+                            )
+                            .child(self.render_button(
+                                "create-profile",
+                                "Create",
+                                false,
+                                cx,
+                                Self::create,
+                            )),
+                    )
+                    .child(
+                        div()
+                            .text_sm()
+                            .text_color(rgb(0x776e65))
+                            .child(self.status.clone()),
+                    )
+                    .child(div().flex().justify_end().child(self.render_button(
+                        "close-profiles",
+                        "Back",
+                        false,
+                        cx,
+                        |_, cx| cx.emit(ProfileEvent::Closed),
+                    ))),
+            )
+    }
+}
@@ -0,0 +1,470 @@
+use crate::engine::{Cell, Direction, GameConfig};
+use crate::net::{Connection, Message};
+use crate::race::{Outcome, Race, Side};
+use crate::settings::Settings;
+use crate::ui::game::{
+    get_color, get_font_color, get_font_size, special_color, special_label, tile_metrics,
+};
+use crate::ui::lobby_panel::OnlineRace;
+use gpui::*;
+use std::time::Duration;
+
+// Two boards share the width one normally has.
+const BOARD_SCALE: f32 = 0.5;
+const POLL_INTERVAL: Duration = Duration::from_millis(30);
+
+pub enum RaceEvent {
+    Closed,
+}
+
+/// Who plays the right-hand board.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Opponent {
+    Human,
+    Ai,
+    /// Someone on another machine, whose board is mirrored as they play.
+    Remote,
+}
+
+/// Two boards raced side by side in one window. The left board answers to
+/// WASD and the right one to the arrow keys, unless the AI or a remote
+/// player has it, in which case either set of keys plays the left board.
+/// A battle toggle turns the race into one where big merges send junk.
+pub struct RacePanel {
+    focus_handle: FocusHandle,
+    race: Race,
+    settings: Settings,
+    opponent: Opponent,
+    ai_task: Option<Task<()>>,
+    // The link to a remote opponent, until it drops.
+    connection: Option<Connection>,
+    // Whether this side hosts an online race, and so calls the rematches.
+    hosting: bool,
+    _connection_task: Option<Task<()>>,
+}
+
+impl EventEmitter<RaceEvent> for RacePanel {}
+
+impl RacePanel {
+    pub fn new(
+        config: GameConfig,
+        settings: Settings,
+        opponent: Opponent,
+        cx: &mut Context<Self>,
+    ) -> RacePanel {
+        RacePanel {
+            focus_handle: cx.focus_handle(),
+            race: Race::new(config, rand::random()),
+            settings,
+            opponent,
+            ai_task: (opponent == Opponent::Ai).then(|| Self::run_ai(cx)),
+            connection: None,
+            hosting: false,
+            _connection_task: None,
+        }
+    }
+
+    pub fn online(online: OnlineRace, settings: Settings, cx: &mut Context<Self>) -> RacePanel {
+        RacePanel {
+            focus_handle: cx.focus_handle(),
+            race: online.race,
+            settings,
+            opponent: Opponent::Remote,
+            ai_task: None,
+            connection: Some(online.connection),
+            hosting: online.hosting,
+            _connection_task: Some(Self::watch_connection(cx)),
+        }
+    }
+
+    fn restart(&mut self, cx: &mut Context<Self>) {
+        self.start(self.race.rematch(rand::random()), cx);
+    }
+
+    fn toggle_battle(&mut self, cx: &mut Context<Self>) {
+        let (config, seed) = (*self.race.config(), rand::random());
+        let race = match self.race.is_battle() {
+            true => Race::new(config, seed),
+            false => Race::battle(config, seed),
+        };
+        self.start(race, cx);
+    }
+
+    // Whether this side may call a rematch or change the rules.
+    fn calls_rematches(&self) -> bool {
+        self.opponent != Opponent::Remote || self.hosting
+    }
+
+    fn start(&mut self, race: Race, cx: &mut Context<Self>) {
+        if !self.calls_rematches() {
+            return;
+        }
+        if self.opponent == Opponent::Remote {
+            // Both boards have to start over together.
+            self.send(&Message::start(&race));
+        }
+        self.race = race;
+        self.ai_task = (self.opponent == Opponent::Ai).then(|| Self::run_ai(cx));
+        cx.notify();
+    }
+
+    fn send(&mut self, message: &Message) {
+        let Some(connection) = &mut self.connection else {
+            return;
+        };
+        if connection.send(message).is_err() {
+            self.connection = None;
+        }
+    }
+
+    // Polls the remote opponent's moves until the link drops.
+    fn watch_connection(cx: &mut Context<Self>) -> Task<()> {
+        cx.spawn(async move |this, cx| {
+            loop {
+                cx.background_executor().timer(POLL_INTERVAL).await;
+                let open = this
+                    .update(cx, |this, cx| this.receive(cx))
+                    .unwrap_or(false);
+                if !open {
+                    break;
+                }
+            }
+        })
+    }
+
+    fn receive(&mut self, cx: &mut Context<Self>) -> bool {
+        let Some(connection) = &mut self.connection else {
+            return false;
+        };
+        let Ok(messages) = connection.receive() else {
+            return self.disconnect(cx);
+        };
+        for message in &messages {
+            match message {
+                Message::Start { .. } => match message.race() {
+                    Some(race) => self.race = race,
+                    None => return self.disconnect(cx),
+                },
+                // A board the race has no room for can only come from a
+                // broken or hostile peer.
+                Message::Board { board, .. } if !self.race.fits(board) => {
+                    return self.disconnect(cx);
+                }
+                Message::Board {
+                    board,
+                    score,
+                    moves,
+                } => self.race.mirror(Side::Right, board.clone(), *score, *moves),
+                Message::Attack { junk } => {
+                    self.race.attack(Side::Left, *junk);
+                    // The sender mirrors our board, junk and all.
+                    self.send_board();
+                }
+            }
+        }
+        if !messages.is_empty() {
+            cx.notify();
+        }
+        true
+    }
+
+    // Drops the link to the opponent, and returns false to stop polling it.
+    fn disconnect(&mut self, cx: &mut Context<Self>) -> bool {
+        self.connection = None;
+        cx.notify();
+        false
+    }
+
+    // Plays the right-hand board at the autoplay speed until the race is
+    // decided.
+    fn run_ai(cx: &mut Context<Self>) -> Task<()> {
+        cx.spawn(async move |this, cx| {
+            loop {
+                let Ok((board, search, delay)) = this.update(cx, |this, _| {
+                    let rate = this.settings.autoplay_speed.max(0.1);
+                    (
+                        this.race.racer(Side::Right).board().clone(),
+                        this.settings.solver(),
+                        Duration::from_secs_f32(1.0 / rate),
+                    )
+                }) else {
+                    return;
+                };
+                cx.background_executor().timer(delay).await;
+                let dir = cx
+                    .background_executor()
+                    .spawn(async move { search.best_move_among(&board, &Direction::ALL) })
+                    .await;
+                let racing = this
+                    .update(cx, |this, cx| {
+                        if let Some(dir) = dir {
+                            this.race.play(Side::Right, dir);
+                            cx.notify();
+                        }
+                        dir.is_some() && this.race.outcome().is_none()
+                    })
+                    .unwrap_or(false);
+                if !racing {
+                    break;
+                }
+            }
+        })
+    }
+
+    fn key_down(&mut self, event: &KeyDownEvent, _window: &mut Window, cx: &mut Context<Self>) {
+        let (side, dir) = match event.keystroke.key.as_str() {
+            "w" => (Side::Left, Direction::Up),
+            "s" => (Side::Left, Direction::Down),
+            "a" => (Side::Left, Direction::Left),
+            "d" => (Side::Left, Direction::Right),
+            "up" => (Side::Right, Direction::Up),
+            "down" => (Side::Right, Direction::Down),
+            "left" => (Side::Right, Direction::Left),
+            "right" => (Side::Right, Direction::Right),
+            "enter" => {
+                self.restart(cx);
+                return;
+            }
+            "escape" => {
+                cx.emit(RaceEvent::Closed);
+                return;
+            }
+            _ => return,
+        };
+        let side = match self.opponent {
+            Opponent::Human => side,
+            Opponent::Ai | Opponent::Remote => Side::Left,
+        };
+        let junk_sent = self.race.racer(side).junk_sent();
+        if !self.race.play(side, dir) {
+            return;
+        }
+        if self.opponent == Opponent::Remote {
+            self.send_board();
+            let junk = self.race.racer(side).junk_sent() - junk_sent;
+            if junk > 0 {
+                self.send(&Message::Attack { junk });
+            }
+        }
+        cx.notify();
+    }
+
+    fn send_board(&mut self) {
+        let racer = self.race.racer(Side::Left);
+        let message = Message::Board {
+            board: racer.board().clone(),
+            score: racer.score(),
+            moves: racer.moves(),
+        };
+        self.send(&message);
+    }
+
+    // Who plays `side`, and with which keys.
+    fn player(&self, side: Side) -> (&'static str, &'static str) {
+        match (side, self.opponent) {
+            (Side::Left, Opponent::Human) => ("Player 1", "WASD"),
+            (Side::Left, Opponent::Ai) => ("You", "WASD or arrows"),
+            (Side::Right, Opponent::Human) => ("Player 2", "Arrows"),
+            (Side::Right, Opponent::Ai) => ("AI", "Thinking..."),
+            (Side::Left, Opponent::Remote) => ("You", "WASD or arrows"),
+            (Side::Right, Opponent::Remote) => match self.connection {
+                Some(_) => ("Opponent", "Online"),
+                None => ("Opponent", "Disconnected"),
+            },
+        }
+    }
+
+    fn render_cell(&self, side: Side, idx: usize, tile: f32) -> Div {
+        let board = self.race.racer(side).board();
+        let cell = div()
+            .size(px(tile))
+            .rounded_md()
+            .flex()
+            .justify_center()
+            .items_center()
+            .font_weight(FontWeight::BOLD);
+        if board.is_hole(idx) {
+            return cell;
+        }
+        if board.is_obstacle(idx) {
+            return cell.bg(rgb(0x5c534a));
+        }
+        match board.cell(idx) {
+            Cell::Number(value) => {
+                let rank = board.rule().rank(value);
+                let label = self.settings.tile_labels.text(value, rank);
+                cell.bg(get_color(rank))
+                    .text_color(get_font_color(rank))
+                    .text_size(get_font_size(&label, tile))
+                    .child(label)
+            }
+            Cell::Special(special) => cell
+                .bg(special_color(special))
+                .text_color(rgb(0xf9f6f2))
+                .text_size(px(tile * 0.4))
+                .child(special_label(special)),
+            Cell::Empty => cell.bg(rgb(0xcdc1b4)),
+        }
+    }
+
+    fn render_board(&self, side: Side) -> impl IntoElement {
+        let racer = self.race.racer(side);
+        let (rows, cols) = (racer.board().rows(), racer.board().cols());
+        let (tile, gap) = tile_metrics(rows, cols, self.settings.ui_scale);
+        let (tile, gap) = (tile * BOARD_SCALE, gap * BOARD_SCALE);
+        let won = self.race.outcome() == Some(Outcome::Won(side));
+        let (name, keys) = self.player(side);
+
+        div()
+            .flex()
+            .flex_col()
+            .items_center()
+            .gap_2()
+            .child(
+                div()
+                    .font_weight(FontWeight::BOLD)
+                    .text_color(rgb(0x776e65))
+                    .child(format!("{name} · {keys}")),
+            )
+            .child(
+                div()
+                    .text_sm()
+                    .text_color(rgb(0x776e65))
+                    .child(match self.race.is_battle() {
+                        true => format!(
+                            "Score {} · {} moves · {} junk sent",
+                            racer.score(),
+                            racer.moves(),
+                            racer.junk_sent()
+                        ),
+                        false => format!("Score {} · {} moves", racer.score(), racer.moves()),
+                    }),
+            )
+            .child(
+                div()
+                    .bg(rgb(0xbbada0))
+                    .p(px(gap))
+                    .rounded_lg()
+                    .border_4()
+                    .border_color(if won { rgb(0xedc22e) } else { rgb(0xbbada0) })
+                    .flex()
+                    .flex_col()
+                    .gap(px(gap))
+                    .children((0..rows).map(|row| {
+                        div().flex().flex_row().gap(px(gap)).children(
+                            (0..cols).map(|col| self.render_cell(side, row * cols + col, tile)),
+                        )
+                    })),
+            )
+    }
+
+    fn render_outcome(&self, outcome: Outcome) -> impl IntoElement {
+        let headline = match outcome {
+            Outcome::Won(Side::Left) if self.opponent != Opponent::Human => "You win!".to_string(),
+            Outcome::Won(side) => format!("{} wins!", self.player(side).0),
+            Outcome::Draw => "It's a draw!".to_string(),
+        };
+        div()
+            .flex()
+            .flex_col()
+            .items_center()
+            .child(
+                div()
+                    .text_2xl()
+                    .font_weight(FontWeight::BOLD)
+                    .text_color(rgb(0x776e65))
+                    .child(headline),
+            )
+            .child(
+                div()
+                    .text_sm()
+                    .text_color(rgb(0x776e65))
+                    .child(match self.calls_rematches() {
+                        true => "Press Enter for a rematch",
+                        false => "Waiting for the host to call a rematch",
+                    }),
+            )
+    }
+
+    fn render_button(
+        &self,
+        id: &'static str,
+        label: &'static str,
+        cx: &mut Context<Self>,
+        on_click: impl Fn(&mut Self, &mut Context<Self>) + 'static,
+    ) -> impl IntoElement {
+        div()
+            .id(id)
+            .px_4()
+            .py_2()
+            .bg(rgb(0x8f7a66))
+            .text_color(rgb(0xf9f6f2))
+            .rounded_md()
+            .font_weight(FontWeight::BOLD)
+            .child(label)
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(move |this, _: &MouseDownEvent, _window, cx| on_click(this, cx)),
+            )
+    }
+}
+
+impl Focusable for RacePanel {
+    fn focus_handle(&self, _: &App) -> FocusHandle {
+        self.focus_handle.clone()
+    }
+}
+
+impl Render for RacePanel {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        div()
+            .size_full()
+            .track_focus(&self.focus_handle)
+            .on_key_down(cx.listener(Self::key_down))
+            .flex()
+            .flex_col()
+            .justify_center()
+            .items_center()
+            .gap_4()
+            .child(
+                div()
+                    .text_3xl()
+                    .font_weight(FontWeight::BOLD)
+                    .text_color(rgb(0x776e65))
+                    .child(match self.race.is_battle() {
+                        true => "Battle to 2048",
+                        false => "Race to 2048",
+                    }),
+            )
+            .child(
+                div()
+                    .flex()
+                    .gap_4()
+                    .children(Side::BOTH.map(|side| self.render_board(side))),
+            )
+            .children(
+                self.race
+                    .outcome()
+                    .map(|outcome| self.render_outcome(outcome)),
+            )
+            .child(
+                div()
+                    .flex()
+                    .gap_2()
+                    .when(self.calls_rematches(), |row| {
+                        row.child(self.render_button(
+                            "toggle-battle",
+                            match self.race.is_battle() {
+                                true => "Battle: On",
+                                false => "Battle: Off",
+                            },
+                            cx,
+                            Self::toggle_battle,
+                        ))
+                    })
+                    .child(self.render_button("close-race", "Back", cx, |_, cx| {
+                        cx.emit(RaceEvent::Closed)
+                    })),
+            )
+    }
+}
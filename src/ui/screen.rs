@@ -0,0 +1,969 @@
+use crate::audio::Music;
+use crate::challenge::Challenge;
+use crate::engine::{Direction, GameConfig, GameMode};
+use crate::gamepad::{Gamepads, PadCommand, PadEvent};
+use crate::persistence::{self, Session};
+use crate::replay::Replay;
+use crate::settings::{self, Settings};
+use crate::ui::challenge_panel::{ChallengeEvent, ChallengePanel, ChallengeSource};
+use crate::ui::dialog::{Dialog, DialogEvent};
+use crate::ui::game::{Down, Enter, Game, GameEvent, Left, Right, Undo, Up, bind_keys};
+use crate::ui::lobby_panel::{LobbyEvent, LobbyPanel};
+use crate::ui::menu::{MainMenu, MenuEvent};
+use crate::ui::profile_panel::{ProfileEvent, ProfilePanel};
+use crate::ui::race_panel::{Opponent, RaceEvent, RacePanel};
+use crate::ui::settings_panel::{SettingsEvent, SettingsPanel};
+use crate::ui::stats_panel::{StatsEvent, StatsPanel};
+use crate::ui::summary_panel::{SummaryEvent, SummaryPanel};
+use gpui::*;
+use std::time::Duration;
+
+const GAMEPAD_POLL: Duration = Duration::from_millis(16);
+// The window's size at the usual UI scale, and in mini mode.
+const WINDOW_SIZE: (f32, f32) = (500.0, 600.0);
+const MINI_WINDOW_SIZE: (f32, f32) = (240.0, 270.0);
+
+/// How the window opens: a normal one for the full game, or in mini mode a
+/// small borderless one that floats above the others.
+pub fn window_options(mini: bool, ui_scale: f32, cx: &App) -> WindowOptions {
+    let (width, height) = match mini {
+        true => MINI_WINDOW_SIZE,
+        false => WINDOW_SIZE,
+    };
+    let bounds = Bounds::centered(None, size(px(width * ui_scale), px(height * ui_scale)), cx);
+    let options = WindowOptions {
+        window_bounds: Some(WindowBounds::Windowed(bounds)),
+        ..Default::default()
+    };
+    match mini {
+        true => WindowOptions {
+            titlebar: None,
+            kind: WindowKind::PopUp,
+            ..options
+        },
+        false => options,
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Screen {
+    Menu,
+    Game,
+    Settings,
+    Stats,
+    Summary,
+    Challenges,
+    Lobby,
+    Race,
+    Replay,
+    Profiles,
+}
+
+/// The window's root view. Each screen is its own view and this one decides
+/// which fills the window. The game view lives as long as the window, so
+/// visiting the menu or the settings never costs any progress.
+pub struct Root {
+    screen: Screen,
+    // Screens to return to as settings, statistics, challenges or a replay
+    // close.
+    back: Vec<Screen>,
+    menu: Entity<MainMenu>,
+    game: Entity<Game>,
+    game_subscriptions: Vec<Subscription>,
+    settings_panel: Option<(Entity<SettingsPanel>, Subscription)>,
+    stats_panel: Option<(Entity<StatsPanel>, Subscription)>,
+    summary_panel: Option<(Entity<SummaryPanel>, Subscription)>,
+    challenge_panel: Option<(Entity<ChallengePanel>, Subscription)>,
+    // The list the last challenge was picked from, to go back to.
+    challenge_source: ChallengeSource,
+    lobby_panel: Option<(Entity<LobbyPanel>, Subscription)>,
+    race_panel: Option<(Entity<RacePanel>, Subscription)>,
+    replay_viewer: Option<(Entity<Game>, Subscription)>,
+    profile_panel: Option<(Entity<ProfilePanel>, Subscription)>,
+    // A question shown over the current screen, waiting for an answer.
+    dialog: Option<(Entity<Dialog>, Subscription)>,
+    _subscriptions: Vec<Subscription>,
+    _gamepads: Option<Task<()>>,
+    music: Music,
+}
+
+impl Root {
+    pub fn new(
+        config: GameConfig,
+        settings: Settings,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Root {
+        // Records are checked once a run, before the first game reads them.
+        let repairs = persistence::repair_records();
+        let game = cx.new(|cx| Game::new(config, settings, cx));
+        game.update(cx, |game, cx| {
+            for (holds, recovery) in repairs {
+                game.toast(persistence::recovery_message(holds, recovery), cx);
+            }
+            game.serve_spectators(None, cx);
+        });
+        let mut root = Root::with_game(game, Screen::Menu, window, cx);
+        // Quitting properly clears the session; one still there means the
+        // last run crashed.
+        root._subscriptions.push(cx.on_app_quit(|_, _| {
+            persistence::clear_session();
+            async {}
+        }));
+        if let Some(session) = persistence::load_session() {
+            root.offer_session(session, window, cx);
+        }
+        root
+    }
+
+    fn offer_session(&mut self, session: Session, window: &mut Window, cx: &mut Context<Self>) {
+        self.open_dialog(
+            |cx| {
+                Dialog::confirm(
+                    "Restore previous session?",
+                    "The game didn't close properly last time. Pick up where it left off, undo history and all?",
+                    "Restore",
+                    cx,
+                )
+            },
+            window,
+            cx,
+            move |this, answer, window, cx| match answer {
+                Some(1) => {
+                    this.game
+                        .update(cx, |game, cx| game.restore_session(session, cx));
+                    this.switch(Screen::Game, window, cx);
+                }
+                _ => persistence::clear_session(),
+            },
+        );
+    }
+
+    // Builds the window around `game`, showing `screen`. Mini mode uses
+    // this to carry the game over to a new window.
+    fn with_game(
+        game: Entity<Game>,
+        screen: Screen,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Root {
+        let menu = cx.new(MainMenu::new);
+        let subscriptions = vec![
+            cx.subscribe_in(&menu, window, Self::on_menu_event),
+            cx.observe_window_activation(window, Self::update_clock),
+        ];
+        let mut root = Root {
+            screen,
+            back: Vec::new(),
+            game_subscriptions: Root::subscribe_game(&game, window, cx),
+            menu,
+            game,
+            settings_panel: None,
+            stats_panel: None,
+            summary_panel: None,
+            challenge_panel: None,
+            challenge_source: ChallengeSource::Packs,
+            lobby_panel: None,
+            race_panel: None,
+            replay_viewer: None,
+            profile_panel: None,
+            dialog: None,
+            _subscriptions: subscriptions,
+            _gamepads: Root::watch_gamepads(window, cx),
+            music: Music::new(),
+        };
+        root.update_music(cx);
+        root.show(screen, window, cx);
+        root
+    }
+
+    fn subscribe_game(
+        game: &Entity<Game>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Vec<Subscription> {
+        vec![
+            cx.subscribe_in(game, window, Self::on_game_event),
+            // The background follows the game's theme, and the music its
+            // progress.
+            cx.observe(game, |this, _, cx| {
+                this.update_music(cx);
+                cx.notify()
+            }),
+        ]
+    }
+
+    // Polls the controllers for as long as the window is open. Their input
+    // goes through the same actions as the keyboard's, so it drives
+    // whichever screen has focus.
+    fn watch_gamepads(window: &mut Window, cx: &mut Context<Self>) -> Option<Task<()>> {
+        let mut gamepads = match Gamepads::new() {
+            Ok(gamepads) => gamepads,
+            Err(e) => {
+                eprintln!("Controllers are unavailable: {e}");
+                return None;
+            }
+        };
+        Some(cx.spawn_in(window, async move |this, cx| {
+            loop {
+                cx.background_executor().timer(GAMEPAD_POLL).await;
+                let events = gamepads.poll();
+                if events.is_empty() {
+                    continue;
+                }
+                let open = this.update_in(cx, |this, window, cx| {
+                    this.on_gamepad_events(events, window, cx)
+                });
+                if open.is_err() {
+                    break;
+                }
+            }
+        }))
+    }
+
+    fn on_gamepad_events(
+        &mut self,
+        events: Vec<PadEvent>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        for event in events {
+            let message = match event {
+                // Moves go straight to the game, so they never hold-to-repeat
+                // the way a key does.
+                PadEvent::Command(PadCommand::Move(dir)) if self.screen == Screen::Game => {
+                    self.game.update(cx, |game, cx| game.handle_move(dir, cx));
+                    continue;
+                }
+                PadEvent::Command(command) => {
+                    let action: Box<dyn Action> = match command {
+                        PadCommand::Move(Direction::Up) => Box::new(Up),
+                        PadCommand::Move(Direction::Down) => Box::new(Down),
+                        PadCommand::Move(Direction::Left) => Box::new(Left),
+                        PadCommand::Move(Direction::Right) => Box::new(Right),
+                        PadCommand::NewGame => Box::new(Enter),
+                        PadCommand::Undo => Box::new(Undo),
+                    };
+                    window.dispatch_action(action, cx);
+                    continue;
+                }
+                PadEvent::Connected(name) => format!("{name} connected"),
+                PadEvent::Disconnected(name) => format!("{name} disconnected"),
+            };
+            self.game.update(cx, |game, cx| game.toast(message, cx));
+        }
+    }
+
+    fn update_menu(&mut self, cx: &mut Context<Self>) {
+        let game = self.game.read(cx);
+        let can_continue = game.is_started() || game.has_saved_game();
+        let has_ghost = persistence::has_best_game();
+        self.menu.update(cx, |menu, cx| {
+            menu.set_can_continue(can_continue, cx);
+            menu.set_has_ghost(has_ghost, cx);
+        });
+    }
+
+    // Replaces the current screen, forgetting where we came from.
+    fn switch(&mut self, screen: Screen, window: &mut Window, cx: &mut Context<Self>) {
+        self.back.clear();
+        if screen != Screen::Replay {
+            self.replay_viewer = None;
+        }
+        if screen != Screen::Lobby {
+            self.lobby_panel = None;
+        }
+        if screen != Screen::Race {
+            self.race_panel = None;
+        }
+        if screen != Screen::Profiles {
+            self.profile_panel = None;
+        }
+        self.show(screen, window, cx);
+    }
+
+    // Opens a screen on top of the current one; `close` comes back here.
+    fn push(&mut self, screen: Screen, window: &mut Window, cx: &mut Context<Self>) {
+        if self.screen != screen {
+            self.back.push(self.screen);
+        }
+        self.show(screen, window, cx);
+    }
+
+    fn close(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let screen = self.back.pop().unwrap_or(Screen::Menu);
+        self.show(screen, window, cx);
+    }
+
+    fn show(&mut self, screen: Screen, window: &mut Window, cx: &mut Context<Self>) {
+        if screen == Screen::Menu {
+            self.update_menu(cx);
+        }
+        self.screen = screen;
+        self.update_clock(window, cx);
+        let focus = match screen {
+            Screen::Menu => Some(self.menu.focus_handle(cx)),
+            Screen::Game => Some(self.game.focus_handle(cx)),
+            Screen::Replay => self
+                .replay_viewer
+                .as_ref()
+                .map(|(viewer, _)| viewer.focus_handle(cx)),
+            Screen::Lobby => self
+                .lobby_panel
+                .as_ref()
+                .map(|(panel, _)| panel.focus_handle(cx)),
+            Screen::Race => self
+                .race_panel
+                .as_ref()
+                .map(|(panel, _)| panel.focus_handle(cx)),
+            Screen::Profiles => self
+                .profile_panel
+                .as_ref()
+                .map(|(panel, _)| panel.focus_handle(cx)),
+            Screen::Settings => self
+                .settings_panel
+                .as_ref()
+                .map(|(panel, _)| panel.focus_handle(cx)),
+            Screen::Stats | Screen::Summary | Screen::Challenges => None,
+        };
+        if let Some(focus) = focus {
+            window.focus(&focus);
+        }
+        cx.notify();
+    }
+
+    // gpui can't change what kind of window a window is once it is open, so
+    // switching mini mode on or off opens the other kind with the same game
+    // in it and closes this one.
+    fn toggle_mini_mode(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let game = self.game.clone();
+        let mini = !game.read(cx).is_mini();
+        let options = window_options(mini, game.read(cx).settings().ui_scale, cx);
+        let old = window.window_handle();
+        // Both windows can't be updated from inside this one's update.
+        cx.defer(move |cx| {
+            game.update(cx, |game, cx| game.set_mini(mini, cx));
+            let carried = game.clone();
+            let opened = cx.open_window(options, |window, cx| {
+                cx.new(|cx| Root::with_game(carried, Screen::Game, window, cx))
+            });
+            match opened {
+                Ok(_) => {
+                    old.update(cx, |_, window, _| window.remove_window()).ok();
+                }
+                Err(e) => game.update(cx, |game, cx| {
+                    game.set_mini(!mini, cx);
+                    game.toast(format!("Couldn't switch to mini mode: {e}"), cx);
+                }),
+            }
+        });
+    }
+
+    fn update_music(&mut self, cx: &mut Context<Self>) {
+        let game = self.game.read(cx);
+        let settings = game.settings();
+        let volume = match settings.sound && settings.music {
+            true => settings.music_volume,
+            false => 0.0,
+        };
+        self.music.set(volume, game.intensity());
+    }
+
+    // Timed games only run while they are on screen in the active window.
+    fn update_clock(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let running =
+            window.is_window_active() && self.screen == Screen::Game && self.dialog.is_none();
+        self.game
+            .update(cx, |game, cx| game.set_clock_running(running, cx));
+    }
+
+    fn on_menu_event(
+        &mut self,
+        _: &Entity<MainMenu>,
+        event: &MenuEvent,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        match event {
+            MenuEvent::Continue => self.play(window, cx),
+            MenuEvent::Start {
+                mode,
+                size,
+                move_budget,
+            } => {
+                let mut config = match mode {
+                    GameMode::Daily => GameConfig::daily(),
+                    _ => GameConfig {
+                        mode: *mode,
+                        move_budget: *move_budget,
+                        ..self.base_config(cx)
+                    },
+                };
+                if let Some(size) = *size {
+                    config.rows = size;
+                    config.cols = size;
+                }
+                self.start(config, window, cx);
+            }
+            MenuEvent::OpenChallenges(source) => self.open_challenges(*source, window, cx),
+            MenuEvent::OpenRace(opponent) => self.open_race(*opponent, window, cx),
+            MenuEvent::OpenLobby => self.open_lobby(window, cx),
+            MenuEvent::OpenReplay => self.open_replay(window, cx),
+            MenuEvent::ExportGame => self.export_game(window, cx),
+            MenuEvent::ImportGame => self.import_game(window, cx),
+            MenuEvent::OpenStats => self.open_stats(window, cx),
+            MenuEvent::OpenSettings => self.open_settings(window, cx),
+            MenuEvent::OpenProfiles => self.open_profiles(window, cx),
+            MenuEvent::RaceGhost => {
+                let Some(replay) = persistence::load_best_game() else {
+                    return;
+                };
+                self.replace_game(self.base_config(cx), window, cx, move |game, window, cx| {
+                    game.race_ghost(replay, window, cx)
+                });
+            }
+        }
+    }
+
+    fn on_game_event(
+        &mut self,
+        _: &Entity<Game>,
+        event: &GameEvent,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        match event {
+            GameEvent::OpenMenu => self.switch(Screen::Menu, window, cx),
+            GameEvent::OpenSettings => self.open_settings(window, cx),
+            GameEvent::OpenChallenges => self.open_challenges(self.challenge_source, window, cx),
+            GameEvent::OpenReplay => self.open_replay(window, cx),
+            GameEvent::CloseReplay => {
+                self.replay_viewer = None;
+                self.close(window, cx);
+            }
+            GameEvent::Won => self.announce_win(window, cx),
+            GameEvent::ToggleMiniMode => self.toggle_mini_mode(window, cx),
+            // Mini mode has no room for it.
+            GameEvent::GameOver if self.screen == Screen::Game && !self.game.read(cx).is_mini() => {
+                self.open_summary(window, cx)
+            }
+            GameEvent::GameOver => {}
+            GameEvent::ConfirmNewGame => self.confirm(
+                "Start a new game?",
+                "The game in progress will be lost.",
+                "New Game",
+                window,
+                cx,
+                |this, window, cx| this.game.update(cx, |game, cx| game.new_game(window, cx)),
+            ),
+        }
+    }
+
+    // Goes back to the game in progress, picking up the saved one if the
+    // window was just opened.
+    fn play(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        self.game.update(cx, |game, cx| {
+            if !game.is_started() {
+                game.resume_game(cx);
+            }
+        });
+        self.switch(Screen::Game, window, cx);
+    }
+
+    // The config a new game starts from: the current game's, unless that
+    // is a daily puzzle, whose config ignores the settings.
+    fn base_config(&self, cx: &App) -> GameConfig {
+        let game = self.game.read(cx);
+        match game.config().mode {
+            GameMode::Daily => game.settings().game_config(),
+            _ => game.config(),
+        }
+    }
+
+    // Builds a fresh game view for the chosen mode, replacing the old one.
+    fn start(&mut self, config: GameConfig, window: &mut Window, cx: &mut Context<Self>) {
+        self.replace_game(config, window, cx, |game, window, cx| {
+            game.new_game(window, cx)
+        });
+    }
+
+    fn start_challenge(
+        &mut self,
+        id: String,
+        challenge: Challenge,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let board = challenge.start_board();
+        let config = GameConfig {
+            mode: GameMode::Classic,
+            rows: board.rows(),
+            cols: board.cols(),
+            seed: challenge.seed,
+            ..self.base_config(cx)
+        };
+        self.replace_game(config, window, cx, move |game, window, cx| {
+            game.start_challenge(id, challenge, window, cx)
+        });
+    }
+
+    // Shows a dialog over the current screen and hands its answer to
+    // `on_answer`: the button picked, or `None` if it was closed.
+    fn open_dialog(
+        &mut self,
+        dialog: impl FnOnce(&mut Context<Dialog>) -> Dialog,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+        on_answer: impl FnOnce(&mut Root, Option<usize>, &mut Window, &mut Context<Root>) + 'static,
+    ) {
+        let dialog = cx.new(dialog);
+        let mut on_answer = Some(on_answer);
+        let subscription = cx.subscribe_in(
+            &dialog,
+            window,
+            move |this, _, event: &DialogEvent, window, cx| {
+                this.dialog = None;
+                this.show(this.screen, window, cx);
+                let answer = match *event {
+                    DialogEvent::Chose(i) => Some(i),
+                    DialogEvent::Dismissed => None,
+                };
+                if let Some(on_answer) = on_answer.take() {
+                    on_answer(this, answer, window, cx);
+                }
+            },
+        );
+        window.focus(&dialog.focus_handle(cx));
+        self.dialog = Some((dialog, subscription));
+        self.update_clock(window, cx);
+        cx.notify();
+    }
+
+    // Asks before going on, and runs `on_confirm` if the answer is yes.
+    fn confirm(
+        &mut self,
+        title: &'static str,
+        message: &'static str,
+        confirm_label: &'static str,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+        on_confirm: impl FnOnce(&mut Root, &mut Window, &mut Context<Root>) + 'static,
+    ) {
+        self.open_dialog(
+            |cx| Dialog::confirm(title, message, confirm_label, cx),
+            window,
+            cx,
+            move |this, answer, window, cx| {
+                if answer == Some(1) {
+                    on_confirm(this, window, cx);
+                }
+            },
+        );
+    }
+
+    // Shows a message with nothing to decide but closing it.
+    fn tell(
+        &mut self,
+        title: impl Into<SharedString>,
+        message: impl Into<SharedString>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let (title, message) = (title.into(), message.into());
+        self.open_dialog(
+            |cx| Dialog::new(title, message, vec!["OK".into()], cx),
+            window,
+            cx,
+            |_, _, _, _| {},
+        );
+    }
+
+    // Closing the win dialog keeps the game going, as Keep Playing does.
+    fn announce_win(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let title = match self.game.read(cx).config().mode.has_placer() {
+            true => "The mover wins!",
+            false => "You win!",
+        };
+        self.open_dialog(
+            |cx| {
+                Dialog::new(
+                    title,
+                    "The winning tile is on the board.",
+                    vec!["New Game".into(), "Keep Playing".into()],
+                    cx,
+                )
+            },
+            window,
+            cx,
+            |this, answer, window, cx| {
+                this.game.update(cx, |game, cx| match answer {
+                    Some(0) => game.new_game(window, cx),
+                    _ => game.continue_playing(cx),
+                })
+            },
+        );
+    }
+
+    fn replace_game(
+        &mut self,
+        config: GameConfig,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+        start: impl FnOnce(&mut Game, &mut Window, &mut Context<Game>) + 'static,
+    ) {
+        if !self.game.read(cx).has_run_at_stake() {
+            self.replace_game_now(config, window, cx, start);
+            return;
+        }
+        self.confirm(
+            "Abandon your game?",
+            "Starting something new will lose the game in progress.",
+            "Abandon",
+            window,
+            cx,
+            move |this, window, cx| this.replace_game_now(config, window, cx, start),
+        );
+    }
+
+    fn replace_game_now(
+        &mut self,
+        config: GameConfig,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+        start: impl FnOnce(&mut Game, &mut Window, &mut Context<Game>),
+    ) {
+        // The game being replaced still counts towards the statistics.
+        self.game.update(cx, |game, _| {
+            if game.is_started() {
+                game.record_stats();
+            }
+        });
+        let settings = self.game.read(cx).settings().clone();
+        let game = cx.new(|cx| Game::new(config, settings, cx));
+        self.hand_over_spectators(&game, cx);
+        game.update(cx, |game, cx| start(game, window, cx));
+        self.game_subscriptions = Root::subscribe_game(&game, window, cx);
+        self.game = game;
+        self.challenge_panel = None;
+        self.switch(Screen::Game, window, cx);
+    }
+
+    // Moves the spectator feed from the game being replaced over to `game`,
+    // which couldn't bind the port while the old one held it.
+    fn hand_over_spectators(&mut self, game: &Entity<Game>, cx: &mut Context<Self>) {
+        let spectators = self.game.update(cx, |game, _| game.take_spectators());
+        game.update(cx, |game, cx| game.serve_spectators(spectators, cx));
+    }
+
+    fn open_settings(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let settings = self.game.read(cx).settings().clone();
+        let panel = cx.new(|cx| SettingsPanel::new(settings, cx));
+        let subscription = cx.subscribe_in(
+            &panel,
+            window,
+            |this, _, event: &SettingsEvent, window, cx| match event {
+                SettingsEvent::Changed(settings) => {
+                    let views = std::iter::once(this.game.clone()).chain(
+                        this.replay_viewer
+                            .as_ref()
+                            .map(|(viewer, _)| viewer.clone()),
+                    );
+                    for view in views {
+                        view.update(cx, |game, cx| game.apply_settings(settings.clone(), cx));
+                    }
+                }
+                SettingsEvent::Closed => {
+                    this.settings_panel = None;
+                    this.close(window, cx);
+                }
+                SettingsEvent::ConfirmReset => this.confirm(
+                    "Reset all settings?",
+                    "Every setting, including your keybindings, goes back to its default.",
+                    "Reset",
+                    window,
+                    cx,
+                    |this, _, cx| {
+                        if let Some((panel, _)) = &this.settings_panel {
+                            panel.update(cx, |panel, cx| panel.reset(cx));
+                        }
+                    },
+                ),
+                SettingsEvent::SaveFailed(message) => this.game.update(cx, |game, cx| {
+                    game.report_storage_error("save settings", message.clone(), cx)
+                }),
+            },
+        );
+        self.settings_panel = Some((panel, subscription));
+        self.push(Screen::Settings, window, cx);
+    }
+
+    fn open_race(&mut self, opponent: Opponent, window: &mut Window, cx: &mut Context<Self>) {
+        let config = self.base_config(cx);
+        let settings = self.game.read(cx).settings().clone();
+        let panel = cx.new(|cx| RacePanel::new(config, settings, opponent, cx));
+        self.show_race(panel, window, cx);
+    }
+
+    fn show_race(&mut self, panel: Entity<RacePanel>, window: &mut Window, cx: &mut Context<Self>) {
+        let subscription = cx.subscribe_in(
+            &panel,
+            window,
+            |this, _, event: &RaceEvent, window, cx| match event {
+                RaceEvent::Closed => this.switch(Screen::Menu, window, cx),
+            },
+        );
+        self.race_panel = Some((panel, subscription));
+        self.switch(Screen::Race, window, cx);
+    }
+
+    fn open_lobby(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let config = self.base_config(cx);
+        let panel = cx.new(|cx| LobbyPanel::new(config, cx));
+        let subscription = cx.subscribe_in(
+            &panel,
+            window,
+            |this, panel, event: &LobbyEvent, window, cx| match event {
+                LobbyEvent::Joined => {
+                    let Some(online) = panel.update(cx, |panel, _| panel.take_race()) else {
+                        return;
+                    };
+                    let settings = this.game.read(cx).settings().clone();
+                    let race = cx.new(|cx| RacePanel::online(online, settings, cx));
+                    this.show_race(race, window, cx);
+                }
+                LobbyEvent::Closed => this.switch(Screen::Menu, window, cx),
+            },
+        );
+        self.lobby_panel = Some((panel, subscription));
+        self.switch(Screen::Lobby, window, cx);
+    }
+
+    fn open_profiles(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let panel = cx.new(ProfilePanel::new);
+        let subscription = cx.subscribe_in(
+            &panel,
+            window,
+            |this, _, event: &ProfileEvent, window, cx| match event {
+                ProfileEvent::Switch(profile) => this.switch_profile(profile, window, cx),
+                ProfileEvent::Closed => this.switch(Screen::Menu, window, cx),
+            },
+        );
+        self.profile_panel = Some((panel, subscription));
+        self.switch(Screen::Profiles, window, cx);
+    }
+
+    // Swaps in a fresh game on the profile's own settings. The game being
+    // left was saved after its last move, so it waits for the profile's
+    // return rather than counting as finished.
+    fn switch_profile(&mut self, profile: &str, window: &mut Window, cx: &mut Context<Self>) {
+        if persistence::switch_profile(profile).is_err() {
+            return;
+        }
+        let settings = settings::load();
+        bind_keys(&settings.keybindings, cx);
+        let config = settings.game_config();
+        let game = cx.new(|cx| Game::new(config, settings, cx));
+        self.hand_over_spectators(&game, cx);
+        self.game_subscriptions = Root::subscribe_game(&game, window, cx);
+        self.game = game;
+        self.menu.update(cx, |menu, cx| {
+            menu.set_profile(persistence::active_profile(), cx)
+        });
+        self.switch(Screen::Menu, window, cx);
+    }
+
+    fn open_stats(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let panel = cx.new(|_| StatsPanel::new(persistence::load_stats()));
+        let subscription = cx.subscribe_in(
+            &panel,
+            window,
+            |this, _, event: &StatsEvent, window, cx| match event {
+                StatsEvent::Closed => {
+                    this.stats_panel = None;
+                    this.close(window, cx);
+                }
+            },
+        );
+        self.stats_panel = Some((panel, subscription));
+        self.push(Screen::Stats, window, cx);
+    }
+
+    fn open_summary(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let (summary, replay) = self.game.read(cx).summary();
+        let settings = self.game.read(cx).settings().clone();
+        let panel = cx.new(|cx| SummaryPanel::new(summary, replay, settings, cx));
+        let subscription = cx.subscribe_in(
+            &panel,
+            window,
+            |this, _, event: &SummaryEvent, window, cx| {
+                this.summary_panel = None;
+                this.close(window, cx);
+                if let SummaryEvent::NewGame = event {
+                    this.game.update(cx, |game, cx| game.new_game(window, cx));
+                }
+            },
+        );
+        self.summary_panel = Some((panel, subscription));
+        self.push(Screen::Summary, window, cx);
+    }
+
+    fn open_challenges(
+        &mut self,
+        source: ChallengeSource,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.challenge_source = source;
+        let solved = persistence::load_stats().solved_challenges;
+        let panel = cx.new(|_| ChallengePanel::new(source, solved));
+        let subscription = cx.subscribe_in(
+            &panel,
+            window,
+            |this, panel, event: &ChallengeEvent, window, cx| match event {
+                ChallengeEvent::Start { id, challenge } => {
+                    this.start_challenge(id.clone(), challenge.clone(), window, cx)
+                }
+                ChallengeEvent::Import => Root::import_challenges(panel.clone(), window, cx),
+                ChallengeEvent::Closed => {
+                    this.challenge_panel = None;
+                    this.close(window, cx);
+                }
+            },
+        );
+        self.challenge_panel = Some((panel, subscription));
+        self.push(Screen::Challenges, window, cx);
+    }
+
+    fn import_challenges(
+        panel: Entity<ChallengePanel>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let paths = cx.prompt_for_paths(PathPromptOptions {
+            files: true,
+            directories: false,
+            multiple: true,
+            prompt: Some("Import".into()),
+        });
+        cx.spawn_in(window, async move |_, cx| {
+            let Ok(Ok(Some(paths))) = paths.await else {
+                return;
+            };
+            for path in &paths {
+                // A pack that doesn't load is left out; the panel can only
+                // list what made it into the challenges directory.
+                persistence::import_challenge_pack(path).ok();
+            }
+            panel.update(cx, |panel, cx| panel.reload(cx)).ok();
+        })
+        .detach();
+    }
+
+    fn open_replay(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let paths = cx.prompt_for_paths(PathPromptOptions {
+            files: true,
+            directories: false,
+            multiple: false,
+            prompt: Some("Watch".into()),
+        });
+        cx.spawn_in(window, async move |this, cx| {
+            let Ok(Ok(Some(paths))) = paths.await else {
+                return;
+            };
+            let Some(replay) = paths
+                .first()
+                .and_then(|path| persistence::load_replay(path))
+            else {
+                return;
+            };
+            this.update_in(cx, |this, window, cx| this.watch_replay(replay, window, cx))
+                .ok();
+        })
+        .detach();
+    }
+
+    fn export_game(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(session) = self.game.read(cx).current_session() else {
+            return;
+        };
+        let (title, message) = match persistence::export_game(&session) {
+            Ok(path) => ("Game exported", format!("Saved to {}", path.display())),
+            Err(err) => (
+                "Export failed",
+                persistence::failure_message("export the game", &err),
+            ),
+        };
+        self.tell(title, message, window, cx);
+    }
+
+    // Replaces the game being played with one exported earlier.
+    fn import_game(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let paths = cx.prompt_for_paths(PathPromptOptions {
+            files: true,
+            directories: false,
+            multiple: false,
+            prompt: Some("Import".into()),
+        });
+        cx.spawn_in(window, async move |this, cx| {
+            let Ok(Ok(Some(paths))) = paths.await else {
+                return;
+            };
+            let Some(path) = paths.first() else {
+                return;
+            };
+            let imported = persistence::import_game(path);
+            this.update_in(cx, |this, window, cx| match imported {
+                Ok(session) => {
+                    this.game
+                        .update(cx, |game, cx| game.restore_session(session, cx));
+                    this.switch(Screen::Game, window, cx);
+                }
+                Err(message) => this.tell("Import failed", message, window, cx),
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    fn watch_replay(&mut self, replay: Replay, window: &mut Window, cx: &mut Context<Self>) {
+        let game = self.game.read(cx);
+        let (config, settings) = (game.config(), game.settings().clone());
+        let viewer = cx.new(|cx| Game::for_playback(replay, config, settings, cx));
+        let subscription = cx.subscribe_in(&viewer, window, Self::on_game_event);
+        self.replay_viewer = Some((viewer, subscription));
+        self.push(Screen::Replay, window, cx);
+    }
+
+    fn current_view(&self) -> AnyView {
+        let view = match self.screen {
+            Screen::Menu => Some(self.menu.clone().into()),
+            Screen::Game => None,
+            Screen::Settings => self.settings_panel.as_ref().map(|(p, _)| p.clone().into()),
+            Screen::Stats => self.stats_panel.as_ref().map(|(p, _)| p.clone().into()),
+            Screen::Summary => self.summary_panel.as_ref().map(|(p, _)| p.clone().into()),
+            Screen::Challenges => self.challenge_panel.as_ref().map(|(p, _)| p.clone().into()),
+            Screen::Lobby => self.lobby_panel.as_ref().map(|(p, _)| p.clone().into()),
+            Screen::Race => self.race_panel.as_ref().map(|(p, _)| p.clone().into()),
+            Screen::Replay => self.replay_viewer.as_ref().map(|(v, _)| v.clone().into()),
+            Screen::Profiles => self.profile_panel.as_ref().map(|(p, _)| p.clone().into()),
+        };
+        view.unwrap_or_else(|| self.game.clone().into())
+    }
+}
+
+impl Render for Root {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        // Text and spacing everywhere are measured in rems.
+        window.set_rem_size(px(16.0 * self.game.read(cx).settings().ui_scale));
+        div()
+            .size_full()
+            .relative()
+            .bg(self.game.read(cx).background())
+            .child(self.current_view())
+            .children(
+                self.dialog
+                    .as_ref()
+                    .map(|(dialog, _)| div().absolute().inset_0().child(dialog.clone())),
+            )
+    }
+}
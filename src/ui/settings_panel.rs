@@ -0,0 +1,653 @@
+use crate::ai::Strategy;
+use crate::audio::Sound;
+use crate::engine::{BoardShape, Difficulty, MAX_SIZE, MIN_SIZE, SpawnPolicy};
+use crate::labels::TileLabels;
+use crate::persistence;
+use crate::settings::{self, KeyAction, KeyLayout, Keybindings, Settings};
+use crate::spectate::SPECTATE_PORT;
+use crate::theme::TilePalette;
+use gpui::*;
+
+pub enum SettingsEvent {
+    Changed(Settings),
+    Closed,
+    /// Reset All was clicked; [`SettingsPanel::reset`] does it once the
+    /// player agrees.
+    ConfirmReset,
+    /// The settings file couldn't be written; the message says why.
+    SaveFailed(String),
+}
+
+// What clicking a sound's volume steps through.
+const VOLUME_STEPS: [f32; 5] = [0.0, 0.25, 0.5, 0.75, 1.0];
+
+// Keys that only modify others, which can't be bound alone.
+const MODIFIERS: [&str; 6] = [
+    "shift", "control", "alt", "platform", "function", "capslock",
+];
+
+/// Edits the settings in place and saves every change straight to the
+/// settings file. Clicking an action's keys waits for the next keypress and
+/// binds it instead.
+pub struct SettingsPanel {
+    focus_handle: FocusHandle,
+    settings: Settings,
+    rebinding: Option<KeyAction>,
+}
+
+impl EventEmitter<SettingsEvent> for SettingsPanel {}
+
+impl SettingsPanel {
+    pub fn new(settings: Settings, cx: &mut Context<Self>) -> SettingsPanel {
+        SettingsPanel {
+            focus_handle: cx.focus_handle(),
+            settings,
+            rebinding: None,
+        }
+    }
+
+    fn key_down(&mut self, event: &KeyDownEvent, _window: &mut Window, cx: &mut Context<Self>) {
+        let Some(action) = self.rebinding else {
+            return;
+        };
+        let key = event.keystroke.key.as_str();
+        if MODIFIERS.contains(&key) {
+            return;
+        }
+        cx.stop_propagation();
+        self.rebinding = None;
+        match key {
+            "escape" => cx.notify(),
+            _ => {
+                let key = event.keystroke.unparse();
+                self.update_settings(cx, |s| s.keybindings.rebind(action, &key));
+            }
+        }
+    }
+
+    fn update_settings(&mut self, cx: &mut Context<Self>, update: impl FnOnce(&mut Settings)) {
+        update(&mut self.settings);
+        if let Err(err) = settings::save(&self.settings) {
+            let message = persistence::failure_message("save settings", &err);
+            cx.emit(SettingsEvent::SaveFailed(message));
+        }
+        cx.emit(SettingsEvent::Changed(self.settings.clone()));
+        cx.notify();
+    }
+
+    pub fn reset(&mut self, cx: &mut Context<Self>) {
+        self.update_settings(cx, |s| *s = Settings::default());
+    }
+
+    fn close(&mut self, _: &MouseDownEvent, _window: &mut Window, cx: &mut Context<Self>) {
+        cx.emit(SettingsEvent::Closed);
+    }
+
+    fn render_option(
+        &self,
+        id: impl Into<ElementId>,
+        label: impl Into<SharedString>,
+        selected: bool,
+        cx: &mut Context<Self>,
+        on_select: impl Fn(&mut Settings) + 'static,
+    ) -> impl IntoElement {
+        div()
+            .id(id)
+            .px_3()
+            .py_1()
+            .rounded_md()
+            .bg(if selected {
+                rgb(0x8f7a66)
+            } else {
+                rgb(0xcdc1b4)
+            })
+            .text_color(if selected {
+                rgb(0xf9f6f2)
+            } else {
+                rgb(0x776e65)
+            })
+            .font_weight(FontWeight::BOLD)
+            .child(label.into())
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(move |this, _: &MouseDownEvent, _window, cx| {
+                    this.update_settings(cx, &on_select)
+                }),
+            )
+    }
+
+    fn render_keybinding(&self, action: KeyAction, cx: &mut Context<Self>) -> impl IntoElement {
+        let rebinding = self.rebinding == Some(action);
+        let keys = match rebinding {
+            true => "press a key…".to_string(),
+            false => self.settings.keybindings.keys(action).join(", "),
+        };
+        div()
+            .id(("keybinding", action as usize))
+            .px_3()
+            .py_1()
+            .rounded_md()
+            .bg(if rebinding {
+                rgb(0x8f7a66)
+            } else {
+                rgb(0xcdc1b4)
+            })
+            .text_color(if rebinding {
+                rgb(0xf9f6f2)
+            } else {
+                rgb(0x776e65)
+            })
+            .child(format!("{}: {keys}", action.label()))
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(move |this, _: &MouseDownEvent, _window, cx| {
+                    this.rebinding = Some(action);
+                    cx.notify();
+                }),
+            )
+    }
+
+    // A sound's volume, turned up a step by each click and back round to
+    // silent after the loudest.
+    fn render_volume(&self, sound: Sound, cx: &mut Context<Self>) -> impl IntoElement {
+        let volume = self.settings.volumes.get(sound);
+        div()
+            .id(("volume", sound as usize))
+            .px_3()
+            .py_1()
+            .rounded_md()
+            .bg(rgb(0xcdc1b4))
+            .text_color(rgb(0x776e65))
+            .child(format!("{}: {:.0}%", sound.name(), volume * 100.0))
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(move |this, _: &MouseDownEvent, _window, cx| {
+                    let next = VOLUME_STEPS
+                        .into_iter()
+                        .find(|&step| step > volume)
+                        .unwrap_or(0.0);
+                    this.update_settings(cx, |s| s.volumes.set(sound, next));
+                }),
+            )
+    }
+
+    fn render_row(
+        &self,
+        label: &'static str,
+        options: impl IntoIterator<Item = AnyElement>,
+    ) -> impl IntoElement {
+        div()
+            .flex()
+            .flex_col()
+            .gap_1()
+            .child(
+                div()
+                    .text_sm()
+                    .text_color(rgb(0x776e65))
+                    .font_weight(FontWeight::BOLD)
+                    .child(label),
+            )
+            .child(div().flex().flex_wrap().gap_1().children(options))
+    }
+}
+
+impl Focusable for SettingsPanel {
+    fn focus_handle(&self, _: &App) -> FocusHandle {
+        self.focus_handle.clone()
+    }
+}
+
+impl Render for SettingsPanel {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let current = self.settings.clone();
+
+        let themes = current
+            .themes()
+            .into_iter()
+            .enumerate()
+            .map(|(i, theme)| {
+                self.render_option(
+                    ("theme", i),
+                    theme.clone(),
+                    current.theme.eq_ignore_ascii_case(&theme),
+                    cx,
+                    move |s| s.theme = theme.clone(),
+                )
+                .into_any_element()
+            })
+            .collect::<Vec<_>>();
+
+        let palettes = TilePalette::ALL
+            .into_iter()
+            .map(|palette| {
+                self.render_option(
+                    ("palette", palette as usize),
+                    palette.name(),
+                    current.tile_palette == palette,
+                    cx,
+                    move |s| s.tile_palette = palette,
+                )
+                .into_any_element()
+            })
+            .collect::<Vec<_>>();
+
+        let labels = TileLabels::ALL
+            .into_iter()
+            .map(|labels| {
+                self.render_option(
+                    ("labels", labels as usize),
+                    labels.name(),
+                    current.tile_labels == labels,
+                    cx,
+                    move |s| s.tile_labels = labels,
+                )
+                .into_any_element()
+            })
+            .collect::<Vec<_>>();
+
+        let sizes = (MIN_SIZE..=MAX_SIZE)
+            .map(|n| {
+                self.render_option(
+                    ("size", n),
+                    format!("{n}x{n}"),
+                    current.rows == n && current.cols == n,
+                    cx,
+                    move |s| {
+                        s.rows = n;
+                        s.cols = n;
+                    },
+                )
+                .into_any_element()
+            })
+            .collect::<Vec<_>>();
+
+        let shapes = BoardShape::ALL
+            .into_iter()
+            .map(|shape| {
+                self.render_option(
+                    ("shape", shape as usize),
+                    shape.label(),
+                    current.shape == shape,
+                    cx,
+                    move |s| s.shape = shape,
+                )
+                .into_any_element()
+            })
+            .collect::<Vec<_>>();
+
+        let spawns = [
+            ("Classic", SpawnPolicy::CLASSIC),
+            ("Hard (4s only)", SpawnPolicy::HARD),
+            (
+                "Some 8s",
+                SpawnPolicy {
+                    eight_probability: 0.05,
+                    ..SpawnPolicy::CLASSIC
+                },
+            ),
+            (
+                "Bombs",
+                SpawnPolicy {
+                    bomb_probability: 0.03,
+                    ..SpawnPolicy::CLASSIC
+                },
+            ),
+            (
+                "Wildcards",
+                SpawnPolicy {
+                    wild_probability: 0.03,
+                    ..SpawnPolicy::CLASSIC
+                },
+            ),
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(i, (label, policy))| {
+            // The tile count belongs to the difficulty row.
+            let difficulty = current.spawn_policy().difficulty();
+            self.render_option(
+                ("spawns", i),
+                label,
+                current.spawn_policy() == policy.with_difficulty(difficulty),
+                cx,
+                move |s| {
+                    let difficulty = s.spawn_policy().difficulty();
+                    s.set_spawn_policy(policy.with_difficulty(difficulty))
+                },
+            )
+            .into_any_element()
+        })
+        .collect::<Vec<_>>();
+
+        let difficulties = Difficulty::ALL
+            .into_iter()
+            .map(|difficulty| {
+                self.render_option(
+                    ("difficulty", difficulty as usize),
+                    difficulty.label(),
+                    current.spawn_policy().difficulty() == difficulty,
+                    cx,
+                    move |s| s.spawn_count = difficulty.tiles_per_move(),
+                )
+                .into_any_element()
+            })
+            .collect::<Vec<_>>();
+
+        let speeds = [
+            ("Off (instant)", 0.0),
+            ("0.5x", 0.5),
+            ("1x", 1.0),
+            ("2x", 2.0),
+            ("4x", 4.0),
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(i, (label, speed))| {
+            let selected = match current.animations {
+                true => current.animation_speed == speed,
+                false => speed == 0.0,
+            };
+            self.render_option(("speed", i), label, selected, cx, move |s| {
+                s.animations = speed > 0.0;
+                if speed > 0.0 {
+                    s.animation_speed = speed;
+                }
+            })
+            .into_any_element()
+        })
+        .collect::<Vec<_>>();
+
+        let motion = [("Full", false), ("Reduced", true)]
+            .into_iter()
+            .map(|(label, reduced)| {
+                self.render_option(
+                    ("motion", reduced as usize),
+                    label,
+                    current.reduced_motion == reduced,
+                    cx,
+                    move |s| s.reduced_motion = reduced,
+                )
+                .into_any_element()
+            })
+            .collect::<Vec<_>>();
+
+        let sound = [("On", true), ("Off", false)]
+            .into_iter()
+            .map(|(label, on)| {
+                self.render_option(
+                    ("sound", on as usize),
+                    label,
+                    current.sound == on,
+                    cx,
+                    move |s| s.sound = on,
+                )
+                .into_any_element()
+            })
+            .collect::<Vec<_>>();
+
+        let music = [
+            ("Off", 0.0),
+            ("Quiet", 0.25),
+            ("Normal", 0.5),
+            ("Loud", 1.0),
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(i, (label, volume))| {
+            let selected = match current.music {
+                true => current.music_volume == volume,
+                false => volume == 0.0,
+            };
+            self.render_option(("music", i), label, selected, cx, move |s| {
+                s.music = volume > 0.0;
+                if volume > 0.0 {
+                    s.music_volume = volume;
+                }
+            })
+            .into_any_element()
+        })
+        .collect::<Vec<_>>();
+
+        let volumes = Sound::ALL
+            .into_iter()
+            .map(|sound| self.render_volume(sound, cx).into_any_element())
+            .collect::<Vec<_>>();
+
+        let autoplay = [1.0, 2.0, 4.0, 8.0]
+            .into_iter()
+            .enumerate()
+            .map(|(i, rate)| {
+                self.render_option(
+                    ("autoplay", i),
+                    format!("{rate}/s"),
+                    current.autoplay_speed == rate,
+                    cx,
+                    move |s| s.autoplay_speed = rate,
+                )
+                .into_any_element()
+            })
+            .collect::<Vec<_>>();
+
+        let dpad = [("On", true), ("Off", false)]
+            .into_iter()
+            .map(|(label, on)| {
+                self.render_option(
+                    ("dpad", on as usize),
+                    label,
+                    current.dpad == on,
+                    cx,
+                    move |s| s.dpad = on,
+                )
+                .into_any_element()
+            })
+            .collect::<Vec<_>>();
+
+        let strategies = Strategy::ALL
+            .into_iter()
+            .map(|strategy| {
+                self.render_option(
+                    ("strategy", strategy as usize),
+                    strategy.name(),
+                    current.ai_strategy == strategy,
+                    cx,
+                    move |s| s.ai_strategy = strategy,
+                )
+                .into_any_element()
+            })
+            .collect::<Vec<_>>();
+
+        let coach = [("On", true), ("Off", false)]
+            .into_iter()
+            .map(|(label, on)| {
+                self.render_option(
+                    ("coach", on as usize),
+                    label,
+                    current.coach == on,
+                    cx,
+                    move |s| s.coach = on,
+                )
+                .into_any_element()
+            })
+            .collect::<Vec<_>>();
+
+        let speedrun = [("On", true), ("Off", false)]
+            .into_iter()
+            .map(|(label, on)| {
+                self.render_option(
+                    ("speedrun", on as usize),
+                    label,
+                    current.speedrun == on,
+                    cx,
+                    move |s| s.speedrun = on,
+                )
+                .into_any_element()
+            })
+            .collect::<Vec<_>>();
+
+        let layouts = KeyLayout::ALL
+            .into_iter()
+            .map(|layout| {
+                self.render_option(
+                    ("layout", layout as usize),
+                    layout.label(),
+                    current.keybindings.layout() == Some(layout),
+                    cx,
+                    move |s| s.keybindings = Keybindings::with_layout(layout),
+                )
+                .into_any_element()
+            })
+            .collect::<Vec<_>>();
+
+        let keybindings = KeyAction::ALL
+            .into_iter()
+            .map(|action| self.render_keybinding(action, cx).into_any_element())
+            .collect::<Vec<_>>();
+
+        let repeat = [("Off", 0), ("Slow", 250), ("Normal", 150), ("Fast", 80)]
+            .into_iter()
+            .enumerate()
+            .map(|(i, (label, interval))| {
+                let selected = match current.key_repeat {
+                    true => current.repeat_interval_ms == interval,
+                    false => interval == 0,
+                };
+                self.render_option(("repeat", i), label, selected, cx, move |s| {
+                    s.key_repeat = interval > 0;
+                    if interval > 0 {
+                        s.repeat_interval_ms = interval;
+                    }
+                })
+                .into_any_element()
+            })
+            .collect::<Vec<_>>();
+
+        let badges = [("On", true), ("Off", false)]
+            .into_iter()
+            .map(|(label, on)| {
+                self.render_option(
+                    ("badges", on as usize),
+                    label,
+                    current.tile_badges == on,
+                    cx,
+                    move |s| s.tile_badges = on,
+                )
+                .into_any_element()
+            })
+            .collect::<Vec<_>>();
+
+        let scales = [("100%", 1.0), ("125%", 1.25), ("150%", 1.5), ("200%", 2.0)]
+            .into_iter()
+            .enumerate()
+            .map(|(i, (label, scale))| {
+                self.render_option(
+                    ("scale", i),
+                    label,
+                    current.ui_scale == scale,
+                    cx,
+                    move |s| s.ui_scale = scale,
+                )
+                .into_any_element()
+            })
+            .collect::<Vec<_>>();
+
+        let spectate = [
+            (format!("On (ws://localhost:{SPECTATE_PORT})"), true),
+            ("Off".to_string(), false),
+        ]
+        .into_iter()
+        .map(|(label, on)| {
+            self.render_option(
+                ("spectate", on as usize),
+                label,
+                current.spectate == on,
+                cx,
+                move |s| s.spectate = on,
+            )
+            .into_any_element()
+        })
+        .collect::<Vec<_>>();
+
+        div()
+            .size_full()
+            .track_focus(&self.focus_handle)
+            .on_key_down(cx.listener(Self::key_down))
+            .flex()
+            .justify_center()
+            .items_center()
+            .child(
+                div()
+                    .w(rems(23.75))
+                    .p_4()
+                    .rounded_lg()
+                    .bg(rgb(0xfaf8ef))
+                    .flex()
+                    .flex_col()
+                    .gap_3()
+                    .child(
+                        div()
+                            .text_2xl()
+                            .font_weight(FontWeight::BOLD)
+                            .text_color(rgb(0x776e65))
+                            .child("Settings"),
+                    )
+                    .child(self.render_row("THEME", themes))
+                    .child(self.render_row("TILE COLORS", palettes))
+                    .child(self.render_row("TILE LABELS", labels))
+                    .child(self.render_row("SHAPE BADGES", badges))
+                    .child(self.render_row("UI SCALE", scales))
+                    .child(self.render_row("BOARD SIZE (next game)", sizes))
+                    .child(self.render_row("SHAPE (next game)", shapes))
+                    .child(self.render_row("SPAWNS (next game)", spawns))
+                    .child(self.render_row("DIFFICULTY (next game)", difficulties))
+                    .child(self.render_row("ANIMATIONS", speeds))
+                    .child(self.render_row("MOTION", motion))
+                    .child(self.render_row("SOUND", sound))
+                    .child(self.render_row("VOLUME (click to turn up)", volumes))
+                    .child(self.render_row("MUSIC", music))
+                    .child(self.render_row("AI", strategies))
+                    .child(self.render_row("AUTOPLAY SPEED", autoplay))
+                    .child(self.render_row("ARROW BUTTONS", dpad))
+                    .child(self.render_row("COACH", coach))
+                    .child(self.render_row("SPEEDRUN TIMER", speedrun))
+                    .child(self.render_row("HOLD TO REPEAT", repeat))
+                    .child(self.render_row("SPECTATOR FEED", spectate))
+                    .child(self.render_row("KEYS", layouts))
+                    .child(self.render_row("KEYBINDINGS (click, then press a key)", keybindings))
+                    .child(
+                        div()
+                            .flex()
+                            .justify_end()
+                            .gap_2()
+                            .child(
+                                div()
+                                    .id("reset-settings")
+                                    .px_4()
+                                    .py_2()
+                                    .bg(rgb(0xcdc1b4))
+                                    .text_color(rgb(0x776e65))
+                                    .rounded_md()
+                                    .font_weight(FontWeight::BOLD)
+                                    .child("Reset All")
+                                    .on_mouse_down(
+                                        MouseButton::Left,
+                                        cx.listener(|_, _: &MouseDownEvent, _, cx| {
+                                            cx.emit(SettingsEvent::ConfirmReset)
+                                        }),
+                                    ),
+                            )
+                            .child(
+                                div()
+                                    .id("close-settings")
+                                    .px_4()
+                                    .py_2()
+                                    .bg(rgb(0x8f7a66))
+                                    .text_color(rgb(0xf9f6f2))
+                                    .rounded_md()
+                                    .font_weight(FontWeight::BOLD)
+                                    .child("Done")
+                                    .on_mouse_down(MouseButton::Left, cx.listener(Self::close)),
+                            ),
+                    ),
+            )
+    }
+}
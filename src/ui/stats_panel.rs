@@ -0,0 +1,244 @@
+use crate::daily::Date;
+use crate::stats::Stats;
+use gpui::*;
+
+pub enum StatsEvent {
+    Closed,
+}
+
+// In rems, so the charts grow with the UI scale.
+const BAR_WIDTH: f32 = 13.75;
+const HISTORY_HEIGHT: f32 = 5.0;
+const CALENDAR_CELL: f32 = 2.5;
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// Lifetime statistics, with a bar per highest-tile bucket.
+pub struct StatsPanel {
+    stats: Stats,
+}
+
+impl EventEmitter<StatsEvent> for StatsPanel {}
+
+impl StatsPanel {
+    pub fn new(stats: Stats) -> StatsPanel {
+        StatsPanel { stats }
+    }
+
+    fn close(&mut self, _: &MouseDownEvent, _window: &mut Window, cx: &mut Context<Self>) {
+        cx.emit(StatsEvent::Closed);
+    }
+
+    fn render_stat(&self, label: &'static str, value: String) -> impl IntoElement {
+        div()
+            .flex()
+            .justify_between()
+            .text_color(rgb(0x776e65))
+            .child(div().text_sm().font_weight(FontWeight::BOLD).child(label))
+            .child(div().text_sm().child(value))
+    }
+
+    fn render_bar(&self, tile: u32, count: u32, most: u32) -> impl IntoElement {
+        let width = BAR_WIDTH * count as f32 / most.max(1) as f32;
+        div()
+            .flex()
+            .items_center()
+            .gap_2()
+            .text_sm()
+            .text_color(rgb(0x776e65))
+            .child(div().w(rems(3.0)).child(tile.to_string()))
+            .child(
+                div()
+                    .h(rems(0.875))
+                    .w(rems(width.max(0.125)))
+                    .rounded_sm()
+                    .bg(rgb(0xf59563)),
+            )
+            .child(count.to_string())
+    }
+
+    // One column per recent game, scaled to the best of them.
+    fn render_history(&self) -> impl IntoElement {
+        let scores = &self.stats.recent_scores;
+        let best = scores.iter().copied().max().unwrap_or(0).max(1);
+        div()
+            .flex()
+            .items_end()
+            .gap_0p5()
+            .h(rems(HISTORY_HEIGHT))
+            .p_1()
+            .rounded_md()
+            .bg(rgb(0xeee4da))
+            .children(scores.iter().map(|&score| {
+                let height = HISTORY_HEIGHT * score as f32 / best as f32;
+                div()
+                    .flex_1()
+                    .h(rems(height.max(0.0625)))
+                    .rounded_sm()
+                    .bg(rgb(0x8f7a66))
+            }))
+    }
+
+    // This month's dailies, a week per row starting on Monday; played days
+    // are filled in and won ones highlighted.
+    fn render_calendar(&self, today: Date) -> impl IntoElement {
+        let first = today.first_of_month();
+        let cells = (0..first.weekday())
+            .map(|_| None)
+            .chain((1..=first.days_in_month()).map(|day| Some(Date { day, ..first })))
+            .collect::<Vec<_>>();
+        div()
+            .flex()
+            .flex_col()
+            .gap_0p5()
+            .children(cells.chunks(7).map(|week| {
+                div().flex().gap_0p5().children(week.iter().map(|&date| {
+                    let cell = div()
+                        .w(rems(CALENDAR_CELL))
+                        .h(rems(CALENDAR_CELL * 0.6))
+                        .flex()
+                        .justify_center()
+                        .items_center()
+                        .rounded_sm()
+                        .text_xs();
+                    let Some(date) = date else {
+                        return cell;
+                    };
+                    let (bg, fg) = match self.stats.dailies.get(&date) {
+                        Some(result) if result.won => (rgb(0xedc22e), rgb(0xf9f6f2)),
+                        Some(_) => (rgb(0xf59563), rgb(0xf9f6f2)),
+                        None => (rgb(0xeee4da), rgb(0x776e65)),
+                    };
+                    cell.bg(bg)
+                        .text_color(fg)
+                        .when(date == today, |cell| {
+                            cell.border_2().border_color(rgb(0x776e65))
+                        })
+                        .child(date.day.to_string())
+                }))
+            }))
+    }
+}
+
+impl Render for StatsPanel {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let stats = &self.stats;
+        let today = Date::today();
+        let most = stats.highest_tiles.values().copied().max().unwrap_or(0);
+        let bars = stats
+            .highest_tiles
+            .iter()
+            .map(|(&tile, &count)| self.render_bar(tile, count, most))
+            .collect::<Vec<_>>();
+
+        div()
+            .size_full()
+            .flex()
+            .justify_center()
+            .items_center()
+            .child(
+                div()
+                    .w(rems(23.75))
+                    .p_4()
+                    .rounded_lg()
+                    .bg(rgb(0xfaf8ef))
+                    .flex()
+                    .flex_col()
+                    .gap_2()
+                    .child(
+                        div()
+                            .text_2xl()
+                            .font_weight(FontWeight::BOLD)
+                            .text_color(rgb(0x776e65))
+                            .child("Statistics"),
+                    )
+                    .child(self.render_stat("Games played", stats.games_played.to_string()))
+                    .child(
+                        self.render_stat("Win rate", format!("{:.0}%", stats.win_rate() * 100.0)),
+                    )
+                    .child(
+                        self.render_stat("Average score", format!("{:.0}", stats.average_score())),
+                    )
+                    .child(self.render_stat("Highest tile", stats.highest_tile.to_string()))
+                    .child(self.render_stat("Total moves", stats.total_moves.to_string()))
+                    .children((stats.time_attack_games > 0).then(|| {
+                        self.render_stat("Time attack best", stats.time_attack_best.to_string())
+                    }))
+                    .children(stats.best_score_per_move.map(|best| {
+                        self.render_stat(
+                            "Best points per move",
+                            format!("{:.2}", best.score_per_move()),
+                        )
+                    }))
+                    .children(stats.best_merges_per_move.map(|best| {
+                        self.render_stat(
+                            "Best merges per move",
+                            format!("{:.2}", best.merges_per_move()),
+                        )
+                    }))
+                    .child(
+                        div()
+                            .mt_2()
+                            .text_sm()
+                            .font_weight(FontWeight::BOLD)
+                            .text_color(rgb(0x776e65))
+                            .child("HIGHEST TILE PER GAME"),
+                    )
+                    .children(bars)
+                    .child(
+                        div()
+                            .mt_2()
+                            .text_sm()
+                            .font_weight(FontWeight::BOLD)
+                            .text_color(rgb(0x776e65))
+                            .child(format!("LAST {} SCORES", stats.recent_scores.len())),
+                    )
+                    .child(self.render_history())
+                    .child(
+                        div()
+                            .mt_2()
+                            .flex()
+                            .justify_between()
+                            .text_sm()
+                            .font_weight(FontWeight::BOLD)
+                            .text_color(rgb(0x776e65))
+                            .child(format!(
+                                "DAILY PUZZLES · {} {}",
+                                MONTHS[today.month as usize - 1].to_uppercase(),
+                                today.year
+                            ))
+                            .child(format!("STREAK {}", stats.daily_streak(today))),
+                    )
+                    .children(stats.dailies.get(&today).map(|result| {
+                        self.render_stat("Today's best", result.best_score.to_string())
+                    }))
+                    .child(self.render_calendar(today))
+                    .child(
+                        div().flex().justify_end().child(
+                            div()
+                                .id("close-stats")
+                                .px_4()
+                                .py_2()
+                                .bg(rgb(0x8f7a66))
+                                .text_color(rgb(0xf9f6f2))
+                                .rounded_md()
+                                .font_weight(FontWeight::BOLD)
+                                .child("Done")
+                                .on_mouse_down(MouseButton::Left, cx.listener(Self::close)),
+                        ),
+                    ),
+            )
+    }
+}
@@ -0,0 +1,200 @@
+use crate::ai::Expectimax;
+use crate::analysis::{self, Divergence, GameSummary};
+use crate::engine::Cell;
+use crate::replay::Replay;
+use crate::settings::Settings;
+use crate::splits;
+use crate::ui::game::{
+    get_color, get_font_color, get_font_size, special_color, special_label, tile_metrics,
+};
+use gpui::*;
+
+// The final board is shown at this fraction of its size in play.
+const BOARD_SCALE: f32 = 0.6;
+
+pub enum SummaryEvent {
+    Closed,
+    NewGame,
+}
+
+/// A look back over a game that has just ended: the board it finished on,
+/// its numbers, and how often it parted ways with the AI. The AI goes over
+/// the game in the background, so that line fills in last.
+pub struct SummaryPanel {
+    summary: GameSummary,
+    settings: Settings,
+    divergence: Option<Divergence>,
+    _analysis: Task<()>,
+}
+
+impl EventEmitter<SummaryEvent> for SummaryPanel {}
+
+impl SummaryPanel {
+    pub fn new(
+        summary: GameSummary,
+        replay: Replay,
+        settings: Settings,
+        cx: &mut Context<Self>,
+    ) -> SummaryPanel {
+        let search = Expectimax {
+            depth: analysis::ANALYSIS_DEPTH,
+            time_budget: None,
+            ..settings.expectimax()
+        };
+        let analysis = cx.background_spawn(async move { analysis::divergence(&replay, &search) });
+        SummaryPanel {
+            summary,
+            settings,
+            divergence: None,
+            _analysis: cx.spawn(async move |this, cx| {
+                let divergence = analysis.await;
+                this.update(cx, |this, cx| {
+                    this.divergence = Some(divergence);
+                    cx.notify();
+                })
+                .ok();
+            }),
+        }
+    }
+
+    fn render_stat(&self, label: &'static str, value: String) -> impl IntoElement {
+        div()
+            .flex()
+            .justify_between()
+            .text_color(rgb(0x776e65))
+            .child(div().text_sm().font_weight(FontWeight::BOLD).child(label))
+            .child(div().text_sm().child(value))
+    }
+
+    fn render_cell(&self, idx: usize, tile: f32) -> Div {
+        let board = &self.summary.board;
+        let cell = div()
+            .size(px(tile))
+            .rounded_md()
+            .flex()
+            .justify_center()
+            .items_center()
+            .font_weight(FontWeight::BOLD);
+        if board.is_hole(idx) {
+            return cell;
+        }
+        if board.is_obstacle(idx) {
+            return cell.bg(rgb(0x5c534a));
+        }
+        match board.cell(idx) {
+            Cell::Number(value) => {
+                let rank = board.rule().rank(value);
+                let label = self.settings.tile_labels.text(value, rank);
+                cell.bg(get_color(rank))
+                    .text_color(get_font_color(rank))
+                    .text_size(get_font_size(&label, tile))
+                    .child(label)
+            }
+            Cell::Special(special) => cell
+                .bg(special_color(special))
+                .text_color(rgb(0xf9f6f2))
+                .text_size(px(tile * 0.4))
+                .child(special_label(special)),
+            Cell::Empty => cell.bg(rgb(0xcdc1b4)),
+        }
+    }
+
+    fn render_board(&self) -> impl IntoElement {
+        let board = &self.summary.board;
+        let (rows, cols) = (board.rows(), board.cols());
+        let (tile, gap) = tile_metrics(rows, cols, self.settings.ui_scale);
+        let (tile, gap) = (tile * BOARD_SCALE, gap * BOARD_SCALE);
+        div()
+            .flex()
+            .flex_col()
+            .gap(px(gap))
+            .p(px(gap))
+            .rounded_lg()
+            .bg(rgb(0xbbada0))
+            .children((0..rows).map(|row| {
+                div()
+                    .flex()
+                    .gap(px(gap))
+                    .children((0..cols).map(|col| self.render_cell(row * cols + col, tile)))
+            }))
+    }
+
+    fn render_button(&self, id: &'static str, label: &'static str) -> Stateful<Div> {
+        div()
+            .id(id)
+            .px_4()
+            .py_2()
+            .bg(rgb(0x8f7a66))
+            .text_color(rgb(0xf9f6f2))
+            .rounded_md()
+            .font_weight(FontWeight::BOLD)
+            .child(label)
+    }
+}
+
+impl Render for SummaryPanel {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let summary = &self.summary;
+        let divergence = match self.divergence {
+            None => "Comparing...".to_string(),
+            Some(Divergence { checked: 0, .. }) => "-".to_string(),
+            Some(Divergence { diverged, checked }) => format!(
+                "{diverged} of {checked} ({:.0}%)",
+                diverged as f64 * 100.0 / checked as f64
+            ),
+        };
+
+        div()
+            .size_full()
+            .flex()
+            .justify_center()
+            .items_center()
+            .child(
+                div()
+                    .w(rems(23.75))
+                    .p_4()
+                    .rounded_lg()
+                    .bg(rgb(0xfaf8ef))
+                    .flex()
+                    .flex_col()
+                    .gap_2()
+                    .child(
+                        div()
+                            .text_2xl()
+                            .font_weight(FontWeight::BOLD)
+                            .text_color(rgb(0x776e65))
+                            .child("Game Summary"),
+                    )
+                    .child(div().flex().justify_center().child(self.render_board()))
+                    .child(self.render_stat("Score", summary.score.to_string()))
+                    .child(self.render_stat("Highest tile", summary.max_tile().to_string()))
+                    .child(self.render_stat("Time", splits::format_time(summary.millis)))
+                    .child(self.render_stat("Moves", summary.moves.to_string()))
+                    .child(self.render_stat(
+                        "Biggest merge",
+                        match summary.biggest_merge {
+                            0 => "-".to_string(),
+                            tile => tile.to_string(),
+                        },
+                    ))
+                    .child(self.render_stat("Moves unlike the AI's", divergence))
+                    .child(
+                        div()
+                            .flex()
+                            .justify_end()
+                            .gap_2()
+                            .child(self.render_button("summary-close", "Back").on_mouse_down(
+                                MouseButton::Left,
+                                cx.listener(|_, _, _, cx| cx.emit(SummaryEvent::Closed)),
+                            ))
+                            .child(
+                                self.render_button("summary-new-game", "New Game")
+                                    .on_mouse_down(
+                                        MouseButton::Left,
+                                        cx.listener(|_, _, _, cx| cx.emit(SummaryEvent::NewGame)),
+                                    ),
+                            ),
+                    ),
+            )
+    }
+}
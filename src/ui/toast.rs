@@ -0,0 +1,99 @@
+use gpui::*;
+use std::{collections::VecDeque, time::Duration};
+
+const TOAST_DURATION: Duration = Duration::from_millis(2500);
+const MAX_VISIBLE: usize = 3;
+// Portions of a toast's lifetime spent fading in and out.
+const FADE_IN: f32 = 0.08;
+const FADE_OUT: f32 = 0.15;
+
+struct Toast {
+    id: usize,
+    message: SharedString,
+    // Removes the toast once its time is up; started when it becomes visible.
+    dismiss: Option<Task<()>>,
+}
+
+/// Short messages stacked over the top of the window. Up to three show at
+/// once; the rest wait their turn, and each fades out on its own timer.
+pub struct ToastLayer {
+    toasts: VecDeque<Toast>,
+    next_id: usize,
+}
+
+impl ToastLayer {
+    pub fn new() -> ToastLayer {
+        ToastLayer {
+            toasts: VecDeque::new(),
+            next_id: 0,
+        }
+    }
+
+    pub fn show(&mut self, message: impl Into<SharedString>, cx: &mut Context<Self>) {
+        self.toasts.push_back(Toast {
+            id: self.next_id,
+            message: message.into(),
+            dismiss: None,
+        });
+        self.next_id += 1;
+        self.start_timers(cx);
+        cx.notify();
+    }
+
+    fn start_timers(&mut self, cx: &mut Context<Self>) {
+        for toast in self.toasts.iter_mut().take(MAX_VISIBLE) {
+            if toast.dismiss.is_some() {
+                continue;
+            }
+            let id = toast.id;
+            toast.dismiss = Some(cx.spawn(async move |this, cx| {
+                cx.background_executor().timer(TOAST_DURATION).await;
+                this.update(cx, |this, cx| {
+                    this.toasts.retain(|toast| toast.id != id);
+                    this.start_timers(cx);
+                    cx.notify();
+                })
+                .ok();
+            }));
+        }
+    }
+}
+
+fn toast_opacity(progress: f32) -> f32 {
+    if progress < FADE_IN {
+        progress / FADE_IN
+    } else if progress > 1.0 - FADE_OUT {
+        (1.0 - progress) / FADE_OUT
+    } else {
+        1.0
+    }
+}
+
+impl Render for ToastLayer {
+    fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
+        div()
+            .absolute()
+            .top_4()
+            .left_0()
+            .right_0()
+            .flex()
+            .flex_col()
+            .items_center()
+            .gap_2()
+            .children(self.toasts.iter().take(MAX_VISIBLE).map(|toast| {
+                div()
+                    .px_4()
+                    .py_2()
+                    .rounded_md()
+                    .bg(rgba(0x3c3a32e6))
+                    .text_color(rgb(0xf9f6f2))
+                    .font_weight(FontWeight::BOLD)
+                    .child(toast.message.clone())
+                    .with_animation(
+                        ("toast", toast.id),
+                        Animation::new(TOAST_DURATION),
+                        |this, progress| this.opacity(toast_opacity(progress)),
+                    )
+            }))
+    }
+}
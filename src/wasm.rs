@@ -0,0 +1,94 @@
+use crate::engine::{Direction, GameConfig, MAX_SIZE, MIN_SIZE};
+use crate::headless::{TextGame, render};
+use wasm_bindgen::prelude::*;
+
+/// The engine for a browser page to drive: the page sends moves and draws
+/// the board from [`WebGame::cells`]. Build it with
+/// `cargo rustc --lib --crate-type cdylib --target wasm32-unknown-unknown
+/// --no-default-features --features wasm` and run `wasm-bindgen` on the
+/// result.
+#[wasm_bindgen]
+pub struct WebGame {
+    game: TextGame,
+}
+
+#[wasm_bindgen]
+impl WebGame {
+    /// A classic game on a `rows` by `cols` board. The same seed always
+    /// spawns the same tiles; without one a random seed is drawn.
+    #[wasm_bindgen(constructor)]
+    pub fn new(rows: usize, cols: usize, seed: Option<u64>) -> Result<WebGame, JsError> {
+        let valid = MIN_SIZE..=MAX_SIZE;
+        if !valid.contains(&rows) || !valid.contains(&cols) {
+            return Err(JsError::new(&format!(
+                "each side must be between {MIN_SIZE} and {MAX_SIZE}"
+            )));
+        }
+        let config = GameConfig {
+            rows,
+            cols,
+            seed,
+            ..GameConfig::default()
+        };
+        Ok(WebGame {
+            game: TextGame::new(&config, seed.unwrap_or_else(rand::random)),
+        })
+    }
+
+    /// Slides the board "up", "down", "left" or "right" and returns whether
+    /// anything moved; new tiles spawn only if it did.
+    pub fn apply_move(&mut self, direction: &str) -> Result<bool, JsError> {
+        let dir = match direction.to_ascii_lowercase().as_str() {
+            "up" => Direction::Up,
+            "down" => Direction::Down,
+            "left" => Direction::Left,
+            "right" => Direction::Right,
+            _ => return Err(JsError::new(&format!("unknown direction {direction:?}"))),
+        };
+        Ok(self.game.slide(dir).moved)
+    }
+
+    /// Every cell's number row by row, 0 for an empty one.
+    pub fn cells(&self) -> Vec<u32> {
+        self.game.board().cells().to_vec()
+    }
+
+    pub fn rows(&self) -> usize {
+        self.game.board().rows()
+    }
+
+    pub fn cols(&self) -> usize {
+        self.game.board().cols()
+    }
+
+    pub fn score(&self) -> u32 {
+        self.game.score()
+    }
+
+    pub fn moves(&self) -> u32 {
+        self.game.moves()
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.game.seed()
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.game.is_over()
+    }
+
+    /// Whether the winning tile has just been made. It answers true once;
+    /// play carries on after.
+    pub fn take_win(&mut self) -> bool {
+        let won = self.game.has_won();
+        if won {
+            self.game.keep_playing();
+        }
+        won
+    }
+
+    /// The board as plain ASCII, for a console or a `<pre>`.
+    pub fn to_text(&self) -> String {
+        render(self.game.board())
+    }
+}